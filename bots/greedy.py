@@ -0,0 +1,56 @@
+#!/usr/bin/env python3
+"""A tiny snek bot that heads straight for the food, avoiding walls and itself.
+
+It starts snek itself (passing along its arguments) and prints the results:
+
+    python3 bots/greedy.py --seed 3
+"""
+
+import json
+import subprocess
+import sys
+
+MOVES = {"up": (0, -1), "down": (0, 1), "left": (-1, 0), "right": (1, 0)}
+
+
+def choose(state):
+    """Picks the safe move that gets closest to the nearest food."""
+    head = state["snek"][-1]
+    blocked = {tuple(part) for part in state["snek"][1:]}
+    blocked |= {tuple(obstacle) for obstacle in state["obstacles"]}
+    blocked |= {tuple(decoy) for decoy in state["decoys"]}
+    best, best_distance = "none", None
+    for name, (dx, dy) in MOVES.items():
+        x, y = head[0] + dx, head[1] + dy
+        if not (0 <= x < state["width"] and 0 <= y < state["height"]):
+            continue
+        if (x, y) in blocked:
+            continue
+        distance = min(
+            (abs(x - food[0]) + abs(y - food[1]) for food in state["foods"]),
+            default=0,
+        )
+        if best_distance is None or distance < best_distance:
+            best, best_distance = name, distance
+    return best
+
+
+def main():
+    snek = subprocess.Popen(
+        ["snek", "--bot-io", "--headless", *sys.argv[1:]],
+        stdin=subprocess.PIPE,
+        stdout=subprocess.PIPE,
+        text=True,
+    )
+    for line in snek.stdout:
+        state = json.loads(line)
+        if "outcome" in state:
+            print(line, end="")
+            break
+        snek.stdin.write(choose(state) + "\n")
+        snek.stdin.flush()
+    snek.wait()
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,32 @@
+#!/usr/bin/env python3
+"""A tiny vote sender for `snek --vote-input`, for wiring chat up to the snek.
+
+It sends every direction given to it (or read from stdin, one per line, if
+there are none) as a vote to a snek listening on an address:
+
+    snek --vote-input 127.0.0.1:7779
+    python3 bots/vote.py 127.0.0.1:7779 up up left
+    my-chat-reader | python3 bots/vote.py 127.0.0.1:7779
+"""
+
+import socket
+import sys
+
+DIRECTIONS = {"up", "down", "left", "right"}
+
+
+def main():
+    if len(sys.argv) < 2:
+        sys.exit("usage: vote.py HOST:PORT [DIRECTION...]")
+    host, _, port = sys.argv[1].rpartition(":")
+    address = (host, int(port))
+    votes = sys.argv[2:] or sys.stdin
+    with socket.socket(socket.AF_INET, socket.SOCK_DGRAM) as sock:
+        for vote in votes:
+            vote = vote.strip().lower()
+            if vote in DIRECTIONS:
+                sock.sendto(vote.encode(), address)
+
+
+if __name__ == "__main__":
+    main()
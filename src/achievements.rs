@@ -0,0 +1,178 @@
+use crate::options::Difficulty;
+use crate::summary::GameSummary;
+
+// Something a player can unlock once, by doing well enough in a game
+pub struct Achievement {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub earned: fn(&GameSummary) -> bool,
+}
+
+// Every achievement, in the order they are shown
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    Achievement {
+        id: "first_blood",
+        name: "First Blood",
+        description: "Eat a pellet",
+        earned: |summary| summary.eaten >= 1,
+    },
+    Achievement {
+        id: "half_board",
+        name: "Half Board",
+        description: "Fill half of the board",
+        earned: |summary| summary.length as u32 >= summary.width * summary.height / 2,
+    },
+    Achievement {
+        id: "untouchable",
+        name: "Untouchable",
+        description: "Score 20 without ever touching your own body",
+        earned: |summary| summary.score >= 20 && !summary.touched_body,
+    },
+    Achievement {
+        id: "speed_demon",
+        name: "Speed Demon",
+        description: "Score 10 on hard",
+        earned: |summary| summary.score >= 10 && summary.difficulty == Difficulty::Hard,
+    },
+    Achievement {
+        id: "full_clear",
+        name: "Full Clear",
+        description: "Fill the whole board",
+        earned: |summary| summary.length as u32 >= summary.width * summary.height,
+    },
+];
+
+// Returns the achievements a game earned that aren't already unlocked
+pub fn newly_earned<'a>(
+    summary: &'a GameSummary,
+    unlocked: &'a [String],
+) -> impl Iterator<Item = &'static Achievement> + 'a {
+    ACHIEVEMENTS.iter().filter(|achievement| {
+        !unlocked.iter().any(|id| id == achievement.id) && (achievement.earned)(summary)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns whether a game earns the achievement with an id
+    fn earns(id: &str, summary: &GameSummary) -> bool {
+        let achievement = ACHIEVEMENTS.iter().find(|a| a.id == id).unwrap();
+        (achievement.earned)(summary)
+    }
+
+    // Returns a game on a 10x10 board that ate nothing
+    fn empty() -> GameSummary {
+        GameSummary {
+            score: 0,
+            length: 4,
+            eaten: 0,
+            width: 10,
+            height: 10,
+            ..GameSummary::example()
+        }
+    }
+
+    #[test]
+    fn first_blood() {
+        assert!(!earns("first_blood", &empty()));
+        assert!(earns(
+            "first_blood",
+            &GameSummary {
+                eaten: 1,
+                ..empty()
+            }
+        ));
+    }
+
+    #[test]
+    fn half_board() {
+        assert!(!earns(
+            "half_board",
+            &GameSummary {
+                length: 49,
+                ..empty()
+            }
+        ));
+        assert!(earns(
+            "half_board",
+            &GameSummary {
+                length: 50,
+                ..empty()
+            }
+        ));
+    }
+
+    #[test]
+    fn untouchable() {
+        let scored = GameSummary {
+            score: 20,
+            ..empty()
+        };
+        assert!(earns("untouchable", &scored));
+        let touched = GameSummary {
+            touched_body: true,
+            ..scored.clone()
+        };
+        assert!(!earns("untouchable", &touched));
+        assert!(!earns(
+            "untouchable",
+            &GameSummary {
+                score: 19,
+                ..empty()
+            }
+        ));
+    }
+
+    #[test]
+    fn speed_demon() {
+        let scored = GameSummary {
+            score: 10,
+            ..empty()
+        };
+        assert!(!earns("speed_demon", &scored));
+        let hard = GameSummary {
+            difficulty: Difficulty::Hard,
+            ..scored
+        };
+        assert!(earns("speed_demon", &hard));
+        assert!(!earns("speed_demon", &GameSummary { score: 9, ..hard }));
+    }
+
+    #[test]
+    fn full_clear() {
+        assert!(!earns(
+            "full_clear",
+            &GameSummary {
+                length: 99,
+                ..empty()
+            }
+        ));
+        assert!(earns(
+            "full_clear",
+            &GameSummary {
+                length: 100,
+                ..empty()
+            }
+        ));
+    }
+
+    #[test]
+    fn unlocked_ones_are_skipped() {
+        let summary = GameSummary {
+            eaten: 1,
+            length: 100,
+            ..empty()
+        };
+        let ids = |unlocked: &[String]| -> Vec<&str> {
+            newly_earned(&summary, unlocked).map(|a| a.id).collect()
+        };
+        assert_eq!(ids(&[]), ["first_blood", "half_board", "full_clear"]);
+        assert_eq!(
+            ids(&["half_board".to_owned()]),
+            ["first_blood", "full_clear"]
+        );
+    }
+}
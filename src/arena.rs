@@ -0,0 +1,193 @@
+// Sneks sharing one map, each trying to outlast the others.
+//
+// The first snek starts in the top left corner heading right and the second in
+// the bottom right corner heading left; with more players, the third starts in
+// the bottom left corner heading up and the fourth in the top right corner
+// heading down. Every snek that's alive moves at once every tick; a snek dies
+// by running into a wall, itself, or any part of another living snek (so when
+// two heads meet, both do), and the last one alive wins. Food works like the
+// classic mode, with one pellet on the map shared between them.
+
+use fastrand::Rng;
+
+use crate::Point;
+use crate::events::SnekEvent;
+use crate::options::Options;
+use crate::world::{self, Direction, Snek};
+
+// Most sneks an arena can hold (one for each corner)
+pub const MAX_PLAYERS: usize = 4;
+
+// How a finished head-to-head game ended
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArenaOutcome {
+    Winner(usize),
+    Draw,
+}
+
+// Everything about a head-to-head game that doesn't need a terminal
+pub struct Arena {
+    pub sneks: Vec<Snek>,
+    pub food: Vec<Point>,
+    pub rng: Rng,
+    pub options: Options,
+    pub ticks: u64,
+}
+
+impl Arena {
+    // Creates a new arena with two sneks in their starting positions
+    pub fn new(options: Options) -> Self {
+        Self::with_players(options, 2)
+    }
+
+    // Creates a new arena with some sneks (up to `MAX_PLAYERS`) in their
+    // starting positions
+    pub fn with_players(options: Options, players: usize) -> Self {
+        let (width, height) = (options.width as i32, options.height as i32);
+        let starts = [
+            (Point::new(3, 0), Direction::Right),
+            (Point::new(width - 4, height - 1), Direction::Left),
+            (Point::new(0, height - 4), Direction::Up),
+            (Point::new(width - 1, 3), Direction::Down),
+        ];
+        let sneks: Vec<Snek> = starts[..players.min(MAX_PLAYERS)]
+            .iter()
+            .map(|&(head, heading)| {
+                let mut snek = Snek::new(&world::starting_body(Some((head, heading))), false)
+                    .with_growth_delay(options.growth_delay)
+                    .with_reverse_lockout(options.reverse_lockout);
+                snek.direction = heading;
+                snek.heading = heading;
+                snek
+            })
+            .collect();
+        let mut rng = Rng::with_seed(options.seed);
+        let taken: Vec<Point> = sneks.iter().flat_map(|snek| snek.body.clone()).collect();
+        Self {
+            food: vec![world::rand_point(
+                &mut rng,
+                options.width,
+                options.height,
+                &taken,
+                None,
+            )],
+            sneks,
+            rng,
+            options,
+            ticks: 0,
+        }
+    }
+
+    // Returns whether more than one snek is still alive
+    pub fn running(&self) -> bool {
+        self.sneks.iter().filter(|snek| snek.alive).count() > 1
+    }
+
+    // Returns how the game ended, if it's over
+    pub fn outcome(&self) -> Option<ArenaOutcome> {
+        let mut alive = (0..self.sneks.len()).filter(|&index| self.sneks[index].alive);
+        match (alive.next(), alive.next()) {
+            (Some(_), Some(_)) => None,
+            (Some(winner), None) => Some(ArenaOutcome::Winner(winner)),
+            (None, _) => Some(ArenaOutcome::Draw),
+        }
+    }
+
+    // Turns the living sneks (each by its direction) and moves them one step,
+    // killing any that crashed
+    pub fn advance(&mut self, directions: &[Direction]) {
+        if !self.running() {
+            return;
+        }
+        let mut eaten = Vec::new();
+        for (snek, direction) in self.sneks.iter_mut().zip(directions) {
+            if !snek.alive {
+                continue;
+            }
+            snek.change_direction(*direction);
+            if let SnekEvent::Ate(head) = snek.slither(&self.food) {
+                eaten.push(head);
+            }
+        }
+        self.ticks += 1;
+        self.food.retain(|food| !eaten.contains(food));
+        let (width, height) = (self.options.width, self.options.height);
+        for _ in eaten {
+            let taken = [&self.bodies()[..], &self.food].concat();
+            if taken.len() < (width * height) as usize {
+                let food = world::rand_point(&mut self.rng, width, height, &taken, None);
+                self.food.push(food);
+            }
+        }
+        let crashed: Vec<bool> = (0..self.sneks.len())
+            .map(|index| {
+                let snek = &self.sneks[index];
+                let head = snek.body.last().unwrap();
+                let hit_other =
+                    self.sneks.iter().enumerate().any(|(other, body)| {
+                        other != index && body.alive && body.body.contains(head)
+                    });
+                snek.alive && (snek.death(width, height).is_some() || hit_other)
+            })
+            .collect();
+        for (snek, crashed) in self.sneks.iter_mut().zip(crashed) {
+            snek.alive &= !crashed;
+        }
+    }
+
+    // Returns every cell the living sneks are in
+    fn bodies(&self) -> Vec<Point> {
+        self.sneks
+            .iter()
+            .filter(|snek| snek.alive)
+            .flat_map(|snek| snek.body.iter().copied())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns a two player arena on a short board, with the second snek's head
+    // two cells right of and below the first one's
+    fn arena() -> Arena {
+        Arena::new(Options {
+            width: 9,
+            height: 3,
+            seed: 1,
+            ..Options::default()
+        })
+    }
+
+    #[test]
+    fn heads_meeting_is_a_draw() {
+        let mut arena = arena();
+        arena.advance(&[Direction::Down, Direction::Up]);
+        assert_eq!(arena.outcome(), None);
+        arena.advance(&[Direction::Right, Direction::Left]);
+        assert_eq!(arena.sneks[0].body.last(), arena.sneks[1].body.last());
+        assert_eq!(arena.outcome(), Some(ArenaOutcome::Draw));
+        assert!(!arena.running());
+    }
+
+    #[test]
+    fn last_snek_alive_wins() {
+        let mut arena = arena();
+        arena.advance(&[Direction::Up, Direction::Left]);
+        assert!(!arena.sneks[0].alive);
+        assert_eq!(arena.outcome(), Some(ArenaOutcome::Winner(1)));
+        arena.advance(&[Direction::Right, Direction::Left]);
+        assert_eq!(arena.ticks, 1);
+    }
+
+    #[test]
+    fn running_into_another_snek_kills_only_the_runner() {
+        let mut arena = arena();
+        arena.advance(&[Direction::Down, Direction::Left]);
+        arena.advance(&[Direction::Right, Direction::Left]);
+        arena.advance(&[Direction::Down, Direction::Left]);
+        assert!(arena.sneks[1].alive);
+        assert_eq!(arena.outcome(), Some(ArenaOutcome::Winner(1)));
+    }
+}
@@ -0,0 +1,95 @@
+// Sound effects played through the audio device (`--sound effects`, with the
+// `audio` feature). The effects are short clips built into the snek, played
+// on a thread of their own that's sent cues over a channel, so the game never
+// waits on the device. If there isn't one to play on, the thread gives up
+// and the cues sent to it are dropped.
+
+use std::io::Cursor;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use rodio::{Decoder, OutputStream, Source};
+
+use crate::sound::{Cue, SoundSink};
+
+// The clip played for each cue
+const EAT_CLIP: &[u8] = include_bytes!("../assets/sounds/eat.wav");
+const DIE_CLIP: &[u8] = include_bytes!("../assets/sounds/die.wav");
+const COUNTDOWN_CLIP: &[u8] = include_bytes!("../assets/sounds/countdown.wav");
+const HIGH_SCORE_CLIP: &[u8] = include_bytes!("../assets/sounds/high_score.wav");
+
+// Plays the clips for cues on the audio thread
+pub struct Effects {
+    sender: Sender<Cue>,
+}
+
+impl Effects {
+    // Starts the audio thread, playing the clips at a volume (in percent)
+    pub fn start(volume: u8) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let spawned = thread::Builder::new()
+            .name("audio".to_owned())
+            .spawn(move || play_cues(&receiver, f32::from(volume) / 100.0));
+        if let Err(err) = spawned {
+            log::warn!("audio thread failed to start: {err}");
+        }
+        Self { sender }
+    }
+}
+
+impl SoundSink for Effects {
+    fn play(&mut self, cue: Cue) {
+        // The thread's gone if there's no audio device, which leaves the game silent
+        let _ = self.sender.send(cue);
+    }
+
+    fn flush(&mut self) {}
+}
+
+// Returns the clip for a cue
+fn clip(cue: Cue) -> &'static [u8] {
+    match cue {
+        Cue::Ate => EAT_CLIP,
+        Cue::Died => DIE_CLIP,
+        Cue::Countdown => COUNTDOWN_CLIP,
+        Cue::HighScore => HIGH_SCORE_CLIP,
+    }
+}
+
+// Plays the clip for every cue sent until the game's over, mixed over any
+// still playing
+fn play_cues(receiver: &Receiver<Cue>, volume: f32) {
+    let (_stream, handle) = match OutputStream::try_default() {
+        Ok(output) => output,
+        Err(err) => {
+            log::warn!("no audio device to play sounds on: {err}");
+            return;
+        }
+    };
+    for cue in receiver {
+        let played = Decoder::new(Cursor::new(clip(cue)))
+            .map_err(|err| err.to_string())
+            .and_then(|source| {
+                handle
+                    .play_raw(source.convert_samples().amplify(volume))
+                    .map_err(|err| err.to_string())
+            });
+        if let Err(err) = played {
+            log::warn!("couldn't play the sound for {cue:?}: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_cue_has_a_clip_that_plays() {
+        for cue in [Cue::Ate, Cue::Died, Cue::Countdown, Cue::HighScore] {
+            let source = Decoder::new(Cursor::new(clip(cue))).unwrap();
+            assert!(source.channels() > 0, "{cue:?}");
+            assert!(source.sample_rate() > 0, "{cue:?}");
+        }
+    }
+}
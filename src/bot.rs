@@ -0,0 +1,117 @@
+// Playing the game with a bot over stdin and stdout (`snek --bot-io`).
+//
+// Before every move a JSON state line is written to stdout:
+//
+//     {"tick":0,"width":17,"height":15,"snek":[[0,0],[1,0],[2,0],[3,0]],"food":[5,7],"foods":[[5,7]],"decoys":[],"obstacles":[]}
+//
+// The snek's body goes from tail to head. `food` is the first of the `foods`
+// on the map (there's more than one in a rush), or `[]` if there's none left.
+// `decoys` has the decoy in choice mode, which shrinks the snek if it's eaten.
+// The bot answers with one line of `up`, `down`, `left`, `right`, or `none`;
+// if it doesn't answer within the timeout, the snek carries on straight (and a
+// late answer counts for the next move). When the game ends, the JSON results are written as the last line.
+// Closing stdin quits the game.
+
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::options::Options;
+use crate::render::{self, Buffer};
+use crate::world::{Direction, World};
+use crate::{GAME_PROMPT, Point};
+
+// Plays a game with moves read from stdin, drawing it to stderr unless it's
+// headless; returns the finished world and how long the game took
+pub fn run(options: Options, headless: bool, timeout: Duration) -> (World, Duration) {
+    let body = options.starting_body();
+    let mut world = World::new(options, &body);
+    let moves = read_moves();
+    let started = Instant::now();
+    let mut stdout = io::stdout().lock();
+    let mut previous: Option<Buffer> = None;
+    loop {
+        let frame_started = Instant::now();
+        let frame_time = Duration::from_secs(1) / world.fps().max(1);
+        world.check_death();
+        if !headless {
+            let (width, height) = world.options.screen_size();
+            let mut buffer = Buffer::new(width, height);
+            render::draw_world(&mut buffer, &world, GAME_PROMPT);
+            eprint!("{}", buffer.to_ansi(previous.as_ref()));
+            previous = Some(buffer);
+        }
+        if !world.running() {
+            break;
+        }
+        let state = serde_json::to_string(&State::new(&world)).unwrap_or_default();
+        if writeln!(stdout, "{state}").is_err() || stdout.flush().is_err() {
+            world.snek.alive = false;
+            break;
+        }
+        match moves.recv_timeout(timeout) {
+            Ok(Some(direction)) => world.snek.change_direction(direction),
+            Ok(None) | Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => {
+                world.snek.alive = false;
+                break;
+            }
+        }
+        world.advance();
+        if !headless {
+            thread::sleep(frame_time.saturating_sub(frame_started.elapsed()));
+        }
+    }
+    (world, started.elapsed())
+}
+
+// The state of a world, as it's written to a bot
+#[derive(Serialize)]
+pub struct State<'a> {
+    tick: u64,
+    width: u32,
+    height: u32,
+    snek: &'a [Point],
+    food: Vec<i32>,
+    foods: &'a [Point],
+    decoys: Vec<Point>,
+    obstacles: &'a [Point],
+}
+
+impl<'a> State<'a> {
+    // Returns the state of a world
+    pub fn new(world: &'a World) -> Self {
+        Self {
+            tick: world.ticks,
+            width: world.width(),
+            height: world.height(),
+            snek: &world.snek.body,
+            food: world
+                .food
+                .first()
+                .map_or(Vec::new(), |food| vec![food.x, food.y]),
+            foods: &world.food,
+            decoys: world.decoy.into_iter().collect(),
+            obstacles: &world.options.walls,
+        }
+    }
+}
+
+// Reads moves from stdin on another thread, so waiting for one can time out
+fn read_moves() -> Receiver<Option<Direction>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.send(Direction::from_name(line.trim())).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
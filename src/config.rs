@@ -0,0 +1,299 @@
+// The config file (`config.json` in the config directory), which sets defaults
+// for the options.
+//
+// File format: a JSON object holding the `version` of the format and any of
+// the options, by the same names and with the same values as in a save file.
+// When an option is renamed or changes meaning, `CONFIG_VERSION` goes up and a
+// step is added to `MIGRATIONS` that upgrades a config of the version before
+// it. Older configs are upgraded step by step when they're loaded, and then
+// rewritten in the new version, with the old file kept next to it as
+// `config.json.vVERSION`. A config from a newer snek is an error instead, since
+// reading it would quietly drop the settings this one doesn't know about.
+//
+// Configs before version 3 were `config.txt`: a `snek-config VERSION` header
+// line followed by one `key value` pair per line. One of those is read when
+// there's no `config.json` yet, upgraded, and written as `config.json`, with
+// the old file renamed to `config.txt.vVERSION`.
+//
+// Versions:
+//
+//     1  the first
+//     2  `difficulty` sets the speed too (like `--difficulty`), unless `fps` is
+//        also given; before, the speed was only ever `fps`
+//     3  JSON in `config.json`, instead of `key value` lines in `config.txt`
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+use crate::FPS;
+use crate::options::Options;
+use crate::storage;
+
+// Name of the config file inside the config directory
+pub const CONFIG_FILE: &str = "config.json";
+
+// Name of the config file before it was JSON
+pub const LEGACY_CONFIG_FILE: &str = "config.txt";
+
+// Version of the config file format this snek writes
+const CONFIG_VERSION: u32 = 3;
+
+// Last version of the config file that was written as `key value` lines
+const LEGACY_VERSION: u32 = 2;
+
+// Start of the header line of a config from before it was JSON, which is
+// followed by the version
+const LEGACY_HEADER_PREFIX: &str = "snek-config ";
+
+// The options a config sets, by name
+pub type Config = Map<String, Value>;
+
+// What's in a config file
+#[derive(Serialize, Deserialize)]
+struct ConfigFile {
+    version: u32,
+    #[serde(flatten)]
+    options: Config,
+}
+
+// Steps that each upgrade a config by one version, the first from version 1 to 2
+const MIGRATIONS: [fn(&mut Config); CONFIG_VERSION as usize - 1] =
+    [keep_difficulty_speed, written_as_json];
+
+// Loads the options a config file sets (none if there isn't one), upgrading
+// and rewriting it if it's from an older version. Without one at `path`, a
+// config from before it was JSON is looked for at `legacy`
+pub fn load(path: &Path, legacy: Option<&Path>) -> Result<Config, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return match legacy.filter(|legacy| legacy.exists()) {
+                Some(legacy) => upgrade_legacy(legacy, path),
+                None => Ok(Config::new()),
+            };
+        }
+        Err(err) => return Err(format!("couldn't read the config: {err}")),
+    };
+    let (version, mut config) = parse(&contents)?;
+    if version < CONFIG_VERSION {
+        migrate(version, &mut config);
+        let backup = path.with_extension(format!("json.v{version}"));
+        fs::copy(path, &backup)
+            .and_then(|_| storage::write_atomic(path, &serialize(&config)))
+            .map_err(|err| format!("couldn't upgrade the config: {err}"))?;
+    }
+    Ok(config)
+}
+
+// Upgrades a config from before it was JSON, writing it to `path` and
+// renaming the old one out of the way
+fn upgrade_legacy(legacy: &Path, path: &Path) -> Result<Config, String> {
+    let contents =
+        fs::read_to_string(legacy).map_err(|err| format!("couldn't read the config: {err}"))?;
+    let (version, mut config) = parse_legacy(&contents)?;
+    migrate(version, &mut config);
+    let backup = legacy.with_extension(format!("txt.v{version}"));
+    storage::write_atomic(path, &serialize(&config))
+        .and_then(|_| fs::rename(legacy, &backup))
+        .map_err(|err| format!("couldn't upgrade the config: {err}"))?;
+    Ok(config)
+}
+
+// Reads the version and options from the contents of a config file
+pub fn parse(contents: &str) -> Result<(u32, Config), String> {
+    let file: ConfigFile =
+        serde_json::from_str(contents).map_err(|err| format!("malformed config: {err}"))?;
+    check_version(file.version)?;
+    Ok((file.version, file.options))
+}
+
+// Reads the version and options from the contents of a config file from
+// before it was JSON
+pub fn parse_legacy(contents: &str) -> Result<(u32, Config), String> {
+    let mut lines = contents.lines();
+    let version = lines
+        .next()
+        .and_then(|header| header.strip_prefix(LEGACY_HEADER_PREFIX))
+        .and_then(|version| version.parse::<u32>().ok())
+        .filter(|version| (1..=LEGACY_VERSION).contains(version))
+        .ok_or("unknown config file version")?;
+    let defaults = match serde_json::to_value(Options::default()) {
+        Ok(Value::Object(defaults)) => defaults,
+        _ => Config::new(),
+    };
+    let config = lines
+        .filter_map(|line| line.split_once(' '))
+        .map(|(key, value)| (key.to_owned(), legacy_value(key, value, &defaults)))
+        .collect();
+    Ok((version, config))
+}
+
+// Makes sure a config isn't from a newer snek, or no snek at all
+fn check_version(version: u32) -> Result<(), String> {
+    if version == 0 {
+        return Err("unknown config file version".to_owned());
+    }
+    if version > CONFIG_VERSION {
+        return Err(format!(
+            "config written by a newer snek (version {version}, but this one only knows up to \
+            {CONFIG_VERSION})"
+        ));
+    }
+    Ok(())
+}
+
+// Turns the value of a `key value` line into JSON, going by what the option's
+// default looks like. Points were `X,Y` and separated by spaces, a start was
+// its head and heading, and options that can be unset were `none` when they
+// were. Anything that doesn't fit is kept as a string, to be refused when the
+// options are read
+fn legacy_value(key: &str, text: &str, defaults: &Config) -> Value {
+    match (key, defaults.get(key)) {
+        ("walls" | "food_sequence", _) => text
+            .split(' ')
+            .filter(|point| !point.is_empty())
+            .map(legacy_point)
+            .collect(),
+        ("start", _) if text != "none" => match text.split_once(' ') {
+            Some((head, heading)) => json!([legacy_point(head), heading]),
+            None => text.into(),
+        },
+        (_, Some(Value::Null)) if text == "none" => Value::Null,
+        (_, Some(Value::Bool(_))) => text.parse().map_or_else(|_| text.into(), Value::Bool),
+        (_, Some(Value::Number(_) | Value::Null)) => text
+            .parse::<u64>()
+            .map_or_else(|_| text.into(), Value::from),
+        _ => text.into(),
+    }
+}
+
+// Turns a point written as `X,Y` into JSON
+fn legacy_point(text: &str) -> Value {
+    let point = text
+        .split_once(',')
+        .and_then(|(x, y)| Some((x.parse::<i32>().ok()?, y.parse::<i32>().ok()?)));
+    match point {
+        Some((x, y)) => json!([x, y]),
+        None => text.into(),
+    }
+}
+
+// Upgrades a config from an older version to the current one
+pub fn migrate(version: u32, config: &mut Config) {
+    for step in &MIGRATIONS[version as usize - 1..] {
+        step(config);
+    }
+}
+
+// Writes a config in the format of the current version
+pub fn serialize(config: &Config) -> String {
+    let file = ConfigFile {
+        version: CONFIG_VERSION,
+        options: config.clone(),
+    };
+    serde_json::to_string_pretty(&file).unwrap_or_default() + "\n"
+}
+
+// Upgrades a version 1 config, where a difficulty didn't change the speed, so
+// it still plays at the speed it did (the default, unless it gave one)
+fn keep_difficulty_speed(config: &mut Config) {
+    if config.contains_key("difficulty") && !config.contains_key("fps") {
+        config.insert("fps".to_owned(), FPS.into());
+    }
+}
+
+// Upgrades a version 2 config, which only changed how the file's written
+// (taken care of as it's read)
+fn written_as_json(_: &mut Config) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    // A config from before difficulties set the speed
+    const V1: &str = include_str!("../tests/fixtures/config/v1.txt");
+
+    // Returns the options some JSON sets
+    fn config(json: Value) -> Config {
+        match json {
+            Value::Object(config) => config,
+            _ => panic!("{json} isn't an object"),
+        }
+    }
+
+    #[test]
+    fn version_1_keeps_its_speed() {
+        let (version, mut config) = parse_legacy(V1).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(config.get("fps"), None);
+        migrate(version, &mut config);
+        assert_eq!(config["difficulty"], "hard");
+        assert_eq!(config["fps"], 8);
+        assert_eq!(config["cell_width"], 1);
+        assert_eq!(config["presence"], false);
+        let mut given = self::config(json!({"difficulty": "easy", "fps": 20}));
+        migrate(1, &mut given);
+        assert_eq!(given.len(), 2);
+        let mut plain = self::config(json!({"cell_width": 3}));
+        migrate(1, &mut plain);
+        assert_eq!(plain.get("fps"), None);
+    }
+
+    #[test]
+    fn legacy_values_get_their_types() {
+        let lines = "snek-config 2\nwalls 1,2 3,4\nfood_sequence \nstart 5,5 up\ntarget none\n\
+            goal 3\nassist slowmo\nname none\nwidth wide\n";
+        let (_, config) = parse_legacy(lines).unwrap();
+        assert_eq!(
+            Value::Object(config),
+            json!({
+                "walls": [[1, 2], [3, 4]],
+                "food_sequence": [],
+                "start": [[5, 5], "up"],
+                "target": null,
+                "goal": 3,
+                "assist": "slowmo",
+                "name": "none",
+                "width": "wide",
+            })
+        );
+    }
+
+    #[test]
+    fn load_upgrades_and_keeps_the_old_file() {
+        let dir = env::temp_dir().join(format!("snek-config-{}", process::id()));
+        let path = dir.join(CONFIG_FILE);
+        let legacy = dir.join(LEGACY_CONFIG_FILE);
+        storage::write_atomic(&legacy, V1).unwrap();
+        let config = load(&path, Some(&legacy)).unwrap();
+        assert_eq!(config["fps"], 8);
+        assert_eq!(fs::read_to_string(dir.join("config.txt.v1")).unwrap(), V1);
+        assert!(!legacy.exists());
+        let upgraded = fs::read_to_string(&path).unwrap();
+        assert_eq!(upgraded, serialize(&config));
+        assert_eq!(parse(&upgraded).unwrap(), (CONFIG_VERSION, config.clone()));
+        // A config that's already upgraded is left as it is
+        assert_eq!(load(&path, Some(&legacy)).unwrap(), config);
+        assert_eq!(fs::read_to_string(&path).unwrap(), upgraded);
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(load(&path, Some(&legacy)).unwrap(), Config::new());
+    }
+
+    #[test]
+    fn unknown_versions_are_errors() {
+        let newer = format!("{{\"version\": {}, \"fps\": 9}}", CONFIG_VERSION + 1);
+        assert!(parse(&newer).unwrap_err().contains("newer snek"));
+        assert!(parse(r#"{"version": 0}"#).is_err());
+        assert!(parse(r#"{"fps": 9}"#).is_err());
+        assert!(parse("").is_err());
+        assert!(parse_legacy("snek-config 3\nfps 9\n").is_err());
+        assert!(parse_legacy("snek-config 0\n").is_err());
+        assert!(parse_legacy("fps 9\n").is_err());
+    }
+}
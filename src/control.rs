@@ -0,0 +1,338 @@
+// Attaching tools to a running game over a Unix socket (`--control-socket PATH`).
+//
+// Every line either way is a JSON object. Clients are sent the game's state
+// after every move and whenever it's paused or resumed, in the bot state format
+// with a few more fields:
+//
+//     {"event":"state","tick":0,"width":17,"height":15,"snek":[[0,0],[1,0],[2,0],[3,0]],"food":[5,7],"foods":[[5,7]],"decoys":[],"obstacles":[],"score":0,"paused":false}
+//
+// and `{"event":"ended","score":N}` once the game is over. They can send
+// `{"command":"pause"}`, `{"command":"resume"}`, `{"command":"state"}` (which
+// is answered with the state, to that client only), and with
+// `--control-steering`, `{"command":"direction","direction":"up"}`. Commands
+// that can't be followed are answered with `{"error":"..."}`.
+//
+// The socket is served on its own threads, joined to the game by bounded
+// channels that drop whatever doesn't fit, so a slow client can never hold up
+// a move. The socket file is removed when the game is over.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bot;
+use crate::world::{Direction, World};
+
+// Most messages that can wait in either direction before more are dropped
+const QUEUE_SIZE: usize = 64;
+
+// How long a client gets to take a line before it's dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Something a client asked the game to do
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ControlCommand {
+    Pause,
+    Resume,
+    Steer(Direction),
+    State,
+}
+
+// A line the game sends to clients
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    State {
+        #[serde(flatten)]
+        state: bot::State<'a>,
+        score: usize,
+        paused: bool,
+    },
+    Ended {
+        score: usize,
+    },
+}
+
+// A line a client sends to the game
+#[derive(Deserialize)]
+struct Request {
+    command: Option<String>,
+    direction: Option<String>,
+}
+
+// The answer to a command that can't be followed
+#[derive(Serialize)]
+struct Refusal {
+    error: &'static str,
+}
+
+// What the game (or a client's reader) tells the writer thread
+enum Outgoing {
+    Joined(usize, UnixStream),
+    Everyone(String),
+    Client(usize, String),
+    Ended(String),
+}
+
+// A control socket being served for a game
+pub struct Control {
+    path: PathBuf,
+    outgoing: SyncSender<Outgoing>,
+    commands: Receiver<(usize, ControlCommand)>,
+    writer: Option<JoinHandle<()>>,
+    last_sent: Option<(u64, bool)>,
+}
+
+impl Control {
+    // Starts serving a socket at a path (replacing a socket left behind there),
+    // only taking directions if steering is allowed
+    pub fn start(path: &Path, steering: bool) -> io::Result<Self> {
+        if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+            fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        let (outgoing, outgoing_receiver) = mpsc::sync_channel(QUEUE_SIZE);
+        let (commands_sender, commands) = mpsc::sync_channel(QUEUE_SIZE);
+        let writer = thread::spawn(move || write_clients(outgoing_receiver));
+        let accepted = outgoing.clone();
+        thread::spawn(move || accept(listener, accepted, commands_sender, steering));
+        Ok(Self {
+            path: path.to_owned(),
+            outgoing,
+            commands,
+            writer: Some(writer),
+            last_sent: None,
+        })
+    }
+
+    // Returns the next command a client sent and the client it came from, if there is one
+    pub fn command(&self) -> Option<(usize, ControlCommand)> {
+        self.commands.try_recv().ok()
+    }
+
+    // Sends the game's state to every client, if it's changed since it was last sent
+    pub fn update(&mut self, world: &World, paused: bool) {
+        if self.last_sent == Some((world.ticks, paused)) {
+            return;
+        }
+        self.last_sent = Some((world.ticks, paused));
+        let _ = self
+            .outgoing
+            .try_send(Outgoing::Everyone(state(world, paused)));
+    }
+
+    // Sends the game's state to one client
+    pub fn reply(&self, client: usize, world: &World, paused: bool) {
+        let _ = self
+            .outgoing
+            .try_send(Outgoing::Client(client, state(world, paused)));
+    }
+
+    // Tells every client the game is over and waits for them to be told
+    pub fn finish(mut self, score: usize) {
+        let ended = serde_json::to_string(&Event::Ended { score }).unwrap_or_default();
+        if self.outgoing.send(Outgoing::Ended(ended)).is_ok()
+            && let Some(writer) = self.writer.take()
+        {
+            let _ = writer.join();
+        }
+    }
+}
+
+impl Drop for Control {
+    // Removes the socket file, so it isn't left behind
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Returns the state line of a game
+fn state(world: &World, paused: bool) -> String {
+    let state = Event::State {
+        state: bot::State::new(world),
+        score: world.score(),
+        paused,
+    };
+    serde_json::to_string(&state).unwrap_or_default()
+}
+
+// Takes new clients, reading each one's commands on its own thread
+fn accept(
+    listener: UnixListener,
+    outgoing: SyncSender<Outgoing>,
+    commands: SyncSender<(usize, ControlCommand)>,
+    steering: bool,
+) {
+    for (client, stream) in listener.incoming().enumerate() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let Ok(writer) = stream.try_clone() else {
+            continue;
+        };
+        if outgoing.send(Outgoing::Joined(client, writer)).is_err() {
+            return;
+        }
+        let (outgoing, commands) = (outgoing.clone(), commands.clone());
+        thread::spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else {
+                    return;
+                };
+                match parse_command(&line, steering) {
+                    Ok(command) => {
+                        let _ = commands.try_send((client, command));
+                    }
+                    Err(err) => {
+                        let error = serde_json::to_string(&Refusal { error: err });
+                        let error = error.unwrap_or_default();
+                        let _ = outgoing.try_send(Outgoing::Client(client, error));
+                    }
+                }
+            }
+        });
+    }
+}
+
+// Reads a command sent by a client
+fn parse_command(line: &str, steering: bool) -> Result<ControlCommand, &'static str> {
+    let request: Request = serde_json::from_str(line).map_err(|_| "malformed JSON")?;
+    match request.command.as_deref() {
+        Some("pause") => Ok(ControlCommand::Pause),
+        Some("resume") => Ok(ControlCommand::Resume),
+        Some("state") => Ok(ControlCommand::State),
+        Some("direction") if !steering => Err("steering is turned off"),
+        Some("direction") => request
+            .direction
+            .as_deref()
+            .and_then(Direction::from_name)
+            .map(ControlCommand::Steer)
+            .ok_or("unknown direction"),
+        _ => Err("unknown command"),
+    }
+}
+
+// Writes lines to the clients until the game is over, dropping any that hang
+// up or don't keep up
+fn write_clients(outgoing: Receiver<Outgoing>) {
+    let mut clients: Vec<(usize, UnixStream)> = Vec::new();
+    for message in outgoing {
+        match message {
+            Outgoing::Joined(client, stream) => {
+                if stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_ok() {
+                    clients.push((client, stream));
+                }
+            }
+            Outgoing::Everyone(line) => clients.retain(|(_, stream)| send(stream, &line)),
+            Outgoing::Client(client, line) => {
+                clients.retain(|(id, stream)| *id != client || send(stream, &line));
+            }
+            Outgoing::Ended(line) => {
+                clients.retain(|(_, stream)| send(stream, &line));
+                return;
+            }
+        }
+    }
+}
+
+// Writes a line to a client, returning whether it's still there
+fn send(mut stream: &UnixStream, line: &str) -> bool {
+    writeln!(stream, "{line}").is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::time::Instant;
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::STARTING_BODY;
+    use crate::options::Options;
+
+    // Waits for the next command a client sent
+    fn next_command(control: &Control) -> (usize, ControlCommand) {
+        let started = Instant::now();
+        loop {
+            if let Some(command) = control.command() {
+                return command;
+            }
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "no command came"
+            );
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn clients_are_answered_over_the_socket() {
+        let path = env::temp_dir().join(format!("snek-control-{}.sock", std::process::id()));
+        let mut control = Control::start(&path, true).unwrap();
+        let mut client = UnixStream::connect(&path).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut lines = BufReader::new(client.try_clone().unwrap()).lines();
+        let mut read = || lines.next().unwrap().unwrap();
+
+        writeln!(client, r#"{{"command":"state"}}"#).unwrap();
+        let (id, command) = next_command(&control);
+        assert_eq!(command, ControlCommand::State);
+        let mut world = World::new(Options::default(), &STARTING_BODY);
+        control.reply(id, &world, true);
+        let state: Value = serde_json::from_str(&read()).unwrap();
+        assert_eq!(state["event"], "state");
+        assert_eq!(state["paused"], true);
+        assert_eq!(state["snek"][0], serde_json::json!([0, 0]));
+
+        writeln!(client, r#"{{"command":"direction","direction":"down"}}"#).unwrap();
+        writeln!(client, r#"{{"command":"pause"}}"#).unwrap();
+        assert_eq!(
+            next_command(&control).1,
+            ControlCommand::Steer(Direction::Down)
+        );
+        assert_eq!(next_command(&control).1, ControlCommand::Pause);
+        writeln!(client, "not json").unwrap();
+        assert_eq!(read(), r#"{"error":"malformed JSON"}"#);
+
+        world.advance();
+        // The same state isn't sent twice
+        control.update(&world, false);
+        control.update(&world, false);
+        let state: Value = serde_json::from_str(&read()).unwrap();
+        assert_eq!(state["tick"], 1);
+
+        control.finish(0);
+        assert_eq!(read(), r#"{"event":"ended","score":0}"#);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn commands_are_checked() {
+        assert_eq!(
+            parse_command(r#"{"command":"resume"}"#, false),
+            Ok(ControlCommand::Resume)
+        );
+        assert_eq!(
+            parse_command(r#"{"command":"direction","direction":"up"}"#, false),
+            Err("steering is turned off")
+        );
+        assert_eq!(
+            parse_command(r#"{"command":"direction","direction":"sideways"}"#, true),
+            Err("unknown direction")
+        );
+        assert_eq!(
+            parse_command(r#"{"command":"jump"}"#, true),
+            Err("unknown command")
+        );
+    }
+}
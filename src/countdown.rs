@@ -0,0 +1,114 @@
+use std::time::{Duration, Instant};
+
+// Counts down how long the game over screen stays up, unless a key press
+// stops it first (leaving the screen up until the player's done with it)
+pub struct Countdown {
+    timeout: Duration,
+    shown: Instant,
+    stopped: bool,
+}
+
+impl Countdown {
+    // Starts counting down from some time
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            shown: Instant::now(),
+            stopped: false,
+        }
+    }
+
+    // Stops the countdown, so it never runs out
+    pub fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    // Returns whether the countdown's run out
+    pub fn done(&self) -> bool {
+        self.done_after(self.shown.elapsed())
+    }
+
+    // Returns how many seconds are left (counting part of one as a whole
+    // one), or nothing if it's been stopped
+    pub fn seconds_left(&self) -> Option<u64> {
+        self.seconds_left_after(self.shown.elapsed())
+    }
+
+    // Returns whether the countdown's run out some time after it started
+    fn done_after(&self, elapsed: Duration) -> bool {
+        !self.stopped && elapsed >= self.timeout
+    }
+
+    // Returns how many seconds are left some time after it started
+    fn seconds_left_after(&self, elapsed: Duration) -> Option<u64> {
+        let left = self.timeout.saturating_sub(elapsed);
+        (!self.stopped).then(|| left.as_secs() + u64::from(left.subsec_nanos() > 0))
+    }
+}
+
+// Counts down the seconds before a resumed game carries on, in frames so it
+// keeps time with the game (and can be tested without waiting)
+pub struct ResumeCountdown {
+    frames_left: u32,
+    frames_per_second: u32,
+}
+
+impl ResumeCountdown {
+    // Starts counting down some seconds at some frames a second
+    pub fn new(seconds: u32, frames_per_second: u32) -> Self {
+        Self {
+            frames_left: seconds * frames_per_second,
+            frames_per_second,
+        }
+    }
+
+    // Counts a frame, returning whether the countdown's still going (so the
+    // game should still be held)
+    pub fn frame(&mut self) -> bool {
+        self.frames_left = self.frames_left.saturating_sub(1);
+        self.frames_left > 0
+    }
+
+    // Returns how many seconds are left, counting part of one as a whole one
+    pub fn seconds_left(&self) -> u32 {
+        self.frames_left.div_ceil(self.frames_per_second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_out_after_the_timeout() {
+        let countdown = Countdown::new(Duration::from_secs(3));
+        let left = |millis| countdown.seconds_left_after(Duration::from_millis(millis));
+        assert_eq!(
+            [left(0), left(1), left(1000), left(2500)],
+            [Some(3), Some(3), Some(2), Some(1)]
+        );
+        assert!(!countdown.done_after(Duration::from_millis(2999)));
+        assert!(countdown.done_after(Duration::from_secs(3)));
+        assert!(countdown.done_after(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn stopping_keeps_it_from_running_out() {
+        let mut countdown = Countdown::new(Duration::from_secs(3));
+        countdown.stop();
+        assert!(!countdown.done_after(Duration::from_secs(60)));
+        assert_eq!(countdown.seconds_left_after(Duration::from_secs(1)), None);
+        assert!(Countdown::new(Duration::ZERO).done_after(Duration::ZERO));
+    }
+
+    #[test]
+    fn resuming_counts_down_every_second() {
+        let mut countdown = ResumeCountdown::new(3, 2);
+        let mut shown = vec![countdown.seconds_left()];
+        while countdown.frame() {
+            shown.push(countdown.seconds_left());
+        }
+        assert_eq!(shown, [3, 3, 2, 2, 1, 1]);
+        assert!(!countdown.frame());
+    }
+}
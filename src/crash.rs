@@ -0,0 +1,124 @@
+// Crash reports, written when snek panics.
+//
+// The panic hook puts the terminal back the way it was (since the game leaves
+// it in raw mode on the alternate screen) and writes a report to
+// `crash-TIMESTAMP.txt` in the cache directory, with the panic, a backtrace,
+// the options in effect, and the last `RECENT_EVENTS` inputs and ticks. Those
+// are always kept in a fixed size ring buffer, so recording them never
+// allocates.
+
+use std::backtrace::Backtrace;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use console_engine::KeyCode;
+use console_engine::crossterm::{cursor, execute, terminal};
+
+use crate::input;
+use crate::options::Options;
+use crate::storage;
+
+// How many of the latest inputs and ticks go in a crash report
+const RECENT_EVENTS: usize = 50;
+
+// Something recorded for crash reports
+#[derive(Clone, Copy)]
+pub enum CrashEvent {
+    Input { tick: u64, key: KeyCode },
+    Tick { number: u64, micros: u64 },
+}
+
+// The latest events, oldest first from `next` on
+struct Recent {
+    events: [Option<CrashEvent>; RECENT_EVENTS],
+    next: usize,
+}
+
+static RECENT: Mutex<Recent> = Mutex::new(Recent {
+    events: [None; RECENT_EVENTS],
+    next: 0,
+});
+static TICK: AtomicU64 = AtomicU64::new(0);
+static OPTIONS: Mutex<Option<String>> = Mutex::new(None);
+
+// Installs the panic hook, keeping the default one for printing the panic
+pub fn install_hook() {
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default(info);
+        match write_report(info) {
+            Ok(path) => eprintln!("Saved a crash report to {}", path.display()),
+            Err(err) => eprintln!("Couldn't save a crash report: {err}"),
+        }
+    }));
+}
+
+// Remembers the options of the game being played, for crash reports
+pub fn set_options(options: &Options) {
+    if let Ok(mut current) = OPTIONS.lock() {
+        *current = serde_json::to_string_pretty(options).ok();
+    }
+}
+
+// Records an input or tick, forgetting the oldest one once there are enough
+pub fn record(event: CrashEvent) {
+    if let CrashEvent::Tick { number, .. } = event {
+        TICK.store(number, Ordering::Relaxed);
+    }
+    if let Ok(mut recent) = RECENT.lock() {
+        let next = recent.next;
+        recent.events[next] = Some(event);
+        recent.next = (next + 1) % RECENT_EVENTS;
+    }
+}
+
+// Leaves raw mode and the alternate screen (and the kitty keyboard protocol),
+// if the game was running in them
+fn restore_terminal() {
+    input::restore_keyboard();
+    if terminal::is_raw_mode_enabled().unwrap_or(false) {
+        let _ = terminal::disable_raw_mode();
+        let _ = execute!(io::stdout(), terminal::LeaveAlternateScreen, cursor::Show);
+    }
+}
+
+// Writes a crash report for a panic, returning where it was written
+fn write_report(info: &PanicHookInfo) -> io::Result<PathBuf> {
+    let dir = storage::cache_dir().ok_or_else(|| io::Error::other("no cache directory"))?;
+    storage::create_dir(&dir)?;
+    let now = storage::now();
+    let path = dir.join(format!("crash-{now}.txt"));
+    let mut report = format!(
+        "snek {} crashed at {}\n\n{info}\n\ntick {}\n",
+        env!("CARGO_PKG_VERSION"),
+        storage::format_time(now),
+        TICK.load(Ordering::Relaxed)
+    );
+    // `try_lock`, in case the panic happened while one of them was held
+    if let Ok(options) = OPTIONS.try_lock()
+        && let Some(options) = options.as_ref()
+    {
+        report += &format!("\noptions:\n{options}\n");
+    }
+    if let Ok(recent) = RECENT.try_lock() {
+        report += "\nrecent events (oldest first):\n";
+        let (newer, older) = recent.events.split_at(recent.next);
+        for event in older.iter().chain(newer).flatten() {
+            let _ = match event {
+                CrashEvent::Input { tick, key } => writeln!(report, "input {key:?} at tick {tick}"),
+                CrashEvent::Tick { number, micros } => {
+                    writeln!(report, "tick {number} took {micros}us")
+                }
+            };
+        }
+    }
+    report += &format!("\nbacktrace:\n{}\n", Backtrace::force_capture());
+    fs::File::create(&path)?.write_all(report.as_bytes())?;
+    Ok(path)
+}
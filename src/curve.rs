@@ -0,0 +1,206 @@
+// Speed curves (`--ramp-curve`), which decide how much faster the snek moves
+// as the score goes up, on top of the difficulty's speed.
+//
+// A curve is written as one of:
+//
+//     flat                  never speeds up (the default)
+//     linear:RATE           a move a second faster for every 1/RATE points
+//     log                   a move a second faster each time the score doubles
+//     steps:POINTS          a quarter of the difficulty's speed faster every POINTS points
+//     table:SCORE=FPS,...   exactly FPS moves a second from SCORE on
+//
+// A table's scores have to go up and its speeds can't go down, so the snek
+// never slows as the score goes up; below its first score, the snek moves at
+// the difficulty's speed. Whatever the curve, the snek still can't move faster
+// than `--max-fps` or `INPUT_POLLS` times the difficulty's speed.
+
+use serde::{Deserialize, Serialize};
+
+// How the snek's speed goes up with the score (written in files as its name)
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum SpeedCurve {
+    #[default]
+    Flat,
+    Linear(f32),
+    Log,
+    Steps(u32),
+    Table(Vec<(usize, u32)>),
+}
+
+impl SpeedCurve {
+    // Returns how many moves a second the snek makes at a score, from the
+    // difficulty's speed
+    pub fn fps_for(&self, score: usize, base: u32) -> u32 {
+        let faster = match self {
+            SpeedCurve::Flat => 0,
+            SpeedCurve::Linear(rate) => (score as f32 * rate) as u32,
+            SpeedCurve::Log => (score + 1).ilog2(),
+            SpeedCurve::Steps(points) => {
+                let steps = u32::try_from(score / *points as usize).unwrap_or(u32::MAX);
+                steps.saturating_mul(base / 4)
+            }
+            SpeedCurve::Table(rows) => {
+                return rows
+                    .iter()
+                    .rev()
+                    .find(|(from, _)| score >= *from)
+                    .map_or(base, |(_, fps)| *fps);
+            }
+        };
+        base.saturating_add(faster)
+    }
+
+    // Returns the curve as it's written in files and on the command line
+    pub fn name(&self) -> String {
+        match self {
+            SpeedCurve::Flat => "flat".to_owned(),
+            SpeedCurve::Linear(rate) => format!("linear:{rate}"),
+            SpeedCurve::Log => "log".to_owned(),
+            SpeedCurve::Steps(points) => format!("steps:{points}"),
+            SpeedCurve::Table(rows) => {
+                let rows: Vec<String> = rows
+                    .iter()
+                    .map(|(from, fps)| format!("{from}={fps}"))
+                    .collect();
+                format!("table:{}", rows.join(","))
+            }
+        }
+    }
+
+    // Reads a curve written by `name`, checking that it makes sense
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid speed curve '{name}'");
+        let (kind, arg) = name.split_once(':').unwrap_or((name, ""));
+        let curve = match (kind, arg) {
+            ("flat", "") => SpeedCurve::Flat,
+            ("log", "") => SpeedCurve::Log,
+            ("linear", rate) => SpeedCurve::Linear(rate.parse().map_err(|_| invalid())?),
+            ("steps", points) => SpeedCurve::Steps(points.parse().map_err(|_| invalid())?),
+            ("table", rows) => SpeedCurve::Table(
+                rows.split(',')
+                    .map(|row| {
+                        let (from, fps) = row.split_once('=').ok_or_else(invalid)?;
+                        Ok((
+                            from.parse().map_err(|_| invalid())?,
+                            fps.parse().map_err(|_| invalid())?,
+                        ))
+                    })
+                    .collect::<Result<_, String>>()?,
+            ),
+            _ => return Err(invalid()),
+        };
+        curve
+            .check()
+            .map_err(|err| format!("{} ({err})", invalid()))?;
+        Ok(curve)
+    }
+
+    // Checks that the curve never slows the snek down or stops it
+    fn check(&self) -> Result<(), &'static str> {
+        match self {
+            SpeedCurve::Linear(rate) if !rate.is_finite() || *rate < 0.0 => {
+                Err("the rate has to be a number that isn't negative")
+            }
+            SpeedCurve::Steps(0) => Err("there has to be at least a point between steps"),
+            SpeedCurve::Table(rows) if rows.iter().any(|(_, fps)| *fps == 0) => {
+                Err("every speed has to be at least a move a second")
+            }
+            SpeedCurve::Table(rows) if rows.windows(2).any(|pair| pair[0].0 >= pair[1].0) => {
+                Err("the scores have to go up")
+            }
+            SpeedCurve::Table(rows) if rows.windows(2).any(|pair| pair[0].1 > pair[1].1) => {
+                Err("the speeds can't go down")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<SpeedCurve> for String {
+    fn from(curve: SpeedCurve) -> Self {
+        curve.name()
+    }
+}
+
+impl TryFrom<String> for SpeedCurve {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, String> {
+        Self::from_name(&name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns the speed of a curve at each of some scores, from a speed of 8
+    fn speeds(name: &str, scores: &[usize]) -> Vec<u32> {
+        let curve = SpeedCurve::from_name(name).unwrap();
+        scores
+            .iter()
+            .map(|score| curve.fps_for(*score, 8))
+            .collect()
+    }
+
+    #[test]
+    fn curves_speed_up_as_they_say() {
+        assert_eq!(speeds("flat", &[0, 1, 100]), [8, 8, 8]);
+        assert_eq!(speeds("linear:0.5", &[0, 1, 2, 10]), [8, 8, 9, 13]);
+        assert_eq!(
+            speeds("log", &[0, 1, 3, 6, 7, 1023]),
+            [8, 9, 10, 10, 11, 18]
+        );
+        assert_eq!(speeds("steps:5", &[0, 4, 5, 12, 15]), [8, 8, 10, 12, 14]);
+        assert_eq!(
+            speeds("table:5=10,10=12", &[0, 4, 5, 9, 10, 100]),
+            [8, 8, 10, 10, 12, 12]
+        );
+        assert_eq!(speeds("steps:1", &[usize::MAX]), [u32::MAX]);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for name in [
+            "flat",
+            "linear:0.25",
+            "log",
+            "steps:3",
+            "table:0=4,20=9,40=9",
+        ] {
+            assert_eq!(SpeedCurve::from_name(name).unwrap().name(), name);
+        }
+        assert_eq!(SpeedCurve::default(), SpeedCurve::Flat);
+        let curve = SpeedCurve::Table(vec![(0, 4), (20, 9)]);
+        assert_eq!(
+            serde_json::to_string(&curve).unwrap(),
+            r#""table:0=4,20=9""#
+        );
+        assert!(serde_json::from_str::<SpeedCurve>(r#""steps:0""#).is_err());
+    }
+
+    #[test]
+    fn curves_that_slow_down_are_refused() {
+        let bad = [
+            "linear:-1",
+            "linear:inf",
+            "linear:fast",
+            "steps:0",
+            "table:",
+            "table:5=0",
+            "table:5=10,5=12",
+            "table:10=12,5=10",
+            "table:5=12,10=10",
+            "log:2",
+            "exponential",
+        ];
+        for name in bad {
+            let err = SpeedCurve::from_name(name).unwrap_err();
+            assert!(
+                err.starts_with(&format!("invalid speed curve '{name}'")),
+                "{err}"
+            );
+        }
+    }
+}
@@ -0,0 +1,262 @@
+// The daily challenge (`snek daily`): one game a day per mode, on the default
+// board with the same food for everyone, since its seed comes from the day.
+//
+// Only the first attempt at a day's challenge in each mode counts, and it's
+// kept in a ledger in the data directory. Days are UTC days since the unix
+// epoch, so the challenge (and the streak of days played in a row) doesn't
+// depend on the time zone or daylight saving.
+//
+// File format (version 1): a `snek-daily 1` header line followed by one
+// result per line with tab separated `day`, `mode`, and `score` fields.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use fastrand::Rng;
+
+use crate::options::Mode;
+use crate::storage;
+
+// Name of the ledger file inside the data directory
+pub const DAILY_FILE: &str = "daily.tsv";
+
+// Header line (including version) of the ledger file
+const HEADER: &str = "snek-daily 1";
+
+// How many weeks the calendar shows
+const CALENDAR_WEEKS: u64 = 4;
+
+// The modes there are daily challenges in, in the order their seeds are worked
+// out by (so a new mode can't change the seeds of the ones before it)
+pub const DAILY_MODES: [Mode; 4] = [Mode::Classic, Mode::Ouroboros, Mode::Rush, Mode::Choice];
+
+// The recorded attempt at one day's challenge in one mode
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DailyResult {
+    pub day: u64,
+    pub mode: Mode,
+    pub score: usize,
+}
+
+// Every recorded attempt at the daily challenge, oldest first
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct DailyLedger {
+    results: Vec<DailyResult>,
+}
+
+impl DailyLedger {
+    // Loads the ledger from a file, which is empty if the file doesn't exist
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Loads the ledger from the data directory, ignoring any errors
+    pub fn load_default() -> Self {
+        storage::data_file(DAILY_FILE)
+            .and_then(|path| Self::load(&path).ok())
+            .unwrap_or_default()
+    }
+
+    // Saves the ledger to a file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        storage::write_atomic(path, &self.serialize())
+    }
+
+    // Reads the ledger from the contents of a ledger file, skipping malformed lines
+    pub fn parse(contents: &str) -> io::Result<Self> {
+        let mut lines = contents.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown daily file version",
+            ));
+        }
+        let results = lines
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                Some(DailyResult {
+                    day: fields.next()?.parse().ok()?,
+                    mode: Mode::from_name(fields.next()?)?,
+                    score: fields.next()?.parse().ok()?,
+                })
+            })
+            .collect();
+        Ok(Self { results })
+    }
+
+    // Writes the ledger in the ledger file format
+    pub fn serialize(&self) -> String {
+        let mut contents = format!("{HEADER}\n");
+        for result in &self.results {
+            contents += &format!("{}\t{}\t{}\n", result.day, result.mode.name(), result.score);
+        }
+        contents
+    }
+
+    // Returns the recorded attempt at a day's challenge in a mode, if there is one
+    pub fn result(&self, day: u64, mode: Mode) -> Option<&DailyResult> {
+        self.results
+            .iter()
+            .find(|result| result.day == day && result.mode == mode)
+    }
+
+    // Records an attempt, returning whether it counted (it doesn't if that
+    // day's challenge in that mode was already played)
+    pub fn record(&mut self, result: DailyResult) -> bool {
+        if self.result(result.day, result.mode).is_some() {
+            return false;
+        }
+        self.results.push(result);
+        true
+    }
+
+    // Returns how many days in a row a challenge (in any mode) has been
+    // played, up to today, or yesterday if today's hasn't been played yet
+    pub fn streak(&self, today: u64) -> u64 {
+        let played = |day: u64| self.results.iter().any(|result| result.day == day);
+        let mut day = match played(today) {
+            true => today,
+            false => today.saturating_sub(1),
+        };
+        let mut streak = 0;
+        while played(day) {
+            streak += 1;
+            let Some(before) = day.checked_sub(1) else {
+                break;
+            };
+            day = before;
+        }
+        streak
+    }
+
+    // Returns the line shown on the start screen about today's challenge in a mode
+    pub fn highlight(&self, today: u64, mode: Mode) -> String {
+        match self.result(today, mode) {
+            Some(result) => format!("DAILY: {} (STREAK {})", result.score, self.streak(today)),
+            None => "DAILY: NOT YET PLAYED".to_owned(),
+        }
+    }
+
+    // Formats the last few weeks of a mode's results as a calendar, a week to
+    // a row starting on Monday, with `.` for days that weren't played
+    pub fn format_calendar(&self, today: u64, mode: Mode) -> String {
+        let monday = today - weekday(today);
+        let start = monday.saturating_sub((CALENDAR_WEEKS - 1) * 7);
+        let mut text = format!(
+            "Daily {} results (streak {})\n\n            Mon  Tue  Wed  Thu  Fri  Sat  Sun\n",
+            mode.name(),
+            self.streak(today)
+        );
+        for week in (start..=monday).step_by(7) {
+            text += &storage::format_date(week * 86400);
+            for day in week..week + 7 {
+                let cell = match self.result(day, mode) {
+                    _ if day > today => String::new(),
+                    Some(result) => result.score.to_string(),
+                    None => ".".to_owned(),
+                };
+                text += &format!("{cell:>5}");
+            }
+            text += "\n";
+        }
+        text.trim_end().to_owned()
+    }
+}
+
+// Returns the seed of a day's challenge in a mode (one of `DAILY_MODES`)
+pub fn seed(day: u64, mode: Mode) -> u64 {
+    let mode = DAILY_MODES.iter().position(|other| *other == mode).unwrap() as u64;
+    Rng::with_seed(day.wrapping_mul(DAILY_MODES.len() as u64) + mode).u64(..)
+}
+
+// Returns the day of the week of a day since the unix epoch (0 for Monday,
+// since the epoch was a Thursday)
+fn weekday(day: u64) -> u64 {
+    (day + 3) % 7
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns a ledger with a classic game played on each of some days
+    fn played_on(days: &[u64]) -> DailyLedger {
+        let mut ledger = DailyLedger::default();
+        for &day in days {
+            ledger.record(DailyResult {
+                day,
+                mode: Mode::Classic,
+                score: 1,
+            });
+        }
+        ledger
+    }
+
+    #[test]
+    fn streak_counts_days_in_a_row() {
+        let ledger = played_on(&[10, 11, 12, 14, 15]);
+        assert_eq!(ledger.streak(15), 2);
+        // Today's challenge not being played yet doesn't break the streak
+        assert_eq!(ledger.streak(16), 2);
+        assert_eq!(ledger.streak(17), 0);
+        assert_eq!(ledger.streak(12), 3);
+        assert_eq!(ledger.streak(13), 3);
+        assert_eq!(played_on(&[0, 1]).streak(1), 2);
+        assert_eq!(played_on(&[]).streak(0), 0);
+    }
+
+    #[test]
+    fn streak_counts_any_mode_once_a_day() {
+        let mut ledger = played_on(&[20, 21]);
+        assert!(ledger.record(DailyResult {
+            day: 21,
+            mode: Mode::Rush,
+            score: 4,
+        }));
+        assert!(!ledger.record(DailyResult {
+            day: 21,
+            mode: Mode::Rush,
+            score: 9,
+        }));
+        ledger.record(DailyResult {
+            day: 22,
+            mode: Mode::Choice,
+            score: 0,
+        });
+        assert_eq!(ledger.streak(22), 3);
+        assert_eq!(ledger.result(21, Mode::Rush).unwrap().score, 4);
+    }
+
+    #[test]
+    fn streak_is_kept_over_daylight_saving_changes() {
+        // Playing at 23:30 in New York on the evenings either side of the
+        // clocks changing, 23 hours apart in the spring and 25 in the fall
+        for (before, after) in [
+            (1_710_045_000, 1_710_127_800),
+            (1_730_604_600, 1_730_694_600),
+        ] {
+            let (before, after) = (storage::day(before), storage::day(after));
+            assert_eq!(after, before + 1);
+            assert_eq!(played_on(&[before, after]).streak(after), 2);
+        }
+    }
+
+    #[test]
+    fn ledger_round_trips() {
+        let mut ledger = played_on(&[19_000, 19_001]);
+        ledger.record(DailyResult {
+            day: 19_001,
+            mode: Mode::Ouroboros,
+            score: 7,
+        });
+        assert_eq!(DailyLedger::parse(&ledger.serialize()).unwrap(), ledger);
+        let skipped = DailyLedger::parse("snek-daily 1\n5\tclassic\t1\nbroken\n6\tnope\t1\n");
+        assert_eq!(skipped.unwrap(), played_on(&[5]));
+        assert!(DailyLedger::parse("snek-daily 2\n").is_err());
+    }
+}
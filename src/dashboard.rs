@@ -0,0 +1,148 @@
+// A one screen summary of everything snek remembers between games, shown from
+// the start screen: the best score of every mode from the score table, totals
+// from the stats, and the play time and favorite mode from the history.
+
+use crate::history::History;
+use crate::options::Mode;
+use crate::scores::ScoreTable;
+use crate::stats::Stats;
+
+// What the dashboard shows, worked out from the files it's made from
+#[derive(Clone, PartialEq, Debug)]
+pub struct Dashboard {
+    pub best: Vec<(Mode, usize)>,
+    pub games_played: u64,
+    pub play_time: u64,
+    pub longest_snek: usize,
+    pub favorite_mode: Option<Mode>,
+}
+
+impl Dashboard {
+    // Sums up the stats, score table, and history
+    pub fn new(stats: &Stats, scores: &ScoreTable, history: &History) -> Self {
+        let best = Mode::all()
+            .iter()
+            .filter_map(|&mode| Some((mode, scores.mode_entries(mode).next()?.score)))
+            .collect();
+        // Ties go to whichever mode comes first
+        let favorite_mode = Mode::all()
+            .iter()
+            .map(|&mode| {
+                let games = history
+                    .entries()
+                    .iter()
+                    .filter(|entry| entry.summary.mode == mode);
+                (mode, games.count())
+            })
+            .filter(|(_, games)| *games > 0)
+            .rev()
+            .max_by_key(|(_, games)| *games)
+            .map(|(mode, _)| mode);
+        Self {
+            best,
+            games_played: stats.games_played,
+            play_time: history
+                .entries()
+                .iter()
+                .map(|entry| entry.summary.duration)
+                .sum(),
+            longest_snek: stats.longest_snek,
+            favorite_mode,
+        }
+    }
+
+    // Loads the files from the data directory and sums them up
+    pub fn load_default() -> Self {
+        Self::new(
+            &Stats::load_default(),
+            &ScoreTable::load_default(),
+            &History::load_default(),
+        )
+    }
+
+    // Returns the lines of the panel, with labels on the left and values on the right
+    pub fn lines(&self) -> Vec<String> {
+        if self.games_played == 0 && self.best.is_empty() {
+            return vec!["NO GAMES YET".to_owned()];
+        }
+        let time = self.play_time;
+        let favorite = self.favorite_mode.map_or("-", |mode| mode.name());
+        let mut rows = vec![
+            ("GAMES".to_owned(), self.games_played.to_string()),
+            (
+                "PLAY TIME".to_owned(),
+                format!("{}:{:02}:{:02}", time / 3600, time / 60 % 60, time % 60),
+            ),
+            ("LONGEST".to_owned(), self.longest_snek.to_string()),
+            ("FAVORITE".to_owned(), favorite.to_uppercase()),
+        ];
+        for (mode, score) in &self.best {
+            rows.push((
+                format!("BEST {}", mode.name().to_uppercase()),
+                score.to_string(),
+            ));
+        }
+        rows.iter()
+            .map(|(label, value)| format!("{label:<15}{value:>10}"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::HistoryEntry;
+    use crate::options::Options;
+    use crate::summary::GameSummary;
+
+    // Returns the history entry of a game in a mode that took some seconds
+    fn played(mode: Mode, duration: u64) -> HistoryEntry {
+        let summary = GameSummary {
+            mode,
+            duration,
+            ..GameSummary::example()
+        };
+        HistoryEntry::new(&summary, &Options::default())
+    }
+
+    #[test]
+    fn totals_come_from_every_file() {
+        let stats = Stats::parse("snek-stats 1\ngames_played 4\nlongest_snek 23\n").unwrap();
+        let scores = ScoreTable::parse(
+            "snek-scores 1\n\
+            ann\t12\tclassic\t17\t15\t1\t1700000000\t60\n\
+            bob\t30\tclassic\t17\t15\t2\t1700000100\t90\n\
+            cat\t7\trush\t17\t15\t3\t1700000200\t30\n",
+        )
+        .unwrap();
+        let history = History::from_entries(vec![
+            played(Mode::Classic, 60),
+            played(Mode::Rush, 30),
+            played(Mode::Rush, 3_600),
+            played(Mode::Classic, 90),
+        ]);
+        let dashboard = Dashboard::new(&stats, &scores, &history);
+        assert_eq!(dashboard.best, [(Mode::Classic, 30), (Mode::Rush, 7)]);
+        assert_eq!(dashboard.games_played, 4);
+        assert_eq!(dashboard.play_time, 3_780);
+        assert_eq!(dashboard.longest_snek, 23);
+        // Ties go to the mode that comes first
+        assert_eq!(dashboard.favorite_mode, Some(Mode::Classic));
+        assert_eq!(
+            dashboard.lines()[1],
+            format!("{:<15}{:>10}", "PLAY TIME", "1:03:00")
+        );
+    }
+
+    #[test]
+    fn nothing_played_yet_is_shown_as_such() {
+        let dashboard = Dashboard::new(
+            &Stats::default(),
+            &ScoreTable::default(),
+            &History::default(),
+        );
+        assert_eq!(dashboard.best, []);
+        assert_eq!(dashboard.favorite_mode, None);
+        assert_eq!(dashboard.lines(), ["NO GAMES YET"]);
+    }
+}
@@ -0,0 +1,153 @@
+// The debug log (`--debug`), for looking into deaths that seem like they
+// shouldn't have happened.
+//
+// After every move, the game notes the tick, where the head went, the way the
+// snek was going, the turns still queued up for the moves after it, and what
+// the death check made of the move, keeping the last `DEBUG_TICKS` of them. If
+// the snek dies, they're written (oldest first, after the game's options) to
+// `death-TIMESTAMP.txt` in the cache directory, one move per line:
+//
+//     tick 41 head 3,7 direction up queued [Left] death wall
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io;
+use std::path::PathBuf;
+
+use console_engine::KeyCode;
+
+use crate::Point;
+use crate::storage;
+use crate::world::{DeathCause, Direction, World};
+
+// How many of the latest moves go in the log
+const DEBUG_TICKS: usize = 100;
+
+// What the game made of one move
+struct TickRecord {
+    tick: u64,
+    head: Point,
+    direction: Direction,
+    queued: Vec<KeyCode>,
+    death: Option<DeathCause>,
+}
+
+// The latest moves of a game, oldest first
+#[derive(Default)]
+pub struct DebugLog {
+    ticks: VecDeque<TickRecord>,
+}
+
+impl DebugLog {
+    // Records the move just made in a world, with the turns still queued,
+    // forgetting the oldest move once there are enough
+    pub fn record(&mut self, world: &World, queued: &VecDeque<KeyCode>) {
+        if self.ticks.len() == DEBUG_TICKS {
+            self.ticks.pop_front();
+        }
+        self.ticks.push_back(TickRecord {
+            tick: world.ticks,
+            head: *world.snek.body.last().unwrap(),
+            direction: world.snek.direction,
+            queued: queued.iter().copied().collect(),
+            death: world.death(),
+        });
+    }
+
+    // Writes the log of a game that's over to the cache directory, returning
+    // where it was written
+    pub fn write(&self, world: &World) -> io::Result<PathBuf> {
+        let dir = storage::cache_dir().ok_or_else(|| io::Error::other("no cache directory"))?;
+        storage::create_dir(&dir)?;
+        let now = storage::now();
+        let path = dir.join(format!("death-{now}.txt"));
+        storage::write_atomic(&path, &self.format(world, now))?;
+        Ok(path)
+    }
+
+    // Formats the log of a game that ended at a time
+    fn format(&self, world: &World, now: u64) -> String {
+        let mut log = format!(
+            "snek {} died on tick {} at {}\n\noptions:\n{}\n\nmoves (oldest first):\n",
+            env!("CARGO_PKG_VERSION"),
+            world.ticks,
+            storage::format_time(now),
+            serde_json::to_string_pretty(&world.options).unwrap_or_default()
+        );
+        for record in &self.ticks {
+            let _ = writeln!(
+                log,
+                "tick {} head {},{} direction {} queued {:?} death {}",
+                record.tick,
+                record.head.x,
+                record.head.y,
+                record.direction.name(),
+                record.queued,
+                record.death.map_or("none", |cause| cause.name())
+            );
+        }
+        log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::Options;
+    use crate::{STARTING_BODY, UP_KEY};
+
+    #[test]
+    fn log_keeps_the_last_moves() {
+        let mut world = World::new(
+            Options {
+                seed: 1,
+                ..Options::default()
+            },
+            &STARTING_BODY,
+        );
+        world.food.clear();
+        let mut log = DebugLog::default();
+        let mut heads = Vec::new();
+        // Round and round a square, turning every few moves
+        for _ in 0..150 {
+            if world.ticks.is_multiple_of(3) {
+                let turned = world.snek.heading.rotate_right();
+                world.snek.change_direction(turned);
+            }
+            world.advance();
+            assert_eq!(world.check_death(), None);
+            log.record(&world, &VecDeque::new());
+            heads.push(*world.snek.body.last().unwrap());
+        }
+        // Then off the top of the board, with a turn still queued
+        world.snek.change_direction(Direction::Up);
+        while world.running() {
+            world.advance();
+            world.check_death();
+            log.record(&world, &VecDeque::from([UP_KEY]));
+            heads.push(*world.snek.body.last().unwrap());
+        }
+        assert_eq!(log.ticks.len(), DEBUG_TICKS);
+        let first = world.ticks - DEBUG_TICKS as u64 + 1;
+        let ticks: Vec<u64> = log.ticks.iter().map(|record| record.tick).collect();
+        assert_eq!(ticks, (first..=world.ticks).collect::<Vec<u64>>());
+        let logged: Vec<Point> = log.ticks.iter().map(|record| record.head).collect();
+        assert_eq!(logged, heads[heads.len() - DEBUG_TICKS..]);
+        let text = log.format(&world, 0);
+        assert!(text.contains(&format!("died on tick {} at 1970-01-01", world.ticks)));
+        let moves: Vec<&str> = text
+            .lines()
+            .skip_while(|line| !line.starts_with("moves"))
+            .collect();
+        assert_eq!(moves.len(), DEBUG_TICKS + 1);
+        let head = heads.last().unwrap();
+        assert_eq!(
+            *moves.last().unwrap(),
+            format!(
+                "tick {} head {},{} direction up queued [Up] death wall",
+                world.ticks, head.x, head.y
+            )
+        );
+        assert!(moves[DEBUG_TICKS - 1].ends_with("death none"));
+    }
+}
@@ -0,0 +1,386 @@
+// The level editor (`snek edit FILE`), which draws a level's walls and where
+// the snek starts with a cursor on the map, and saves it in the level file
+// format. A file that doesn't exist yet starts as an empty board named after
+// it. Whatever's on the board is always a valid level: walls can't go where
+// the snek starts, the snek can't be put where it doesn't fit, and the board
+// can't be shrunk out from under it.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use console_engine::pixel;
+use console_engine::{ConsoleEngine, KeyCode};
+
+use crate::input;
+use crate::level::Level;
+use crate::options::Options;
+use crate::render;
+use crate::storage;
+use crate::world::{self, Direction, Turn};
+use crate::{
+    BANNER_SECONDS, CURSOR_CHAR, DOWN_KEY, EDIT_PROMPT, HEIGHT, HINT_COLOR, INPUT_POLLS, LEFT_KEY,
+    MAP_COLOR, MIN_BOARD, NARROWER_KEY, NO_ROOM_PROMPT, PLAY_KEY, Point, QUIT_KEY, RIGHT_KEY,
+    SAVE_FAILED_PROMPT, SAVE_KEY, SAVED_PROMPT, SHORTER_KEY, SNEK_COLOR, SNEK_KEY,
+    SNEK_STARTS_PROMPT, TALLER_KEY, TOO_BIG_PROMPT, UNSAVED_PROMPT, UP_KEY, WALL_COLOR, WALL_KEY,
+    WIDER_KEY, WIDTH,
+};
+
+// Why the editor stopped
+pub enum EditorExit {
+    Quit,
+    TestPlay,
+}
+
+// What `run` does after a frame
+enum Step {
+    Stay,
+    Resize,
+    Exit(EditorExit),
+}
+
+// A level being edited, and the file it's saved to
+pub struct Editor {
+    path: PathBuf,
+    level: Level,
+    saved: Level,
+    cursor: Point,
+    banner: Option<(&'static str, u32)>,
+    confirming_quit: bool,
+}
+
+impl Editor {
+    // Opens a level file to edit, or starts an empty level if it doesn't exist
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let level = match path.exists() {
+            true => Level::load(path)?,
+            false => Level {
+                name: path.file_stem().map_or("level".to_owned(), |stem| {
+                    stem.to_string_lossy().into_owned()
+                }),
+                width: WIDTH,
+                height: HEIGHT,
+                walls: Vec::new(),
+                start: None,
+                food: Vec::new(),
+            },
+        };
+        Ok(Self {
+            path: path.to_owned(),
+            saved: level.clone(),
+            level,
+            cursor: Point::new(0, 0),
+            banner: None,
+            confirming_quit: false,
+        })
+    }
+
+    // Returns the level as it is in the editor
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
+
+    // Shows the editor (on an engine the size of the level with some options)
+    // until the player quits or wants to test play the level
+    pub fn run(&mut self, options: &Options) -> io::Result<EditorExit> {
+        // Resizing the board changes the size of the screen, so the engine is
+        // made again for it
+        loop {
+            let mut options = options.clone();
+            self.level.apply(&mut options);
+            let (width, height) = options.screen_size();
+            let mut engine = ConsoleEngine::init(width, height, options.fps * INPUT_POLLS)?;
+            engine.set_title("SNEK");
+            loop {
+                self.draw(&mut engine, &options);
+                engine.draw();
+                engine.clear_screen();
+                engine.wait_frame();
+                match self.input(&engine, &options) {
+                    Step::Stay => (),
+                    Step::Resize => break,
+                    Step::Exit(exit) => return Ok(exit),
+                }
+            }
+        }
+    }
+
+    // Draws the level, where the snek starts, and the cursor
+    fn draw(&mut self, engine: &mut ConsoleEngine, options: &Options) {
+        render::draw_map(engine, options);
+        render::draw_walls(engine, options);
+        render::draw_start(engine, self.level.start, options);
+        let background = if self.level.walls.contains(&self.cursor) {
+            WALL_COLOR
+        } else if self.starting_body().contains(&self.cursor) {
+            SNEK_COLOR
+        } else {
+            MAP_COLOR
+        };
+        let pixel = pixel::pxl_fbg(CURSOR_CHAR, HINT_COLOR, background);
+        render::set_cell(engine, self.cursor, options, pixel);
+
+        let prompt = match self.banner {
+            Some((banner, frames)) => {
+                self.banner = (frames > 1).then_some((banner, frames - 1));
+                banner
+            }
+            None => EDIT_PROMPT,
+        };
+        render::draw_prompt(engine, prompt, 0);
+        let mut size = format!("{}x{}", self.level.width, self.level.height);
+        if self.unsaved() {
+            size.push('*');
+        }
+        render::draw_prompt(engine, &size, engine.get_height() as i32 - 1);
+    }
+
+    // Deals with the keys pressed this frame
+    fn input(&mut self, engine: &ConsoleEngine, options: &Options) -> Step {
+        let pressed = |key: KeyCode| input::pressed(engine, key);
+        if pressed(QUIT_KEY) {
+            if !self.unsaved() || self.confirming_quit {
+                return Step::Exit(EditorExit::Quit);
+            }
+            self.confirming_quit = true;
+            self.show_banner(UNSAVED_PROMPT, options);
+            return Step::Stay;
+        }
+        let keys = [
+            UP_KEY,
+            DOWN_KEY,
+            LEFT_KEY,
+            RIGHT_KEY,
+            WALL_KEY,
+            SNEK_KEY,
+            SAVE_KEY,
+            PLAY_KEY,
+            WIDER_KEY,
+            NARROWER_KEY,
+            TALLER_KEY,
+            SHORTER_KEY,
+        ];
+        if keys.iter().any(|key| pressed(*key)) {
+            self.confirming_quit = false;
+        }
+
+        for (key, direction) in [
+            (UP_KEY, Direction::Up),
+            (DOWN_KEY, Direction::Down),
+            (LEFT_KEY, Direction::Left),
+            (RIGHT_KEY, Direction::Right),
+        ] {
+            let moved = self.cursor + direction.to_vector();
+            if pressed(key) && world::in_bounds(moved, self.level.width, self.level.height) {
+                self.cursor = moved;
+            }
+        }
+        if pressed(WALL_KEY) {
+            self.toggle_wall(options);
+        } else if pressed(SNEK_KEY) {
+            self.place_snek(options);
+        } else if pressed(SAVE_KEY) {
+            self.save(options);
+        } else if pressed(PLAY_KEY) {
+            return Step::Exit(EditorExit::TestPlay);
+        }
+
+        let resizes = [
+            (WIDER_KEY, 1, 0),
+            (NARROWER_KEY, -1, 0),
+            (TALLER_KEY, 0, 1),
+            (SHORTER_KEY, 0, -1),
+        ];
+        for (key, columns, rows) in resizes {
+            if pressed(key) && self.resize(columns, rows, options) {
+                return Step::Resize;
+            }
+        }
+        Step::Stay
+    }
+
+    // Returns whether the level's changed since it was last saved (going by
+    // the file it'd write, so walls put back where they were don't count)
+    fn unsaved(&self) -> bool {
+        self.level.serialize() != self.saved.serialize()
+    }
+
+    // Returns the cells the snek starts in
+    fn starting_body(&self) -> Vec<Point> {
+        world::starting_body(self.level.start)
+    }
+
+    // Turns the cell under the cursor into a wall (where food can't appear),
+    // or back into an open cell
+    fn toggle_wall(&mut self, options: &Options) {
+        if let Some(index) = self
+            .level
+            .walls
+            .iter()
+            .position(|wall| *wall == self.cursor)
+        {
+            self.level.walls.remove(index);
+        } else if self.starting_body().contains(&self.cursor) {
+            self.show_banner(SNEK_STARTS_PROMPT, options);
+        } else {
+            self.level.walls.push(self.cursor);
+            self.level.food.retain(|food| *food != self.cursor);
+        }
+    }
+
+    // Puts the snek's head under the cursor, or turns it a quarter turn
+    // clockwise if it's already there, as long as it still fits
+    fn place_snek(&mut self, options: &Options) {
+        let (head, heading) = self
+            .level
+            .start
+            .unwrap_or((*self.starting_body().last().unwrap(), Direction::Right));
+        let start = match head == self.cursor {
+            true => (head, heading.turn(Turn::Right)),
+            false => (self.cursor, heading),
+        };
+        let previous = self.level.start.replace(start);
+        if self.level.check_start().is_err() {
+            self.level.start = previous;
+            self.show_banner(NO_ROOM_PROMPT, options);
+        }
+    }
+
+    // Grows or shrinks the board by some columns and rows, dropping walls and
+    // food that end up off of it; returns whether it changed
+    fn resize(&mut self, columns: i32, rows: i32, options: &Options) -> bool {
+        let width = self.level.width.saturating_add_signed(columns);
+        let height = self.level.height.saturating_add_signed(rows);
+        if width < MIN_BOARD || height < MIN_BOARD {
+            return false;
+        }
+        let mut resized = self.level.clone();
+        resized.width = width;
+        resized.height = height;
+        resized
+            .walls
+            .retain(|wall| world::in_bounds(*wall, width, height));
+        resized
+            .food
+            .retain(|food| world::in_bounds(*food, width, height));
+        let mut resized_options = options.clone();
+        resized.apply(&mut resized_options);
+        if resized.check_start().is_err() {
+            self.show_banner(NO_ROOM_PROMPT, options);
+            return false;
+        } else if resized_options.check_fits().is_err() {
+            self.show_banner(TOO_BIG_PROMPT, options);
+            return false;
+        }
+        self.level = resized;
+        self.cursor = self
+            .cursor
+            .min(Point::new(width as i32 - 1, height as i32 - 1));
+        true
+    }
+
+    // Saves the level to its file
+    fn save(&mut self, options: &Options) {
+        match storage::write_atomic(&self.path, &self.level.serialize()) {
+            Ok(()) => {
+                self.saved = self.level.clone();
+                self.show_banner(SAVED_PROMPT, options);
+            }
+            Err(err) => {
+                log::warn!("couldn't save the level to {}: {err}", self.path.display());
+                self.show_banner(SAVE_FAILED_PROMPT, options);
+            }
+        }
+    }
+
+    // Shows a banner in place of the prompt for a couple of seconds
+    fn show_banner(&mut self, banner: &'static str, options: &Options) {
+        let frames = options.fps * INPUT_POLLS * BANNER_SECONDS;
+        self.banner = Some((banner, frames));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn edits_round_trip_through_the_file() {
+        let dir = env::temp_dir().join(format!("snek-editor-{}", process::id()));
+        let path = dir.join("maze.txt");
+        let options = Options::default();
+        let mut editor = Editor::open(&path).unwrap();
+        assert_eq!(editor.level().name, "maze");
+        assert_eq!(
+            (editor.level().width, editor.level().height),
+            (WIDTH, HEIGHT)
+        );
+        assert!(!editor.unsaved());
+
+        editor.cursor = Point::new(5, 10);
+        editor.toggle_wall(&options);
+        editor.cursor = Point::new(8, 5);
+        editor.place_snek(&options);
+        editor.place_snek(&options);
+        assert!(editor.unsaved());
+        editor.save(&options);
+        assert!(!editor.unsaved());
+
+        let reopened = Editor::open(&path).unwrap();
+        assert_eq!(reopened.level(), editor.level());
+        assert_eq!(reopened.level().walls, [Point::new(5, 10)]);
+        assert_eq!(
+            reopened.level().start,
+            Some((Point::new(8, 5), Direction::Down))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn putting_a_wall_back_isnt_a_change() {
+        let options = Options::default();
+        let mut editor = Editor::open(Path::new("unsaved.txt")).unwrap();
+        editor.cursor = Point::new(3, 3);
+        editor.toggle_wall(&options);
+        assert!(editor.unsaved());
+        editor.toggle_wall(&options);
+        assert!(!editor.unsaved());
+    }
+
+    #[test]
+    fn the_snek_is_kept_playable() {
+        let options = Options::default();
+        let mut editor = Editor::open(Path::new("unsaved.txt")).unwrap();
+        // Walls can't go under the snek
+        editor.toggle_wall(&options);
+        assert!(editor.level().walls.is_empty());
+        assert_eq!(editor.banner.unwrap().0, SNEK_STARTS_PROMPT);
+        // The snek can't be put where its body would hang off the board
+        editor.cursor = Point::new(1, 0);
+        editor.place_snek(&options);
+        assert_eq!(editor.level().start, None);
+        assert_eq!(editor.banner.unwrap().0, NO_ROOM_PROMPT);
+        // Shrinking drops walls off the edge and stops at the minimum size
+        editor.cursor = Point::new(WIDTH as i32 - 1, 0);
+        editor.toggle_wall(&options);
+        assert!(editor.resize(-1, 0, &options));
+        assert!(editor.level().walls.is_empty());
+        assert_eq!(editor.cursor, Point::new(WIDTH as i32 - 2, 0));
+        while editor.resize(0, -1, &options) {}
+        assert_eq!(editor.level().height, MIN_BOARD);
+    }
+
+    #[test]
+    fn broken_files_arent_opened() {
+        let dir = env::temp_dir().join(format!("snek-editor-broken-{}", process::id()));
+        let path = dir.join("broken.txt");
+        storage::write_atomic(&path, "snek-level 1\n").unwrap();
+        assert_eq!(
+            Editor::open(&path).err(),
+            Some("missing 'name' line".to_owned())
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
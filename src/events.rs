@@ -0,0 +1,117 @@
+use std::sync::mpsc::Sender;
+
+use crate::Point;
+
+// Something that happened to the snek during a tick
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SnekEvent {
+    Moved(Point),
+    Ate(Point),
+    Died(Point),
+}
+
+// Everything a consumer of the event channel is told about
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameEvent {
+    Snek(SnekEvent),
+    Paused,
+    Unpaused,
+    Scored { score: usize },
+    Ended { score: usize },
+}
+
+// Sends events to an optional consumer, doing nothing if there isn't one
+#[derive(Default)]
+pub struct EventSender {
+    sender: Option<Sender<GameEvent>>,
+}
+
+impl EventSender {
+    // Creates a sender that forwards events to a channel
+    pub fn new(sender: Option<Sender<GameEvent>>) -> Self {
+        Self { sender }
+    }
+
+    // Sends an event, dropping the channel if the receiver has hung up
+    pub fn send(&mut self, event: GameEvent) {
+        if let Some(sender) = &self.sender
+            && sender.send(event).is_err()
+        {
+            self.sender = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::STARTING_BODY;
+    use crate::options::Options;
+    use crate::world::World;
+
+    #[test]
+    fn ticks_are_sent_in_order() {
+        let options = Options {
+            width: 8,
+            height: 8,
+            food_sequence: vec![Point::new(5, 0), Point::new(5, 5)],
+            ..Options::default()
+        };
+        let mut world = World::new(options, &STARTING_BODY);
+        let (sender, receiver) = mpsc::channel();
+        let mut events = EventSender::new(Some(sender));
+        // Step the world the way the game loop does, checking for death
+        // before every move
+        while world.running() {
+            if let Some(event) = world.check_death() {
+                events.send(GameEvent::Snek(event));
+                break;
+            }
+            let score = world.score();
+            let event = world.advance().unwrap();
+            events.send(GameEvent::Snek(event));
+            if world.score() != score {
+                events.send(GameEvent::Scored {
+                    score: world.score(),
+                });
+            }
+        }
+        events.send(GameEvent::Ended {
+            score: world.score(),
+        });
+        drop(events);
+        let received: Vec<GameEvent> = receiver.iter().collect();
+        assert_eq!(
+            received,
+            [
+                GameEvent::Snek(SnekEvent::Moved(Point::new(4, 0))),
+                GameEvent::Snek(SnekEvent::Ate(Point::new(5, 0))),
+                GameEvent::Scored { score: 1 },
+                GameEvent::Snek(SnekEvent::Moved(Point::new(6, 0))),
+                GameEvent::Snek(SnekEvent::Moved(Point::new(7, 0))),
+                GameEvent::Snek(SnekEvent::Moved(Point::new(8, 0))),
+                GameEvent::Snek(SnekEvent::Died(Point::new(8, 0))),
+                GameEvent::Ended { score: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn hung_up_receiver_is_dropped() {
+        let (sender, receiver) = mpsc::channel();
+        let mut events = EventSender::new(Some(sender));
+        drop(receiver);
+        events.send(GameEvent::Paused);
+        assert!(events.sender.is_none());
+        events.send(GameEvent::Unpaused);
+    }
+
+    #[test]
+    fn no_sender_does_nothing() {
+        let mut events = EventSender::default();
+        events.send(GameEvent::Ended { score: 0 });
+        assert!(events.sender.is_none());
+    }
+}
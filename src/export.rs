@@ -0,0 +1,333 @@
+// Turns recorded replays into files that can be shared without snek.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::GAME_PROMPT;
+use crate::render::{self, Buffer};
+use crate::replay::Replay;
+
+// Pixels per cell of exported GIFs, unless another scale is given
+pub const DEFAULT_SCALE: u32 = 8;
+
+// The formats a replay can be exported as
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExportFormat {
+    Ansi,
+    Asciicast,
+    Gif,
+}
+
+impl ExportFormat {
+    // Finds the format with the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ansi" => Some(ExportFormat::Ansi),
+            "asciicast" => Some(ExportFormat::Asciicast),
+            "gif" => Some(ExportFormat::Gif),
+            _ => None,
+        }
+    }
+}
+
+// What to export, where to, and how
+pub struct Export {
+    pub format: ExportFormat,
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub scale: u32,
+    pub frame_skip: usize,
+}
+
+impl Export {
+    // Exports the replay in the input file to the output file
+    pub fn run(&self) -> io::Result<()> {
+        let replay = Replay::load(&self.input)?;
+        match self.format {
+            ExportFormat::Ansi => fs::write(&self.output, ansi(&replay)),
+            ExportFormat::Asciicast => fs::write(&self.output, asciicast(&replay)),
+            ExportFormat::Gif => gif(&replay, self.scale, self.frame_skip, &self.output),
+        }
+    }
+}
+
+// Writes a replay as an animated GIF
+#[cfg(feature = "gif")]
+fn gif(replay: &Replay, scale: u32, frame_skip: usize, output: &Path) -> io::Result<()> {
+    let file = io::BufWriter::new(fs::File::create(output)?);
+    animation::write(replay, scale, frame_skip, file)
+}
+
+// Explains that GIFs can't be made without the `gif` feature
+#[cfg(not(feature = "gif"))]
+fn gif(_replay: &Replay, _scale: u32, _frame_skip: usize, _output: &Path) -> io::Result<()> {
+    Err(io::Error::other(
+        "this snek was built without GIF support (rebuild it with `--features gif`)",
+    ))
+}
+
+// Plays a replay back as the escape sequences that draw each tick over the
+// last, alongside how many ticks a second the game was going at
+pub fn ansi_frames(replay: &Replay) -> Vec<(String, u32)> {
+    let (width, height) = replay.options.screen_size();
+    let mut frames = Vec::new();
+    let mut previous: Option<Buffer> = None;
+    replay.simulate(|world| {
+        let mut buffer = Buffer::new(width, height);
+        render::draw_world(&mut buffer, world, GAME_PROMPT);
+        frames.push((buffer.to_ansi(previous.as_ref()), world.fps()));
+        previous = Some(buffer);
+    });
+    frames
+}
+
+// Plays a replay back as one stream of escape sequences that can be `cat`ed,
+// leaving the cursor under the board
+pub fn ansi(replay: &Replay) -> String {
+    let (_, height) = replay.options.screen_size();
+    let frames: String = ansi_frames(replay)
+        .into_iter()
+        .map(|(output, _)| output)
+        .collect();
+    frames + &format!("\x1b[{};1H", height + 1)
+}
+
+// The first line of an asciinema recording
+#[derive(Serialize)]
+struct CastHeader {
+    version: u32,
+    width: u32,
+    height: u32,
+    title: &'static str,
+}
+
+// Plays a replay back as an asciinema (version 2) recording, with one frame per tick
+pub fn asciicast(replay: &Replay) -> String {
+    let (width, height) = replay.options.screen_size();
+    let header = CastHeader {
+        version: 2,
+        width,
+        height,
+        title: "snek",
+    };
+    let mut cast = serde_json::to_string(&header).unwrap_or_default() + "\n";
+    let mut time = 0.0;
+    for (output, fps) in ansi_frames(replay) {
+        cast += &format!(
+            "[{time:.3}, \"o\", {}]\n",
+            serde_json::to_string(&output).unwrap_or_default()
+        );
+        time += 1.0 / fps as f64;
+    }
+    cast
+}
+
+#[cfg(feature = "gif")]
+mod animation {
+    use std::borrow::Cow;
+    use std::io::{self, Write};
+
+    use console_engine::Color;
+    use gif::{Encoder, Frame, Repeat};
+
+    use crate::replay::Replay;
+    use crate::world::World;
+    use crate::{BORDER_COLOR, DECOY_COLOR, FOOD_COLOR, HEAD_COLOR, MAP_COLOR, SNEK_COLOR};
+
+    // Colors of the GIF's palette, in the order of their indices
+    const PALETTE: [Color; 6] = [
+        BORDER_COLOR,
+        MAP_COLOR,
+        FOOD_COLOR,
+        SNEK_COLOR,
+        HEAD_COLOR,
+        DECOY_COLOR,
+    ];
+    const BORDER: u8 = 0;
+    const MAP: u8 = 1;
+    const FOOD: u8 = 2;
+    const SNEK: u8 = 3;
+    const HEAD: u8 = 4;
+    const DECOY: u8 = 5;
+
+    // Plays a replay back as an animated GIF, with a one cell border around the
+    // map and one frame per `frame_skip` ticks (plus the last)
+    pub fn write(
+        replay: &Replay,
+        scale: u32,
+        frame_skip: usize,
+        out: impl Write,
+    ) -> io::Result<()> {
+        let options = &replay.options;
+        let (width, height) = ((options.width + 2) * scale, (options.height + 2) * scale);
+        let palette: Vec<u8> = PALETTE.iter().flat_map(|color| rgb(*color)).collect();
+        let mut encoder =
+            Encoder::new(out, width as u16, height as u16, &palette).map_err(io::Error::other)?;
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(io::Error::other)?;
+        for (pixels, delay) in frames(replay, scale, frame_skip) {
+            let frame = Frame {
+                width: width as u16,
+                height: height as u16,
+                delay,
+                buffer: Cow::Owned(pixels),
+                ..Frame::default()
+            };
+            encoder.write_frame(&frame).map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    // Plays a replay back as the palette indices of every frame of its GIF,
+    // alongside how long each is shown for (in hundredths of a second)
+    fn frames(replay: &Replay, scale: u32, frame_skip: usize) -> Vec<(Vec<u8>, u16)> {
+        let mut frames = Vec::new();
+        let mut ticks = 0;
+        replay.simulate(|world| {
+            if ticks % frame_skip == 0 || !world.running() || world.ticks >= replay.ticks {
+                let delay = (100 * frame_skip as u32 / world.fps()) as u16;
+                frames.push((rasterize(world, scale), delay));
+            }
+            ticks += 1;
+        });
+        frames
+    }
+
+    // Draws a world as palette indices, one square of `scale` pixels per cell
+    fn rasterize(world: &World, scale: u32) -> Vec<u8> {
+        let (columns, rows) = (world.width() + 2, world.height() + 2);
+        let mut cells = vec![BORDER; (columns * rows) as usize];
+        let mut set = |x: i32, y: i32, index: u8| {
+            if (0..world.width() as i32).contains(&x) && (0..world.height() as i32).contains(&y) {
+                cells[((y + 1) as u32 * columns + (x + 1) as u32) as usize] = index;
+            }
+        };
+        for y in 0..world.height() as i32 {
+            for x in 0..world.width() as i32 {
+                set(x, y, MAP);
+            }
+        }
+        for wall in &world.options.walls {
+            set(wall.x, wall.y, BORDER);
+        }
+        for food in &world.food {
+            set(food.x, food.y, FOOD);
+        }
+        if let Some(decoy) = world.decoy {
+            set(decoy.x, decoy.y, DECOY);
+        }
+        for part in &world.snek.body {
+            set(part.x, part.y, SNEK);
+        }
+        let head = world.snek.body.last().unwrap();
+        set(head.x, head.y, HEAD);
+        let width = columns * scale;
+        (0..rows * scale)
+            .flat_map(|y| (0..width).map(move |x| (x / scale, y / scale)))
+            .map(|(x, y)| cells[(y * columns + x) as usize])
+            .collect()
+    }
+
+    // Returns the red, green, and blue of a color as a typical terminal shows it
+    fn rgb(color: Color) -> [u8; 3] {
+        match color {
+            Color::Rgb { r, g, b } => [r, g, b],
+            Color::Black | Color::Reset => [0, 0, 0],
+            Color::DarkRed => [128, 0, 0],
+            Color::DarkGreen => [0, 128, 0],
+            Color::DarkYellow => [128, 128, 0],
+            Color::DarkBlue => [0, 0, 128],
+            Color::DarkMagenta => [128, 0, 128],
+            Color::DarkCyan => [0, 128, 128],
+            Color::Grey => [192, 192, 192],
+            Color::DarkGrey => [128, 128, 128],
+            Color::Red => [255, 0, 0],
+            Color::Green => [0, 255, 0],
+            Color::Yellow => [255, 255, 0],
+            Color::Blue => [0, 0, 255],
+            Color::Magenta => [255, 0, 255],
+            Color::Cyan => [0, 255, 255],
+            Color::White | Color::AnsiValue(_) => [255, 255, 255],
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::export::tests::replay;
+
+        #[test]
+        fn frames_are_skipped_but_the_last() {
+            assert_eq!(frames(&replay(), 1, 1).len(), 4);
+            assert_eq!(frames(&replay(), 1, 2).len(), 3);
+            assert_eq!(frames(&replay(), 1, 10).len(), 2);
+        }
+
+        #[test]
+        fn frames_are_drawn_cell_by_cell() {
+            let (pixels, delay) = frames(&replay(), 2, 1).remove(0);
+            let row = (6 + 2) * 2;
+            let pixel = |x: usize, y: usize| pixels[y * row + x];
+            assert_eq!(pixels.len(), row * (5 + 2) * 2);
+            assert_eq!(delay, 100 / 8);
+            assert_eq!(pixel(0, 0), BORDER);
+            assert_eq!([pixel(2, 2), pixel(3, 3)], [SNEK, SNEK]);
+            assert_eq!(pixel(8, 2), HEAD);
+            assert_eq!(pixel(12, 10), FOOD);
+            assert_eq!(pixel(6, 8), MAP);
+        }
+
+        #[test]
+        fn writes_a_gif() {
+            let mut gif = Vec::new();
+            write(&replay(), 2, 1, &mut gif).unwrap();
+            assert!(gif.starts_with(b"GIF89a"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point;
+    use crate::options::Options;
+
+    // Returns a replay of a snek going straight into the right wall of a tiny
+    // board in three moves
+    pub fn replay() -> Replay {
+        Replay {
+            options: Options {
+                width: 6,
+                height: 5,
+                food_sequence: vec![Point::new(5, 4)],
+                ..Options::default()
+            },
+            turns: Vec::new(),
+            ticks: 3,
+            score: 0,
+            length: Some(4),
+        }
+    }
+
+    #[test]
+    fn ansi_has_a_frame_for_every_tick() {
+        let frames = ansi_frames(&replay());
+        assert_eq!(frames.len(), 4);
+        assert!(frames.iter().all(|(_, fps)| *fps == 8));
+        // Only the first frame's drawn in full
+        assert!(
+            frames[1..]
+                .iter()
+                .all(|(frame, _)| frame.len() < frames[0].0.len())
+        );
+        let (_, height) = replay().options.screen_size();
+        assert!(ansi(&replay()).ends_with(&format!("\x1b[{};1H", height + 1)));
+        let cast = asciicast(&replay());
+        assert_eq!(cast.lines().count(), 1 + 4);
+        assert!(cast.lines().nth(2).unwrap().starts_with("[0.125, \"o\", "));
+    }
+}
@@ -0,0 +1,132 @@
+// The ghost of the best run (with `--ghost`): a faint snek that retraces the
+// best game played before in the same mode with the same seed, tick for tick,
+// to race against. It's only drawn, so it can't be eaten or run into.
+//
+// The best runs are kept as replays (see `replay`) in the `ghosts` directory
+// inside the data directory, named `MODE-SEED.snekreplay`. A game replaces the
+// one there if it scored more, or the same in fewer ticks.
+
+use std::io;
+use std::path::PathBuf;
+
+use crate::Point;
+use crate::options::Options;
+use crate::replay::Replay;
+use crate::storage;
+
+// Name of the directory the best runs are kept in, inside the data directory
+pub const GHOSTS_DIR: &str = "ghosts";
+
+// The path the snek took in a past game
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Ghost {
+    cells: Vec<Point>,
+    lengths: Vec<usize>,
+    start_len: usize,
+}
+
+impl Ghost {
+    // Plays a replay back to find where the snek was on every tick
+    pub fn from_replay(replay: &Replay) -> Self {
+        // The snek's body is always the end of the path its head has taken
+        // (from its starting body on), so only that and its length are kept
+        let start = replay.options.starting_body();
+        let mut ghost = Self {
+            start_len: start.len(),
+            cells: start,
+            lengths: Vec::new(),
+        };
+        replay.simulate(|world| {
+            let head = *world.snek.body.last().unwrap();
+            if world.ticks > 0 {
+                ghost.cells.push(head);
+            }
+            ghost.lengths.push(world.snek.body.len());
+        });
+        ghost
+    }
+
+    // Loads the best run in the mode and with the seed of some options, if
+    // there's been one
+    pub fn load(options: &Options) -> Option<Self> {
+        let replay = Replay::load(&path(options)?).ok()?;
+        Some(Self::from_replay(&replay))
+    }
+
+    // Returns the snek's body (tail to head) on a tick, or `None` once the
+    // game it's from was over
+    pub fn body(&self, tick: u64) -> Option<&[Point]> {
+        let tick = usize::try_from(tick).ok()?;
+        let length = *self.lengths.get(tick)?;
+        let end = self.start_len + tick;
+        Some(&self.cells[end.saturating_sub(length)..end])
+    }
+}
+
+// Keeps a finished game as the best run for its mode and seed if it beat the
+// one there was (scoring more, or the same in fewer ticks)
+pub fn record(replay: &Replay) -> io::Result<()> {
+    let Some(path) = path(&replay.options) else {
+        return Ok(());
+    };
+    if let Ok(best) = Replay::load(&path)
+        && !beats(replay, &best)
+    {
+        return Ok(());
+    }
+    replay.save(&path)
+}
+
+// Returns whether a game beat the best run (scoring more, or the same in
+// fewer ticks)
+fn beats(replay: &Replay, best: &Replay) -> bool {
+    (replay.score, best.ticks) > (best.score, replay.ticks)
+}
+
+// Returns where the best run in the mode and with the seed of some options is kept
+fn path(options: &Options) -> Option<PathBuf> {
+    let name = format!("{}-{}.snekreplay", options.mode.name(), options.seed);
+    Some(storage::data_file(GHOSTS_DIR)?.join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A short recorded game that eats three pellets and then runs into the
+    // right wall
+    const VALID: &str = include_str!("../tests/fixtures/valid.snekreplay");
+
+    #[test]
+    fn ghost_is_where_the_snek_was_on_every_tick() {
+        let replay = Replay::parse(VALID).unwrap();
+        let ghost = Ghost::from_replay(&replay);
+        let mut bodies = Vec::new();
+        replay.simulate(|world| bodies.push(world.snek.body.clone()));
+        assert_eq!(bodies.len() as u64, replay.ticks + 1);
+        for (tick, body) in bodies.iter().enumerate() {
+            assert_eq!(
+                ghost.body(tick as u64),
+                Some(body.as_slice()),
+                "tick {tick}"
+            );
+        }
+        assert_eq!(ghost.body(bodies.len() as u64), None);
+        assert_eq!(ghost.body(u64::MAX), None);
+        assert_eq!(Ghost::default().body(0), None);
+    }
+
+    #[test]
+    fn only_better_runs_replace_the_best() {
+        let best = Replay::parse(VALID).unwrap();
+        let run = |score, ticks| Replay {
+            score,
+            ticks,
+            ..best.clone()
+        };
+        assert!(beats(&run(4, 30), &best));
+        assert!(beats(&run(3, 17), &best));
+        assert!(!beats(&run(3, 18), &best));
+        assert!(!beats(&run(2, 5), &best));
+    }
+}
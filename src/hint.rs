@@ -0,0 +1,193 @@
+// Suggestions for where to move next, shown while practicing.
+//
+// The suggestion comes from a simple greedy player: of the moves that don't
+// run the snek into a wall or its own body, it takes the one that gets its head
+// closest to the nearest food, keeping the way it's going when that's as good.
+// It only steps onto the decoy in choice mode if there's nowhere else to go.
+//
+// Hints also warn when carrying on the way the snek's going would kill it
+// within a couple of moves, by playing those moves out on a copy of it.
+
+use crate::Point;
+use crate::options::Mode;
+use crate::world::{self, Direction, Turn, World};
+
+// How many moves ahead the snek is looked out for
+pub const DANGER_MOVES: usize = 2;
+
+// Returns the cell the snek should move into next, or nothing if every move is fatal
+pub fn suggestion(world: &World) -> Option<Point> {
+    let snek = &world.snek;
+    let head = *snek.body.last().unwrap();
+    [Turn::Straight, Turn::Left, Turn::Right]
+        .map(|turn| snek.heading.turn(turn))
+        .into_iter()
+        .filter(|direction| {
+            let (food, walls) = (&world.food, &world.options.walls);
+            !snek.move_fatal(*direction, food, walls, world.width(), world.height())
+        })
+        .map(|direction: Direction| head + direction.to_vector())
+        .min_by_key(|cell| {
+            (
+                world.decoy == Some(*cell),
+                distance_to_food(*cell, &world.food),
+            )
+        })
+}
+
+// Returns how many moves it'd take to get from a cell to the nearest food,
+// ignoring everything in the way
+fn distance_to_food(cell: Point, food: &[Point]) -> i32 {
+    food.iter()
+        .map(|food| (*food - cell).abs().to_array().iter().sum())
+        .min()
+        .unwrap_or(0)
+}
+
+// Returns whether the snek dies within some moves if it goes a way and then
+// carries straight on. The moves are made on a copy of the snek, so the tail
+// is out of the way of a head that gets to it after it's moved on (unless the
+// snek grows), and in ouroboros mode only walls count
+pub fn in_danger(world: &World, direction: Direction, moves: usize) -> bool {
+    let (food, walls) = (&world.food, &world.options.walls);
+    let (width, height) = (world.width(), world.height());
+    let mut snek = world.snek.clone();
+    snek.direction = direction;
+    for _ in 0..moves {
+        if world.options.mode == Mode::Twin && snek.heading.angle_to(snek.direction) == Turn::Around
+        {
+            snek.swap_ends();
+        }
+        if snek.move_fatal(snek.direction, food, walls, width, height) {
+            let head = *snek.body.last().unwrap() + snek.direction.to_vector();
+            return world.options.mode != Mode::Ouroboros
+                || !world::in_bounds(head, width, height)
+                || walls.contains(&head);
+        }
+        snek.slither(food);
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::STARTING_BODY;
+    use crate::options::Options;
+
+    #[test]
+    fn suggestions_are_next_to_the_head_and_safe() {
+        for seed in 0..20 {
+            let options = Options {
+                width: 8,
+                height: 8,
+                seed,
+                ..Options::default()
+            };
+            let mut world = World::new(options, &STARTING_BODY);
+            while world.running() {
+                let Some(cell) = suggestion(&world) else {
+                    break;
+                };
+                let head = *world.snek.body.last().unwrap();
+                let direction = Direction::all()
+                    .into_iter()
+                    .find(|direction| head + direction.to_vector() == cell)
+                    .expect("suggestion isn't next to the head");
+                world.snek.change_direction(direction);
+                world.advance();
+                assert_eq!(world.check_death(), None, "seed {seed} died taking a hint");
+            }
+        }
+    }
+
+    #[test]
+    fn suggestions_head_for_the_food() {
+        let mut world = World::new(
+            Options {
+                seed: 1,
+                ..Options::default()
+            },
+            &STARTING_BODY,
+        );
+        world.food = vec![Point::new(3, 5)];
+        assert_eq!(suggestion(&world), Some(Point::new(3, 1)));
+        world.food = vec![Point::new(9, 0)];
+        assert_eq!(suggestion(&world), Some(Point::new(4, 0)));
+    }
+
+    #[test]
+    fn no_suggestion_when_every_move_is_fatal() {
+        let mut world = World::new(
+            Options {
+                seed: 1,
+                ..Options::default()
+            },
+            &STARTING_BODY,
+        );
+        world.options.walls = vec![Point::new(4, 0), Point::new(3, 1)];
+        assert_eq!(suggestion(&world), None);
+    }
+
+    // Returns a world with a snek laid out from tail to head, and the food out
+    // of the way
+    fn laid_out(body: &[(i32, i32)], mode: Mode) -> World {
+        let body: Vec<Point> = body.iter().map(|(x, y)| Point::new(*x, *y)).collect();
+        let mut world = World::new(
+            Options {
+                mode,
+                seed: 1,
+                ..Options::default()
+            },
+            &body,
+        );
+        world.food = vec![Point::new(0, 14)];
+        world
+    }
+
+    #[test]
+    fn danger_is_seen_two_moves_ahead() {
+        // Along the top edge, a cell short of the right side
+        let mut world = laid_out(
+            &[(11, 0), (12, 0), (13, 0), (14, 0), (15, 0)],
+            Mode::Classic,
+        );
+        assert!(!in_danger(&world, Direction::Right, 1));
+        assert!(in_danger(&world, Direction::Right, 2));
+        assert!(!in_danger(&world, Direction::Down, 2));
+        world.options.walls = vec![Point::new(15, 2)];
+        assert!(in_danger(&world, Direction::Down, 2));
+        // Curled round so the body's two cells to the right of the head, with
+        // two more parts of it behind there that won't have moved on yet
+        let body = [(9, 5), (8, 5), (7, 5), (7, 6), (6, 6), (5, 6), (5, 5)];
+        let world = laid_out(&body, Mode::Classic);
+        assert!(!in_danger(&world, Direction::Right, 1));
+        assert!(in_danger(&world, Direction::Right, 2));
+        // Only walls count in ouroboros mode
+        assert!(!in_danger(
+            &laid_out(&body, Mode::Ouroboros),
+            Direction::Right,
+            2
+        ));
+    }
+
+    #[test]
+    fn the_tail_moving_on_isnt_danger() {
+        // The cell two to the right of the head is the tail's, and it'll have
+        // moved on by the time the head gets there
+        let body = [(7, 5), (7, 6), (6, 6), (5, 6), (5, 5)];
+        let mut world = laid_out(&body, Mode::Classic);
+        assert!(!in_danger(&world, Direction::Right, 2));
+        assert!(!in_danger(&world, Direction::Right, 3));
+        // One more part of it, and it's the tail's only as the head gets there
+        let longer = [(8, 5), (7, 5), (7, 6), (6, 6), (5, 6), (5, 5)];
+        assert!(!in_danger(
+            &laid_out(&longer, Mode::Classic),
+            Direction::Right,
+            2
+        ));
+        // Unless the snek's growing, and the tail stays where it is
+        world.snek.pending_growth = 2;
+        assert!(in_danger(&world, Direction::Right, 2));
+    }
+}
@@ -0,0 +1,301 @@
+// Every finished game, kept in a log in the data directory that games are only
+// ever added to. It's what the CSV export and `snek history` read, and the
+// stats can be worked out again from it with `snek stats --rebuild`.
+//
+// File format: one game per line, each a JSON object with the same fields as
+// the JSON results (see `summary`), plus `options` (the game's options, like
+// in a save file, or as `key value` lines in games from before those were
+// JSON) and `replay` (the file the replay was recorded to, or null). Lines that can't be read are skipped. Once the
+// log is `MAX_LOG_BYTES` long, it's moved to `history.1.jsonl` (replacing the
+// one that was there) and a new one is started, so the latest one to two logs'
+// worth of games are kept.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::options::Options;
+use crate::storage;
+use crate::summary::GameSummary;
+
+// Name of the history file inside the data directory
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+// How long the log can get before it's moved aside and a new one is started
+const MAX_LOG_BYTES: u64 = 1 << 20;
+
+// Columns of the CSV export
+const CSV_HEADER: &str = "timestamp,mode,board,seed,score,length,duration,cause";
+
+// One finished game in the history
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    #[serde(flatten)]
+    pub summary: GameSummary,
+    pub options: Value,
+    pub replay: Option<PathBuf>,
+}
+
+impl HistoryEntry {
+    // Creates the entry for a game that finished with some options
+    pub fn new(summary: &GameSummary, options: &Options) -> Self {
+        Self {
+            summary: summary.clone(),
+            options: serde_json::to_value(options).unwrap_or_default(),
+            replay: options.record.clone(),
+        }
+    }
+
+    // Returns how the game ended: `wall` or `body` if the snek died, and `won`
+    // or `quit` otherwise
+    pub fn cause(&self) -> &'static str {
+        let summary = &self.summary;
+        summary
+            .cause
+            .map_or(summary.outcome(), |cause| cause.name())
+    }
+
+    // Converts the entry to a line of the log
+    fn to_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    // Reads an entry from a line of the log
+    fn from_line(line: &str) -> Option<Self> {
+        let mut entry: Self = serde_json::from_str(line).ok()?;
+        // The goal isn't in the results, but it is in the options
+        entry.summary.goal = match &entry.options {
+            Value::String(pairs) => pairs
+                .lines()
+                .find_map(|line| line.strip_prefix("goal "))
+                .and_then(|goal| goal.parse().ok()),
+            options => options["goal"].as_u64().map(|goal| goal as usize),
+        };
+        Some(entry)
+    }
+
+    // Converts the entry to a CSV row
+    fn to_csv(&self) -> String {
+        let summary = &self.summary;
+        let fields = [
+            storage::format_time(summary.date),
+            summary.mode.name().to_owned(),
+            format!("{}x{}", summary.width, summary.height),
+            summary.seed.to_string(),
+            summary.score.to_string(),
+            summary.length.to_string(),
+            summary.duration.to_string(),
+            self.cause().to_owned(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        fields.join(",")
+    }
+}
+
+// The finished games in the log, from oldest to newest
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    // Returns the games, oldest first
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    // Loads the history from a log and the one moved aside before it, which
+    // is empty if neither exists
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut history = Self::default();
+        for path in [rotated_path(path), path.to_owned()] {
+            match fs::read_to_string(&path) {
+                Ok(contents) => history.extend(&contents),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(history)
+    }
+
+    // Loads the history from the data directory, ignoring any errors
+    pub fn load_default() -> Self {
+        storage::data_file(HISTORY_FILE)
+            .and_then(|path| Self::load(&path).ok())
+            .unwrap_or_default()
+    }
+
+    // Adds the games in the contents of a log, skipping malformed ones
+    fn extend(&mut self, contents: &str) {
+        self.entries
+            .extend(contents.lines().filter_map(HistoryEntry::from_line));
+    }
+
+    // Adds a game to the end of a log, moving it aside first if it's full
+    pub fn append(path: &Path, entry: &HistoryEntry) -> io::Result<()> {
+        if fs::metadata(path).is_ok_and(|metadata| metadata.len() >= MAX_LOG_BYTES) {
+            fs::rename(path, rotated_path(path))?;
+        }
+        if let Some(parent) = path.parent() {
+            storage::create_dir(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all((entry.to_line() + "\n").as_bytes())
+    }
+
+    // Writes every game as CSV, with a header row
+    pub fn to_csv(&self) -> String {
+        let mut csv = CSV_HEADER.to_owned() + "\n";
+        for entry in &self.entries {
+            csv += &(entry.to_csv() + "\n");
+        }
+        csv
+    }
+
+    // Formats the latest games for printing, newest first
+    pub fn format(&self, last: usize) -> String {
+        if self.entries.is_empty() {
+            return "No games yet".to_owned();
+        }
+        let mut text = "DATE        MODE        BOARD  SCORE  LENGTH   TIME  CAUSE\n".to_owned();
+        for entry in self.entries.iter().rev().take(last) {
+            let summary = &entry.summary;
+            text += &format!(
+                "{}  {:<10} {:>5}  {:>5}  {:>6}  {:>2}:{:02}  {}\n",
+                storage::format_date(summary.date),
+                summary.mode.name(),
+                format!("{}x{}", summary.width, summary.height),
+                summary.score,
+                summary.length,
+                summary.duration / 60,
+                summary.duration % 60,
+                entry.cause()
+            );
+        }
+        text.trim_end().to_owned()
+    }
+}
+
+#[cfg(test)]
+impl History {
+    // Returns a history of some games, for tests that don't need a log
+    pub fn from_entries(entries: Vec<HistoryEntry>) -> Self {
+        Self { entries }
+    }
+}
+
+// Returns where a log is moved to once it's full
+fn rotated_path(path: &Path) -> PathBuf {
+    path.with_extension("1.jsonl")
+}
+
+// Quotes a CSV field if it has anything in it that needs quoting
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::DeathCause;
+    use std::env;
+    use std::process;
+
+    // Returns the entry for a game, with the options it was played with
+    fn entry(summary: GameSummary) -> HistoryEntry {
+        let options = Options {
+            seed: summary.seed,
+            record: Some(PathBuf::from("runs/a, \"b\".snekreplay")),
+            ..Options::default()
+        };
+        HistoryEntry::new(&summary, &options)
+    }
+
+    #[test]
+    fn appended_games_load_back() {
+        let dir = env::temp_dir().join(format!("snek-history-{}", process::id()));
+        let path = dir.join(HISTORY_FILE);
+        let first = entry(GameSummary::example());
+        let second = entry(GameSummary {
+            cause: None,
+            won: true,
+            date: 1_700_100_000,
+            ..GameSummary::example()
+        });
+        History::append(&path, &first).unwrap();
+        History::append(&path, &second).unwrap();
+        fs::write(&path, fs::read_to_string(&path).unwrap() + "not a game\n").unwrap();
+        let history = History::load(&path).unwrap();
+        assert_eq!(history.entries(), [first, second]);
+        assert_eq!(history.entries()[0].options["seed"], 42);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn full_logs_are_moved_aside() {
+        let dir = env::temp_dir().join(format!("snek-history-rotate-{}", process::id()));
+        let path = dir.join(HISTORY_FILE);
+        let old = entry(GameSummary::example());
+        History::append(&path, &old).unwrap();
+        let padding = "x".repeat(MAX_LOG_BYTES as usize);
+        fs::write(&path, fs::read_to_string(&path).unwrap() + &padding).unwrap();
+        let new = entry(GameSummary {
+            date: 1_700_100_000,
+            ..GameSummary::example()
+        });
+        History::append(&path, &new).unwrap();
+        assert!(dir.join("history.1.jsonl").exists());
+        assert_eq!(History::load(&path).unwrap().entries(), [old, new.clone()]);
+        // Only the one before the latest is kept
+        fs::write(&path, padding).unwrap();
+        History::append(&path, &new).unwrap();
+        assert_eq!(History::load(&path).unwrap().entries(), [new]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn games_keep_their_goal() {
+        let options = Options {
+            goal: Some(12),
+            ..Options::default()
+        };
+        let line = HistoryEntry::new(&GameSummary::example(), &options).to_line();
+        assert_eq!(
+            HistoryEntry::from_line(&line).unwrap().summary.goal,
+            Some(12)
+        );
+        // Games from before the options were JSON had them as `key value` lines
+        let mut old: Value = serde_json::from_str(&line).unwrap();
+        old["options"] = "width 17\ngoal 5\n".into();
+        let old = HistoryEntry::from_line(&old.to_string()).unwrap();
+        assert_eq!(old.summary.goal, Some(5));
+    }
+
+    #[test]
+    fn csv_has_a_row_per_game() {
+        let history = History {
+            entries: vec![
+                entry(GameSummary::example()),
+                entry(GameSummary {
+                    cause: Some(DeathCause::Body),
+                    ..GameSummary::example()
+                }),
+            ],
+        };
+        assert_eq!(
+            history.to_csv(),
+            "timestamp,mode,board,seed,score,length,duration,cause\n\
+            2023-11-14 22:13:20,classic,17x15,42,3,7,65,wall\n\
+            2023-11-14 22:13:20,classic,17x15,42,3,7,65,body\n"
+        );
+        assert_eq!(csv_field("a, \"b\""), "\"a, \"\"b\"\"\"");
+        assert_eq!(History::default().format(5), "No games yet");
+    }
+}
@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+// Watches for the player going quiet (not turning the snek, and it not eating
+// anything either), to notice when they've probably walked away from the game
+pub struct IdleTimer {
+    threshold: Option<Duration>,
+    last_active: Instant,
+}
+
+impl IdleTimer {
+    // Creates a timer that counts the player as idle after some seconds, or
+    // never if it isn't given any
+    pub fn new(seconds: Option<u32>) -> Self {
+        Self {
+            threshold: seconds.map(|seconds| Duration::from_secs(seconds.into())),
+            last_active: Instant::now(),
+        }
+    }
+
+    // Records that the player did something (or that the game's just started
+    // or carried on), starting the count over
+    pub fn active(&mut self) {
+        self.last_active = Instant::now();
+    }
+
+    // Returns whether the player's been quiet for long enough to be idle
+    pub fn idle(&self) -> bool {
+        self.is_idle(self.last_active.elapsed())
+    }
+
+    // Returns whether being quiet for a while counts as idle
+    fn is_idle(&self, quiet: Duration) -> bool {
+        self.threshold.is_some_and(|threshold| quiet >= threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_from_the_threshold_on() {
+        let timer = IdleTimer::new(Some(30));
+        assert!(!timer.is_idle(Duration::ZERO));
+        assert!(!timer.is_idle(Duration::from_millis(29_999)));
+        assert!(timer.is_idle(Duration::from_secs(30)));
+        assert!(timer.is_idle(Duration::from_secs(3_000)));
+        assert!(!timer.idle());
+    }
+
+    #[test]
+    fn never_idle_without_a_threshold() {
+        let timer = IdleTimer::new(None);
+        assert!(!timer.is_idle(Duration::MAX));
+        assert!(!timer.idle());
+        // With nothing to wait for, it's idle straight away
+        let mut timer = IdleTimer::new(Some(0));
+        timer.active();
+        assert!(timer.idle());
+    }
+}
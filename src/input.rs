@@ -0,0 +1,370 @@
+// Evening out how terminals report keys, between console_engine and the game.
+//
+// Letter keys come through in upper case with Caps Lock or Shift held, so
+// they're matched in either case. Windows consoles send an event when a key is
+// released as well as when it's pressed, which can show up as the key being
+// pressed on two frames in a row; for a turn key, that turns the snek twice (a
+// U-turn with relative steering), so there a turn key pressed on the frame
+// right after it was last pressed is taken for the release and dropped.
+//
+// Terminals with the kitty keyboard protocol are switched to it for the
+// game, so a press of Esc (the pause key) comes through on its own straight
+// away, instead of being held back to see if it starts an escape sequence, and
+// repeats and releases come through apart from presses. That way a key can be
+// told to be held down from the moment it's pressed until it's let go; other
+// terminals only show it by repeating it as a press, so there it can't be
+// told from being pressed again until the repeats start.
+//
+// The quirks this evens out, and the terminals they come from (the tests below
+// feed each one the keys those terminals send):
+//
+// - Windows Terminal and the older conhost send a release after every press,
+//   which can land on the next frame
+// - xterm, GNOME Terminal (and other VTE ones), Konsole, and the macOS Terminal
+//   send arrows as `ESC [ A` normally but `ESC O A` once a program's asked for
+//   application cursor keys, and letters in upper case with Caps Lock on
+// - kitty, foot, WezTerm, and Ghostty send keys as `ESC [ CODE u` with the
+//   kitty keyboard protocol on, so Esc comes through as `ESC [ 27 u` rather
+//   than a lone ESC byte, and say whether it was a press, repeat, or release
+//   (`ESC [ 1 ; 1 : 3 A` is the up arrow being let go)
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use console_engine::crossterm::event::{
+    KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use console_engine::crossterm::{execute, terminal};
+use console_engine::{ConsoleEngine, KeyCode};
+
+use crate::TURN_KEYS;
+
+// Whether a key's release can look like another press
+const RELEASE_ECHOES: bool = cfg!(windows);
+
+// Whether the terminal's been switched to the kitty keyboard protocol (and so
+// reports releases)
+static ENHANCED: AtomicBool = AtomicBool::new(false);
+
+// Switches the terminal to the kitty keyboard protocol if it has it, which
+// has to be done once the engine's put it in raw mode
+pub fn enhance_keyboard() {
+    let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+        | KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+    if terminal::supports_keyboard_enhancement().unwrap_or(false)
+        && execute!(io::stdout(), PushKeyboardEnhancementFlags(flags)).is_ok()
+    {
+        log::info!("keyboard enhancement on");
+        ENHANCED.store(true, Ordering::Relaxed);
+    }
+}
+
+// Switches the terminal back to its usual keyboard reporting, if it was
+// switched to the kitty keyboard protocol
+pub fn restore_keyboard() {
+    if ENHANCED.swap(false, Ordering::Relaxed) {
+        let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+    }
+}
+
+// The keys the terminal reported on a frame, as they come out of the engine
+pub trait Keys {
+    // Returns whether a key was reported pressed
+    fn key_pressed(&self, key: KeyCode) -> bool;
+
+    // Returns whether a key was reported repeating (being held down)
+    fn key_held(&self, key: KeyCode) -> bool;
+
+    // Returns whether a key was reported let go
+    fn key_released(&self, key: KeyCode) -> bool;
+}
+
+impl Keys for ConsoleEngine {
+    fn key_pressed(&self, key: KeyCode) -> bool {
+        self.is_key_pressed(key)
+    }
+
+    fn key_held(&self, key: KeyCode) -> bool {
+        self.is_key_held(key)
+    }
+
+    fn key_released(&self, key: KeyCode) -> bool {
+        self.is_key_released(key)
+    }
+}
+
+// Returns whether a key was pressed this frame, matching letters in either case
+pub fn pressed(keys: &impl Keys, key: KeyCode) -> bool {
+    keys.key_pressed(key)
+        || match key {
+            KeyCode::Char(letter) if letter.is_ascii_lowercase() => {
+                keys.key_pressed(KeyCode::Char(letter.to_ascii_uppercase()))
+            }
+            _ => false,
+        }
+}
+
+// The turn key pressed on the last frame, to tell a release from a press, and
+// the turn keys being held down
+pub struct TurnKeys {
+    last: Option<KeyCode>,
+    down: Vec<KeyCode>,
+    release_echoes: bool,
+    releases: bool,
+}
+
+impl Default for TurnKeys {
+    fn default() -> Self {
+        Self {
+            last: None,
+            down: Vec::new(),
+            release_echoes: RELEASE_ECHOES,
+            releases: ENHANCED.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl TurnKeys {
+    // Returns the turn key pressed this frame (unless it's really the last
+    // one's release), which has to be asked every frame
+    pub fn pressed(&mut self, keys: &impl Keys) -> Option<KeyCode> {
+        self.update_down(keys);
+        let key = TURN_KEYS.into_iter().find(|key| keys.key_pressed(*key));
+        let echo = self.release_echoes && key.is_some() && key == self.last;
+        self.last = key;
+        key.filter(|_| !echo)
+    }
+
+    // Keeps track of which turn keys are down: from when they're pressed until
+    // they're let go if the terminal says when that is, or otherwise just on
+    // the frames they repeat
+    fn update_down(&mut self, keys: &impl Keys) {
+        if !self.releases {
+            self.down.clear();
+        }
+        for key in TURN_KEYS {
+            let down = keys.key_held(key) || (self.releases && keys.key_pressed(key));
+            if down && !self.down.contains(&key) {
+                self.down.push(key);
+            }
+        }
+        self.down.retain(|key| !keys.key_released(*key));
+    }
+
+    // Returns whether a turn key was down as of the last frame asked about
+    pub fn held(&self, key: KeyCode) -> bool {
+        self.down.contains(&key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use console_engine::crossterm::event::{KeyEvent, KeyEventKind, KeyModifiers};
+
+    use super::*;
+    use crate::{LEFT_KEY, PAUSE_KEY, UP_KEY};
+
+    // The keys reported on a frame, made up of key events
+    struct Frame(Vec<KeyEvent>);
+
+    impl Keys for Frame {
+        fn key_pressed(&self, key: KeyCode) -> bool {
+            self.0
+                .iter()
+                .any(|event| event.code == key && event.kind == KeyEventKind::Press)
+        }
+
+        fn key_held(&self, key: KeyCode) -> bool {
+            self.0
+                .iter()
+                .any(|event| event.code == key && event.kind == KeyEventKind::Repeat)
+        }
+
+        fn key_released(&self, key: KeyCode) -> bool {
+            self.0
+                .iter()
+                .any(|event| event.code == key && event.kind == KeyEventKind::Release)
+        }
+    }
+
+    // Decodes what a terminal sends into key events the way crossterm does,
+    // for the sequences these tests use: plain characters, arrows as
+    // `ESC [ X` or `ESC O X`, and the kitty protocol's `ESC [ CODE ; MODS : KIND u`
+    // (and `ESC [ 1 ; MODS : KIND X` for arrows)
+    fn decode(sequence: &str) -> Vec<KeyEvent> {
+        let arrow = |letter| match letter {
+            'A' => KeyCode::Up,
+            'B' => KeyCode::Down,
+            'C' => KeyCode::Right,
+            'D' => KeyCode::Left,
+            _ => panic!("unknown arrow {letter}"),
+        };
+        let kind = |kind: Option<&str>| match kind {
+            None | Some("1") => KeyEventKind::Press,
+            Some("2") => KeyEventKind::Repeat,
+            Some("3") => KeyEventKind::Release,
+            Some(kind) => panic!("unknown kind {kind}"),
+        };
+        let mut events = Vec::new();
+        let mut rest = sequence;
+        while let Some(chr) = rest.chars().next() {
+            if let Some(sequence) = rest.strip_prefix("\x1bO") {
+                let letter = sequence.chars().next().unwrap();
+                events.push(KeyEvent::new(arrow(letter), KeyModifiers::NONE));
+                rest = &sequence[1..];
+            } else if let Some(sequence) = rest.strip_prefix("\x1b[") {
+                let end = sequence
+                    .find(|chr: char| chr.is_ascii_alphabetic())
+                    .unwrap();
+                let (parameters, last) = (&sequence[..end], &sequence[end..end + 1]);
+                let (code, modifiers) = parameters.split_once(';').unwrap_or((parameters, "1"));
+                let mut modifiers = modifiers.split(':');
+                let shift = modifiers.next().unwrap() == "2";
+                let kind = kind(modifiers.next());
+                let code = match (last, code) {
+                    ("u", "27") => KeyCode::Esc,
+                    ("u", "13") => KeyCode::Enter,
+                    ("u", code) => {
+                        let chr = char::from_u32(code.parse().unwrap()).unwrap();
+                        KeyCode::Char(if shift { chr.to_ascii_uppercase() } else { chr })
+                    }
+                    (letter, _) => arrow(letter.chars().next().unwrap()),
+                };
+                events.push(KeyEvent::new_with_kind(code, KeyModifiers::NONE, kind));
+                rest = &sequence[end + 1..];
+            } else {
+                let modifiers = match chr.is_ascii_uppercase() {
+                    true => KeyModifiers::SHIFT,
+                    false => KeyModifiers::NONE,
+                };
+                events.push(KeyEvent::new(KeyCode::Char(chr), modifiers));
+                rest = &rest[chr.len_utf8()..];
+            }
+        }
+        events
+    }
+
+    // Returns the frame of keys a terminal sent
+    fn frame(sequence: &str) -> Frame {
+        Frame(decode(sequence))
+    }
+
+    #[test]
+    fn letters_match_in_either_case() {
+        assert!(pressed(&frame("p"), KeyCode::Char('p')));
+        assert!(pressed(&frame("P"), KeyCode::Char('p')));
+        assert!(pressed(&frame("\x1b[112;2u"), KeyCode::Char('p')));
+        assert!(!pressed(&frame("p"), KeyCode::Char('P')));
+        assert!(!pressed(&frame("o"), KeyCode::Char('p')));
+    }
+
+    #[test]
+    fn arrows_match_however_theyre_sent() {
+        for sequence in ["\x1b[A", "\x1bOA", "\x1b[1;1:1A"] {
+            assert!(pressed(&frame(sequence), UP_KEY), "{sequence:?}");
+            assert!(!pressed(&frame(sequence), LEFT_KEY), "{sequence:?}");
+        }
+        assert_eq!(
+            TurnKeys::default().pressed(&frame("x\x1bOD")),
+            Some(LEFT_KEY)
+        );
+    }
+
+    #[test]
+    fn kitty_escape_is_a_press_of_its_own() {
+        assert_eq!(
+            decode("\x1b[27u"),
+            [KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)]
+        );
+        assert!(pressed(&frame("\x1b[27u"), PAUSE_KEY));
+        // Repeats and releases aren't presses
+        assert!(!pressed(&frame("\x1b[1;1:2A\x1b[1;1:3A"), UP_KEY));
+    }
+
+    // Returns which frames a key's held on, as the frames go by
+    fn held_on(mut turn_keys: TurnKeys, frames: &[&str], key: KeyCode) -> Vec<bool> {
+        frames
+            .iter()
+            .map(|sequence| {
+                turn_keys.pressed(&frame(sequence));
+                turn_keys.held(key)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keys_are_held_from_press_to_release_when_releases_are_reported() {
+        let turn_keys = TurnKeys {
+            releases: true,
+            ..TurnKeys::default()
+        };
+        // Pressed, nothing while it waits to repeat, repeating, then let go
+        let frames = [
+            "",
+            "\x1b[1;1:1A",
+            "",
+            "",
+            "\x1b[1;1:2A",
+            "\x1b[1;1:2A\x1b[1;1:3A",
+            "",
+        ];
+        assert_eq!(
+            held_on(turn_keys, &frames, UP_KEY),
+            [false, true, true, true, true, false, false]
+        );
+        // Other keys coming and going don't let go of it
+        let turn_keys = TurnKeys {
+            releases: true,
+            ..TurnKeys::default()
+        };
+        let frames = ["\x1b[A", "\x1b[1;1:1D", "\x1b[1;1:3D", "\x1b[1;1:3A"];
+        assert_eq!(
+            held_on(turn_keys, &frames, UP_KEY),
+            [true, true, true, false]
+        );
+    }
+
+    #[test]
+    fn keys_are_only_held_while_repeating_otherwise() {
+        let turn_keys = TurnKeys {
+            releases: false,
+            ..TurnKeys::default()
+        };
+        let frames = ["\x1b[A", "", "\x1b[1;1:2A", "\x1b[1;1:2A", ""];
+        assert_eq!(
+            held_on(turn_keys, &frames, UP_KEY),
+            [false, false, true, true, false]
+        );
+    }
+
+    #[test]
+    fn windows_release_echoes_are_dropped() {
+        let mut turn_keys = TurnKeys {
+            release_echoes: true,
+            ..TurnKeys::default()
+        };
+        let frames = ["\x1b[D", "\x1b[D", "", "\x1b[D", "\x1b[A", "\x1b[A"];
+        let turns: Vec<Option<KeyCode>> = frames
+            .iter()
+            .map(|sequence| turn_keys.pressed(&frame(sequence)))
+            .collect();
+        assert_eq!(
+            turns,
+            [
+                Some(LEFT_KEY),
+                None,
+                None,
+                Some(LEFT_KEY),
+                Some(UP_KEY),
+                None
+            ]
+        );
+        // Elsewhere, pressing a key on two frames in a row is two presses
+        let mut turn_keys = TurnKeys {
+            release_echoes: false,
+            ..TurnKeys::default()
+        };
+        assert_eq!(turn_keys.pressed(&frame("\x1b[D")), Some(LEFT_KEY));
+        assert_eq!(turn_keys.pressed(&frame("\x1b[D")), Some(LEFT_KEY));
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+use crate::logging::LogEvent;
+
+// How many slow frames in a row count as the game lagging
+const LAG_FRAMES: u32 = 5;
+
+// Watches how long frames take, to notice when the game can't keep up with its speed
+pub struct LagMeter {
+    frame_time: Duration,
+    last_frame: Instant,
+    slow_frames: u32,
+}
+
+impl LagMeter {
+    // Creates a meter for a game running at some frames per second
+    pub fn new(fps: u32) -> Self {
+        Self {
+            frame_time: Duration::from_secs(1) / fps.max(1),
+            last_frame: Instant::now(),
+            slow_frames: 0,
+        }
+    }
+
+    // Records that a frame finished; returns whether the game just started lagging
+    pub fn frame(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now - self.last_frame;
+        let slow = self.is_slow(elapsed);
+        if slow {
+            log::debug!(
+                "{}",
+                LogEvent::SlowFrame {
+                    duration_us: elapsed.as_micros(),
+                    expected_us: self.frame_time.as_micros(),
+                }
+            );
+        }
+        self.last_frame = now;
+        self.record(slow)
+    }
+
+    // Returns whether a frame took too long (half as long again as it should)
+    fn is_slow(&self, elapsed: Duration) -> bool {
+        elapsed > self.frame_time * 3 / 2
+    }
+
+    // Counts slow frames in a row, returning whether there are now just enough to be lagging
+    fn record(&mut self, slow: bool) -> bool {
+        self.slow_frames = match slow {
+            true => self.slow_frames + 1,
+            false => 0,
+        };
+        self.slow_frames == LAG_FRAMES
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lags_after_enough_slow_frames_in_a_row() {
+        let mut meter = LagMeter::new(10);
+        for _ in 1..LAG_FRAMES {
+            assert!(!meter.record(true));
+        }
+        assert!(!meter.record(false));
+        for _ in 1..LAG_FRAMES {
+            assert!(!meter.record(true));
+        }
+        assert!(meter.record(true));
+        // It only says so once for every stretch of lag
+        assert!(!meter.record(true));
+    }
+
+    #[test]
+    fn slow_frames_take_half_as_long_again() {
+        let meter = LagMeter::new(10);
+        assert!(!meter.is_slow(Duration::from_millis(150)));
+        assert!(meter.is_slow(Duration::from_millis(151)));
+    }
+}
@@ -0,0 +1,436 @@
+// Levels, which are boards with walls in them, read from `.txt` files in the
+// `levels` directory inside the data directory (and the `--level-dir`, if
+// there is one) and picked from the start screen.
+//
+// File format (version 1): a `snek-level 1` header line, a `name NAME` line,
+// an optional `start X,Y DIRECTION` line (where the snek's head starts and
+// which way it's heading, the rest of it trailing behind), an optional
+// `food X,Y X,Y ...` line (the cells food appears in, in order, starting over
+// from the first after the last), and then the board,
+// one line per row, with `#` for a wall and `.` for an open cell. Every row has
+// to be as long as the first, the board has to be at least `MIN_BOARD` cells
+// across and down, and the snek's starting body (in the top left corner heading
+// right, without a `start` line) and the food's cells have to be on the board
+// and clear of walls.
+// Files that break any of that show up in the picker as broken instead of
+// stopping the rest from being found.
+//
+// The path of the last level picked is kept in `last_level.txt` in the data
+// directory, so the picker starts on it.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::options::Options;
+use crate::storage;
+use crate::world::{self, Direction};
+use crate::{MIN_BOARD, Point};
+
+// Name of the levels directory inside the data directory
+pub const LEVELS_DIR: &str = "levels";
+
+// Name of the file inside the data directory that has the last level picked
+pub const LAST_LEVEL_FILE: &str = "last_level.txt";
+
+// Header line (including version) of a level file
+const HEADER: &str = "snek-level 1";
+
+// Characters of a wall and an open cell in a level file
+const WALL: char = '#';
+const OPEN: char = '.';
+
+// How many levels the picker lists at once
+const PICKER_ROWS: usize = 5;
+
+// A board with walls in it
+#[derive(Clone, PartialEq, Debug)]
+pub struct Level {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub walls: Vec<Point>,
+    pub start: Option<(Point, Direction)>,
+    pub food: Vec<Point>,
+}
+
+impl Level {
+    // Loads a level from a file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        Self::parse(&contents)
+    }
+
+    // Reads a level from the contents of a level file, explaining what's wrong
+    // with it if it can't be
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut lines = contents.lines();
+        if lines.next() != Some(HEADER) {
+            return Err("unknown level file version".to_owned());
+        }
+        let name = lines
+            .next()
+            .and_then(|line| line.strip_prefix("name "))
+            .filter(|name| !name.is_empty())
+            .ok_or("missing 'name' line")?
+            .to_owned();
+        let mut rows: Vec<&str> = lines.collect();
+        let mut start = None;
+        if let Some(line) = rows.first().and_then(|row| row.strip_prefix("start ")) {
+            start = Some(parse_start(line).ok_or("invalid 'start' line")?);
+            rows.remove(0);
+        }
+        let mut food = Vec::new();
+        if let Some(line) = rows.first().and_then(|row| row.strip_prefix("food ")) {
+            food = parse_food(line).ok_or("invalid 'food' line")?;
+            rows.remove(0);
+        }
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        let mut walls = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(format!("row {} isn't as long as the first", y + 1));
+            }
+            for (x, cell) in row.chars().enumerate() {
+                match cell {
+                    WALL => walls.push(Point::new(x as i32, y as i32)),
+                    OPEN => (),
+                    _ => return Err(format!("unknown cell '{cell}' in row {}", y + 1)),
+                }
+            }
+        }
+        let (width, height) = (width as u32, rows.len() as u32);
+        if width < MIN_BOARD || height < MIN_BOARD {
+            return Err(format!(
+                "the board is {width}x{height}, but needs to be at least {MIN_BOARD}x{MIN_BOARD}"
+            ));
+        }
+        let level = Self {
+            name,
+            width,
+            height,
+            walls,
+            start,
+            food,
+        };
+        level.check_start()?;
+        level.check_food()?;
+        Ok(level)
+    }
+
+    // Checks that the snek's starting body is on the board and clear of walls
+    pub fn check_start(&self) -> Result<(), String> {
+        let body = world::starting_body(self.start);
+        if body.iter().any(|part| {
+            !world::in_bounds(*part, self.width, self.height) || self.walls.contains(part)
+        }) {
+            return Err(format!(
+                "the {} cells the snek starts in need to be on the board and open",
+                body.len()
+            ));
+        }
+        Ok(())
+    }
+
+    // Checks that every cell food appears in is on the board and clear of walls
+    pub fn check_food(&self) -> Result<(), String> {
+        match self.food.iter().find(|food| {
+            !world::in_bounds(**food, self.width, self.height) || self.walls.contains(food)
+        }) {
+            Some(food) => Err(format!(
+                "food at {},{} needs to be on the board and open",
+                food.x, food.y
+            )),
+            None => Ok(()),
+        }
+    }
+
+    // Writes the level in the level file format
+    pub fn serialize(&self) -> String {
+        let mut contents = format!("{HEADER}\nname {}\n", self.name);
+        if let Some((head, heading)) = self.start {
+            contents += &format!("start {},{} {}\n", head.x, head.y, heading.name());
+        }
+        if !self.food.is_empty() {
+            let food: Vec<String> = self
+                .food
+                .iter()
+                .map(|food| format!("{},{}", food.x, food.y))
+                .collect();
+            contents += &format!("food {}\n", food.join(" "));
+        }
+        for y in 0..self.height as i32 {
+            let row: String = (0..self.width as i32)
+                .map(|x| match self.walls.contains(&Point::new(x, y)) {
+                    true => WALL,
+                    false => OPEN,
+                })
+                .collect();
+            contents += &row;
+            contents.push('\n');
+        }
+        contents
+    }
+
+    // Sets the options to play the level
+    pub fn apply(&self, options: &mut Options) {
+        options.width = self.width;
+        options.height = self.height;
+        options.walls = self.walls.clone();
+        options.start = self.start;
+        options.food_sequence = self.food.clone();
+        options.level = Some(self.name.clone());
+    }
+
+    // Draws the level as text at most some columns wide and rows high, with
+    // each character standing for a block of cells (a wall if any of them are)
+    pub fn thumbnail(&self, max_width: u32, max_height: u32) -> Vec<String> {
+        let scale = self
+            .width
+            .div_ceil(max_width.max(1))
+            .max(self.height.div_ceil(max_height.max(1)));
+        (0..self.height.div_ceil(scale))
+            .map(|row| {
+                (0..self.width.div_ceil(scale))
+                    .map(|column| {
+                        let wall = self.walls.iter().any(|wall| {
+                            wall.x as u32 / scale == column && wall.y as u32 / scale == row
+                        });
+                        if wall { WALL } else { OPEN }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+// Reads where the snek starts from a `start` line (`X,Y DIRECTION`)
+fn parse_start(line: &str) -> Option<(Point, Direction)> {
+    let (head, heading) = line.split_once(' ')?;
+    let (x, y) = head.split_once(',')?;
+    let head = Point::new(x.parse().ok()?, y.parse().ok()?);
+    Some((head, Direction::from_name(heading)?))
+}
+
+// Reads the cells food appears in from a `food` line (`X,Y X,Y ...`)
+fn parse_food(line: &str) -> Option<Vec<Point>> {
+    line.split(' ')
+        .filter(|food| !food.is_empty())
+        .map(|food| {
+            let (x, y) = food.split_once(',')?;
+            Some(Point::new(x.parse().ok()?, y.parse().ok()?))
+        })
+        .collect()
+}
+
+// A level file that was found, which might not be a valid level
+pub struct LevelEntry {
+    pub path: PathBuf,
+    pub level: Result<Level, String>,
+}
+
+impl LevelEntry {
+    // Returns the level's name, or the file's if it's broken
+    pub fn name(&self) -> String {
+        match &self.level {
+            Ok(level) => level.name.clone(),
+            Err(_) => self
+                .path
+                .file_stem()
+                .map_or(String::new(), |stem| stem.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+// Returns the directories levels are found in: the one in the data directory,
+// and another if one was given
+pub fn level_dirs(extra: Option<&Path>) -> Vec<PathBuf> {
+    let default = storage::data_dir().map(|dir| dir.join(LEVELS_DIR));
+    default
+        .into_iter()
+        .chain(extra.map(Path::to_owned))
+        .collect()
+}
+
+// Finds every `.txt` file in some directories (skipping ones that can't be
+// read) and tries to load them as levels, sorted by path
+pub fn discover(dirs: &[PathBuf]) -> Vec<LevelEntry> {
+    let mut paths: Vec<PathBuf> = dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| LevelEntry {
+            level: Level::load(&path),
+            path,
+        })
+        .collect()
+}
+
+// Returns the path of the last level picked, if there is one
+pub fn load_last() -> Option<PathBuf> {
+    let contents = fs::read_to_string(storage::data_file(LAST_LEVEL_FILE)?).ok()?;
+    Some(PathBuf::from(contents.trim_end()))
+}
+
+// Remembers the path of the last level picked
+pub fn save_last(path: &Path) -> io::Result<()> {
+    let file =
+        storage::data_file(LAST_LEVEL_FILE).ok_or_else(|| io::Error::other("no data directory"))?;
+    storage::write_atomic(&file, &format!("{}\n", path.display()))
+}
+
+// The levels that were found, and which one is picked, shown on the start screen
+pub struct LevelPicker {
+    pub entries: Vec<LevelEntry>,
+    pub selected: usize,
+}
+
+impl LevelPicker {
+    // Creates a picker for some levels, starting on a path if it's one of them
+    pub fn new(entries: Vec<LevelEntry>, last: Option<&Path>) -> Self {
+        let selected = entries
+            .iter()
+            .position(|entry| Some(entry.path.as_path()) == last)
+            .unwrap_or(0);
+        Self { entries, selected }
+    }
+
+    // Moves the selection up or down a level, wrapping around
+    pub fn step(&mut self, down: bool) {
+        let count = self.entries.len().max(1);
+        self.selected = match down {
+            true => (self.selected + 1) % count,
+            false => (self.selected + count - 1) % count,
+        };
+    }
+
+    // Returns the selected level, if it isn't broken
+    pub fn selected(&self) -> Option<(&Path, &Level)> {
+        let entry = self.entries.get(self.selected)?;
+        Some((&entry.path, entry.level.as_ref().ok()?))
+    }
+
+    // Returns the lines of the picker, fitting in some columns and rows: a
+    // window of levels around the selected one, with their sizes (or that
+    // they're broken), and then a thumbnail of it (or what's wrong with it)
+    pub fn lines(&self, columns: u32, rows: u32) -> Vec<String> {
+        if self.entries.is_empty() {
+            return vec!["NO LEVELS FOUND".to_owned()];
+        }
+        let first = self.selected.saturating_sub(PICKER_ROWS / 2);
+        let first = first.min(self.entries.len().saturating_sub(PICKER_ROWS));
+        let mut lines: Vec<String> = self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(PICKER_ROWS)
+            .map(|(index, entry)| {
+                let marker = if index == self.selected { '>' } else { ' ' };
+                let size = match &entry.level {
+                    Ok(level) => format!("{}x{}", level.width, level.height),
+                    Err(_) => "BROKEN".to_owned(),
+                };
+                format!("{marker} {:<16}{size:>7}", entry.name().to_uppercase())
+            })
+            .collect();
+        lines.push(String::new());
+        let room = rows.saturating_sub(lines.len() as u32);
+        match &self.entries[self.selected].level {
+            Ok(level) => lines.extend(level.thumbnail(columns, room)),
+            Err(err) => lines.push(err.clone()),
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    // A small valid level with a wall in the middle
+    const BOX: &str = "snek-level 1\nname box\n.....\n.....\n..#..\n.....\n.....\n";
+
+    #[test]
+    fn parse_round_trips() {
+        let level = Level::parse(BOX).unwrap();
+        assert_eq!((level.width, level.height), (5, 5));
+        assert_eq!(level.walls, [Point::new(2, 2)]);
+        assert_eq!(level.serialize(), BOX);
+        let level = Level {
+            start: Some((Point::new(0, 3), Direction::Down)),
+            food: vec![Point::new(0, 0), Point::new(4, 0)],
+            ..level
+        };
+        assert_eq!(Level::parse(&level.serialize()), Ok(level));
+    }
+
+    #[test]
+    fn parse_explains_whats_wrong() {
+        assert_eq!(
+            Level::parse("snek-level 2\n"),
+            Err("unknown level file version".to_owned())
+        );
+        assert_eq!(
+            Level::parse(&BOX.replace("name box", "box")),
+            Err("missing 'name' line".to_owned())
+        );
+        assert_eq!(
+            Level::parse(&BOX.replace("..#..", "..#.")),
+            Err("row 3 isn't as long as the first".to_owned())
+        );
+        assert_eq!(
+            Level::parse(&BOX.replace("..#..", "..x..")),
+            Err("unknown cell 'x' in row 3".to_owned())
+        );
+        assert!(Level::parse("snek-level 1\nname tiny\n..\n..\n").is_err());
+        assert!(Level::parse(&BOX.replace("box\n.", "box\n#")).is_err());
+        assert!(Level::parse(&BOX.replace("name box\n", "name box\nfood 2,2\n")).is_err());
+    }
+
+    #[test]
+    fn discovery_marks_broken_levels() {
+        let dir = env::temp_dir().join(format!("snek-levels-{}", process::id()));
+        let extra = dir.join("extra");
+        fs::create_dir_all(&extra).unwrap();
+        fs::write(dir.join("a.txt"), BOX).unwrap();
+        fs::write(dir.join("b.txt"), "snek-level 1\nname broken\n##\n").unwrap();
+        fs::write(dir.join("notes.md"), "not a level").unwrap();
+        fs::write(extra.join("c.txt"), BOX.replace("name box", "name other")).unwrap();
+        let entries = discover(&[dir.clone(), extra.clone(), dir.join("missing")]);
+        let names: Vec<String> = entries.iter().map(LevelEntry::name).collect();
+        assert_eq!(names, ["box", "b", "other"]);
+        assert!(entries[1].level.is_err());
+
+        let mut picker = LevelPicker::new(entries, Some(&extra.join("c.txt")));
+        assert_eq!(picker.selected().unwrap().1.name, "other");
+        picker.step(true);
+        assert_eq!(picker.selected().unwrap().1.name, "box");
+        picker.step(false);
+        picker.step(false);
+        assert!(picker.selected().is_none());
+        let lines = picker.lines(20, 20);
+        assert_eq!(lines[1], format!("> {:<16}{:>7}", "B", "BROKEN"));
+        assert!(lines.last().unwrap().contains("at least"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn thumbnails_fit() {
+        let level = Level::parse(BOX).unwrap();
+        assert_eq!(level.thumbnail(5, 5)[2], "..#..");
+        assert_eq!(level.thumbnail(3, 3), ["...", ".#.", "..."]);
+        assert_eq!(
+            LevelPicker::new(Vec::new(), None).lines(10, 10),
+            ["NO LEVELS FOUND"]
+        );
+    }
+}
@@ -0,0 +1,270 @@
+// Debug logging to a file (`--log-file`), through the `log` crate.
+//
+// Nothing is ever logged to the terminal, since the game is drawn there. Every
+// record is one line of space separated fields:
+//
+//     MILLIS LEVEL EVENT key=value...
+//
+// where `MILLIS` is how long the logger has been running, `LEVEL` is `ERROR`,
+// `WARN`, `INFO`, `DEBUG`, or `TRACE`, and `EVENT` is a single word naming what
+// happened. Values never contain spaces, so lines can be split on them. How
+// much is logged is set with `SNEK_LOG` (`info` by default), and ticks are
+// logged at `debug` as `tick number=N duration_us=MICROSECONDS`. The events
+// the game itself logs are the `LogEvent`s, whose fields don't change.
+
+use std::env;
+use std::fmt::{self, Display};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use console_engine::KeyCode;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::Point;
+use crate::options::Options;
+use crate::world::{DeathCause, Direction};
+
+// How much is logged when `SNEK_LOG` isn't set (or isn't a level)
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+
+// Something that happened in a game, logged as `EVENT key=value...`
+pub enum LogEvent<'a> {
+    Init {
+        options: &'a Options,
+    },
+    SlowFrame {
+        duration_us: u128,
+        expected_us: u128,
+    },
+    Tick {
+        number: u64,
+        duration_us: u64,
+    },
+    Input {
+        key: KeyCode,
+        queued: usize,
+    },
+    Direction {
+        tick: u64,
+        direction: Direction,
+    },
+    FoodSpawned {
+        at: Point,
+    },
+    Death {
+        cause: Option<DeathCause>,
+        head: Point,
+        score: usize,
+    },
+    Teardown {
+        score: usize,
+        ticks: u64,
+        won: bool,
+    },
+}
+
+impl Display for LogEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogEvent::Init { options } => write!(
+                f,
+                "init mode={} difficulty={} width={} height={} fps={} seed={}",
+                options.mode.name(),
+                options.difficulty.name(),
+                options.width,
+                options.height,
+                options.fps,
+                options.seed
+            ),
+            LogEvent::SlowFrame {
+                duration_us,
+                expected_us,
+            } => write!(
+                f,
+                "slow_frame duration_us={duration_us} expected_us={expected_us}"
+            ),
+            LogEvent::Tick {
+                number,
+                duration_us,
+            } => write!(f, "tick number={number} duration_us={duration_us}"),
+            LogEvent::Input { key, queued } => {
+                // Key names like `Char('a')` have no spaces in them
+                write!(f, "input key={key:?} queued={queued}")
+            }
+            LogEvent::Direction { tick, direction } => {
+                write!(f, "direction tick={tick} direction={}", direction.name())
+            }
+            LogEvent::FoodSpawned { at } => write!(f, "food_spawned x={} y={}", at.x, at.y),
+            LogEvent::Death { cause, head, score } => write!(
+                f,
+                "death cause={} x={} y={} score={score}",
+                cause.map_or("quit", |cause| cause.name()),
+                head.x,
+                head.y
+            ),
+            LogEvent::Teardown { score, ticks, won } => {
+                write!(f, "teardown score={score} ticks={ticks} won={won}")
+            }
+        }
+    }
+}
+
+// Returns the line a record is written as, some milliseconds after logging started
+fn line(millis: u128, level: Level, message: impl Display) -> String {
+    format!("{millis} {level} {message}\n")
+}
+
+// Writes records to a file as they come, one per line
+struct FileLogger {
+    file: Mutex<File>,
+    started: Instant,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = line(
+            self.started.elapsed().as_millis(),
+            record.level(),
+            record.args(),
+        );
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+// Starts logging to the end of a file, at the level given by `SNEK_LOG`
+pub fn start(path: &Path) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let logger = FileLogger {
+        file: Mutex::new(file),
+        started: Instant::now(),
+    };
+    log::set_logger(Box::leak(Box::new(logger)))
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    let level = env::var("SNEK_LOG")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(DEFAULT_LEVEL);
+    log::set_max_level(level);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_written_as_documented() {
+        let options = Options {
+            seed: 42,
+            ..Options::default()
+        };
+        let events = [
+            (
+                Level::Info,
+                LogEvent::Init { options: &options },
+                "init mode=classic difficulty=normal width=17 height=15 fps=8 seed=42",
+            ),
+            (
+                Level::Debug,
+                LogEvent::SlowFrame {
+                    duration_us: 190_000,
+                    expected_us: 125_000,
+                },
+                "slow_frame duration_us=190000 expected_us=125000",
+            ),
+            (
+                Level::Debug,
+                LogEvent::Tick {
+                    number: 7,
+                    duration_us: 125_012,
+                },
+                "tick number=7 duration_us=125012",
+            ),
+            (
+                Level::Debug,
+                LogEvent::Input {
+                    key: KeyCode::Char('a'),
+                    queued: 2,
+                },
+                "input key=Char('a') queued=2",
+            ),
+            (
+                Level::Debug,
+                LogEvent::Direction {
+                    tick: 7,
+                    direction: Direction::Up,
+                },
+                "direction tick=7 direction=up",
+            ),
+            (
+                Level::Debug,
+                LogEvent::FoodSpawned {
+                    at: Point::new(3, 9),
+                },
+                "food_spawned x=3 y=9",
+            ),
+            (
+                Level::Info,
+                LogEvent::Death {
+                    cause: Some(DeathCause::Wall),
+                    head: Point::new(17, 4),
+                    score: 5,
+                },
+                "death cause=wall x=17 y=4 score=5",
+            ),
+            (
+                Level::Info,
+                LogEvent::Death {
+                    cause: None,
+                    head: Point::new(2, 0),
+                    score: 0,
+                },
+                "death cause=quit x=2 y=0 score=0",
+            ),
+            (
+                Level::Info,
+                LogEvent::Teardown {
+                    score: 5,
+                    ticks: 40,
+                    won: false,
+                },
+                "teardown score=5 ticks=40 won=false",
+            ),
+        ];
+        for (level, event, expected) in events {
+            assert_eq!(
+                line(1_250, level, event),
+                format!("1250 {level} {expected}\n")
+            );
+        }
+        assert_eq!(
+            line(
+                3,
+                Level::Debug,
+                LogEvent::Tick {
+                    number: 1,
+                    duration_us: 9
+                }
+            ),
+            "3 DEBUG tick number=1 duration_us=9\n"
+        );
+    }
+}
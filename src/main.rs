@@ -1,36 +1,268 @@
-use console_engine::{self, Color, ConsoleEngine, KeyCode, pixel};
+mod achievements;
+mod arena;
+#[cfg(feature = "audio")]
+mod audio;
+mod bot;
+mod config;
+#[cfg(all(unix, feature = "control"))]
+mod control;
+mod countdown;
+mod crash;
+mod curve;
+mod daily;
+mod dashboard;
+mod debug_log;
+mod editor;
+mod events;
+mod export;
+mod ghost;
+mod hint;
+mod history;
+mod idle;
+mod input;
+mod lag;
+mod level;
+mod logging;
+mod marathon;
+#[cfg(feature = "net")]
+mod net;
+#[cfg(feature = "notify")]
+mod notify;
+#[cfg(feature = "online")]
+mod online;
+mod options;
+mod pace;
+#[cfg(feature = "discord")]
+mod presence;
+mod render;
+mod replay;
+mod save;
+mod scores;
+#[cfg(feature = "online")]
+mod serve;
+mod share;
+mod sound;
+mod spectate;
+mod splits;
+mod stats;
+mod storage;
+mod summary;
+mod trail;
+mod versus;
+mod vote;
+mod world;
+
+use std::collections::VecDeque;
+#[cfg(not(feature = "discord"))]
+use std::convert::Infallible;
+use std::fs;
+use std::net::{TcpListener, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use achievements::Achievement;
+use config::CONFIG_FILE;
+use console_engine::{self, Color, ConsoleEngine, KeyCode};
+use countdown::{Countdown, ResumeCountdown};
+use crash::CrashEvent;
+use daily::{DAILY_FILE, DailyLedger, DailyResult};
+use dashboard::Dashboard;
+use debug_log::DebugLog;
+use editor::{Editor, EditorExit};
 use euclid::{Point2D, UnknownUnit, Vector2D};
+use events::{EventSender, GameEvent, SnekEvent};
+use ghost::Ghost;
+use history::{HISTORY_FILE, History, HistoryEntry};
+use idle::IdleTimer;
+use input::TurnKeys;
+use lag::LagMeter;
+use level::{Level, LevelPicker};
+use logging::LogEvent;
+use marathon::Marathon;
+use options::{Assist, Command, Mode, Options, Scoring, SteeringMode};
+use pace::{PACE_FILE, PaceLog, Timeline};
+use render::Buffer;
+use replay::Replay;
+use save::{SAVE_FILE, SaveState};
+use scores::{SCORES_FILE, ScoreEntry, ScoreTable};
+use sound::{Cue, SoundSink};
+use spectate::Broadcaster;
+use splits::{BestSplits, SPLITS_FILE, SplitTracker};
+use stats::{STATS_FILE, Stats};
+use summary::GameSummary;
+use trail::Trail;
+use vote::Votes;
+use world::{Direction, Snek, Turn, World};
 
 // Engine initialization
 const WIDTH: u32 = 17;
 const HEIGHT: u32 = 15;
 const FPS: u32 = 8;
 
+// Smallest board the game can be played on, across and down
+const MIN_BOARD: u32 = 5;
+
+// Biggest board the game can be played on, across and down
+const MAX_BOARD: u32 = 256;
+
+// How many times input is read for every move of the snek, so quick taps between moves aren't missed
+const INPUT_POLLS: u32 = 4;
+
+// How many seconds a resumed game counts down before the snek moves again
+const RESUME_COUNTDOWN_SECONDS: u32 = 3;
+
+// Most turns that can be waiting for the snek's next moves
+const MAX_QUEUED_TURNS: usize = 3;
+
 // Controls
 const QUIT_KEY: KeyCode = KeyCode::Char('q');
 const PAUSE_KEY: KeyCode = KeyCode::Esc;
+const START_KEY: KeyCode = KeyCode::Char(' ');
+const SCORES_KEY: KeyCode = KeyCode::Tab;
+const SAVE_KEY: KeyCode = KeyCode::Char('s');
+const HINT_KEY: KeyCode = KeyCode::Char('h');
+const LEVEL_KEY: KeyCode = KeyCode::Char('l');
+const SCREENSHOT_KEY: KeyCode = KeyCode::F(12);
+const PAUSED_SCREENSHOT_KEY: KeyCode = KeyCode::Char('p');
+const WALL_KEY: KeyCode = KeyCode::Char(' ');
+const SNEK_KEY: KeyCode = KeyCode::Char('o');
+const PLAY_KEY: KeyCode = KeyCode::Char('p');
+const WIDER_KEY: KeyCode = KeyCode::Char(']');
+const NARROWER_KEY: KeyCode = KeyCode::Char('[');
+const TALLER_KEY: KeyCode = KeyCode::Char('=');
+const SHORTER_KEY: KeyCode = KeyCode::Char('-');
 const UP_KEY: KeyCode = KeyCode::Up;
 const DOWN_KEY: KeyCode = KeyCode::Down;
 const LEFT_KEY: KeyCode = KeyCode::Left;
 const RIGHT_KEY: KeyCode = KeyCode::Right;
+const TURN_KEYS: [KeyCode; 4] = [UP_KEY, DOWN_KEY, LEFT_KEY, RIGHT_KEY];
 
 // Colors of the on screen objects
 const MAP_COLOR: Color = Color::Green;
 const BORDER_COLOR: Color = Color::Black;
+const BORDER_LINE_COLOR: Color = Color::Green;
 const FOOD_COLOR: Color = Color::Red;
+// Color of what a food's worth, shown on it with `--food-decay`
+const FOOD_VALUE_COLOR: Color = Color::White;
+const WALL_COLOR: Color = Color::DarkGrey;
+// Color of the column and row indexes on the border (with `--grid-labels`)
+const GRID_LABEL_COLOR: Color = Color::DarkGrey;
+// Color of the decoy in choice mode, which shrinks the snek instead of growing it
+const DECOY_COLOR: Color = Color::DarkYellow;
+// What pulsing food fades between, on terminals that can show it
+const FOOD_DIM_RGB: [u8; 3] = [128, 0, 0];
+const FOOD_BRIGHT_RGB: [u8; 3] = [255, 96, 96];
 const SNEK_COLOR: Color = Color::Blue;
+// What the cells the tail left fade between (from just left to gone), on
+// terminals that can show it, and what they are elsewhere until they're gone
+const TRAIL_RGB: [u8; 3] = [0, 96, 192];
+const TRAIL_FADED_RGB: [u8; 3] = [0, 255, 0];
+const TRAIL_COLOR: Color = Color::DarkGreen;
+// Color of the ghost of the best run (with `--ghost`)
+const GHOST_COLOR: Color = Color::DarkCyan;
+// Colors the snek shifts through as it grows, one per `LENGTH_MILESTONE` segments grown
+const SNEK_COLORS: [Color; 4] = [SNEK_COLOR, Color::Cyan, Color::Yellow, Color::White];
+// Colors the snek cycles through on its victory lap
+const VICTORY_COLORS: [Color; 6] = [
+    Color::Red,
+    Color::Yellow,
+    Color::White,
+    Color::Cyan,
+    Color::Blue,
+    Color::Magenta,
+];
+const LENGTH_MILESTONE: usize = 10;
+const RIVAL_COLOR: Color = Color::Magenta;
 const HEAD_COLOR: Color = Color::Black;
+// Color of the snek's head whatever color its body is (with `--fixed-head`)
+const FIXED_HEAD_COLOR: Color = Color::DarkMagenta;
+const HINT_COLOR: Color = Color::White;
+// Color of the snek's head when it's about to die (with hints shown)
+const DANGER_COLOR: Color = Color::DarkRed;
+// Color of the border while the snek's slowed down by `--assist slowmo`
+const SLOWMO_BORDER_COLOR: Color = Color::DarkGrey;
+// Colors of a split that's ahead of or behind the best one, or beat it
+const AHEAD_COLOR: Color = Color::Green;
+const BEHIND_COLOR: Color = Color::Red;
+const GOLD_COLOR: Color = Color::Yellow;
 
 // Characters and strings that will be drawn
 const EYE_CHAR: char = '^';
+const BAR_FULL_CHAR: char = '█';
+const BAR_EMPTY_CHAR: char = '░';
 const DEAD_EYE_CHAR: char = 'x';
+const HINT_CHAR: char = '+';
+const CURSOR_CHAR: char = '*';
+// Arrows for the directions, in the order they're declared (up, down, left, right)
+const HEADING_CHARS: [char; 4] = ['^', 'v', '<', '>'];
+// Whether the game is ahead of, behind, or level with the best one
+const PACE_AHEAD_CHAR: char = '▲';
+const PACE_BEHIND_CHAR: char = '▼';
+const PACE_EVEN_CHAR: char = '=';
+// Blocks filling the left (and the bottom) of a cell an eighth more each, for
+// sliding the snek between cells with `--smooth-motion`
+const LEFT_EIGHTH_CHARS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+const LOWER_EIGHTH_CHARS: [char; 7] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇'];
+// Corners (top left, top right, bottom left, bottom right) and edges (across,
+// down) of the line around the map with `--border sharp` or `rounded`
+const SHARP_BORDER_CHARS: [char; 6] = ['┌', '┐', '└', '┘', '─', '│'];
+const ROUNDED_BORDER_CHARS: [char; 6] = ['╭', '╮', '╰', '╯', '─', '│'];
 const GAME_PROMPT: &str = "SNEK";
 const PAUSE_PROMPT: &str = "PAUSED";
+const IDLE_PAUSE_PROMPT: &str = "PAUSED (IDLE)";
+const RESUME_PROMPT: &str = "READY IN ";
 const SCORE_PROMPT: &str = "SCORE: ";
+const BEST_PROMPT: &str = "BEST ";
+const SPEED_SUFFIX: &str = "/S";
+const SCORES_PROMPT: &str = "HIGH SCORES";
+const DASHBOARD_PROMPT: &str = "RECORDS";
+const LEVELS_PROMPT: &str = "LEVELS";
+const START_PROMPT: &str = "PRESS SPACE";
+const SAVE_FAILED_PROMPT: &str = "SAVE FAILED";
+const SCREENSHOT_FAILED_PROMPT: &str = "SCREENSHOT FAILED";
+const LAG_PROMPT: &str = "LAGGING";
+const WATCH_PROMPT: &str = "WATCHING";
+const GAME_OVER_PROMPT: &str = "GAME OVER";
+const DISCONNECTED_PROMPT: &str = "DISCONNECTED";
+const WAITING_PROMPT: &str = "WAITING";
+const RECONNECTING_PROMPT: &str = "RECONNECTING";
+const WIN_PROMPT: &str = "YOU WIN";
+const LOSE_PROMPT: &str = "YOU LOSE";
+const DRAW_PROMPT: &str = "DRAW";
+const EDIT_PROMPT: &str = "EDITING";
+const SAVED_PROMPT: &str = "SAVED";
+const UNSAVED_PROMPT: &str = "UNSAVED, Q TO QUIT";
+const SNEK_STARTS_PROMPT: &str = "SNEK STARTS HERE";
+const NO_ROOM_PROMPT: &str = "NO ROOM FOR SNEK";
+const TOO_BIG_PROMPT: &str = "TOO BIG TO SHOW";
+
+// Longest the progress bar toward the target score can be
+const MAX_BAR_LEN: usize = 10;
+
+// How long banners stay on screen, in seconds
+const BANNER_SECONDS: u32 = 2;
+
+// How long the victory lap after a win lasts, and how long each of its colors
+// (and each flash of its prompt) is shown for
+const VICTORY_LAP: Duration = Duration::from_secs(3);
+const VICTORY_STEP: Duration = Duration::from_millis(250);
 
-// Printed at the end of the game
+// How many moves `--assist slowmo` slows the snek down for when it's about to
+// die, and how long it has to wait before it can do it again
+const SLOWMO_MOVES: u32 = 2;
+// How many moves have to pass after a slowmo before there can be another one,
+// counted in game ticks so time spent paused doesn't count
+const SLOWMO_COOLDOWN: u64 = 50;
+
+// How long the running total is shown between the lives of a marathon
+const MARATHON_BREAK: Duration = Duration::from_secs(2);
+
+// Printed at the end of the game when the snek died, or else when it didn't
 const END_MESSAGE: &str = "Due to your subpar prowess and deriliction of duty, the snek's concept \
 of a subjective experience and consciousness has ceased to be...\nFinal Score: ";
+const SCORE_MESSAGE: &str = "Final Score: ";
 
 // Snek initialization
 const STARTING_BODY: [Point; 4] = [
@@ -40,272 +272,1781 @@ const STARTING_BODY: [Point; 4] = [
     Point::new(3, 0),
 ];
 
+// A control socket being served for the game (which can't exist without the
+// `control` feature on a Unix system)
+#[cfg(all(unix, feature = "control"))]
+type ControlSocket = control::Control;
+#[cfg(not(all(unix, feature = "control")))]
+type ControlSocket = std::convert::Infallible;
+
 // Represents an on scren point and vector
 type Point = Point2D<i32, UnknownUnit>;
 type Vector = Vector2D<i32, UnknownUnit>;
 
-// Represents the game (the snek and the engine)
+// What the player chose to do from the start screen
+enum StartChoice {
+    Play,
+    Quit,
+    Level(PathBuf, Level),
+}
+
+// Represents the game (the world and the engine)
 struct Game {
-    snek: Snek,
-    food: Point,
+    world: World,
     paused: bool,
+    paused_idle: bool,
+    // A game carried on from a save counts down before the snek moves again,
+    // once it's first unpaused
+    count_down_on_unpause: bool,
+    resume: Option<ResumeCountdown>,
+    idle: IdleTimer,
+    showing_scores: bool,
+    showing_hint: bool,
+    scores: ScoreTable,
+    stats: Stats,
     engine: ConsoleEngine,
-    width: u32,
-    height: u32,
+    frame: Buffer,
+    started: Instant,
+    saved: bool,
+    unlocked: Vec<String>,
+    banner: Option<(String, u32)>,
+    lag: LagMeter,
+    trail: Trail,
+    ghost: Option<Ghost>,
+    move_progress: u32,
+    // How many more moves are slowed down by `--assist slowmo`, and when it
+    // last started slowing them
+    slowmo_moves: u32,
+    last_slowmo: Option<u64>,
+    last_move: Instant,
+    turns: VecDeque<KeyCode>,
+    turn_keys: TurnKeys,
+    events: EventSender,
+    sound: Box<dyn SoundSink>,
+    control: Option<ControlSocket>,
+    broadcaster: Option<Broadcaster>,
+    votes: Option<Votes>,
+    splits: SplitTracker,
+    pace: Timeline,
+    best_pace: Option<Timeline>,
+    marathon: Marathon,
+    debug_log: Option<DebugLog>,
 }
 
 impl Game {
-    // Creates a new game
-    fn new(width: u32, height: u32, fps: u32, starting_body: &[Point]) -> Self {
+    // Creates a new game, optionally sending what happens in it over a channel
+    fn new(options: Options, starting_body: &[Point], events: Option<Sender<GameEvent>>) -> Self {
+        let (screen_width, screen_height) = options.screen_size();
+        let engine = ConsoleEngine::init(screen_width, screen_height, options.fps * INPUT_POLLS)
+            .expect("Console Engine failed to initialize");
+        input::enhance_keyboard();
+        Self::with_engine(engine, options, starting_body, events)
+    }
+
+    // Creates a new game on an engine that's already running, which must be
+    // the size of the screen and run at `fps * INPUT_POLLS` frames a second
+    fn with_engine(
+        engine: ConsoleEngine,
+        options: Options,
+        starting_body: &[Point],
+        events: Option<Sender<GameEvent>>,
+    ) -> Self {
+        let stats = Stats::load_default();
+        crash::set_options(&options);
+        log::info!("{}", LogEvent::Init { options: &options });
         Self {
-            snek: Snek::new(starting_body),
-            food: rand_point(width, height, starting_body),
             paused: false,
-            engine: ConsoleEngine::init(width * 2 + 4, height + 2, fps)
-                .expect("Console Engine failed to initialize"),
-            width,
-            height,
+            paused_idle: false,
+            count_down_on_unpause: false,
+            resume: None,
+            idle: IdleTimer::new(options.idle_pause),
+            debug_log: options.debug.then(DebugLog::default),
+            showing_scores: false,
+            showing_hint: options.hints,
+            scores: ScoreTable::load_default(),
+            unlocked: stats.achievements.clone(),
+            stats,
+            pace: Timeline::default(),
+            best_pace: PaceLog::load_default().best(options.mode).cloned(),
+            splits: SplitTracker::new(&BestSplits::load_default(), &options, starting_body.len()),
+            frame: Buffer::new(engine.get_width(), engine.get_height()),
+            engine,
+            lag: LagMeter::new(options.fps * INPUT_POLLS),
+            trail: Trail::default(),
+            ghost: options.ghost.then(|| Ghost::load(&options)).flatten(),
+            move_progress: 0,
+            slowmo_moves: 0,
+            last_slowmo: None,
+            last_move: Instant::now(),
+            turns: VecDeque::new(),
+            turn_keys: TurnKeys::default(),
+            sound: sound::sink(options.sound, options.volume),
+            world: World::new(options, starting_body),
+            started: Instant::now(),
+            saved: false,
+            banner: None,
+            events: EventSender::new(events),
+            control: None,
+            broadcaster: None,
+            votes: None,
+            marathon: Marathon::default(),
+        }
+    }
+
+    // Creates a game that carries on from a save
+    fn from_save(save: SaveState, events: Option<Sender<GameEvent>>) -> Self {
+        let mut game = Self::new(save.options.clone(), &save.body, events);
+        game.world = save.world();
+        game.turns = save
+            .queued_turns
+            .iter()
+            .map(|direction| TURN_KEYS[*direction as usize])
+            .collect();
+        game.started = Instant::now()
+            .checked_sub(Duration::from_secs(save.elapsed))
+            .unwrap_or_else(Instant::now);
+        game.marathon = save.marathon;
+        game.paused = true;
+        game.count_down_on_unpause = true;
+        game
+    }
+
+    // Returns everything needed to resume the game later
+    fn save_state(&self) -> SaveState {
+        let queued_turns = self.turns.iter().map(|key| key_direction(*key)).collect();
+        SaveState::new(
+            &self.world,
+            self.started.elapsed().as_secs(),
+            self.marathon,
+            queued_turns,
+        )
+    }
+
+    // Saves the game to the save file, quitting if it worked
+    fn save_and_quit(&mut self) {
+        let result = match storage::data_file(SAVE_FILE) {
+            Some(path) => self.save_state().save(&path),
+            None => Err(std::io::Error::other("no data directory")),
+        };
+        match result {
+            Ok(()) => self.saved = true,
+            Err(_) => self.show_banner(SAVE_FAILED_PROMPT),
+        }
+    }
+
+    // Saves the frame on screen as text (colored with escape sequences) to a
+    // file in the data directory named after the time, showing its name
+    fn screenshot(&mut self) {
+        let time = storage::format_time(storage::now()).replace([' ', ':'], "-");
+        let name = format!("screenshot-{time}.txt");
+        let result = match storage::data_file(&name) {
+            Some(path) => storage::write_atomic(&path, &self.frame.to_text()),
+            None => Err(std::io::Error::other("no data directory")),
+        };
+        match result {
+            Ok(()) => self.show_banner(format!("SAVED {name}")),
+            Err(_) => self.show_banner(SCREENSHOT_FAILED_PROMPT),
+        }
+    }
+
+    // Shows the start screen until the player starts, quits, or picks a level
+    fn start_screen(&mut self) -> StartChoice {
+        self.engine.set_title("SNEK");
+        let today = storage::today();
+        let mut highlights = self.stats.highlights(today);
+        highlights.push(DailyLedger::load_default().highlight(today, self.world.options.mode));
+        if let Some(level) = &self.world.options.level {
+            highlights.push(format!("LEVEL: {}", level.to_uppercase()));
+        }
+        let mut dashboard: Option<Vec<String>> = None;
+        let mut picker: Option<LevelPicker> = None;
+        loop {
+            let options = &self.world.options;
+            let picker_lines;
+            let (prompt, lines) = match (&picker, &dashboard) {
+                (Some(picker), _) => {
+                    picker_lines = picker.lines(options.width * options.cell_width, options.height);
+                    (LEVELS_PROMPT, &picker_lines)
+                }
+                (None, Some(lines)) => (DASHBOARD_PROMPT, lines),
+                (None, None) => (GAME_PROMPT, &highlights),
+            };
+            render::draw_map(&mut self.engine, options);
+            render::draw_prompt(&mut self.engine, prompt, 0);
+            let bottom = self.engine.get_height() as i32 - 1;
+            render::draw_prompt(&mut self.engine, START_PROMPT, bottom);
+            render::draw_lines(&mut self.engine, options, lines);
+
+            self.engine.draw();
+            self.engine.clear_screen();
+            self.engine.wait_frame();
+
+            if self.quit() {
+                return StartChoice::Quit;
+            } else if let Some(levels) = &mut picker {
+                if input::pressed(&self.engine, START_KEY)
+                    && let Some((path, level)) = levels.selected()
+                {
+                    return StartChoice::Level(path.to_owned(), level.clone());
+                } else if input::pressed(&self.engine, UP_KEY) {
+                    levels.step(false);
+                } else if input::pressed(&self.engine, DOWN_KEY) {
+                    levels.step(true);
+                } else if input::pressed(&self.engine, LEVEL_KEY)
+                    || input::pressed(&self.engine, PAUSE_KEY)
+                {
+                    picker = None;
+                }
+            } else if input::pressed(&self.engine, START_KEY) {
+                self.started = Instant::now();
+                return StartChoice::Play;
+            } else if input::pressed(&self.engine, SCORES_KEY) {
+                dashboard = match dashboard {
+                    Some(_) => None,
+                    None => Some(Dashboard::load_default().lines()),
+                };
+            } else if input::pressed(&self.engine, LEVEL_KEY) && self.world.options.daily.is_none()
+            {
+                let dirs = level::level_dirs(self.world.options.level_dir.as_deref());
+                let last = level::load_last();
+                picker = Some(LevelPicker::new(level::discover(&dirs), last.as_deref()));
+            }
         }
     }
 
     // The main game loop that runs throughout the game
     fn main_loop(&mut self) {
         self.engine.set_title("SNEK");
-        while self.snek.alive {
-            self.snek.alive = !(self.quit() || self.snek.dead(self.width, self.height));
-            self.draw();
+        // A marathon carries on with its next life until they've all run out
+        loop {
+            self.idle.active();
+            while self.world.running() && !self.saved {
+                // Quitting stops the game where it is, without drawing the
+                // snek as if it died
+                if self.quit() {
+                    self.world.snek.alive = false;
+                    break;
+                }
+                if let Some(event) = self.world.check_death() {
+                    self.log_death();
+                    self.snek_event(event);
+                }
+                if let Some(broadcaster) = &mut self.broadcaster {
+                    broadcaster.update(&self.world);
+                }
+                self.draw();
+
+                self.engine.draw();
+                self.sound.flush();
+                self.engine.clear_screen();
+                self.engine.wait_frame();
+                self.trail.frame();
+                if self.lag.frame() && self.world.options.lag_warning {
+                    self.show_banner(LAG_PROMPT);
+                }
+
+                self.input();
+                self.serve_control();
+                if !self.paused && self.idle.idle() {
+                    log::info!("idle_pause ticks={}", self.world.ticks);
+                    self.set_paused(true);
+                    self.paused_idle = true;
+                }
+                if self.move_due() {
+                    if let Some(key) = self.turns.pop_front() {
+                        self.steer(key);
+                    }
+                    if let Some(direction) = self.votes.as_ref().and_then(Votes::take) {
+                        self.world.snek.change_direction(direction);
+                        self.idle.active();
+                    }
+                    let score = self.run_score();
+                    let food = self.world.food.clone();
+                    let body = self.world.snek.body.clone();
+                    if let Some(event) = self.world.advance() {
+                        self.log_move(&food);
+                        if let Some(debug_log) = &mut self.debug_log {
+                            debug_log.record(&self.world, &self.turns);
+                        }
+                        if self.world.options.trail_fade {
+                            self.trail.vacate(&body, &self.world.snek.body);
+                        }
+                        self.snek_event(event);
+                        if self.run_score() != score {
+                            self.events.send(GameEvent::Scored {
+                                score: self.run_score(),
+                            });
+                            if self.beat_high_score(score) {
+                                self.sound.play(Cue::HighScore);
+                            }
+                        }
+                        self.update_splits();
+                        self.pace.update(self.run_ticks(), self.run_score());
+                        self.check_achievements();
+                    }
+                }
+            }
+            if !self.next_life() {
+                break;
+            }
+        }
+        let score = self.run_score();
+        log::info!(
+            "{}",
+            LogEvent::Teardown {
+                score,
+                ticks: self.world.ticks,
+                won: self.world.won,
+            }
+        );
+        self.events.send(GameEvent::Ended { score });
+        self.finish_control();
+        if let Some(broadcaster) = self.broadcaster.take() {
+            broadcaster.finish();
+        }
+        if let Some(votes) = self.votes.take() {
+            votes.finish();
+        }
+    }
+
+    // Shows how the game ended until the player presses start or quit, or the
+    // timeout runs out first (which any other key stops, leaving the screen up)
+    fn game_over_screen(&mut self, timeout: Duration) {
+        let prompt = match self.world.won {
+            true => WIN_PROMPT,
+            false => GAME_OVER_PROMPT,
+        };
+        let mut countdown = Countdown::new(timeout);
+        let mut last_second = None;
+        loop {
+            if countdown.done() {
+                log::info!("game over screen timed out");
+                return;
+            }
+            let second = countdown.seconds_left();
+            if second.is_some() && last_second != second {
+                self.sound.play(Cue::Countdown);
+                last_second = second;
+            }
+            let text = match second {
+                Some(second) => format!("{prompt} ({second})"),
+                None => prompt.to_owned(),
+            };
+            render::draw_world(&mut self.engine, &self.world, &text);
 
             self.engine.draw();
+            self.sound.flush();
             self.engine.clear_screen();
             self.engine.wait_frame();
 
-            self.input();
-            if !self.paused {
-                self.snek.slither(&mut self.food, self.width, self.height);
+            if self.quit() || input::pressed(&self.engine, START_KEY) {
+                return;
+            }
+            if self.any_key() {
+                countdown.stop();
             }
         }
     }
 
-    // Returns the score of the game
-    fn score(&self) -> usize {
-        self.snek.score()
+    // Plays the victory lap after a win, with the snek cycling through colors
+    // under a flashing prompt, until it's over or a key's pressed
+    fn victory_lap(&mut self) {
+        let shown = Instant::now();
+        while shown.elapsed() < VICTORY_LAP {
+            render::draw_victory_lap(&mut self.engine, &self.world, shown.elapsed());
+
+            self.engine.draw();
+            self.engine.clear_screen();
+            self.engine.wait_frame();
+
+            if self.any_key() {
+                return;
+            }
+        }
     }
 
-    // Draws the map, snek, and food
-    fn draw(&mut self) {
-        self.draw_map();
-        self.draw_prompts();
-        self.draw_food();
-        self.draw_snek();
-    }
-
-    // Draws the border and map
-    fn draw_map(&mut self) {
-        self.engine.fill(pixel::pxl_bg(' ', BORDER_COLOR));
-        self.engine.fill_rect(
-            2,
-            1,
-            self.engine.get_width() as i32 - 3,
-            self.engine.get_height() as i32 - 2,
-            pixel::pxl_bg(' ', MAP_COLOR),
-        );
+    // Returns whether any of the game's keys was pressed this frame
+    fn any_key(&self) -> bool {
+        let keys = [
+            QUIT_KEY, START_KEY, PAUSE_KEY, SCORES_KEY, SAVE_KEY, HINT_KEY,
+        ];
+        TURN_KEYS
+            .iter()
+            .chain(&keys)
+            .any(|key| input::pressed(&self.engine, *key))
     }
 
-    // Draws the prompts (game, pause, and score)
-    fn draw_prompts(&mut self) {
-        let score = SCORE_PROMPT.to_owned() + &self.score().to_string();
-        let mid = self.engine.get_width() / 2 - score.len() as u32 / 2;
-        self.engine.print_fbg(
-            mid as i32,
-            self.engine.get_height() as i32 - 1,
-            &score,
-            Color::Reset,
-            BORDER_COLOR,
-        );
-        let prompt = match self.paused {
-            true => PAUSE_PROMPT,
-            false => GAME_PROMPT,
+    // Returns whether the score just went past the best one in the score
+    // table for the mode, from what it was before the last move
+    fn beat_high_score(&self, before: usize) -> bool {
+        self.scores
+            .mode_entries(self.world.options.mode)
+            .next()
+            .is_some_and(|best| before <= best.score && self.run_score() > best.score)
+    }
+
+    // Sends something that happened to the snek down the event channel, and
+    // plays its sound (eating counting as the player being there)
+    fn snek_event(&mut self, event: SnekEvent) {
+        self.events.send(GameEvent::Snek(event));
+        if let SnekEvent::Ate(_) = event {
+            self.idle.active();
+        }
+        if let Some(cue) = Cue::for_event(event) {
+            self.sound.play(cue);
+        }
+    }
+
+    // Returns whether it's time for the snek's next move, which comes every
+    // `INPUT_POLLS` frames (or sooner, once it's sped up in a rush, or later
+    // along one axis with `--aspect-compensate` or while `--assist slowmo` has
+    // it slowed down)
+    fn move_due(&mut self) -> bool {
+        if self.paused || resume_holding(&mut self.resume) {
+            return false;
+        }
+        self.start_slowmo();
+        let frames_per_second = self.world.options.fps * INPUT_POLLS;
+        let speed = self
+            .world
+            .move_fps(self.boosting(), self.slowmo_moves > 0)
+            .min(frames_per_second);
+        self.move_progress += match self.world.options.aspect_compensate {
+            true => self.axis_speed(speed),
+            false => speed,
         };
-        let mid = self.engine.get_width() / 2 - prompt.len() as u32 / 2;
-        self.engine
-            .print_fbg(mid as i32, 0, prompt, Color::Reset, BORDER_COLOR);
+        if self.move_progress < frames_per_second {
+            return false;
+        }
+        self.move_progress -= frames_per_second;
+        self.slowmo_moves = self.slowmo_moves.saturating_sub(1);
+        true
+    }
+
+    // Returns whether the snek's sped up with `--hold-boost`, by the key for
+    // the way it's heading (or up, when it's steered by turning) being held
+    fn boosting(&self) -> bool {
+        self.world.options.hold_boost && self.turn_keys.held(self.boost_key())
+    }
+
+    // Returns the key that speeds the snek up when it's held with `--hold-boost`
+    fn boost_key(&self) -> KeyCode {
+        boost_key(self.world.options.steering, self.world.snek.heading)
     }
 
-    // Draws the food
-    fn draw_food(&mut self) {
-        self.engine.set_pxl(
-            self.food.x * 2 + 2,
-            self.food.y + 1,
-            pixel::pxl_bg(' ', FOOD_COLOR),
+    // Slows the snek down for a few moves with `--assist slowmo` when it's due
+    fn start_slowmo(&mut self) {
+        if self.slowmo_moves > 0
+            || !slowmo_due(&self.world, !self.turns.is_empty(), self.last_slowmo)
+        {
+            return;
+        }
+        log::debug!("slowmo tick={}", self.world.ticks);
+        self.slowmo_moves = SLOWMO_MOVES;
+        self.last_slowmo = Some(self.world.ticks);
+    }
+
+    // Returns how many moves a second the snek makes along the way it's going,
+    // slowed along whichever axis a cell looks longer on (across, for cells
+    // three columns wide, or down, for cells one column wide, taking a column to
+    // be half as wide as a row is tall) so it seems as fast either way
+    fn axis_speed(&self, speed: u32) -> u32 {
+        let cell_width = self.world.options.cell_width;
+        let scaled = match self.world.snek.direction {
+            Direction::Left | Direction::Right if cell_width > 2 => speed * 2 / cell_width,
+            Direction::Up | Direction::Down if cell_width < 2 => speed * cell_width / 2,
+            _ => speed,
+        };
+        scaled.max(1)
+    }
+
+    // Logs (and records for crash reports) how long the move that was just
+    // made took, and logs any food it made appear (anything that isn't in the
+    // food from before it)
+    fn log_move(&mut self, food: &[Point]) {
+        let micros = self.last_move.elapsed().as_micros() as u64;
+        let number = self.world.ticks;
+        log::debug!(
+            "{}",
+            LogEvent::Tick {
+                number,
+                duration_us: micros,
+            }
         );
-        self.engine.set_pxl(
-            self.food.x * 2 + 3,
-            self.food.y + 1,
-            pixel::pxl_bg(' ', FOOD_COLOR),
+        crash::record(CrashEvent::Tick { number, micros });
+        self.last_move = Instant::now();
+        for spawned in self.world.food.iter().filter(|point| !food.contains(point)) {
+            log::debug!("{}", LogEvent::FoodSpawned { at: *spawned });
+        }
+    }
+
+    // Logs what the snek just died from
+    fn log_death(&self) {
+        log::info!(
+            "{}",
+            LogEvent::Death {
+                cause: self.world.cause,
+                head: *self.world.snek.body.last().unwrap(),
+                score: self.world.score(),
+            }
         );
     }
 
-    // Draws the snek
-    fn draw_snek(&mut self) {
-        for part in &self.snek.body {
-            self.engine
-                .set_pxl(part.x * 2 + 2, part.y + 1, pixel::pxl_bg(' ', SNEK_COLOR));
-            self.engine
-                .set_pxl(part.x * 2 + 3, part.y + 1, pixel::pxl_bg(' ', SNEK_COLOR));
+    // Returns the summary of the game so far (a marathon's being the whole
+    // run's, from the seed it started on)
+    fn summary(&self) -> GameSummary {
+        let mut summary = GameSummary::new(&self.world, self.started.elapsed().as_secs());
+        summary.score = self.run_score();
+        summary.seed = self.marathon.first_seed(summary.seed);
+        summary
+    }
+
+    // Returns the score of the whole run, which in a marathon counts the lives
+    // before this one
+    fn run_score(&self) -> usize {
+        self.marathon.total(self.world.score())
+    }
+
+    // Returns how many ticks the whole run's taken, which in a marathon counts
+    // the lives before this one
+    fn run_ticks(&self) -> u64 {
+        self.marathon.ticks + self.world.ticks
+    }
+
+    // Starts the next life of a marathon on a fresh board once the snek's died
+    // with lives left, after showing the running total for a moment; returns
+    // whether it did (and not if the player quit while it was shown)
+    fn next_life(&mut self) -> bool {
+        if self.world.options.mode != Mode::Marathon
+            || self.world.cause.is_none()
+            || self.saved
+            || !self.marathon.lives_left()
+        {
+            return false;
         }
-        let last = self.snek.body.last().unwrap();
-        let eye = match self.snek.alive {
-            true => EYE_CHAR,
-            false => DEAD_EYE_CHAR,
-        };
-        self.engine.set_pxl(
-            last.x * 2 + 2,
-            last.y + 1,
-            pixel::pxl_fbg(eye, HEAD_COLOR, SNEK_COLOR),
+        let prompt = format!(
+            "LIFE {}/{} TOTAL {}",
+            self.marathon.life + 1,
+            marathon::LIVES,
+            self.run_score()
         );
-        self.engine.set_pxl(
-            last.x * 2 + 3,
-            last.y + 1,
-            pixel::pxl_fbg(eye, HEAD_COLOR, SNEK_COLOR),
+        let shown = Instant::now();
+        while shown.elapsed() < MARATHON_BREAK {
+            render::draw_world(&mut self.engine, &self.world, &prompt);
+            self.engine.draw();
+            self.engine.clear_screen();
+            self.engine.wait_frame();
+            if self.quit() {
+                return false;
+            }
+        }
+        self.marathon.bank(self.world.score(), self.world.ticks);
+        log::info!(
+            "marathon life={} total={}",
+            self.marathon.life,
+            self.marathon.marathon_total
         );
+        let mut options = self.world.options.clone();
+        options.seed = marathon::next_seed(options.seed);
+        let starting_body = options.starting_body();
+        self.world = World::new(options, &starting_body);
+        self.trail = Trail::default();
+        self.move_progress = 0;
+        self.slowmo_moves = 0;
+        self.last_slowmo = None;
+        self.turns.clear();
+        self.banner = None;
+        true
+    }
+
+    // Adds a split if the snek's just reached the next length milestone
+    fn update_splits(&mut self) {
+        let length = self.world.snek.body.len();
+        if self
+            .splits
+            .update(length, self.world.ticks, self.started.elapsed())
+        {
+            log::info!("split length={length} ticks={}", self.world.ticks);
+        }
+    }
+
+    // Shows a banner for any achievements unlocked since the last check
+    fn check_achievements(&mut self) {
+        let summary = self.summary();
+        let earned: Vec<&Achievement> =
+            achievements::newly_earned(&summary, &self.unlocked).collect();
+        for achievement in earned {
+            self.unlocked.push(achievement.id.to_owned());
+            self.show_banner(achievement.name);
+        }
+    }
+
+    // Shows a banner in place of the prompt for a couple of seconds
+    fn show_banner(&mut self, banner: impl Into<String>) {
+        let frames = self.world.options.fps * INPUT_POLLS * BANNER_SECONDS;
+        self.banner = Some((banner.into(), frames));
+    }
+
+    // Draws the map, snek, and food (or the score table) into the frame, and
+    // then the frame onto the screen
+    fn draw(&mut self) {
+        let prompt = self.prompt();
+        if self.showing_scores {
+            render::draw_map(&mut self.frame, &self.world.options);
+            render::draw_score(&mut self.frame, &self.world);
+            render::draw_prompt(&mut self.frame, &prompt, 0);
+            self.draw_scores();
+        } else {
+            render::draw_world(&mut self.frame, &self.world, &prompt);
+            render::draw_trail(&mut self.frame, &self.trail, &self.world);
+            if let Some(body) = self
+                .ghost
+                .as_ref()
+                .and_then(|ghost| ghost.body(self.world.ticks))
+            {
+                render::draw_ghost(&mut self.frame, body, &self.world);
+            }
+            if self.world.options.smooth_motion && !self.paused && self.world.running() {
+                let frames_per_second = self.world.options.fps * INPUT_POLLS;
+                let progress = self.move_progress as f32 / frames_per_second as f32;
+                let direction = self.next_direction();
+                render::draw_glide(&mut self.frame, &self.world, direction, progress);
+            }
+            if let Some(best) = &self.best_pace {
+                let pace = pace::pace(self.run_score(), self.run_ticks(), best);
+                render::draw_pace(&mut self.frame, &prompt, best.final_score(), pace);
+            }
+            if let Some(votes) = &self.votes {
+                render::draw_votes(&mut self.frame, &prompt, votes.counts());
+            }
+            if let Some((Some(delta), gold)) = self.splits.latest() {
+                render::draw_split(&mut self.frame, &self.world, delta, gold);
+            }
+            if self.world.options.debug {
+                render::draw_speed(&mut self.frame, &self.world);
+            }
+            if self.world.options.food_pulse.is_some() {
+                let color = render::food_color(&self.world.options, self.started.elapsed());
+                render::draw_food(
+                    &mut self.frame,
+                    &self.world.food,
+                    &self.world.options,
+                    color,
+                );
+            }
+            if self.world.options.food_decay && self.world.options.scoring == Scoring::Points {
+                let color = render::food_color(&self.world.options, self.started.elapsed());
+                render::draw_food_values(&mut self.frame, &self.world, color);
+            }
+            if self.showing_hint
+                && self.world.running()
+                && let Some(cell) = hint::suggestion(&self.world)
+            {
+                render::draw_hint(&mut self.frame, cell, &self.world);
+            }
+            if self.showing_hint
+                && self.world.running()
+                && hint::in_danger(&self.world, self.next_direction(), hint::DANGER_MOVES)
+            {
+                render::draw_danger(&mut self.frame, &self.world);
+            }
+            if self.slowmo_moves > 0 {
+                render::tint_border(&mut self.frame, SLOWMO_BORDER_COLOR);
+            }
+        }
+        self.frame.blit(&mut self.engine);
+    }
+
+    // Returns the prompt shown above the map (game, pause, scores, or a banner)
+    fn prompt(&mut self) -> String {
+        let resuming = self
+            .resume
+            .as_ref()
+            .map(|resume| format!("{RESUME_PROMPT}{}", resume.seconds_left()));
+        let prompt = match (self.showing_scores, self.paused, &self.banner) {
+            (true, _, _) => SCORES_PROMPT,
+            (false, _, Some((banner, _))) => banner,
+            (false, true, None) if self.paused_idle => IDLE_PAUSE_PROMPT,
+            (false, true, None) => PAUSE_PROMPT,
+            (false, false, None) => resuming.as_deref().unwrap_or(GAME_PROMPT),
+        }
+        .to_owned();
+        if let Some((banner, frames)) = self.banner.take() {
+            self.banner = (frames > 1).then_some((banner, frames - 1));
+        }
+        prompt
+    }
+
+    // Draws the best scores of the current mode over the map
+    fn draw_scores(&mut self) {
+        let lines: Vec<String> = self
+            .scores
+            .mode_entries(self.world.options.mode)
+            .enumerate()
+            .map(|(rank, entry)| format!("{:>2}. {} {}", rank + 1, entry.name, entry.score))
+            .collect();
+        render::draw_lines(&mut self.frame, &self.world.options, &lines);
     }
 
     // Checks if the player wants to quit
     fn quit(&mut self) -> bool {
-        self.engine.is_key_pressed(QUIT_KEY)
+        input::pressed(&self.engine, QUIT_KEY)
     }
 
     // Deals with movement input; returns whether should quit or not
     fn input(&mut self) {
-        if self.engine.is_key_pressed(PAUSE_KEY) {
-            self.paused = !self.paused;
-        } else if self.engine.is_key_pressed(UP_KEY) {
-            self.snek.change_direction(Direction::Up);
-        } else if self.engine.is_key_pressed(DOWN_KEY) {
-            self.snek.change_direction(Direction::Down);
-        } else if self.engine.is_key_pressed(LEFT_KEY) {
-            self.snek.change_direction(Direction::Left);
-        } else if self.engine.is_key_pressed(RIGHT_KEY) {
-            self.snek.change_direction(Direction::Right);
+        let turn = self.turn_keys.pressed(&self.engine);
+        if input::pressed(&self.engine, PAUSE_KEY) {
+            self.set_paused(!self.paused);
+        } else if input::pressed(&self.engine, SCORES_KEY) && self.paused {
+            self.showing_scores = !self.showing_scores;
+        } else if input::pressed(&self.engine, HINT_KEY) {
+            self.showing_hint = !self.showing_hint;
+        } else if input::pressed(&self.engine, SAVE_KEY) && self.paused {
+            self.save_and_quit();
+        } else if input::pressed(&self.engine, SCREENSHOT_KEY)
+            || (input::pressed(&self.engine, PAUSED_SCREENSHOT_KEY) && self.paused)
+        {
+            self.screenshot();
+        } else if let Some(key) = turn
+            && self.votes.is_none()
+            && !self.repeated_boost(key)
+            && queue_turn(&mut self.turns, key)
+        {
+            log::debug!(
+                "{}",
+                LogEvent::Input {
+                    key,
+                    queued: self.turns.len(),
+                }
+            );
+            crash::record(CrashEvent::Input {
+                tick: self.world.ticks,
+                key,
+            });
+            self.idle.active();
         }
     }
+
+    // Returns whether a turn key is the boost key being held with
+    // `--hold-boost`, which wouldn't turn the snek, so its repeats don't fill
+    // the queue of turns
+    fn repeated_boost(&self, key: KeyCode) -> bool {
+        self.world.options.hold_boost && self.turns.is_empty() && key == self.boost_key()
+    }
+
+    // Pauses or unpauses the game, if it isn't already (starting the idle
+    // count over when it carries on)
+    fn set_paused(&mut self, paused: bool) {
+        if self.paused == paused {
+            return;
+        }
+        self.paused = paused;
+        self.paused_idle = false;
+        self.resume = None;
+        if !paused && self.count_down_on_unpause {
+            self.count_down_on_unpause = false;
+            self.resume = Some(ResumeCountdown::new(
+                RESUME_COUNTDOWN_SECONDS,
+                self.world.options.fps * INPUT_POLLS,
+            ));
+        }
+        self.showing_scores = false;
+        self.idle.active();
+        self.events.send(match paused {
+            true => GameEvent::Paused,
+            false => GameEvent::Unpaused,
+        });
+    }
+
+    // Follows the commands sent to the control socket and sends it the game's
+    // state, if there is one
+    #[cfg(all(unix, feature = "control"))]
+    fn serve_control(&mut self) {
+        let Some(mut control) = self.control.take() else {
+            return;
+        };
+        while let Some((client, command)) = control.command() {
+            match command {
+                control::ControlCommand::Pause => self.set_paused(true),
+                control::ControlCommand::Resume => self.set_paused(false),
+                control::ControlCommand::Steer(direction) => {
+                    self.world.snek.change_direction(direction);
+                    self.idle.active();
+                }
+                control::ControlCommand::State => control.reply(client, &self.world, self.paused),
+            }
+        }
+        control.update(&self.world, self.paused);
+        self.control = Some(control);
+    }
+
+    // Does nothing, since there's no control socket without the `control` feature
+    #[cfg(not(all(unix, feature = "control")))]
+    fn serve_control(&mut self) {}
+
+    // Tells the control socket's clients the game is over and removes it, if there is one
+    #[cfg(all(unix, feature = "control"))]
+    fn finish_control(&mut self) {
+        if let Some(control) = self.control.take() {
+            control.finish(self.world.score());
+        }
+    }
+
+    // Does nothing, since there's no control socket without the `control` feature
+    #[cfg(not(all(unix, feature = "control")))]
+    fn finish_control(&mut self) {}
+
+    // Turns the snek for a key, depending on how it's steered
+    fn steer(&mut self, key: KeyCode) {
+        steer_snek(&mut self.world.snek, self.world.options.steering, key);
+        log::debug!(
+            "{}",
+            LogEvent::Direction {
+                tick: self.world.ticks,
+                direction: self.world.snek.direction,
+            }
+        );
+    }
+
+    // Returns the way the snek's next move goes, counting the turn that's
+    // next in the queue
+    fn next_direction(&self) -> Direction {
+        let mut snek = self.world.snek.clone();
+        if let Some(key) = self.turns.front() {
+            steer_snek(&mut snek, self.world.options.steering, *key);
+        }
+        snek.direction
+    }
+}
+
+impl Drop for Game {
+    // Switches the terminal's keyboard back before the engine gives it back
+    fn drop(&mut self) {
+        input::restore_keyboard();
+    }
 }
 
-// Contains information about the snek
-struct Snek {
-    body: Vec<Point>,
-    start_len: usize,
-    direction: Direction,
-    eating: bool,
-    alive: bool,
+// Returns the key that speeds the snek up when it's held with `--hold-boost`,
+// which is up with relative steering or whichever way it's heading otherwise
+fn boost_key(steering: SteeringMode, heading: Direction) -> KeyCode {
+    match (steering, heading) {
+        (SteeringMode::Relative, _) | (SteeringMode::Absolute, Direction::Up) => UP_KEY,
+        (SteeringMode::Absolute, Direction::Down) => DOWN_KEY,
+        (SteeringMode::Absolute, Direction::Left) => LEFT_KEY,
+        (SteeringMode::Absolute, Direction::Right) => RIGHT_KEY,
+    }
 }
 
-impl Snek {
-    // Creates a new snek
-    fn new(starting_body: &[Point]) -> Self {
-        Self {
-            body: Vec::from(starting_body),
-            start_len: starting_body.len(),
-            direction: Direction::Right,
-            eating: false,
-            alive: true,
+// Returns whether `--assist slowmo` should slow the snek down, which is when
+// its next move would kill it and there's no turn queued to save it, unless it
+// was slowed down (on the given tick) too recently
+fn slowmo_due(world: &World, turns_queued: bool, last_slowmo: Option<u64>) -> bool {
+    world.options.assist == Some(Assist::Slowmo)
+        && !turns_queued
+        && world.running()
+        && last_slowmo.is_none_or(|last| world.ticks >= last + SLOWMO_COOLDOWN)
+        && hint::in_danger(world, world.snek.direction, 1)
+}
+
+// Counts a frame of the countdown before a resumed game carries on, returning
+// whether it's still holding the snek still (and dropping it once it's done)
+fn resume_holding(resume: &mut Option<ResumeCountdown>) -> bool {
+    if resume.as_mut().is_some_and(ResumeCountdown::frame) {
+        return true;
+    }
+    *resume = None;
+    false
+}
+
+// Adds a turn key pressed on a frame to the back of the queue, so every press
+// between two moves gets its own move, unless the queue's full; returns
+// whether it was added
+fn queue_turn(turns: &mut VecDeque<KeyCode>, key: KeyCode) -> bool {
+    if turns.len() >= MAX_QUEUED_TURNS {
+        return false;
+    }
+    turns.push_back(key);
+    true
+}
+
+// Returns the way a turn key's arrow points
+fn key_direction(key: KeyCode) -> Direction {
+    let index = TURN_KEYS.iter().position(|turn_key| *turn_key == key);
+    Direction::all()[index.unwrap_or(Direction::Right as usize)]
+}
+
+// Turns a snek for a key, depending on how it's steered
+fn steer_snek(snek: &mut Snek, steering: SteeringMode, key: KeyCode) {
+    match (steering, key) {
+        (SteeringMode::Relative, LEFT_KEY) => snek.rotate(Turn::Left),
+        (SteeringMode::Relative, RIGHT_KEY) => snek.rotate(Turn::Right),
+        (SteeringMode::Relative, _) => (),
+        (SteeringMode::Absolute, UP_KEY) => snek.change_direction(Direction::Up),
+        (SteeringMode::Absolute, DOWN_KEY) => snek.change_direction(Direction::Down),
+        (SteeringMode::Absolute, LEFT_KEY) => snek.change_direction(Direction::Left),
+        (SteeringMode::Absolute, _) => snek.change_direction(Direction::Right),
+    }
+}
+
+// Starts logging to a file, if one was given
+fn start_logging(options: &Options) {
+    if let Some(path) = &options.log_file
+        && let Err(err) = logging::start(path)
+    {
+        eprintln!("Couldn't start logging to {}: {err}", path.display());
+        process::exit(1);
+    }
+}
+
+// Sets up a game (with everything it was asked to serve) and shows the start
+// screen, returning nothing if the player quit from it
+fn start_game(mut options: Options) -> Option<Game> {
+    fit_min_board(&mut options);
+    exit_if_too_small(&options);
+    start_logging(&options);
+    let control = start_control(&options);
+    let listener = listen_for_spectators(&options);
+    let votes = listen_for_votes(&options);
+    // Picking a level can change the size of the screen, so the game (and
+    // its engine) is made again for it
+    let mut game = loop {
+        let mut game = Game::new(options.clone(), &options.starting_body(), None);
+        match game.start_screen() {
+            StartChoice::Play => break game,
+            StartChoice::Quit => return None,
+            StartChoice::Level(path, level) => {
+                drop(game);
+                level.apply(&mut options);
+                exit_if_too_small(&options);
+                if let Err(err) = level::save_last(&path) {
+                    eprintln!("Couldn't remember the level: {err}");
+                }
+            }
         }
+    };
+    game.control = control;
+    game.broadcaster = listener.map(|listener| Broadcaster::start(listener, &game.world));
+    game.votes = votes.map(Votes::start);
+    Some(game)
+}
+
+// Turns options into today's daily challenge in their mode, exiting if it's
+// already been played
+fn daily_options(mut options: Options) -> Options {
+    let today = storage::today();
+    if let Some(result) = DailyLedger::load_default().result(today, options.mode) {
+        eprintln!(
+            "You've already played today's {} challenge (scoring {}), come back tomorrow",
+            options.mode.name(),
+            result.score
+        );
+        process::exit(1);
     }
+    options.daily = Some(today);
+    options.seed = daily::seed(today, options.mode);
+    options.width = WIDTH;
+    options.height = HEIGHT;
+    options.level = None;
+    options.walls.clear();
+    options.food_sequence.clear();
+    options.start = None;
+    options
+}
 
-    // Moves the snek in the current direction
-    fn slither(&mut self, food: &mut Point, width: u32, height: u32) {
-        self.body
-            .push(*self.body.last().unwrap() + self.direction.to_vector());
-        self.eat(*food);
-        if !self.eating {
-            self.body.remove(0);
-        } else {
-            self.eating = false;
-            *food = rand_point(width, height, &self.body);
+// Exits with an error if the game won't fit in the terminal
+fn exit_if_too_small(options: &Options) {
+    if let Err(err) = options.check_fits() {
+        eprintln!("{err}");
+        process::exit(2);
+    }
+}
+
+// Grows the board to the smallest playable size with a warning, or exits with
+// an error, if it's too small (depending on the `min_board` policy)
+fn fit_min_board(options: &mut Options) {
+    match options.fit_min_board() {
+        Ok(true) => eprintln!(
+            "Warning: the board was too small to play on, so it's been grown to {}x{}",
+            options.width, options.height
+        ),
+        Ok(false) => (),
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(2);
         }
     }
+}
 
-    // Returns whether the snek is dead or not (inside itself or wall)
-    fn dead(&mut self, width: u32, height: u32) -> bool {
-        let last = self.body.last().unwrap();
-        self.body[0..self.body.len() - 1].contains(last)
-            || last.x < 0
-            || last.y < 0
-            || last.x > width as i32 - 1
-            || last.y > height as i32 - 1
+// Starts serving the control socket if one was asked for, exiting with an
+// error if it can't be
+#[cfg(all(unix, feature = "control"))]
+fn start_control(options: &Options) -> Option<ControlSocket> {
+    let path = options.control_socket.as_ref()?;
+    match control::Control::start(path, options.control_steering) {
+        Ok(control) => Some(control),
+        Err(err) => {
+            eprintln!(
+                "Couldn't serve the control socket at {}: {err}",
+                path.display()
+            );
+            process::exit(1);
+        }
     }
+}
+
+// Exits with an error if a control socket was asked for, since there isn't one
+// without the `control` feature
+#[cfg(not(all(unix, feature = "control")))]
+fn start_control(options: &Options) -> Option<ControlSocket> {
+    options.control_socket.as_ref()?;
+    eprintln!(
+        "This snek was built without control socket support (rebuild it on a Unix system with `--features control`)"
+    );
+    process::exit(1);
+}
+
+// Starts listening for spectators if the game's being broadcast, exiting with
+// an error if it can't be
+fn listen_for_spectators(options: &Options) -> Option<TcpListener> {
+    let address = options.broadcast.as_ref()?;
+    match Broadcaster::listen(address) {
+        Ok(listener) => Some(listener),
+        Err(err) => {
+            eprintln!("Couldn't broadcast on {address}: {err}");
+            process::exit(1);
+        }
+    }
+}
+
+// Starts listening for votes if the audience is steering, exiting with an
+// error if it can't
+fn listen_for_votes(options: &Options) -> Option<UdpSocket> {
+    let address = options.vote_input.as_ref()?;
+    match Votes::listen(address) {
+        Ok(socket) => Some(socket),
+        Err(err) => {
+            eprintln!("Couldn't listen for votes on {address}: {err}");
+            process::exit(1);
+        }
+    }
+}
+
+// Reads and removes the saved game, if there is one
+fn take_save() -> Option<SaveState> {
+    let path = storage::data_file(SAVE_FILE)?;
+    SaveState::take(&path).unwrap_or_else(|err| {
+        eprintln!("Couldn't load the saved game: {err}");
+        process::exit(1);
+    })
+}
+
+// Adds a finished game to the score table file, returning its rank in its
+// mode if it made the table
+fn record_score(entry: ScoreEntry) -> Option<usize> {
+    let path = storage::data_file(SCORES_FILE)?;
+    let mut table = match ScoreTable::load(&path) {
+        Ok(table) => table,
+        Err(err) => {
+            eprintln!("Couldn't read the score table: {err}");
+            return None;
+        }
+    };
+    let rank = table.insert(entry)?;
+    if let Err(err) = table.save(&path) {
+        eprintln!("Couldn't save the score table: {err}");
+        return None;
+    }
+    Some(rank)
+}
+
+// Adds a finished game to the lifetime stats file, returning the achievements it unlocked
+fn record_stats(summary: &GameSummary) -> Vec<&'static Achievement> {
+    let Some(path) = storage::data_file(STATS_FILE) else {
+        return Vec::new();
+    };
+    let result = Stats::load(&path).and_then(|mut stats| {
+        let unlocked = stats.record(summary, storage::today());
+        stats.save(&path).map(|_| unlocked)
+    });
+    result.unwrap_or_else(|err| {
+        eprintln!("Couldn't update the stats: {err}");
+        Vec::new()
+    })
+}
 
-    // Does some checking and then changes the direction of the snek
-    fn change_direction(&mut self, direction: Direction) {
-        if self.direction != direction.opposite() {
-            self.direction = direction;
+// Records a finished daily challenge (for the day it was started on) in the ledger
+fn record_daily(summary: &GameSummary, day: u64) {
+    let Some(path) = storage::data_file(DAILY_FILE) else {
+        return;
+    };
+    let result = DailyLedger::load(&path).and_then(|mut ledger| {
+        let result = DailyResult {
+            day,
+            mode: summary.mode,
+            score: summary.score,
+        };
+        match ledger.record(result) {
+            true => ledger.save(&path),
+            false => Ok(()),
         }
+    });
+    if let Err(err) = result {
+        eprintln!("Couldn't record the daily challenge: {err}");
+    }
+}
+
+// Adds a game that finished with some options to the history file
+fn record_history(summary: &GameSummary, options: &Options) {
+    let Some(path) = storage::data_file(HISTORY_FILE) else {
+        return;
+    };
+    if let Err(err) = History::append(&path, &HistoryEntry::new(summary, options)) {
+        eprintln!("Couldn't update the history: {err}");
     }
+}
 
-    // Returns the score (current len - starting len)
-    fn score(&self) -> usize {
-        self.body.len() - self.start_len
+// Keeps any splits from a finished game that beat the best ones in the splits file
+fn record_splits(splits: &SplitTracker, options: &Options) {
+    let Some(path) = storage::data_file(SPLITS_FILE) else {
+        return;
+    };
+    let result =
+        BestSplits::load(&path).and_then(|mut best| match best.record(options, splits.splits()) {
+            true => best.save(&path),
+            false => Ok(()),
+        });
+    if let Err(err) = result {
+        eprintln!("Couldn't update the best splits: {err}");
     }
+}
+
+// Keeps a finished game's score timeline if it's the best game in its mode
+fn record_pace(timeline: &Timeline, mode: options::Mode) {
+    let Some(path) = storage::data_file(PACE_FILE) else {
+        return;
+    };
+    let result = PaceLog::load(&path).and_then(|mut log| match log.record(mode, timeline) {
+        true => log.save(&path),
+        false => Ok(()),
+    });
+    if let Err(err) = result {
+        eprintln!("Couldn't update the best game's pace: {err}");
+    }
+}
+
+// Works the stats out again from the history and saves them, exiting with an
+// error if they can't be
+fn rebuild_stats() -> Stats {
+    let (Some(history_path), Some(stats_path)) = (
+        storage::data_file(HISTORY_FILE),
+        storage::data_file(STATS_FILE),
+    ) else {
+        eprintln!("There's no data directory to rebuild the stats in");
+        process::exit(1);
+    };
+    let stats = History::load(&history_path)
+        .map(|history| Stats::rebuild(history.entries()))
+        .and_then(|stats| stats.save(&stats_path).map(|_| stats));
+    stats.unwrap_or_else(|err| {
+        eprintln!("Couldn't rebuild the stats: {err}");
+        process::exit(1);
+    })
+}
 
-    // Elongates the snek if its head is on a food point
-    fn eat(&mut self, food: Point) {
-        if *self.body.last().unwrap() == food {
-            self.eating = true;
+// Submits a finished game to the online leaderboard, if there is one
+#[cfg(feature = "online")]
+fn submit_score(summary: &GameSummary, replay: &Replay) {
+    let Some(config) = online::OnlineConfig::load_default() else {
+        return;
+    };
+    if let Some(mismatch) = replay.verify().first() {
+        eprintln!("Couldn't submit the score: its replay doesn't play back the same ({mismatch})");
+        return;
+    }
+    match config.submit(summary, replay) {
+        Ok(()) => eprintln!("Score submitted"),
+        Err(err) => eprintln!("Couldn't submit the score: {err}"),
+    }
+}
+
+// Does nothing, since there's no online leaderboard without the `online` feature
+#[cfg(not(feature = "online"))]
+fn submit_score(_summary: &GameSummary, _replay: &Replay) {}
+
+// Prints the online leaderboard, exiting with an error if it can't be fetched
+#[cfg(feature = "online")]
+fn print_leaderboard() {
+    let Some(config) = online::OnlineConfig::load_default() else {
+        eprintln!("There's no leaderboard set up in {}", online::ONLINE_FILE);
+        process::exit(1);
+    };
+    match config.leaderboard() {
+        Ok(leaderboard) => println!("{leaderboard}"),
+        Err(err) => {
+            eprintln!("Couldn't fetch the leaderboard: {err}");
+            process::exit(1);
         }
     }
 }
 
-// Represents one of the four directions
-#[derive(PartialEq)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+// Explains that there's no online leaderboard without the `online` feature
+#[cfg(not(feature = "online"))]
+fn print_leaderboard() {
+    eprintln!("This snek was built without online support (rebuild it with `--features online`)");
+    process::exit(1);
+}
+
+// Runs a leaderboard server until the program is stopped
+#[cfg(feature = "online")]
+fn serve(port: u16, token: Option<String>) {
+    if let Err(err) = serve::Server::new(token).and_then(|server| server.run(port)) {
+        eprintln!("Couldn't run the leaderboard server: {err}");
+        process::exit(1);
+    }
+}
+
+// Explains that there's no leaderboard server without the `online` feature
+#[cfg(not(feature = "online"))]
+fn serve(_port: u16, _token: Option<String>) {
+    eprintln!("This snek was built without online support (rebuild it with `--features online`)");
+    process::exit(1);
 }
 
-impl Direction {
-    // Returns the opposite direction
-    fn opposite(&self) -> Self {
-        match self {
-            Direction::Up => Self::Down,
-            Direction::Down => Self::Up,
-            Direction::Left => Self::Right,
-            Direction::Right => Self::Left,
+// Starts showing Discord presence for a game, unless it's been turned off
+#[cfg(feature = "discord")]
+fn start_presence(game: &mut Game) -> Option<presence::Presence> {
+    if !game.world.options.presence {
+        return None;
+    }
+    let (presence, sender) = presence::Presence::start(game.world.options.mode)?;
+    game.events = EventSender::new(Some(sender));
+    Some(presence)
+}
+
+// Does nothing, since there's no Discord presence without the `discord` feature
+#[cfg(not(feature = "discord"))]
+fn start_presence(_game: &mut Game) -> Option<Infallible> {
+    None
+}
+
+// Waits for Discord presence to be cleared, saying why if it couldn't be shown
+#[cfg(feature = "discord")]
+fn finish_presence(presence: Option<presence::Presence>) {
+    if let Some(Err(err)) = presence.map(presence::Presence::finish) {
+        eprintln!("Couldn't show Discord presence: {err}");
+    }
+}
+
+// Does nothing, since there's no Discord presence without the `discord` feature
+#[cfg(not(feature = "discord"))]
+fn finish_presence(_presence: Option<Infallible>) {}
+
+// Shows a desktop notification for a new high score, logging why if it can't
+#[cfg(feature = "notify")]
+fn notify_high_score(score: usize) {
+    if let Err(err) = notify::high_score(score) {
+        log::warn!("couldn't show the high score notification: {err}");
+    }
+}
+
+// Does nothing, since there are no notifications without the `notify` feature
+#[cfg(not(feature = "notify"))]
+fn notify_high_score(_score: usize) {}
+
+// Plays a head-to-head game over the network, printing how it ended
+fn play_versus(role: versus::Role, mut options: Options) {
+    fit_min_board(&mut options);
+    match versus::play(role, options) {
+        Ok(end) => println!("{}", end.message()),
+        Err(err) => {
+            eprintln!("Couldn't play the head-to-head game: {err}");
+            process::exit(1);
         }
     }
+}
 
-    // Converts the direction to a vector
-    fn to_vector(&self) -> Vector {
-        match self {
-            Direction::Up => Vector::new(0, -1),
-            Direction::Down => Vector::new(0, 1),
-            Direction::Left => Vector::new(-1, 0),
-            Direction::Right => Vector::new(1, 0),
+// Runs a network game until it's over, printing how it ended
+#[cfg(feature = "net")]
+fn serve_net_game(port: u16, players: usize, mut options: Options) {
+    fit_min_board(&mut options);
+    match net::serve(port, players, options) {
+        Ok(arena::ArenaOutcome::Winner(winner)) => println!("Player {} won", winner + 1),
+        Ok(arena::ArenaOutcome::Draw) => println!("It's a draw"),
+        Err(err) => {
+            eprintln!("Couldn't serve the network game: {err}");
+            process::exit(1);
         }
     }
 }
 
-// Randomizes a point, excluding a list points
-fn rand_point(width: u32, height: u32, exclude: &[Point]) -> Point {
-    let mut point = Point::new(
-        fastrand::i32(0..width as i32),
-        fastrand::i32(0..height as i32),
-    );
-    while exclude.contains(&point) {
-        point = Point::new(
-            fastrand::i32(0..width as i32),
-            fastrand::i32(0..height as i32),
-        );
+// Explains that there are no network games without the `net` feature
+#[cfg(not(feature = "net"))]
+fn serve_net_game(_port: u16, _players: usize, _options: Options) {
+    eprintln!("This snek was built without network support (rebuild it with `--features net`)");
+    process::exit(1);
+}
+
+// Plays a network game being served on an address
+#[cfg(feature = "net")]
+fn join_net_game(address: &str) {
+    match net::join_game(address) {
+        Ok(end) => println!("{}", end.message()),
+        Err(err) => {
+            eprintln!("Couldn't join the network game: {err}");
+            process::exit(1);
+        }
+    }
+}
+
+// Explains that there are no network games without the `net` feature
+#[cfg(not(feature = "net"))]
+fn join_net_game(_address: &str) {
+    eprintln!("This snek was built without network support (rebuild it with `--features net`)");
+    process::exit(1);
+}
+
+// Saves a finished game's replay and exports it as ANSI, if either was asked for
+fn keep_replay(replay: &Replay) {
+    if let Some(path) = &replay.options.record
+        && let Err(err) = replay.save(path)
+    {
+        eprintln!("Couldn't save the replay: {err}");
+    }
+    if let Some(path) = &replay.options.export_replay
+        && let Err(err) = fs::write(path, export::ansi(replay))
+    {
+        eprintln!("Couldn't export the replay: {err}");
     }
-    point
+}
+
+// Prints where each of snek's files is kept
+fn print_paths() {
+    let paths = [
+        ("config", storage::config_file(CONFIG_FILE)),
+        ("scores", storage::data_file(SCORES_FILE)),
+        ("stats", storage::data_file(STATS_FILE)),
+        ("history", storage::data_file(HISTORY_FILE)),
+        ("daily", storage::data_file(DAILY_FILE)),
+        ("splits", storage::data_file(SPLITS_FILE)),
+        ("pace", storage::data_file(PACE_FILE)),
+        ("ghosts", storage::data_file(ghost::GHOSTS_DIR)),
+        ("save", storage::data_file(SAVE_FILE)),
+        ("levels", storage::data_file(level::LEVELS_DIR)),
+        ("last level", storage::data_file(level::LAST_LEVEL_FILE)),
+        ("crash reports", storage::cache_dir()),
+    ];
+    for (name, path) in paths.into_iter().chain(online_paths()) {
+        let path = path.map_or("(no home directory)".to_owned(), |path| {
+            path.display().to_string()
+        });
+        println!("{name:<15}{path}");
+    }
+}
+
+// Returns where the online leaderboard's files are kept
+#[cfg(feature = "online")]
+fn online_paths() -> Vec<(&'static str, Option<PathBuf>)> {
+    vec![
+        ("online", storage::config_file(online::ONLINE_FILE)),
+        ("leaderboard", storage::data_file(serve::LEADERBOARD_FILE)),
+    ]
+}
+
+// Returns where the online leaderboard's files are kept (there aren't any
+// without the `online` feature)
+#[cfg(not(feature = "online"))]
+fn online_paths() -> Vec<(&'static str, Option<PathBuf>)> {
+    Vec::new()
+}
+
+// Checks that a replay file really ends the way it says, exiting with an
+// error if it doesn't
+fn verify(path: &Path) {
+    let replay = Replay::load(path).unwrap_or_else(|err| {
+        eprintln!("Couldn't read the replay: {err}");
+        process::exit(1);
+    });
+    let mismatches = replay.verify();
+    if mismatches.is_empty() {
+        println!("Verified: scored {} on tick {}", replay.score, replay.ticks);
+        return;
+    }
+    for mismatch in mismatches {
+        eprintln!("Doesn't match: {mismatch}");
+    }
+    process::exit(1);
+}
+
+// Edits a level file, test playing it whenever the player asks (which goes
+// back to the editor once the snek dies or they quit)
+fn edit(path: &Path, options: Options) {
+    let mut editor = Editor::open(path).unwrap_or_else(|err| {
+        eprintln!("Couldn't open the level in {}: {err}", path.display());
+        process::exit(1);
+    });
+    loop {
+        let mut level_options = options.clone();
+        editor.level().apply(&mut level_options);
+        exit_if_too_small(&level_options);
+        match editor.run(&options) {
+            Ok(EditorExit::Quit) => return,
+            Ok(EditorExit::TestPlay) => {
+                let body = level_options.starting_body();
+                Game::new(level_options, &body, None).main_loop();
+            }
+            Err(err) => {
+                eprintln!("Couldn't run the editor: {err}");
+                process::exit(1);
+            }
+        }
+    }
+}
+
+// Plays a game with a bot over stdin and stdout, waiting for each move for a
+// timeout in milliseconds (or as long as a move takes, if there isn't one)
+fn play_bot(mut options: Options, headless: bool, timeout: Option<u64>) {
+    fit_min_board(&mut options);
+    let timeout = timeout.map_or(
+        Duration::from_secs(1) / options.fps.max(1),
+        Duration::from_millis,
+    );
+    let (world, duration) = bot::run(options, headless, timeout);
+    keep_replay(&Replay::from_world(&world));
+    println!("{}", GameSummary::new(&world, duration.as_secs()).to_json());
 }
 
 // Entry point
 fn main() {
-    let mut game = Game::new(WIDTH, HEIGHT, FPS, &STARTING_BODY);
+    crash::install_hook();
+    let command = Command::from_args().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(2);
+    });
+    let mut game = match command {
+        Command::Play(options) => match start_game(options) {
+            Some(game) => game,
+            None => return,
+        },
+        Command::Daily {
+            options,
+            history: true,
+        } => {
+            let ledger = DailyLedger::load_default();
+            println!("{}", ledger.format_calendar(storage::today(), options.mode));
+            return;
+        }
+        Command::Daily {
+            options,
+            history: false,
+        } => match start_game(daily_options(options)) {
+            Some(game) => game,
+            None => return,
+        },
+        Command::Bot {
+            options,
+            headless,
+            timeout,
+        } => {
+            play_bot(options, headless, timeout);
+            return;
+        }
+        Command::Versus { role, options } => {
+            play_versus(role, options);
+            return;
+        }
+        Command::NetServe {
+            port,
+            players,
+            options,
+        } => {
+            serve_net_game(port, players, options);
+            return;
+        }
+        Command::NetJoin { address } => {
+            join_net_game(&address);
+            return;
+        }
+        Command::Resume => {
+            let save = take_save().unwrap_or_else(|| {
+                eprintln!("There's no saved game to resume");
+                process::exit(1);
+            });
+            exit_if_too_small(&save.options);
+            start_logging(&save.options);
+            let control = start_control(&save.options);
+            let listener = listen_for_spectators(&save.options);
+            let votes = listen_for_votes(&save.options);
+            let mut game = Game::from_save(save, None);
+            game.control = control;
+            game.broadcaster = listener.map(|listener| Broadcaster::start(listener, &game.world));
+            game.votes = votes.map(Votes::start);
+            game
+        }
+        Command::Scores => {
+            println!("{}", ScoreTable::load_default().format());
+            return;
+        }
+        Command::Stats { rebuild } => {
+            let stats = match rebuild {
+                true => rebuild_stats(),
+                false => Stats::load_default(),
+            };
+            println!("{}", stats.format(storage::today()));
+            return;
+        }
+        Command::History { csv, last } => {
+            let history = History::load_default();
+            match csv {
+                true => print!("{}", history.to_csv()),
+                false => println!("{}", history.format(last)),
+            }
+            return;
+        }
+        Command::Serve { port, token } => {
+            serve(port, token);
+            return;
+        }
+        Command::Watch { address } => {
+            if let Err(err) = spectate::watch(&address) {
+                eprintln!("Couldn't watch the game: {err}");
+                process::exit(1);
+            }
+            return;
+        }
+        Command::Leaderboard => {
+            print_leaderboard();
+            return;
+        }
+        Command::Paths => {
+            print_paths();
+            return;
+        }
+        Command::List(names) => {
+            for name in names {
+                println!("{name}");
+            }
+            return;
+        }
+        Command::Edit { path, options } => {
+            edit(&path, options);
+            return;
+        }
+        Command::Verify(path) => {
+            verify(&path);
+            return;
+        }
+        Command::Export(export) => {
+            if let Err(err) = export.run() {
+                eprintln!("Couldn't export the replay: {err}");
+                process::exit(1);
+            }
+            return;
+        }
+    };
+    let presence = start_presence(&mut game);
     game.main_loop();
-    let score = game.score();
+    if game.world.won && game.world.options.victory_lap {
+        game.victory_lap();
+    }
+    // Quitting skips the game over screen, since the player's already decided
+    let ended = game.world.won || game.world.cause.is_some();
+    if let Some(timeout) = game.world.options.game_over_timeout
+        && ended
+        && !game.saved
+    {
+        game.game_over_screen(Duration::from_secs(timeout.into()));
+    }
+    let json = game.world.options.json;
+    let daily = game.world.options.daily;
+    if game.saved {
+        drop(game);
+        finish_presence(presence);
+        eprintln!("Game saved, carry on with `snek --resume`");
+        return;
+    }
+    let replay = Replay::from_world(&game.world);
+    keep_replay(&replay);
+    if replay.options.ghost
+        && let Err(err) = ghost::record(&replay)
+    {
+        eprintln!("Couldn't save the run as the ghost: {err}");
+    }
+    let summary = game.summary();
+    let splits = game.splits.clone();
+    let timeline = game.pace.clone();
+    let debug_log = game
+        .debug_log
+        .as_ref()
+        .filter(|_| game.world.cause.is_some())
+        .map(|debug_log| debug_log.write(&game.world));
     drop(game);
-    println!("{}", END_MESSAGE.to_string() + &score.to_string());
+    finish_presence(presence);
+    // Only said once the game's closed, so it isn't drawn over
+    match debug_log {
+        Some(Ok(path)) => eprintln!("Saved a debug log to {}", path.display()),
+        Some(Err(err)) => eprintln!("Couldn't save a debug log: {err}"),
+        None => {}
+    }
+    // Assisted games don't go in the score table
+    let rank = match summary.assist {
+        None => record_score(summary.score_entry()),
+        Some(_) => None,
+    };
+    let high_score = scores::new_high_score(rank, summary.score);
+    let unlocked = record_stats(&summary);
+    record_history(&summary, &replay.options);
+    record_splits(&splits, &replay.options);
+    record_pace(&timeline, summary.mode);
+    if let Some(day) = daily {
+        record_daily(&summary, day);
+    }
+    if json {
+        println!("{}", summary.to_json());
+    } else {
+        let message = match summary.cause {
+            Some(_) => END_MESSAGE,
+            None => SCORE_MESSAGE,
+        };
+        println!("{message}{}", summary.score);
+        println!("{}", summary.format());
+        if high_score {
+            println!("*** New high score! ***");
+        }
+        if !splits.splits().is_empty() {
+            println!("{}", splits.report());
+        }
+        for achievement in unlocked {
+            println!(
+                "*** Achievement unlocked: {} ({}) ***",
+                achievement.name, achievement.description
+            );
+        }
+    }
+    if high_score && !json && replay.options.notify {
+        notify_high_score(summary.score);
+    }
+    // A marathon's replay only has its last life, so it can't back up the
+    // total, and assisted games don't count
+    if summary.mode != Mode::Marathon && summary.assist.is_none() {
+        submit_score(&summary, &replay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns which way a snek heading up goes after a key
+    fn steered(steering: SteeringMode, key: KeyCode) -> Direction {
+        let mut snek = Snek::new(&[Point::new(5, 6), Point::new(5, 5)], false);
+        snek.direction = Direction::Up;
+        snek.heading = Direction::Up;
+        steer_snek(&mut snek, steering, key);
+        snek.direction
+    }
+
+    #[test]
+    fn relative_steering_turns_from_the_heading() {
+        let relative = SteeringMode::Relative;
+        assert_eq!(steered(relative, LEFT_KEY), Direction::Left);
+        assert_eq!(steered(relative, RIGHT_KEY), Direction::Right);
+        assert_eq!(steered(relative, UP_KEY), Direction::Up);
+        assert_eq!(steered(relative, DOWN_KEY), Direction::Up);
+        let absolute = SteeringMode::Absolute;
+        assert_eq!(steered(absolute, LEFT_KEY), Direction::Left);
+        assert_eq!(steered(absolute, DOWN_KEY), Direction::Up);
+    }
+
+    #[test]
+    fn presses_between_moves_all_register() {
+        let mut world = World::new(Options::default(), &STARTING_BODY);
+        let mut turns = VecDeque::new();
+        // Frames come INPUT_POLLS times a move, and two keys are tapped on
+        // different frames before the first move
+        let presses = [(1, DOWN_KEY), (2, LEFT_KEY)];
+        let mut directions = Vec::new();
+        for frame in 0..INPUT_POLLS * 2 {
+            for (_, key) in presses.iter().filter(|(at, _)| *at == frame) {
+                assert!(queue_turn(&mut turns, *key));
+            }
+            if frame % INPUT_POLLS == INPUT_POLLS - 1 {
+                if let Some(key) = turns.pop_front() {
+                    steer_snek(&mut world.snek, SteeringMode::Absolute, key);
+                }
+                world.advance();
+                directions.push(world.snek.heading);
+            }
+        }
+        assert_eq!(directions, [Direction::Down, Direction::Left]);
+        for key in [UP_KEY; MAX_QUEUED_TURNS] {
+            assert!(queue_turn(&mut turns, key));
+        }
+        assert!(!queue_turn(&mut turns, UP_KEY));
+    }
+
+    #[test]
+    fn the_boost_key_is_the_way_the_snek_is_heading() {
+        for heading in [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ] {
+            assert_eq!(boost_key(SteeringMode::Relative, heading), UP_KEY);
+        }
+        let absolute = SteeringMode::Absolute;
+        assert_eq!(boost_key(absolute, Direction::Up), UP_KEY);
+        assert_eq!(boost_key(absolute, Direction::Down), DOWN_KEY);
+        assert_eq!(boost_key(absolute, Direction::Left), LEFT_KEY);
+        assert_eq!(boost_key(absolute, Direction::Right), RIGHT_KEY);
+    }
+
+    #[test]
+    fn slowmo_waits_out_its_cooldown_in_ticks() {
+        let options = Options {
+            assist: Some(Assist::Slowmo),
+            seed: 1,
+            ..Options::default()
+        };
+        let mut world = World::new(options, &STARTING_BODY);
+        // Off along the top edge, so the next move hits the wall
+        world.snek.direction = Direction::Up;
+        world.ticks = 100;
+        assert!(slowmo_due(&world, false, None));
+        // A queued turn might save it, so it's left to that
+        assert!(!slowmo_due(&world, true, None));
+        assert!(!slowmo_due(&world, false, Some(100 - SLOWMO_COOLDOWN + 1)));
+        assert!(slowmo_due(&world, false, Some(100 - SLOWMO_COOLDOWN)));
+        world.snek.direction = Direction::Right;
+        assert!(!slowmo_due(&world, false, None));
+        world.snek.direction = Direction::Up;
+        world.options.assist = None;
+        assert!(!slowmo_due(&world, false, None));
+    }
+
+    #[test]
+    fn a_resumed_snek_waits_out_the_countdown() {
+        let options = Options {
+            seed: 1,
+            ..Options::default()
+        };
+        let frames_per_second = options.fps * INPUT_POLLS;
+        let mut world = World::new(options, &STARTING_BODY);
+        let mut resume = Some(ResumeCountdown::new(
+            RESUME_COUNTDOWN_SECONDS,
+            frames_per_second,
+        ));
+        // The snek's given the chance to move every frame, like at top speed
+        let mut held = 0;
+        while world.snek.body == STARTING_BODY {
+            match resume_holding(&mut resume) {
+                true => held += 1,
+                false => _ = world.advance(),
+            }
+        }
+        assert_eq!(held, RESUME_COUNTDOWN_SECONDS * frames_per_second - 1);
+        assert!(resume.is_none());
+        assert!(!resume_holding(&mut resume));
+    }
 }
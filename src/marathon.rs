@@ -0,0 +1,84 @@
+// Marathon mode, where a run is a few lives played one after another, each on
+// a fresh board, and what's scored (and ranked) is the total of them all.
+//
+// Every life plays just like classic mode, in a world of its own. The run
+// keeps what the lives before the one in play added up to, so it can be added
+// to what that world has. The first life is played on the run's seed, and each
+// one after it on the seed after the last one's.
+
+use serde::{Deserialize, Serialize};
+
+// How many lives a marathon has
+pub const LIVES: u32 = 3;
+
+// What the lives of a run that are over added up to (which, outside of
+// marathon mode, is always nothing)
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Marathon {
+    pub life: u32,
+    pub marathon_total: usize,
+    pub ticks: u64,
+}
+
+impl Marathon {
+    // Returns whether there's another life to play after the one in play
+    pub fn lives_left(&self) -> bool {
+        self.life + 1 < LIVES
+    }
+
+    // Adds a life that's over, with the score and ticks it ended on, to the run
+    pub fn bank(&mut self, score: usize, ticks: u64) {
+        self.life += 1;
+        self.marathon_total += score;
+        self.ticks += ticks;
+    }
+
+    // Returns the score of the whole run, from the score of the life in play
+    pub fn total(&self, score: usize) -> usize {
+        self.marathon_total + score
+    }
+
+    // Returns the seed the run started on, from the seed of the life in play
+    pub fn first_seed(&self, seed: u64) -> u64 {
+        seed.wrapping_sub(self.life.into())
+    }
+}
+
+// Returns the seed of the board for the life after one played on a seed
+pub fn next_seed(seed: u64) -> u64 {
+    seed.wrapping_add(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marathon_total_adds_up_every_life() {
+        let mut marathon = Marathon::default();
+        let mut seed = 40;
+        let lives = [(5, 100), (0, 12), (9, 300)];
+        for (life, (score, ticks)) in lives.into_iter().enumerate() {
+            assert_eq!(marathon.life, life as u32);
+            assert_eq!(marathon.first_seed(seed), 40);
+            assert_eq!(marathon.lives_left(), life + 1 < LIVES as usize);
+            if marathon.lives_left() {
+                marathon.bank(score, ticks);
+                seed = next_seed(seed);
+            }
+        }
+        // The last life's still in play, so only it adds its score on top
+        assert_eq!(marathon.marathon_total, 5);
+        assert_eq!(marathon.ticks, 112);
+        assert_eq!(marathon.total(9), 14);
+        assert_eq!(seed, 42);
+    }
+
+    #[test]
+    fn other_modes_have_nothing_to_add() {
+        let marathon = Marathon::default();
+        assert_eq!(marathon.total(7), 7);
+        assert_eq!(marathon.first_seed(u64::MAX), u64::MAX);
+        assert_eq!(next_seed(u64::MAX), 0);
+    }
+}
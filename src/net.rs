@@ -0,0 +1,603 @@
+// Games for up to four players over the network (`snek net-serve` and
+// `snek net-join`).
+//
+// Unlike `snek host`, one process runs the game for everyone: the server owns
+// the arena and steps it at the game's speed, and the players' sneks only send
+// it the way they want to go and draw what it sends back, so a slow player
+// can't hold the others up. The server has no screen of its own.
+//
+// Every message is a JSON object on its own line, with a `type` field. Once a
+// player connects, the server sends
+//
+//     {"type":"welcome","version":1,"player":INDEX,"players":COUNT,"options":OPTIONS}
+//
+// where OPTIONS are the game's options (like in a save file), then `{"type":"waiting","joined":N,"players":COUNT}` to everyone each
+// time someone joins. When everyone's there, the game starts, and after every
+// tick each player is sent
+//
+//     {"type":"state","tick":TICK,"food":[[X,Y],...],"sneks":[{"alive":BOOL,"body":[[X,Y],...]},...]}
+//
+// with the sneks in player order and each body from tail to head, then
+// `{"type":"end","winner":INDEX}` (or null for a draw) once it's over. Players
+// send `{"type":"turn","direction":"up"}` (or down, left, or right) to steer,
+// and `{"type":"quit"}` to give up. A player that quits, hangs up, or falls
+// too far behind to be sent the state has its snek killed. Malformed messages
+// are ignored.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use console_engine::{Color, ConsoleEngine, KeyCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::arena::{Arena, ArenaOutcome, MAX_PLAYERS};
+use crate::input;
+use crate::options::{MinBoard, Mode, Options};
+use crate::render;
+use crate::spectate;
+use crate::world::Direction;
+use crate::{
+    DISCONNECTED_PROMPT, DOWN_KEY, DRAW_PROMPT, FOOD_COLOR, GAME_PROMPT, INPUT_POLLS, LEFT_KEY,
+    LOSE_PROMPT, Point, QUIT_KEY, RIGHT_KEY, RIVAL_COLOR, SNEK_COLORS, UP_KEY, WAITING_PROMPT,
+    WIN_PROMPT,
+};
+
+// Version of the protocol, sent in the welcome message
+const VERSION: u64 = 1;
+
+// How long the server waits for the welcome message before giving up
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How long a player gets to take a message before its snek is killed
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// How a network game ended for this player
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NetEnd {
+    Won,
+    Lost,
+    Draw,
+    Quit,
+    Disconnected,
+}
+
+impl NetEnd {
+    // Returns what's printed once the game has closed
+    pub fn message(&self) -> &'static str {
+        match self {
+            NetEnd::Won => "You won!",
+            NetEnd::Lost => "You lost",
+            NetEnd::Draw => "It's a draw",
+            NetEnd::Quit => "You quit",
+            NetEnd::Disconnected => "Lost the connection to the server",
+        }
+    }
+}
+
+// A message the server sends a player
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    Welcome {
+        version: u64,
+        player: usize,
+        players: usize,
+        #[serde(deserialize_with = "Options::deserialize_checked")]
+        options: Box<Options>,
+    },
+    Waiting {
+        joined: usize,
+        players: usize,
+    },
+    State(State),
+    End {
+        winner: Option<usize>,
+    },
+}
+
+// A message a player sends the server
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum PlayerMessage {
+    Turn { direction: Direction },
+    Quit,
+}
+
+// What a player's connection tells the server
+enum Input {
+    Turn(usize, Direction),
+    Left(usize),
+}
+
+// Runs a game for some players on a port, returning how it ended once it has
+pub fn serve(port: u16, players: usize, mut options: Options) -> io::Result<ArenaOutcome> {
+    options.mode = Mode::Classic;
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    eprintln!("Waiting for {players} players on port {port}");
+    let (sender, inputs) = mpsc::channel();
+    let mut streams: Vec<Option<TcpStream>> = Vec::new();
+    while streams.len() < players {
+        let (stream, address) = listener.accept()?;
+        let player = streams.len();
+        if let Err(err) = join(&stream, player, players, &options, &sender) {
+            eprintln!("Couldn't let {address} join: {err}");
+            continue;
+        }
+        eprintln!("Player {} joined from {address}", player + 1);
+        streams.push(Some(stream));
+        let waiting = ServerMessage::Waiting {
+            joined: streams.len(),
+            players,
+        };
+        broadcast(&mut streams, &encode(&waiting));
+    }
+    eprintln!("Starting the game");
+
+    let mut arena = Arena::with_players(options, players);
+    let mut steering: Vec<Direction> = arena.sneks.iter().map(|snek| snek.direction).collect();
+    let tick = Duration::from_secs(1) / arena.options.fps;
+    let mut next_tick = Instant::now() + tick;
+    broadcast(&mut streams, &state(&arena));
+    loop {
+        thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+        next_tick += tick;
+        for input in inputs.try_iter() {
+            match input {
+                Input::Turn(player, direction) => steering[player] = direction,
+                Input::Left(player) => streams[player] = None,
+            }
+        }
+        // Sneks of players that have gone are killed before they'd move again
+        for (snek, stream) in arena.sneks.iter_mut().zip(&streams) {
+            snek.alive &= stream.is_some();
+        }
+        arena.advance(&steering);
+        broadcast(&mut streams, &state(&arena));
+        if let Some(outcome) = arena.outcome() {
+            let winner = match outcome {
+                ArenaOutcome::Winner(winner) => Some(winner),
+                ArenaOutcome::Draw => None,
+            };
+            broadcast(&mut streams, &encode(&ServerMessage::End { winner }));
+            return Ok(outcome);
+        }
+    }
+}
+
+// Welcomes a player that's connected and starts reading its messages on
+// another thread
+fn join(
+    stream: &TcpStream,
+    player: usize,
+    players: usize,
+    options: &Options,
+    sender: &Sender<Input>,
+) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    send(stream, &welcome(player, players, options))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let sender = sender.clone();
+    thread::spawn(move || {
+        while let Ok(Some(line)) = spectate::read_line(&mut reader) {
+            let input = match serde_json::from_str(&line) {
+                Ok(PlayerMessage::Turn { direction }) => Input::Turn(player, direction),
+                Ok(PlayerMessage::Quit) => break,
+                Err(_) => continue,
+            };
+            if sender.send(input).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send(Input::Left(player));
+    });
+    Ok(())
+}
+
+// Returns the welcome message for a player
+fn welcome(player: usize, players: usize, options: &Options) -> String {
+    encode(&ServerMessage::Welcome {
+        version: VERSION,
+        player,
+        players,
+        options: Box::new(options.clone()),
+    })
+}
+
+// Writes a message as JSON
+fn encode(message: &impl Serialize) -> String {
+    serde_json::to_string(message).unwrap_or_default()
+}
+
+// Sends a message to every player that's still there, dropping the ones it
+// can't be sent to
+fn broadcast(streams: &mut [Option<TcpStream>], message: &str) {
+    for stream in streams.iter_mut() {
+        if stream
+            .as_ref()
+            .is_some_and(|stream| send(stream, message).is_err())
+        {
+            *stream = None;
+        }
+    }
+}
+
+// Writes a message to a connection as its own line
+fn send(mut stream: &TcpStream, message: &str) -> io::Result<()> {
+    stream.write_all(format!("{message}\n").as_bytes())
+}
+
+// Returns the state message for an arena
+fn state(arena: &Arena) -> String {
+    let sneks = arena
+        .sneks
+        .iter()
+        .map(|snek| SnekState {
+            alive: snek.alive,
+            body: snek.body.clone(),
+        })
+        .collect();
+    encode(&ServerMessage::State(State {
+        tick: arena.ticks,
+        food: arena.food.clone(),
+        sneks,
+    }))
+}
+
+// What a state message says the arena looks like
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
+struct State {
+    tick: u64,
+    food: Vec<Point>,
+    sneks: Vec<SnekState>,
+}
+
+// What a state message says one of the sneks looks like
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
+struct SnekState {
+    alive: bool,
+    body: Vec<Point>,
+}
+
+impl State {
+    // Returns the state if it's for an arena with some options and sneks, and
+    // everything in it is on the map
+    fn check(self, options: &Options, sneks: usize) -> Option<Self> {
+        let cells = options.width as usize * options.height as usize;
+        let fits = |points: &[Point]| {
+            points.len() <= cells
+                && points.iter().all(|point| {
+                    (0..options.width as i32).contains(&point.x)
+                        && (0..options.height as i32).contains(&point.y)
+                })
+        };
+        let sneks_fit = self
+            .sneks
+            .iter()
+            .all(|snek| !snek.body.is_empty() && fits(&snek.body));
+        (self.sneks.len() == sneks && fits(&self.food) && sneks_fit).then_some(self)
+    }
+}
+
+// A player's view of a network game
+struct Client {
+    stream: TcpStream,
+    messages: Receiver<Option<String>>,
+    arena: Arena,
+    me: usize,
+    engine: ConsoleEngine,
+    steering: Direction,
+    started: bool,
+    joined: usize,
+}
+
+// Joins a game being served on an address, returning how it ended for this player
+pub fn join_game(address: &str) -> io::Result<NetEnd> {
+    let stream = TcpStream::connect(address)?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let (me, players, options) = read_welcome(&mut reader)?;
+    options.check_fits().map_err(io::Error::other)?;
+    stream.set_read_timeout(None)?;
+
+    let (width, height) = options.screen_size();
+    let mut engine = ConsoleEngine::init(width, height, options.fps * INPUT_POLLS)?;
+    engine.set_title("SNEK");
+    let arena = Arena::with_players(options, players);
+    let mut client = Client {
+        stream,
+        messages: spectate::read_stream(reader),
+        steering: arena.sneks[me].direction,
+        arena,
+        me,
+        engine,
+        started: false,
+        joined: 0,
+    };
+    Ok(client.run())
+}
+
+// Reads the welcome message, returning which player this is, how many there
+// are, and the game's options
+fn read_welcome(reader: &mut impl BufRead) -> io::Result<(usize, usize, Options)> {
+    let welcome: Value = spectate::read_line(reader)?
+        .and_then(|line| serde_json::from_str(&line).ok())
+        .filter(|welcome: &Value| welcome["type"] == "welcome")
+        .ok_or_else(|| invalid("that isn't a snek serving a game"))?;
+    if welcome["version"] != VERSION {
+        return Err(invalid("the server plays a different version of the game"));
+    }
+    // The other end might not be a snek that checked its options, so they're
+    // checked as they're read
+    let welcome = ServerMessage::deserialize(welcome)
+        .map_err(|err| invalid(&format!("malformed welcome ({err})")))?;
+    let ServerMessage::Welcome {
+        player,
+        players,
+        options,
+        ..
+    } = welcome
+    else {
+        return Err(invalid("malformed welcome"));
+    };
+    if players > MAX_PLAYERS || player >= players {
+        return Err(invalid("malformed welcome"));
+    }
+    // The board it plays on can't be grown here without the two disagreeing
+    let mut options = *options;
+    options.min_board = MinBoard::Error;
+    options.fit_min_board().map_err(|err| invalid(&err))?;
+    options.mode = Mode::Classic;
+    Ok((player, players, options))
+}
+
+impl Client {
+    // Draws the game as the server sends it and steers this player's snek,
+    // until it's over
+    fn run(&mut self) -> NetEnd {
+        loop {
+            let lines: Vec<Option<String>> = self.messages.try_iter().collect();
+            for line in lines {
+                let Some(line) = line else {
+                    return self.finish(NetEnd::Disconnected, DISCONNECTED_PROMPT);
+                };
+                if let Some(outcome) = self.receive(&line) {
+                    return match outcome {
+                        ArenaOutcome::Winner(winner) if winner == self.me => {
+                            self.finish(NetEnd::Won, WIN_PROMPT)
+                        }
+                        ArenaOutcome::Winner(_) => self.finish(NetEnd::Lost, LOSE_PROMPT),
+                        ArenaOutcome::Draw => self.finish(NetEnd::Draw, DRAW_PROMPT),
+                    };
+                }
+            }
+            let prompt = match self.started {
+                true => GAME_PROMPT.to_owned(),
+                false => format!(
+                    "{WAITING_PROMPT} {}/{}",
+                    self.joined,
+                    self.arena.sneks.len()
+                ),
+            };
+            self.draw(&prompt);
+
+            if input::pressed(&self.engine, QUIT_KEY) {
+                let _ = send(&self.stream, &encode(&PlayerMessage::Quit));
+                return NetEnd::Quit;
+            }
+            let keys = [
+                (UP_KEY, Direction::Up),
+                (DOWN_KEY, Direction::Down),
+                (LEFT_KEY, Direction::Left),
+                (RIGHT_KEY, Direction::Right),
+            ];
+            if let Some((_, direction)) = keys
+                .into_iter()
+                .find(|(key, _)| input::pressed(&self.engine, *key))
+                && direction != self.steering
+            {
+                self.steering = direction;
+                let _ = send(&self.stream, &encode(&PlayerMessage::Turn { direction }));
+            }
+        }
+    }
+
+    // Deals with a message from the server, returning how the game ended if
+    // it just did
+    fn receive(&mut self, line: &str) -> Option<ArenaOutcome> {
+        match serde_json::from_str(line).ok()? {
+            ServerMessage::Waiting { joined, .. } => self.joined = joined,
+            ServerMessage::State(state) => {
+                self.started = true;
+                self.apply(state);
+            }
+            ServerMessage::End { winner } => {
+                return Some(match winner {
+                    Some(winner) => ArenaOutcome::Winner(winner),
+                    None => ArenaOutcome::Draw,
+                });
+            }
+            ServerMessage::Welcome { .. } => (),
+        }
+        None
+    }
+
+    // Updates the arena from a state message, ignoring it if it's malformed
+    fn apply(&mut self, state: State) -> Option<()> {
+        let state = state.check(&self.arena.options, self.arena.sneks.len())?;
+        for (snek, state) in self.arena.sneks.iter_mut().zip(state.sneks) {
+            snek.alive = state.alive;
+            snek.body = state.body;
+        }
+        self.arena.food = state.food;
+        self.arena.ticks = state.tick;
+        Some(())
+    }
+
+    // Shows how the game ended until the player quits
+    fn finish(&mut self, end: NetEnd, prompt: &str) -> NetEnd {
+        while !input::pressed(&self.engine, QUIT_KEY)
+            && !input::pressed(&self.engine, KeyCode::Char(' '))
+        {
+            self.draw(prompt);
+        }
+        end
+    }
+
+    // Draws the arena, with a prompt on top and this player's score and how
+    // many sneks are left underneath
+    fn draw(&mut self, prompt: &str) {
+        let options = &self.arena.options;
+        let engine = &mut self.engine;
+        render::draw_map(engine, options);
+        render::draw_prompt(engine, prompt, 0);
+        let alive = self.arena.sneks.iter().filter(|snek| snek.alive).count();
+        let scores = format!("YOU: {}  LEFT: {alive}", self.arena.sneks[self.me].score());
+        let bottom = engine.get_height() as i32 - 1;
+        render::draw_prompt(engine, &scores, bottom);
+        render::draw_food(engine, &self.arena.food, options, FOOD_COLOR);
+        for (index, snek) in self.arena.sneks.iter().enumerate() {
+            let palette: &[Color] = match index == self.me {
+                true => &SNEK_COLORS,
+                false => &[RIVAL_COLOR],
+            };
+            render::draw_snek(engine, snek, options, palette);
+        }
+        engine.draw();
+        engine.clear_screen();
+        engine.wait_frame();
+    }
+}
+
+// Returns an error for a malformed message from the server
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    // Returns a two player arena on the usual board
+    fn arena() -> Arena {
+        Arena::with_players(
+            Options {
+                seed: 1,
+                ..Options::default()
+            },
+            2,
+        )
+    }
+
+    // Reads a state message for a two player arena on the usual board
+    fn read_state(message: &str) -> Option<State> {
+        match serde_json::from_str(message).ok()? {
+            ServerMessage::State(state) => state.check(&arena().options, 2),
+            _ => None,
+        }
+    }
+
+    // Reads a welcome message, returning what was wrong with it if anything
+    fn read(welcome: &str) -> Result<(usize, usize, Options), String> {
+        read_welcome(&mut Cursor::new(format!("{welcome}\n"))).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn state_round_trips() {
+        let mut arena = arena();
+        arena.advance(&[Direction::Down, Direction::Left]);
+        let state = read_state(&state(&arena)).unwrap();
+        assert_eq!(state.tick, 1);
+        assert_eq!(state.food, arena.food);
+        let sneks: Vec<SnekState> = arena
+            .sneks
+            .iter()
+            .map(|snek| SnekState {
+                alive: snek.alive,
+                body: snek.body.clone(),
+            })
+            .collect();
+        assert_eq!(state.sneks, sneks);
+    }
+
+    #[test]
+    fn malformed_states_are_ignored() {
+        let good = state(&arena());
+        assert!(read_state(&good).is_some());
+        let snek = "{\"alive\":true,\"body\":[[0,0]]}";
+        let cases = [
+            "not json".to_owned(),
+            good.replace("\"tick\":0", "\"tick\":-1"),
+            format!("{{\"type\":\"state\",\"tick\":0,\"food\":[],\"sneks\":[{snek}]}}"),
+            format!(
+                "{{\"type\":\"state\",\"tick\":0,\"food\":[[17,0]],\"sneks\":[{snek},{snek}]}}"
+            ),
+            format!(
+                "{{\"type\":\"state\",\"tick\":0,\"food\":[[0,-1]],\"sneks\":[{snek},{snek}]}}"
+            ),
+            format!(
+                "{{\"type\":\"state\",\"tick\":0,\"food\":[[4294967295,0]],\"sneks\":[{snek},{snek}]}}"
+            ),
+            format!("{{\"type\":\"state\",\"tick\":0,\"food\":[[1]],\"sneks\":[{snek},{snek}]}}"),
+            format!(
+                "{{\"type\":\"state\",\"tick\":0,\"food\":[],\"sneks\":[{snek},{{\"alive\":true,\"body\":[]}}]}}"
+            ),
+            format!(
+                "{{\"type\":\"state\",\"tick\":0,\"food\":[],\"sneks\":[{snek},{{\"alive\":1,\"body\":[[0,0]]}}]}}"
+            ),
+        ];
+        for case in cases {
+            assert_eq!(read_state(&case), None, "{case} was let through");
+        }
+    }
+
+    #[test]
+    fn welcome_is_read_back() {
+        let played = Options {
+            seed: 7,
+            width: 20,
+            ..Options::default()
+        };
+        let (me, players, options) = read(&welcome(1, 3, &played)).unwrap();
+        assert_eq!((me, players), (1, 3));
+        assert_eq!(options, played);
+    }
+
+    #[test]
+    fn welcomes_out_of_range_are_refused() {
+        let good = welcome(0, 2, &Options::default());
+        let cases = [
+            ("\"version\":1", "\"version\":2"),
+            ("\"player\":0", "\"player\":2"),
+            ("\"players\":2", "\"players\":9"),
+            ("\"players\":2", "\"players\":0"),
+            ("\"fps\":8", "\"fps\":0"),
+            ("\"width\":17", "\"width\":0"),
+            ("\"height\":15", "\"height\":2"),
+            ("\"width\":17", "\"width\":100000"),
+            ("\"cell_width\":2", "\"cell_width\":9"),
+            ("\"fps\":8", "\"fps\":\"fast\""),
+        ];
+        for (from, to) in cases {
+            assert!(good.contains(from));
+            assert!(
+                read(&good.replace(from, to)).is_err(),
+                "{to} was let through"
+            );
+        }
+        // Growing the board would leave the client playing on a different one
+        let clamped = good
+            .replace("\"min_board\":\"error\"", "\"min_board\":\"clamp\"")
+            .replace("\"height\":15", "\"height\":2");
+        assert!(read(&clamped).is_err());
+        assert!(read("{\"type\":\"waiting\"}").is_err());
+        assert!(read("").is_err());
+    }
+}
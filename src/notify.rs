@@ -0,0 +1,28 @@
+// A desktop notification for a new high score (with the `notify` feature), so
+// it's seen even if the terminal is buried under other windows. It's shown
+// once the game's over and the terminal's been given back, and snek only waits
+// so long for the notification server before exiting anyway.
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use notify_rust::Notification;
+
+// Longest snek waits for the notification to be shown
+const SHOW_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Shows a notification for a new high score, returning why it couldn't be
+pub fn high_score(score: usize) -> Result<(), String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let shown = Notification::new()
+            .summary("SNEK")
+            .body(&format!("New high score {score}!"))
+            .show();
+        let _ = sender.send(shown.map(|_| ()).map_err(|err| err.to_string()));
+    });
+    receiver
+        .recv_timeout(SHOW_TIMEOUT)
+        .unwrap_or_else(|_| Err("the notification server didn't answer in time".to_owned()))
+}
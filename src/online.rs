@@ -0,0 +1,150 @@
+// Submitting scores to a remote leaderboard and fetching the best ones back.
+//
+// The leaderboard is set up in `online.txt` in the config directory: a
+// `snek-online 1` header line followed by `url http://host:port` and
+// (optionally) `token TOKEN` lines. Without it, everything stays local.
+//
+// Finished games are POSTed to `URL/scores` as the JSON results with the
+// game's `replay` and its `replay_hash` added, and `URL/leaderboard` returns
+// the best scores as `name  score  mode` tab separated lines. Only plain HTTP
+// is supported.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::replay::Replay;
+use crate::storage;
+use crate::summary::GameSummary;
+
+// Name of the online config file inside the config directory
+pub const ONLINE_FILE: &str = "online.txt";
+
+// Header line (including version) of the online config file
+const HEADER: &str = "snek-online 1";
+
+// How long to wait for the leaderboard before giving up on a request
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+// A finished game as it's submitted: its JSON results, with its replay and the
+// replay's hash added
+#[derive(Serialize, Deserialize)]
+pub struct Submission {
+    #[serde(flatten)]
+    pub summary: GameSummary,
+    pub replay: String,
+    pub replay_hash: String,
+}
+
+impl Submission {
+    // Returns the submission of a game with its replay written out
+    pub fn new(summary: &GameSummary, replay: &str) -> Self {
+        Self {
+            summary: summary.clone(),
+            replay: replay.to_owned(),
+            replay_hash: format!("{:016x}", hash(replay)),
+        }
+    }
+}
+
+// Where the leaderboard is and how to sign submissions
+pub struct OnlineConfig {
+    pub url: String,
+    pub token: Option<String>,
+}
+
+impl OnlineConfig {
+    // Loads the config from the config directory, if there's a valid one
+    pub fn load_default() -> Option<Self> {
+        let contents = fs::read_to_string(storage::config_file(ONLINE_FILE)?).ok()?;
+        Self::parse(&contents)
+    }
+
+    // Reads the config from the contents of a config file
+    pub fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        if lines.next() != Some(HEADER) {
+            return None;
+        }
+        let (mut url, mut token) = (None, None);
+        for line in lines {
+            match line.split_once(' ') {
+                Some(("url", value)) => url = Some(value.trim_end_matches('/').to_owned()),
+                Some(("token", value)) => token = Some(value.to_owned()),
+                _ => (),
+            }
+        }
+        Some(Self { url: url?, token })
+    }
+
+    // Submits a finished game, trying once more if the first attempt fails
+    pub fn submit(&self, summary: &GameSummary, replay: &Replay) -> io::Result<()> {
+        let replay = replay.serialize();
+        let body = serde_json::to_string(&Submission::new(summary, &replay)).unwrap_or_default();
+        let url = self.url.clone() + "/scores";
+        self.request("POST", &url, &body)
+            .or_else(|_| self.request("POST", &url, &body))
+            .map(|_| ())
+    }
+
+    // Fetches the best scores on the leaderboard, formatted for printing
+    pub fn leaderboard(&self) -> io::Result<String> {
+        let body = self.request("GET", &(self.url.clone() + "/leaderboard"), "")?;
+        let mut text = "  #  NAME             SCORE  MODE\n".to_owned();
+        let entries = body.lines().filter_map(|line| {
+            let mut fields = line.split('\t');
+            Some((fields.next()?, fields.next()?, fields.next()?))
+        });
+        for (rank, (name, score, mode)) in entries.enumerate() {
+            text += &format!("{:>3}  {:<16} {:>5}  {}\n", rank + 1, name, score, mode);
+        }
+        Ok(text.trim_end().to_owned())
+    }
+
+    // Sends an HTTP request to the leaderboard, returning the body of a successful response
+    fn request(&self, method: &str, url: &str, body: &str) -> io::Result<String> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| io::Error::other("the leaderboard url must start with http://"))?;
+        let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let address = match host.contains(':') {
+            true => host.to_owned(),
+            false => format!("{host}:80"),
+        };
+        let address = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::other(format!("couldn't find {host}")))?;
+        let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let mut request = format!(
+            "{method} {} HTTP/1.0\r\nHost: {host}\r\nContent-Type: application/json\r\n\
+            Content-Length: {}\r\n",
+            if path.is_empty() { "/" } else { path },
+            body.len()
+        );
+        if let Some(token) = &self.token {
+            request += &format!("Authorization: Bearer {token}\r\n");
+        }
+        stream.write_all((request + "\r\n" + body).as_bytes())?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+        let status = head.lines().next().unwrap_or_default();
+        match status.split(' ').nth(1) {
+            Some(code) if code.starts_with('2') => Ok(body.to_owned()),
+            _ => Err(io::Error::other(format!("the leaderboard said '{status}'"))),
+        }
+    }
+}
+
+// Hashes some text with 64 bit FNV-1a, which stays the same across builds
+pub fn hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,1223 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use console_engine::crossterm::terminal;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::arena::MAX_PLAYERS;
+use crate::config::{self, CONFIG_FILE, Config, LEGACY_CONFIG_FILE};
+use crate::curve::SpeedCurve;
+use crate::daily::DAILY_MODES;
+use crate::export::{DEFAULT_SCALE, Export, ExportFormat};
+use crate::level::Level;
+use crate::share::ShareCode;
+use crate::storage;
+use crate::versus::Role;
+use crate::world::{self, Direction};
+use crate::{FPS, HEIGHT, MAX_BOARD, MIN_BOARD, Point, WIDTH};
+
+// Printed when the command line can't be understood
+const USAGE: &str = "\
+usage: snek [scores | stats [--rebuild] | history [--csv] [--last GAMES] | leaderboard | paths]
+            [--portable] [--scores] [--name NAME] [--seed SEED] [--code CODE]
+            [--difficulty easy|normal|hard]
+            [--mode classic|ouroboros|rush|choice|marathon|twin|cluster] [--rush]
+            [--steering absolute|relative] [--min-board error|clamp]
+            [--scoring length|food|points] [--sound bell|effects|off] [--volume PERCENT] [--mute]
+            [--cell-width 1|2|3] [--aspect-compensate] [--margin-x COLUMNS] [--margin-y ROWS]
+            [--border solid|sharp|rounded]
+            [--safe-start] [--random-heading] [--no-reverse-lockout] [--hold-boost]
+            [--target SCORE] [--goal FOODS] [--min-fps FPS] [--max-fps FPS] [--ramp-curve CURVE]
+            [--growth-delay TICKS] [--food-pulse MS] [--food-decay] [--wall-hug-penalty]
+            [--food-clearance CELLS] [--food-placement uniform|far|weighted] [--no-fair-food]
+            [--double-food] [--game-over-timeout SECONDS] [--idle-pause SECONDS] [--no-victory-lap]
+            [--trail-fade] [--smooth-motion] [--grid-labels] [--fixed-head] [--hints]
+            [--assist slowmo] [--ghost] [--json] [--no-lag-warning] [--no-presence] [--no-notify]
+            [--record FILE] [--export-replay FILE.ansi]
+            [--control-socket PATH [--control-steering]] [--broadcast ADDRESS]
+            [--vote-input ADDRESS] [--log-file PATH] [--debug] [--level FILE] [--level-dir DIR]
+            [--bot-io [--headless] [--bot-timeout MS]] [--resume]
+            [--list-modes] [--list-difficulties]
+       snek serve [--port PORT] [--token TOKEN]
+       snek watch ADDRESS
+       snek host [--port PORT]
+       snek join ADDRESS
+       snek net-serve [--port PORT] [--players 2-4]
+       snek net-join ADDRESS
+       snek export --format ansi|asciicast|gif [--scale PIXELS] [--frame-skip TICKS] REPLAY OUTPUT
+       snek verify REPLAY
+       snek edit LEVEL
+       snek daily [--mode classic|ouroboros|rush|choice] [--history]";
+
+// Port the leaderboard server listens on, unless another one is given
+const DEFAULT_PORT: u16 = 7070;
+
+// How many of the latest games `snek history` lists, unless told otherwise
+const HISTORY_ROWS: usize = 20;
+
+// Port head-to-head games are hosted on, unless another one is given
+const VERSUS_PORT: u16 = 7777;
+
+// Port network games are served on, unless another one is given
+const NET_PORT: u16 = 7778;
+
+// How many columns a cell of the map can take up
+const CELL_WIDTHS: std::ops::RangeInclusive<u32> = 1..=3;
+
+// The different ways the game can be played
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    Classic,
+    Ouroboros,
+    Rush,
+    Choice,
+    Marathon,
+    Twin,
+    Cluster,
+}
+
+impl Mode {
+    // Returns every mode
+    pub fn all() -> &'static [Self] {
+        &[
+            Mode::Classic,
+            Mode::Ouroboros,
+            Mode::Rush,
+            Mode::Choice,
+            Mode::Marathon,
+            Mode::Twin,
+            Mode::Cluster,
+        ]
+    }
+
+    // Returns the name used for the mode in files and on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Classic => "classic",
+            Mode::Ouroboros => "ouroboros",
+            Mode::Rush => "rush",
+            Mode::Choice => "choice",
+            Mode::Marathon => "marathon",
+            Mode::Twin => "twin",
+            Mode::Cluster => "cluster",
+        }
+    }
+
+    // Finds the mode with the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Mode::Classic),
+            "ouroboros" => Some(Mode::Ouroboros),
+            "rush" => Some(Mode::Rush),
+            "choice" => Some(Mode::Choice),
+            "marathon" => Some(Mode::Marathon),
+            "twin" => Some(Mode::Twin),
+            "cluster" => Some(Mode::Cluster),
+            _ => None,
+        }
+    }
+}
+
+// How the arrow keys steer the snek (pointing it, or turning it left and right)
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SteeringMode {
+    Absolute,
+    Relative,
+}
+
+impl SteeringMode {
+    // Finds the steering mode with the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "absolute" => Some(SteeringMode::Absolute),
+            "relative" => Some(SteeringMode::Relative),
+            _ => None,
+        }
+    }
+}
+
+// What happens when the board is smaller than `MIN_BOARD` in either direction
+// (stopping with an error, or growing it to fit and warning)
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MinBoard {
+    Error,
+    Clamp,
+}
+
+impl MinBoard {
+    // Finds the policy with the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "error" => Some(MinBoard::Error),
+            "clamp" => Some(MinBoard::Clamp),
+            _ => None,
+        }
+    }
+}
+
+// What the score counts: how much the snek has grown, how much food it's
+// eaten, or points for each food (worth more the longer the snek is)
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scoring {
+    Length,
+    Food,
+    Points,
+}
+
+impl Scoring {
+    // Finds the scoring with the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "length" => Some(Scoring::Length),
+            "food" => Some(Scoring::Food),
+            "points" => Some(Scoring::Points),
+            _ => None,
+        }
+    }
+}
+
+// What the game sounds like: the terminal bell when the snek eats (and twice
+// when it dies), sound effects through the audio device (with the `audio`
+// feature), or nothing
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sound {
+    Bell,
+    Effects,
+    Off,
+}
+
+impl Sound {
+    // Finds the sound with the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bell" => Some(Sound::Bell),
+            "effects" => Some(Sound::Effects),
+            "off" => Some(Sound::Off),
+            _ => None,
+        }
+    }
+}
+
+// How the border around the map looks: a solid block of color, or with a
+// line around the map's edge that has sharp or rounded corners
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Border {
+    Solid,
+    Sharp,
+    Rounded,
+}
+
+impl Border {
+    // Finds the border with the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "solid" => Some(Border::Solid),
+            "sharp" => Some(Border::Sharp),
+            "rounded" => Some(Border::Rounded),
+            _ => None,
+        }
+    }
+}
+
+// Where new food goes: any free cell as likely as any other, the farthest
+// from the snek's head of a few picked at random, or one of those picked with
+// the farther ones more likely
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FoodPlacement {
+    Uniform,
+    Far,
+    Weighted,
+}
+
+impl FoodPlacement {
+    // Finds the placement with the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "uniform" => Some(FoodPlacement::Uniform),
+            "far" => Some(FoodPlacement::Far),
+            "weighted" => Some(FoodPlacement::Weighted),
+            _ => None,
+        }
+    }
+}
+
+// Help that makes a game easier, which keeps it out of the score table:
+// slowing the snek down for a couple of moves when it's about to die
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Assist {
+    Slowmo,
+}
+
+impl Assist {
+    // Returns the name used for the assist in files and on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Assist::Slowmo => "slowmo",
+        }
+    }
+
+    // Finds the assist with the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "slowmo" => Some(Assist::Slowmo),
+            _ => None,
+        }
+    }
+}
+
+// How fast the snek moves
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    // Returns every difficulty
+    pub fn all() -> &'static [Self] {
+        &[Difficulty::Easy, Difficulty::Normal, Difficulty::Hard]
+    }
+
+    // Returns the name used for the difficulty on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    // Finds the difficulty with the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Difficulty::Easy),
+            "normal" => Some(Difficulty::Normal),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+
+    // Returns how many times a second the snek moves
+    pub fn fps(&self) -> u32 {
+        match self {
+            Difficulty::Easy => FPS * 2 / 3,
+            Difficulty::Normal => FPS,
+            Difficulty::Hard => FPS * 3 / 2,
+        }
+    }
+}
+
+// Everything that decides how a game is set up
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Options {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub min_fps: Option<u32>,
+    pub max_fps: Option<u32>,
+    pub ramp_curve: SpeedCurve,
+    pub cell_width: u32,
+    pub aspect_compensate: bool,
+    pub margin_x: u32,
+    pub margin_y: u32,
+    pub border: Border,
+    pub safe_start: bool,
+    pub random_heading: bool,
+    pub reverse_lockout: bool,
+    pub hold_boost: bool,
+    pub target: Option<usize>,
+    pub goal: Option<usize>,
+    pub growth_delay: u32,
+    pub food_pulse: Option<u32>,
+    pub food_decay: bool,
+    pub wall_hug_penalty: bool,
+    pub double_food: bool,
+    pub food_clearance: u32,
+    pub food_placement: FoodPlacement,
+    pub fair_food: bool,
+    pub game_over_timeout: Option<u32>,
+    pub idle_pause: Option<u32>,
+    pub victory_lap: bool,
+    pub trail_fade: bool,
+    pub smooth_motion: bool,
+    pub grid_labels: bool,
+    pub fixed_head: bool,
+    pub hints: bool,
+    pub assist: Option<Assist>,
+    pub ghost: bool,
+    pub daily: Option<u64>,
+    pub json: bool,
+    pub lag_warning: bool,
+    pub presence: bool,
+    pub notify: bool,
+    pub difficulty: Difficulty,
+    pub mode: Mode,
+    pub steering: SteeringMode,
+    pub min_board: MinBoard,
+    pub scoring: Scoring,
+    pub sound: Sound,
+    pub volume: u8,
+    pub level: Option<String>,
+    pub walls: Vec<Point>,
+    pub food_sequence: Vec<Point>,
+    pub start: Option<(Point, Direction)>,
+    pub seed: u64,
+    pub name: String,
+    // The rest only matter to the run that was given them, so aren't serialized
+    #[serde(skip)]
+    pub record: Option<PathBuf>,
+    #[serde(skip)]
+    pub export_replay: Option<PathBuf>,
+    #[serde(skip)]
+    pub control_socket: Option<PathBuf>,
+    #[serde(skip)]
+    pub control_steering: bool,
+    #[serde(skip)]
+    pub broadcast: Option<String>,
+    #[serde(skip)]
+    pub vote_input: Option<String>,
+    #[serde(skip)]
+    pub log_file: Option<PathBuf>,
+    #[serde(skip)]
+    pub debug: bool,
+    #[serde(skip)]
+    pub level_dir: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            width: WIDTH,
+            height: HEIGHT,
+            fps: FPS,
+            min_fps: None,
+            max_fps: None,
+            ramp_curve: SpeedCurve::Flat,
+            cell_width: 2,
+            aspect_compensate: false,
+            margin_x: 2,
+            margin_y: 1,
+            border: Border::Solid,
+            safe_start: false,
+            random_heading: false,
+            reverse_lockout: true,
+            hold_boost: false,
+            target: None,
+            goal: None,
+            growth_delay: 0,
+            food_pulse: None,
+            food_decay: false,
+            wall_hug_penalty: false,
+            double_food: false,
+            food_clearance: 2,
+            food_placement: FoodPlacement::Uniform,
+            fair_food: true,
+            game_over_timeout: None,
+            idle_pause: None,
+            victory_lap: true,
+            trail_fade: false,
+            smooth_motion: false,
+            grid_labels: false,
+            fixed_head: false,
+            hints: false,
+            assist: None,
+            ghost: false,
+            daily: None,
+            json: false,
+            lag_warning: true,
+            presence: true,
+            notify: true,
+            difficulty: Difficulty::Normal,
+            mode: Mode::Classic,
+            steering: SteeringMode::Absolute,
+            min_board: MinBoard::Error,
+            scoring: Scoring::Length,
+            sound: Sound::Off,
+            volume: 100,
+            level: None,
+            walls: Vec::new(),
+            food_sequence: Vec::new(),
+            start: None,
+            seed: fastrand::u64(..),
+            name: env::var("USER").unwrap_or_else(|_| "snek".to_owned()),
+            record: None,
+            export_replay: None,
+            control_socket: None,
+            control_steering: false,
+            broadcast: None,
+            vote_input: None,
+            log_file: None,
+            debug: false,
+            level_dir: None,
+        }
+    }
+}
+
+impl Options {
+    // Returns the size of the screen (the map and the margins around it) in columns and rows
+    pub fn screen_size(&self) -> (u32, u32) {
+        (
+            self.width * self.cell_width + self.margin_x * 2,
+            self.height + self.margin_y * 2,
+        )
+    }
+
+    // Returns the snek's body at the start, from tail to head
+    pub fn starting_body(&self) -> Vec<Point> {
+        world::starting_body(self.start)
+    }
+
+    // Reads the options a config sets, keeping the defaults of the rest
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        // Each option's read on its own first, so a bad one can be named
+        for (key, value) in config {
+            let one = Config::from_iter([(key.clone(), value.clone())]);
+            if Self::deserialize(Value::Object(one)).is_err() {
+                return Err(format!("invalid value {value} for '{key}'"));
+            }
+        }
+        let mut options =
+            Self::deserialize(Value::Object(config.clone())).map_err(|err| err.to_string())?;
+        // A difficulty sets the speed too, like `--difficulty` does, unless
+        // it's given on its own
+        if !config.contains_key("fps") {
+            options.fps = options.difficulty.fps();
+        }
+        options.checked()
+    }
+
+    // Reads options written by another snek (in a save, a replay, or over the
+    // network), checking them like the ones in a config (into anything that
+    // holds options, like a box for a message that'd be too big otherwise)
+    pub fn deserialize_checked<'de, D: Deserializer<'de>, T: From<Self>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        Self::deserialize(deserializer)?
+            .checked()
+            .map(T::from)
+            .map_err(D::Error::custom)
+    }
+
+    // Tidies up options that were read rather than given on the command line
+    // (a speed limit, food pulse, or idle pause of 0 is none at all, the
+    // margin above and below the map is at least 1, and the volume is at most
+    // 100), then checks the rest are in range
+    fn checked(mut self) -> Result<Self, String> {
+        self.min_fps = self.min_fps.filter(|fps| *fps > 0);
+        self.max_fps = self.max_fps.filter(|fps| *fps > 0);
+        self.food_pulse = self.food_pulse.filter(|pulse| *pulse > 0);
+        self.idle_pause = self.idle_pause.filter(|secs| *secs > 0);
+        self.margin_y = self.margin_y.max(1);
+        self.volume = self.volume.min(100);
+        self.validate()?;
+        Ok(self)
+    }
+
+    // Loads the options from the config file (upgraded to the current
+    // version), keeping the defaults of ones it doesn't set
+    pub fn load_config() -> Result<Self, String> {
+        let Some(path) = storage::config_file(CONFIG_FILE) else {
+            return Ok(Self::default());
+        };
+        let legacy = storage::config_file(LEGACY_CONFIG_FILE);
+        let in_path = |err| format!("{err} in {}", path.display());
+        let config = config::load(&path, legacy.as_deref()).map_err(in_path)?;
+        for key in Self::unknown_keys(&config) {
+            eprintln!("Warning: unknown option '{key}' in {}", path.display());
+        }
+        Self::from_config(&config).map_err(in_path)
+    }
+
+    // Makes sure the board is at least `MIN_BOARD` cells across and down,
+    // following the `min_board` policy; returns whether it had to be grown
+    pub fn fit_min_board(&mut self) -> Result<bool, String> {
+        if self.width >= MIN_BOARD && self.height >= MIN_BOARD {
+            return Ok(false);
+        }
+        match self.min_board {
+            MinBoard::Error => Err(format!(
+                "the board needs to be at least {MIN_BOARD}x{MIN_BOARD}, but this one is {}x{} \
+                (use `--min-board clamp` to grow it instead)",
+                self.width, self.height
+            )),
+            MinBoard::Clamp => {
+                self.width = self.width.max(MIN_BOARD);
+                self.height = self.height.max(MIN_BOARD);
+                Ok(true)
+            }
+        }
+    }
+
+    // Checks that the options that have to be in a range are, wherever they
+    // came from (the command line, a file, or another snek)
+    pub fn validate(&self) -> Result<(), String> {
+        if self.width == 0 || self.height == 0 {
+            return Err("the board can't be empty".to_owned());
+        }
+        if self.width > MAX_BOARD || self.height > MAX_BOARD {
+            return Err(format!(
+                "the board can be at most {MAX_BOARD}x{MAX_BOARD}, but this one is {}x{}",
+                self.width, self.height
+            ));
+        }
+        if self.margin_x > MAX_BOARD || self.margin_y > MAX_BOARD {
+            return Err(format!("the margins can be at most {MAX_BOARD}"));
+        }
+        if self.margin_y == 0 {
+            return Err("the margin above and below the map must be at least 1 to leave room for the prompts".to_owned());
+        }
+        if self.margin_x == 0 && self.border != Border::Solid {
+            return Err("the margin left and right of the map must be at least 1 to leave room for the border's line".to_owned());
+        }
+        if self.fps == 0 {
+            return Err("the speed can't be 0".to_owned());
+        }
+        if !CELL_WIDTHS.contains(&self.cell_width) {
+            return Err("the cell width must be 1, 2, or 3".to_owned());
+        }
+        if self.volume > 100 {
+            return Err("the volume must be from 0 to 100".to_owned());
+        }
+        Ok(())
+    }
+
+    // Returns the keys a config sets that aren't the name of any option
+    pub fn unknown_keys(config: &Config) -> Vec<&str> {
+        let known = match serde_json::to_value(Self::default()) {
+            Ok(Value::Object(known)) => known,
+            _ => Config::new(),
+        };
+        config
+            .keys()
+            .map(String::as_str)
+            .filter(|key| !known.contains_key(*key))
+            .collect()
+    }
+
+    // Checks that the screen fits in the terminal, if its size can be found
+    pub fn check_fits(&self) -> Result<(), String> {
+        let (width, height) = self.screen_size();
+        match terminal::size() {
+            Ok((columns, rows)) if width > columns as u32 || height > rows as u32 => Err(format!(
+                "the game needs a {width}x{height} terminal, but this one is {columns}x{rows}"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+// What the program was asked to do
+pub enum Command {
+    Play(Options),
+    Daily {
+        options: Options,
+        history: bool,
+    },
+    Bot {
+        options: Options,
+        headless: bool,
+        timeout: Option<u64>,
+    },
+    Versus {
+        role: Role,
+        options: Options,
+    },
+    NetServe {
+        port: u16,
+        players: usize,
+        options: Options,
+    },
+    NetJoin {
+        address: String,
+    },
+    Resume,
+    Scores,
+    Stats {
+        rebuild: bool,
+    },
+    History {
+        csv: bool,
+        last: usize,
+    },
+    Leaderboard,
+    Paths,
+    Watch {
+        address: String,
+    },
+    Serve {
+        port: u16,
+        token: Option<String>,
+    },
+    List(Vec<&'static str>),
+    Export(Export),
+    Verify(PathBuf),
+    Edit {
+        path: PathBuf,
+        options: Options,
+    },
+}
+
+impl Command {
+    // Reads the command from the program's arguments
+    pub fn from_args() -> Result<Self, String> {
+        // `--portable` changes where the config is, so it has to be found first
+        let mut args: Vec<String> = env::args().skip(1).collect();
+        if args.iter().any(|arg| arg == "--portable") {
+            args.retain(|arg| arg != "--portable");
+            storage::use_portable()
+                .map_err(|err| format!("couldn't find the portable directory: {err}"))?;
+        }
+        Self::parse(args, Options::load_config()?)
+    }
+
+    // Reads the command from a list of arguments, on top of some default options
+    pub fn parse(args: impl IntoIterator<Item = String>, options: Options) -> Result<Self, String> {
+        Self::parse_args(args, options).map_err(|err| format!("{err}\n{USAGE}"))
+    }
+
+    // Reads the command from a list of arguments, without the usage in errors
+    fn parse_args(
+        args: impl IntoIterator<Item = String>,
+        mut options: Options,
+    ) -> Result<Self, String> {
+        let mut command = None;
+        let (mut bot_io, mut headless, mut timeout) = (false, false, None);
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "scores" | "--scores" => command = Some(Command::Scores),
+                "stats" => return parse_stats(args),
+                "history" => return parse_history(args),
+                "leaderboard" => command = Some(Command::Leaderboard),
+                "paths" => command = Some(Command::Paths),
+                "serve" => return parse_serve(args),
+                "watch" => return parse_watch(args),
+                "host" => return parse_host(args, options),
+                "join" => return parse_join(args, options),
+                "net-serve" => return parse_net_serve(args, options),
+                "net-join" => return parse_net_join(args),
+                "--resume" => command = Some(Command::Resume),
+                "export" => return parse_export(args),
+                "verify" => return parse_verify(args),
+                "edit" => return parse_edit(args, options),
+                "daily" => return parse_daily(args, options),
+                "--list-modes" => return Ok(Command::List(list(Mode::all(), Mode::name))),
+                "--list-difficulties" => {
+                    return Ok(Command::List(list(Difficulty::all(), Difficulty::name)));
+                }
+                "--record" => options.record = Some(value(&arg, args.next())?.into()),
+                "--export-replay" => {
+                    options.export_replay = Some(value(&arg, args.next())?.into());
+                }
+                "--control-socket" => {
+                    options.control_socket = Some(value(&arg, args.next())?.into());
+                }
+                "--control-steering" => options.control_steering = true,
+                "--broadcast" => options.broadcast = Some(value(&arg, args.next())?),
+                "--vote-input" => options.vote_input = Some(value(&arg, args.next())?),
+                "--log-file" => options.log_file = Some(value(&arg, args.next())?.into()),
+                "--debug" => options.debug = true,
+                "--level-dir" => options.level_dir = Some(value(&arg, args.next())?.into()),
+                "--level" => {
+                    let path = value(&arg, args.next())?;
+                    Level::load(Path::new(&path))
+                        .map_err(|err| format!("couldn't load the level in {path}: {err}"))?
+                        .apply(&mut options);
+                }
+                "--name" => options.name = value(&arg, args.next())?,
+                "--seed" => options.seed = parse(&arg, args.next())?,
+                "--code" => ShareCode::decode(&value(&arg, args.next())?)?.apply(&mut options),
+                "--safe-start" => options.safe_start = true,
+                "--random-heading" => options.random_heading = true,
+                "--no-reverse-lockout" => options.reverse_lockout = false,
+                "--hold-boost" => options.hold_boost = true,
+                "--no-fair-food" => options.fair_food = false,
+                "--json" => options.json = true,
+                "--trail-fade" => options.trail_fade = true,
+                "--smooth-motion" => options.smooth_motion = true,
+                "--grid-labels" => options.grid_labels = true,
+                "--fixed-head" => options.fixed_head = true,
+                "--hints" => options.hints = true,
+                "--assist" => options.assist = Some(named(&arg, args.next(), Assist::from_name)?),
+                "--ghost" => options.ghost = true,
+                "--no-lag-warning" => options.lag_warning = false,
+                "--no-presence" => options.presence = false,
+                "--no-notify" => options.notify = false,
+                "--bot-io" => bot_io = true,
+                "--headless" => headless = true,
+                "--bot-timeout" => timeout = Some(parse(&arg, args.next())?),
+                "--target" => options.target = Some(parse(&arg, args.next())?).filter(|t| *t > 0),
+                "--goal" => options.goal = Some(parse(&arg, args.next())?).filter(|g| *g > 0),
+                "--min-fps" => options.min_fps = Some(parse(&arg, args.next())?).filter(|f| *f > 0),
+                "--max-fps" => options.max_fps = Some(parse(&arg, args.next())?).filter(|f| *f > 0),
+                "--ramp-curve" => {
+                    options.ramp_curve = SpeedCurve::from_name(&value(&arg, args.next())?)?;
+                }
+                "--growth-delay" => options.growth_delay = parse(&arg, args.next())?,
+                "--food-pulse" => {
+                    options.food_pulse = Some(parse(&arg, args.next())?).filter(|p| *p > 0);
+                }
+                "--food-decay" => options.food_decay = true,
+                "--wall-hug-penalty" => options.wall_hug_penalty = true,
+                "--double-food" => options.double_food = true,
+                "--food-clearance" => options.food_clearance = parse(&arg, args.next())?,
+                "--food-placement" => {
+                    options.food_placement = named(&arg, args.next(), FoodPlacement::from_name)?
+                }
+                "--game-over-timeout" => {
+                    options.game_over_timeout = Some(parse(&arg, args.next())?);
+                }
+                "--idle-pause" => {
+                    options.idle_pause = Some(parse(&arg, args.next())?).filter(|s| *s > 0);
+                }
+                "--no-victory-lap" => options.victory_lap = false,
+                "--cell-width" => options.cell_width = parse(&arg, args.next())?,
+                "--aspect-compensate" => options.aspect_compensate = true,
+                "--margin-x" => options.margin_x = parse(&arg, args.next())?,
+                "--margin-y" => options.margin_y = parse(&arg, args.next())?,
+                "--border" => options.border = named(&arg, args.next(), Border::from_name)?,
+                "--mode" => options.mode = named(&arg, args.next(), Mode::from_name)?,
+                "--rush" => options.mode = Mode::Rush,
+                "--steering" => {
+                    options.steering = named(&arg, args.next(), SteeringMode::from_name)?;
+                }
+                "--min-board" => {
+                    options.min_board = named(&arg, args.next(), MinBoard::from_name)?;
+                }
+                "--scoring" => options.scoring = named(&arg, args.next(), Scoring::from_name)?,
+                "--sound" => {
+                    options.sound = named(&arg, args.next(), Sound::from_name)?;
+                    if cfg!(not(feature = "audio")) && options.sound == Sound::Effects {
+                        return Err("this snek was built without audio support (rebuild it with `--features audio`)".to_owned());
+                    }
+                }
+                "--volume" => options.volume = parse(&arg, args.next())?,
+                "--mute" => options.volume = 0,
+                "--difficulty" => {
+                    options.difficulty = named(&arg, args.next(), Difficulty::from_name)?;
+                    options.fps = options.difficulty.fps();
+                }
+                _ => return Err(format!("unknown argument '{arg}'")),
+            }
+        }
+        if let (Some(min), Some(max)) = (options.min_fps, options.max_fps)
+            && min > max
+        {
+            return Err("'--min-fps' can't be more than '--max-fps'".to_owned());
+        }
+        options.validate()?;
+        if bot_io {
+            return Ok(Command::Bot {
+                options,
+                headless,
+                timeout,
+            });
+        }
+        Ok(command.unwrap_or(Command::Play(options)))
+    }
+}
+
+// Returns the names of every variant of an option
+fn list<T>(all: &[T], name: fn(&T) -> &'static str) -> Vec<&'static str> {
+    all.iter().map(name).collect()
+}
+
+// Reads the arguments of the history command
+fn parse_history(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut csv = false;
+    let mut last = HISTORY_ROWS;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--csv" => csv = true,
+            "--last" => last = parse(&arg, args.next())?,
+            _ => return Err(format!("unknown argument '{arg}'")),
+        }
+    }
+    Ok(Command::History { csv, last })
+}
+
+// Reads the arguments of the stats command
+fn parse_stats(args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut rebuild = false;
+    for arg in args {
+        match arg.as_str() {
+            "--rebuild" => rebuild = true,
+            _ => return Err(format!("unknown argument '{arg}'")),
+        }
+    }
+    Ok(Command::Stats { rebuild })
+}
+
+// Reads the arguments of the serve command
+fn parse_serve(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut port = DEFAULT_PORT;
+    let mut token = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => port = parse(&arg, args.next())?,
+            "--token" => token = Some(value(&arg, args.next())?),
+            _ => return Err(format!("unknown argument '{arg}'")),
+        }
+    }
+    Ok(Command::Serve { port, token })
+}
+
+// Reads the arguments of the watch command (the address of a broadcast)
+fn parse_watch(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let address = args.next().ok_or("'watch' needs an address")?;
+    match args.next() {
+        Some(arg) => Err(format!("unknown argument '{arg}'")),
+        None => Ok(Command::Watch { address }),
+    }
+}
+
+// Reads the arguments of the host command
+fn parse_host(mut args: impl Iterator<Item = String>, options: Options) -> Result<Command, String> {
+    let mut port = VERSUS_PORT;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => port = parse(&arg, args.next())?,
+            _ => return Err(format!("unknown argument '{arg}'")),
+        }
+    }
+    Ok(Command::Versus {
+        role: Role::Host(port),
+        options,
+    })
+}
+
+// Reads the arguments of the join command (the address of the host)
+fn parse_join(mut args: impl Iterator<Item = String>, options: Options) -> Result<Command, String> {
+    let address = args.next().ok_or("'join' needs an address")?;
+    match args.next() {
+        Some(arg) => Err(format!("unknown argument '{arg}'")),
+        None => Ok(Command::Versus {
+            role: Role::Guest(address),
+            options,
+        }),
+    }
+}
+
+// Reads the arguments of the net-serve command
+fn parse_net_serve(
+    mut args: impl Iterator<Item = String>,
+    options: Options,
+) -> Result<Command, String> {
+    let mut port = NET_PORT;
+    let mut players = 2;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => port = parse(&arg, args.next())?,
+            "--players" => players = parse(&arg, args.next())?,
+            _ => return Err(format!("unknown argument '{arg}'")),
+        }
+    }
+    if !(2..=MAX_PLAYERS).contains(&players) {
+        return Err(format!("'--players' must be from 2 to {MAX_PLAYERS}"));
+    }
+    Ok(Command::NetServe {
+        port,
+        players,
+        options,
+    })
+}
+
+// Reads the arguments of the net-join command (the address of the server)
+fn parse_net_join(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let address = args.next().ok_or("'net-join' needs an address")?;
+    match args.next() {
+        Some(arg) => Err(format!("unknown argument '{arg}'")),
+        None => Ok(Command::NetJoin { address }),
+    }
+}
+
+// Reads the arguments of the export command
+fn parse_export(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut format = None;
+    let mut scale = DEFAULT_SCALE;
+    let mut frame_skip = 1;
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => format = Some(named(&arg, args.next(), ExportFormat::from_name)?),
+            "--scale" => scale = parse(&arg, args.next())?,
+            "--frame-skip" => frame_skip = parse(&arg, args.next())?,
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    let format = format.ok_or("'export' needs a '--format'")?;
+    if scale == 0 || frame_skip == 0 {
+        return Err("'--scale' and '--frame-skip' must be at least 1".to_owned());
+    }
+    match <[PathBuf; 2]>::try_from(paths) {
+        Ok([input, output]) => Ok(Command::Export(Export {
+            format,
+            input,
+            output,
+            scale,
+            frame_skip,
+        })),
+        Err(_) => Err("'export' needs a replay and an output file".to_owned()),
+    }
+}
+
+// Reads the arguments of the daily command
+fn parse_daily(
+    mut args: impl Iterator<Item = String>,
+    mut options: Options,
+) -> Result<Command, String> {
+    let mut history = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--history" => history = true,
+            "--mode" => options.mode = named(&arg, args.next(), Mode::from_name)?,
+            "--rush" => options.mode = Mode::Rush,
+            _ => return Err(format!("unknown argument '{arg}'")),
+        }
+    }
+    if !DAILY_MODES.contains(&options.mode) {
+        let mode = options.mode.name();
+        return Err(format!("there's no daily challenge in {mode} mode"));
+    }
+    Ok(Command::Daily { options, history })
+}
+
+// Reads the arguments of the verify command (the replay to verify)
+fn parse_verify(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let path = args.next().ok_or("'verify' needs a replay")?;
+    match args.next() {
+        Some(arg) => Err(format!("unknown argument '{arg}'")),
+        None => Ok(Command::Verify(path.into())),
+    }
+}
+
+// Reads the arguments of the edit command (the level file to edit)
+fn parse_edit(mut args: impl Iterator<Item = String>, options: Options) -> Result<Command, String> {
+    let path = args.next().ok_or("'edit' needs a level file")?;
+    match args.next() {
+        Some(arg) => Err(format!("unknown argument '{arg}'")),
+        None => Ok(Command::Edit {
+            path: path.into(),
+            options,
+        }),
+    }
+}
+
+// Returns the value given to a flag, erroring if it's missing
+fn value(flag: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("'{flag}' needs a value"))
+}
+
+// Parses the value given to a flag
+fn parse<T: std::str::FromStr>(flag: &str, arg: Option<String>) -> Result<T, String> {
+    let arg = value(flag, arg)?;
+    arg.parse()
+        .map_err(|_| format!("invalid value '{arg}' for '{flag}'"))
+}
+
+// Parses the name of an option given to a flag
+fn named<T>(
+    flag: &str,
+    arg: Option<String>,
+    from_name: fn(&str) -> Option<T>,
+) -> Result<T, String> {
+    let arg = value(flag, arg)?;
+    from_name(&arg).ok_or_else(|| format!("unknown value '{arg}' for '{flag}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns the options some JSON sets
+    fn config(json: Value) -> Config {
+        match json {
+            Value::Object(config) => config,
+            _ => panic!("{json} isn't an object"),
+        }
+    }
+
+    #[test]
+    fn serde_round_trips() {
+        let options = Options {
+            target: Some(30),
+            mode: Mode::Cluster,
+            assist: Some(Assist::Slowmo),
+            level: Some("maze".to_owned()),
+            walls: vec![Point::new(1, 2), Point::new(3, 4)],
+            start: Some((Point::new(5, 5), Direction::Up)),
+            ramp_curve: SpeedCurve::Steps(3),
+            name: "two words".to_owned(),
+            record: Some(PathBuf::from("run.replay")),
+            ..Options::default()
+        };
+        let json = serde_json::to_value(&options).unwrap();
+        assert_eq!(json["mode"], "cluster");
+        assert_eq!(json["walls"], serde_json::json!([[1, 2], [3, 4]]));
+        assert_eq!(json["food_sequence"], serde_json::json!([]));
+        assert_eq!(json["start"], serde_json::json!([[5, 5], "up"]));
+        assert_eq!(json["ramp_curve"], "steps:3");
+        assert_eq!(json["target"], 30);
+        assert_eq!(json["goal"], Value::Null);
+        assert!(json.get("record").is_none());
+        let parsed: Options = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+        assert_eq!(parsed.record, None);
+    }
+
+    #[test]
+    fn serde_fills_in_missing_options() {
+        let parsed: Options = serde_json::from_str(r#"{"width": 20, "mode": "marathon"}"#).unwrap();
+        assert_eq!((parsed.width, parsed.mode), (20, Mode::Marathon));
+        assert_eq!(parsed.height, Options::default().height);
+        assert!(serde_json::from_str::<Options>(r#"{"mode": "nope"}"#).is_err());
+    }
+
+    #[test]
+    fn configs_are_checked() {
+        let values = serde_json::json!({"width": 17, "height": 15, "fps": 8, "cell_width": 2});
+        assert!(Options::from_config(&config(values.clone())).is_ok());
+        let no_margin = config(serde_json::json!({"margin_x": 0}));
+        assert!(Options::from_config(&no_margin).is_ok());
+        let mut lined = no_margin.clone();
+        lined.insert("border".to_owned(), "rounded".into());
+        assert!(Options::from_config(&lined).is_err());
+        for (key, value) in [
+            ("width", serde_json::json!(0)),
+            ("height", serde_json::json!(0)),
+            ("height", serde_json::json!(300)),
+            ("fps", serde_json::json!(0)),
+            ("cell_width", serde_json::json!(0)),
+            ("cell_width", serde_json::json!(4)),
+            ("fps", serde_json::json!("fast")),
+            ("walls", serde_json::json!([[1]])),
+        ] {
+            let mut values = config(values.clone());
+            values.insert(key.to_owned(), value.clone());
+            assert!(
+                Options::from_config(&values).is_err(),
+                "{key} {value} was let through"
+            );
+        }
+        let err = Options::from_config(&config(serde_json::json!({"mode": "nope"})));
+        assert_eq!(err.err().unwrap(), r#"invalid value "nope" for 'mode'"#);
+        // Zero speed limits are none at all, like on the command line
+        let unlimited = config(serde_json::json!({"min_fps": 0, "idle_pause": 0, "volume": 250}));
+        let unlimited = Options::from_config(&unlimited).unwrap();
+        assert_eq!((unlimited.min_fps, unlimited.idle_pause), (None, None));
+        assert_eq!(unlimited.volume, 100);
+    }
+
+    #[test]
+    fn checked_deserializing_refuses_bad_options() {
+        #[derive(Deserialize)]
+        struct Played {
+            #[serde(deserialize_with = "Options::deserialize_checked")]
+            options: Options,
+        }
+        let played = r#"{"options": {"width": 20, "food_pulse": 0}}"#;
+        let played: Played = serde_json::from_str(played).unwrap();
+        assert_eq!(
+            (played.options.width, played.options.food_pulse),
+            (20, None)
+        );
+        assert!(serde_json::from_str::<Played>(r#"{"options": {"width": 0}}"#).is_err());
+    }
+
+    #[test]
+    fn difficulty_sets_the_speed_unless_its_given() {
+        let hard = config(serde_json::json!({"difficulty": "hard"}));
+        assert_eq!(
+            Options::from_config(&hard).unwrap().fps,
+            Difficulty::Hard.fps()
+        );
+        let custom = config(serde_json::json!({"difficulty": "hard", "fps": 5}));
+        assert_eq!(Options::from_config(&custom).unwrap().fps, 5);
+    }
+
+    #[test]
+    fn unknown_keys_are_found() {
+        let values = config(serde_json::json!({"fps": 8, "fsp": 9, "cell_width": 2, "bogus": "x"}));
+        assert_eq!(Options::unknown_keys(&values), ["bogus", "fsp"]);
+    }
+
+    #[test]
+    fn command_line_is_checked_the_same_way() {
+        let parse = |args: &[&str]| {
+            let args = args.iter().map(|arg| arg.to_string());
+            Command::parse(args, Options::default()).map(|_| ())
+        };
+        assert!(parse(&["--cell-width", "3"]).is_ok());
+        for args in [
+            ["--cell-width", "4"],
+            ["--margin-y", "0"],
+            ["--volume", "101"],
+        ] {
+            assert!(parse(&args).is_err(), "{args:?} was let through");
+        }
+    }
+
+    // Returns the names a listing flag prints
+    fn listed(flag: &str) -> Vec<&'static str> {
+        match Command::parse([flag.to_owned()], Options::default()) {
+            Ok(Command::List(names)) => names,
+            _ => panic!("'{flag}' isn't a listing"),
+        }
+    }
+
+    #[test]
+    fn tiny_boards_are_clamped_or_refused() {
+        let mut options = Options {
+            width: 2,
+            height: 2,
+            min_board: MinBoard::Clamp,
+            ..Options::default()
+        };
+        assert_eq!(options.fit_min_board(), Ok(true));
+        assert_eq!((options.width, options.height), (MIN_BOARD, MIN_BOARD));
+        assert_eq!(options.fit_min_board(), Ok(false));
+        let mut options = Options {
+            width: 2,
+            height: 20,
+            ..Options::default()
+        };
+        assert!(options.fit_min_board().is_err());
+        assert_eq!((options.width, options.height), (2, 20));
+    }
+
+    #[test]
+    fn list_flags_name_every_variant() {
+        assert_eq!(
+            listed("--list-modes"),
+            [
+                "classic",
+                "ouroboros",
+                "rush",
+                "choice",
+                "marathon",
+                "twin",
+                "cluster"
+            ]
+        );
+        assert_eq!(listed("--list-difficulties"), ["easy", "normal", "hard"]);
+        for mode in Mode::all() {
+            assert_eq!(Mode::from_name(mode.name()), Some(*mode));
+        }
+        for difficulty in Difficulty::all() {
+            assert_eq!(Difficulty::from_name(difficulty.name()), Some(*difficulty));
+        }
+    }
+}
@@ -0,0 +1,241 @@
+// How the game being played is keeping pace with the personal best: the score
+// it had on the same tick of the best game played before in its mode. The
+// best game's score is kept as a timeline of the ticks it changed on, so the
+// score it had on any tick is the last one up to it (nothing before its first
+// change, and its final score after it ended).
+//
+// File format (version 1): a `snek-pace 1` header line followed by one best
+// game per mode, each a line with tab separated `mode`, `score`, and
+// `timeline` fields, where the timeline is every change as `TICK:SCORE`,
+// joined by `,` (or `-` if the score never changed).
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::options::Mode;
+use crate::storage;
+
+// Name of the pace file inside the data directory
+pub const PACE_FILE: &str = "pace.tsv";
+
+// Header line (including version) of the pace file
+const HEADER: &str = "snek-pace 1";
+
+// The ticks a game's score changed on, and what it changed to
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Timeline {
+    changes: Vec<(u64, usize)>,
+}
+
+impl Timeline {
+    // Adds the score on a tick, if it's changed since the last one
+    pub fn update(&mut self, tick: u64, score: usize) {
+        if self.score_at(tick) != score {
+            self.changes.push((tick, score));
+        }
+    }
+
+    // Returns the score on a tick
+    pub fn score_at(&self, tick: u64) -> usize {
+        self.changes
+            .iter()
+            .take_while(|(changed, _)| *changed <= tick)
+            .last()
+            .map_or(0, |(_, score)| *score)
+    }
+
+    // Returns the score the game ended with
+    pub fn final_score(&self) -> usize {
+        self.changes.last().map_or(0, |(_, score)| *score)
+    }
+
+    // Writes the timeline in the pace file format
+    fn serialize(&self) -> String {
+        if self.changes.is_empty() {
+            return "-".to_owned();
+        }
+        let changes: Vec<String> = self
+            .changes
+            .iter()
+            .map(|(tick, score)| format!("{tick}:{score}"))
+            .collect();
+        changes.join(",")
+    }
+
+    // Reads a timeline written by `serialize`, if its ticks are in order
+    fn parse(text: &str) -> Option<Self> {
+        if text == "-" {
+            return Some(Self::default());
+        }
+        let changes = text
+            .split(',')
+            .map(|change| {
+                let (tick, score) = change.split_once(':')?;
+                Some((tick.parse().ok()?, score.parse().ok()?))
+            })
+            .collect::<Option<Vec<(u64, usize)>>>()?;
+        changes
+            .is_sorted_by_key(|(tick, _)| *tick)
+            .then_some(Self { changes })
+    }
+}
+
+// Returns how far ahead (positive) or behind (negative) of the best game a
+// game with some score is on a tick
+pub fn pace(score: usize, tick: u64, best: &Timeline) -> i64 {
+    score as i64 - best.score_at(tick) as i64
+}
+
+// The best game in each mode, with its timeline
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct PaceLog {
+    bests: Vec<(Mode, Timeline)>,
+}
+
+impl PaceLog {
+    // Loads the best games from a file, which has none if it doesn't exist
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Loads the best games from the data directory, ignoring any errors
+    pub fn load_default() -> Self {
+        storage::data_file(PACE_FILE)
+            .and_then(|path| Self::load(&path).ok())
+            .unwrap_or_default()
+    }
+
+    // Saves the best games to a file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        storage::write_atomic(path, &self.serialize())
+    }
+
+    // Reads the best games from the contents of a pace file, skipping
+    // malformed lines
+    pub fn parse(contents: &str) -> io::Result<Self> {
+        let mut lines = contents.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown pace file version",
+            ));
+        }
+        let bests = lines
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let mode = Mode::from_name(fields.next()?)?;
+                let score: usize = fields.next()?.parse().ok()?;
+                let timeline = Timeline::parse(fields.next()?)?;
+                (timeline.final_score() == score).then_some((mode, timeline))
+            })
+            .collect();
+        Ok(Self { bests })
+    }
+
+    // Writes the best games in the pace file format
+    pub fn serialize(&self) -> String {
+        let mut contents = format!("{HEADER}\n");
+        for (mode, timeline) in &self.bests {
+            contents += &format!(
+                "{}\t{}\t{}\n",
+                mode.name(),
+                timeline.final_score(),
+                timeline.serialize()
+            );
+        }
+        contents
+    }
+
+    // Returns the timeline of the best game in a mode, if there's been one
+    pub fn best(&self, mode: Mode) -> Option<&Timeline> {
+        self.bests
+            .iter()
+            .find(|(best_mode, _)| *best_mode == mode)
+            .map(|(_, timeline)| timeline)
+    }
+
+    // Keeps a finished game's timeline if it scored more than the best one in
+    // its mode, returning whether it did
+    pub fn record(&mut self, mode: Mode, timeline: &Timeline) -> bool {
+        match self
+            .bests
+            .iter_mut()
+            .find(|(best_mode, _)| *best_mode == mode)
+        {
+            Some((_, best)) if best.final_score() >= timeline.final_score() => false,
+            Some((_, best)) => {
+                *best = timeline.clone();
+                true
+            }
+            None => {
+                self.bests.push((mode, timeline.clone()));
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns the timeline of a game that scored on some ticks
+    fn timeline(changes: &[(u64, usize)]) -> Timeline {
+        let mut timeline = Timeline::default();
+        for (tick, score) in changes {
+            timeline.update(*tick, *score);
+        }
+        timeline
+    }
+
+    #[test]
+    fn pace_at_the_start_and_end_of_the_best_game() {
+        let best = timeline(&[(0, 0), (5, 1), (9, 2), (20, 3)]);
+        assert_eq!(best.changes, [(5, 1), (9, 2), (20, 3)]);
+        // Before the best game first scored, it's even
+        assert_eq!(pace(0, 0, &best), 0);
+        assert_eq!(pace(0, 4, &best), 0);
+        assert_eq!(pace(1, 4, &best), 1);
+        // On the tick it scored, it counts
+        assert_eq!(pace(0, 5, &best), -1);
+        assert_eq!(pace(2, 19, &best), 0);
+        assert_eq!(pace(2, 20, &best), -1);
+        // After it ended, it stays at its final score
+        assert_eq!(pace(3, 1000, &best), 0);
+        assert_eq!(pace(5, u64::MAX, &best), 2);
+        assert_eq!(pace(4, 7, &Timeline::default()), 4);
+    }
+
+    #[test]
+    fn only_higher_scores_replace_the_best() {
+        let mut log = PaceLog::default();
+        assert!(log.record(Mode::Classic, &timeline(&[(5, 1), (9, 2)])));
+        assert!(!log.record(Mode::Classic, &timeline(&[(2, 1), (3, 2)])));
+        assert!(log.record(Mode::Rush, &timeline(&[(2, 1)])));
+        assert!(log.record(Mode::Classic, &timeline(&[(8, 3)])));
+        assert_eq!(log.best(Mode::Classic), Some(&timeline(&[(8, 3)])));
+        assert_eq!(log.best(Mode::Marathon), None);
+    }
+
+    #[test]
+    fn serialize_round_trips() {
+        let mut log = PaceLog::default();
+        log.record(Mode::Classic, &timeline(&[(5, 1), (9, 2)]));
+        log.record(Mode::Rush, &Timeline::default());
+        let serialized = log.serialize();
+        assert_eq!(serialized, "snek-pace 1\nclassic\t2\t5:1,9:2\nrush\t0\t-\n");
+        assert_eq!(PaceLog::parse(&serialized).unwrap(), log);
+        // Lines that don't add up are skipped
+        let parsed = PaceLog::parse(
+            "snek-pace 1\nclassic\t3\t5:1,9:2\nrush\t1\t9:1,5:2\nnope\t0\t-\nmarathon\t1\t4:1\n",
+        )
+        .unwrap();
+        assert_eq!(parsed.bests, [(Mode::Marathon, timeline(&[(4, 1)]))]);
+        assert!(PaceLog::parse("snek-pace 2\n").is_err());
+    }
+}
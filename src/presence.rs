@@ -0,0 +1,79 @@
+// Discord rich presence while playing, showing the score, mode, and time played.
+//
+// Presence runs on its own thread fed by the game's event channel, so a slow
+// or missing Discord can never hold up a frame. It needs the id of a Discord
+// application, given as `SNEK_DISCORD_APPLICATION_ID` when building.
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use discord_rich_presence::activity::{Activity, Timestamps};
+use discord_rich_presence::{DiscordIpc, DiscordIpcClient};
+
+use crate::events::GameEvent;
+use crate::options::Mode;
+use crate::storage;
+
+// Id of the Discord application the presence is shown as
+const APPLICATION_ID: Option<&str> = option_env!("SNEK_DISCORD_APPLICATION_ID");
+
+// Least time between presence updates (Discord rate limits them)
+const UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+
+// A running presence thread
+pub struct Presence {
+    thread: JoinHandle<Result<(), String>>,
+}
+
+impl Presence {
+    // Starts showing presence for a game, returning the thread and the sender
+    // its events should go to (if snek knows which application to show)
+    pub fn start(mode: Mode) -> Option<(Self, Sender<GameEvent>)> {
+        let application_id = APPLICATION_ID?;
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::spawn(move || run(application_id, mode, receiver));
+        Some((Self { thread }, sender))
+    }
+
+    // Waits for the presence to be cleared once the game's events have stopped,
+    // returning why it couldn't be shown if it failed
+    pub fn finish(self) -> Result<(), String> {
+        self.thread
+            .join()
+            .unwrap_or_else(|_| Err("the presence thread panicked".to_owned()))
+    }
+}
+
+// Shows presence until the game ends or its events stop
+fn run(application_id: &str, mode: Mode, events: Receiver<GameEvent>) -> Result<(), String> {
+    let mut client = DiscordIpcClient::new(application_id).map_err(|err| err.to_string())?;
+    client.connect().map_err(|err| err.to_string())?;
+    let started = storage::now() as i64;
+    let mut score = 0;
+    let mut changed = true;
+    let mut last_update: Option<Instant> = None;
+    loop {
+        match events.recv_timeout(UPDATE_INTERVAL) {
+            Ok(GameEvent::Scored { score: new_score }) => {
+                score = new_score;
+                changed = true;
+            }
+            Ok(GameEvent::Ended { .. }) | Err(RecvTimeoutError::Disconnected) => break,
+            Ok(_) | Err(RecvTimeoutError::Timeout) => (),
+        }
+        if changed && last_update.is_none_or(|update| update.elapsed() >= UPDATE_INTERVAL) {
+            let details = format!("Score {score} in {}", mode.name());
+            let activity = Activity::new()
+                .state("Playing SNEK")
+                .details(&details)
+                .timestamps(Timestamps::new().start(started));
+            client
+                .set_activity(activity)
+                .map_err(|err| err.to_string())?;
+            changed = false;
+            last_update = Some(Instant::now());
+        }
+    }
+    client.close().map_err(|err| err.to_string())
+}
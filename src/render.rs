@@ -0,0 +1,1294 @@
+use std::env;
+use std::f32::consts::TAU;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use console_engine::pixel::{self, Pixel};
+use console_engine::{Color, ConsoleEngine};
+
+use crate::options::{Border, Mode, Options};
+use crate::splits;
+use crate::trail::Trail;
+use crate::world::{self, Direction, Snek, Turn, World};
+use crate::{
+    AHEAD_COLOR, BAR_EMPTY_CHAR, BAR_FULL_CHAR, BEHIND_COLOR, BEST_PROMPT, BORDER_COLOR,
+    BORDER_LINE_COLOR, DANGER_COLOR, DEAD_EYE_CHAR, DECOY_COLOR, EYE_CHAR, FIXED_HEAD_COLOR,
+    FOOD_BRIGHT_RGB, FOOD_COLOR, FOOD_DIM_RGB, FOOD_VALUE_COLOR, GHOST_COLOR, GOLD_COLOR,
+    GRID_LABEL_COLOR, HEAD_COLOR, HEADING_CHARS, HINT_CHAR, HINT_COLOR, LEFT_EIGHTH_CHARS,
+    LENGTH_MILESTONE, LOWER_EIGHTH_CHARS, MAP_COLOR, MAX_BAR_LEN, PACE_AHEAD_CHAR,
+    PACE_BEHIND_CHAR, PACE_EVEN_CHAR, Point, ROUNDED_BORDER_CHARS, SCORE_PROMPT,
+    SHARP_BORDER_CHARS, SNEK_COLOR, SNEK_COLORS, SPEED_SUFFIX, STARTING_BODY, TRAIL_COLOR,
+    TRAIL_FADED_RGB, TRAIL_RGB, VICTORY_COLORS, VICTORY_STEP, WALL_COLOR, WIN_PROMPT,
+};
+
+// Something the game can be drawn on (the terminal or an in-memory buffer)
+pub trait Canvas {
+    // Returns the width in columns
+    fn width(&self) -> u32;
+
+    // Returns the height in rows
+    fn height(&self) -> u32;
+
+    // Sets every pixel
+    fn fill(&mut self, pixel: Pixel);
+
+    // Sets every pixel in a rectangle (including both corners)
+    fn fill_rect(&mut self, start_x: i32, start_y: i32, end_x: i32, end_y: i32, pixel: Pixel);
+
+    // Prints a string starting at a position
+    fn print_fbg(&mut self, x: i32, y: i32, text: &str, fg: Color, bg: Color);
+
+    // Sets a single pixel
+    fn set_pxl(&mut self, x: i32, y: i32, pixel: Pixel);
+}
+
+impl Canvas for ConsoleEngine {
+    fn width(&self) -> u32 {
+        self.get_width()
+    }
+
+    fn height(&self) -> u32 {
+        self.get_height()
+    }
+
+    fn fill(&mut self, pixel: Pixel) {
+        ConsoleEngine::fill(self, pixel);
+    }
+
+    fn fill_rect(&mut self, start_x: i32, start_y: i32, end_x: i32, end_y: i32, pixel: Pixel) {
+        ConsoleEngine::fill_rect(self, start_x, start_y, end_x, end_y, pixel);
+    }
+
+    fn print_fbg(&mut self, x: i32, y: i32, text: &str, fg: Color, bg: Color) {
+        ConsoleEngine::print_fbg(self, x, y, text, fg, bg);
+    }
+
+    fn set_pxl(&mut self, x: i32, y: i32, pixel: Pixel) {
+        ConsoleEngine::set_pxl(self, x, y, pixel);
+    }
+}
+
+// A grid of pixels in memory, for drawing the game without a terminal
+#[derive(Clone, PartialEq)]
+pub struct Buffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<Pixel>,
+}
+
+impl Buffer {
+    // Creates a blank buffer
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![pixel::pxl(' '); (width * height) as usize],
+        }
+    }
+
+    // Returns the pixel at a position
+    pub fn get(&self, x: u32, y: u32) -> Pixel {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    // Returns the positions of the pixels that differ from another buffer of the same size
+    pub fn diff(&self, other: &Buffer) -> Vec<(u32, u32)> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.get(x, y) != other.get(x, y))
+            .collect()
+    }
+
+    // Returns the escape sequences that turn a terminal showing the previous
+    // buffer (or anything, if there isn't one) into this one
+    pub fn to_ansi(&self, previous: Option<&Buffer>) -> String {
+        let changed = match previous {
+            Some(previous) if previous.width == self.width && previous.height == self.height => {
+                self.diff(previous)
+            }
+            _ => (0..self.height)
+                .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+                .collect(),
+        };
+        let mut ansi = String::new();
+        if previous.is_none() {
+            ansi += "\x1b[2J";
+        }
+        let mut cursor = None;
+        let mut colors = None;
+        for (x, y) in changed {
+            let pixel = self.get(x, y);
+            if cursor != Some((x, y)) {
+                ansi += &format!("\x1b[{};{}H", y + 1, x + 1);
+            }
+            if colors != Some((pixel.fg, pixel.bg)) {
+                ansi += &format!("\x1b[{};{}m", sgr(pixel.fg, false), sgr(pixel.bg, true));
+                colors = Some((pixel.fg, pixel.bg));
+            }
+            ansi.push(pixel.chr);
+            cursor = Some((x + 1, y));
+        }
+        if colors.is_some() {
+            ansi += "\x1b[0m";
+        }
+        ansi
+    }
+
+    // Returns the buffer as lines of text, colored with escape sequences
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for y in 0..self.height {
+            let mut colors = None;
+            for x in 0..self.width {
+                let pixel = self.get(x, y);
+                if colors != Some((pixel.fg, pixel.bg)) {
+                    text += &format!("\x1b[{};{}m", sgr(pixel.fg, false), sgr(pixel.bg, true));
+                    colors = Some((pixel.fg, pixel.bg));
+                }
+                text.push(pixel.chr);
+            }
+            text += "\x1b[0m\n";
+        }
+        text
+    }
+
+    // Copies every pixel onto a canvas
+    pub fn blit(&self, canvas: &mut impl Canvas) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                canvas.set_pxl(x as i32, y as i32, self.get(x, y));
+            }
+        }
+    }
+}
+
+impl Canvas for Buffer {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn fill(&mut self, pixel: Pixel) {
+        self.pixels.fill(pixel);
+    }
+
+    fn fill_rect(&mut self, start_x: i32, start_y: i32, end_x: i32, end_y: i32, pixel: Pixel) {
+        for y in start_y.min(end_y)..=start_y.max(end_y) {
+            for x in start_x.min(end_x)..=start_x.max(end_x) {
+                self.set_pxl(x, y, pixel);
+            }
+        }
+    }
+
+    fn print_fbg(&mut self, x: i32, y: i32, text: &str, fg: Color, bg: Color) {
+        for (i, chr) in text.chars().enumerate() {
+            self.set_pxl(x + i as i32, y, pixel::pxl_fbg(chr, fg, bg));
+        }
+    }
+
+    fn set_pxl(&mut self, x: i32, y: i32, pixel: Pixel) {
+        if x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height {
+            self.pixels[(y as u32 * self.width + x as u32) as usize] = pixel;
+        }
+    }
+}
+
+// Returns the SGR parameters that select a foreground or background color
+fn sgr(color: Color, background: bool) -> String {
+    let base = if background { 48 } else { 38 };
+    let index = match color {
+        Color::Reset => return (base + 1).to_string(),
+        Color::Rgb { r, g, b } => return format!("{base};2;{r};{g};{b}"),
+        Color::AnsiValue(value) => value,
+        Color::Black => 0,
+        Color::DarkRed => 1,
+        Color::DarkGreen => 2,
+        Color::DarkYellow => 3,
+        Color::DarkBlue => 4,
+        Color::DarkMagenta => 5,
+        Color::DarkCyan => 6,
+        Color::Grey => 7,
+        Color::DarkGrey => 8,
+        Color::Red => 9,
+        Color::Green => 10,
+        Color::Yellow => 11,
+        Color::Blue => 12,
+        Color::Magenta => 13,
+        Color::Cyan => 14,
+        Color::White => 15,
+    };
+    format!("{base};5;{index}")
+}
+
+// Draws everything in a world, with a prompt on top, as it looks while playing
+pub fn draw_world(canvas: &mut impl Canvas, world: &World, prompt: &str) {
+    draw_map(canvas, &world.options);
+    draw_score(canvas, world);
+    draw_prompt(canvas, prompt, 0);
+    draw_walls(canvas, &world.options);
+    draw_food(canvas, &world.food, &world.options, FOOD_COLOR);
+    draw_food(canvas, world.decoy.as_slice(), &world.options, DECOY_COLOR);
+    draw_snek(canvas, &world.snek, &world.options, &SNEK_COLORS);
+}
+
+// Draws the victory lap after a win as it is some time into it, with the snek
+// cycling through colors under a flashing prompt
+pub fn draw_victory_lap(canvas: &mut impl Canvas, world: &World, shown: Duration) {
+    let step = (shown.as_millis() / VICTORY_STEP.as_millis()) as usize;
+    let prompt = match step % 2 {
+        0 => WIN_PROMPT,
+        _ => "",
+    };
+    let color = VICTORY_COLORS[step % VICTORY_COLORS.len()];
+    draw_world(canvas, world, prompt);
+    draw_snek(canvas, &world.snek, &world.options, &[color]);
+}
+
+// Draws the border (the margins around the map) and map, with the grid's
+// labels if they're turned on
+pub fn draw_map(canvas: &mut impl Canvas, options: &Options) {
+    let (margin_x, margin_y) = (options.margin_x as i32, options.margin_y as i32);
+    draw_border(canvas, options);
+    canvas.fill_rect(
+        margin_x,
+        margin_y,
+        canvas.width() as i32 - margin_x - 1,
+        canvas.height() as i32 - margin_y - 1,
+        pixel::pxl_bg(' ', MAP_COLOR),
+    );
+    if options.grid_labels {
+        draw_grid_labels(canvas, options);
+    }
+}
+
+// Draws the border: a solid block of the border's color, with a line around
+// the map just outside its edge in the sharp and rounded styles (the rounded
+// corners falling back to sharp ones on terminals that aren't set up for
+// Unicode). The line is drawn on the border alone, so the map's left as it is
+pub fn draw_border(canvas: &mut impl Canvas, options: &Options) {
+    canvas.fill(pixel::pxl_bg(' ', BORDER_COLOR));
+    let chars = match options.border {
+        Border::Solid => return,
+        Border::Rounded if unicode() => ROUNDED_BORDER_CHARS,
+        Border::Sharp | Border::Rounded => SHARP_BORDER_CHARS,
+    };
+    let [top_left, top_right, bottom_left, bottom_right, across, down] =
+        chars.map(|chr| pixel::pxl_fbg(chr, BORDER_LINE_COLOR, BORDER_COLOR));
+    let (left, top) = (options.margin_x as i32 - 1, options.margin_y as i32 - 1);
+    let right = canvas.width() as i32 - options.margin_x as i32;
+    let bottom = canvas.height() as i32 - options.margin_y as i32;
+    for x in left + 1..right {
+        canvas.set_pxl(x, top, across);
+        canvas.set_pxl(x, bottom, across);
+    }
+    for y in top + 1..bottom {
+        canvas.set_pxl(left, y, down);
+        canvas.set_pxl(right, y, down);
+    }
+    canvas.set_pxl(left, top, top_left);
+    canvas.set_pxl(right, top, top_right);
+    canvas.set_pxl(left, bottom, bottom_left);
+    canvas.set_pxl(right, bottom, bottom_right);
+}
+
+// Draws the index of every column on a row of the border above the map and of
+// every row on the columns left of it, as many of the last hex digits of each
+// as fit in a cell (or the left margin), so it's easy to tell where on the map
+// something is. They're kept clear of the prompt on the top row and of the
+// line around the map, so the column labels need a top margin of at least 2
+// (3 with a line) to show up
+pub fn draw_grid_labels(canvas: &mut impl Canvas, options: &Options) {
+    let line = (options.border != Border::Solid) as u32;
+    if let Some(row) = options
+        .margin_y
+        .checked_sub(1 + line)
+        .filter(|row| *row > 0)
+    {
+        for x in 0..options.width {
+            let label = grid_label(x, options.cell_width as usize);
+            let (screen_x, _) = cell_to_screen(Point::new(x as i32, 0), options);
+            canvas.print_fbg(screen_x, row as i32, &label, GRID_LABEL_COLOR, BORDER_COLOR);
+        }
+    }
+    let room = options.margin_x.saturating_sub(line) as usize;
+    if room == 0 {
+        return;
+    }
+    for y in 0..options.height {
+        let label = grid_label(y, room);
+        canvas.print_fbg(
+            0,
+            y as i32 + options.margin_y as i32,
+            &label,
+            GRID_LABEL_COLOR,
+            BORDER_COLOR,
+        );
+    }
+}
+
+// Returns the label of a column or row: the last `room` hex digits of its
+// index, padded with zeros
+pub fn grid_label(index: u32, room: usize) -> String {
+    let label = format!("{index:0room$x}");
+    label[label.len() - room..].to_owned()
+}
+
+// Draws the score (and the progress bar toward the target) under the map
+pub fn draw_score(canvas: &mut impl Canvas, world: &World) {
+    let score = score_text(canvas, world);
+    draw_prompt(canvas, &score, canvas.height() as i32 - 1);
+}
+
+// Returns the score (and the progress bar toward the target) as it's drawn
+fn score_text(canvas: &impl Canvas, world: &World) -> String {
+    let mut score = SCORE_PROMPT.to_owned() + &world.score().to_string();
+    if let Some(target) = world.options.target {
+        let room = (canvas.width() as usize).saturating_sub(score.chars().count() + 3);
+        score += " ";
+        score += &progress_bar(world.score(), target, room.min(MAX_BAR_LEN));
+    }
+    score
+}
+
+// Draws how the latest split compares to the best one (in gold if it beat it)
+// at the right end of the row under the map, to the tenth of a second if
+// there's room beside the score, else to the second, else not at all
+pub fn draw_split(canvas: &mut impl Canvas, world: &World, delta: i64, gold: bool) {
+    let score = score_text(canvas, world);
+    let color = match (gold, delta < 0) {
+        (true, _) => GOLD_COLOR,
+        (false, true) => AHEAD_COLOR,
+        (false, false) => BEHIND_COLOR,
+    };
+    let texts = [true, false].map(|precise| splits::format_delta(delta, precise));
+    let bottom = canvas.height() as i32 - 1;
+    draw_beside(canvas, &score, bottom, &texts, color);
+}
+
+// Draws how far ahead or behind of the best game's score on the same tick the
+// game is (with the best game's final score, if there's room) at the right end
+// of the prompt row, as long as it fits beside the prompt
+pub fn draw_pace(canvas: &mut impl Canvas, prompt: &str, best: usize, pace: i64) {
+    let (arrow, color) = match pace.signum() {
+        1 => (PACE_AHEAD_CHAR, AHEAD_COLOR),
+        -1 => (PACE_BEHIND_CHAR, BEHIND_COLOR),
+        _ => (PACE_EVEN_CHAR, Color::Reset),
+    };
+    let pace = format!("{arrow}{}", pace.unsigned_abs());
+    let texts = [format!("{BEST_PROMPT}{best} {pace}"), pace];
+    draw_beside(canvas, prompt, 0, &texts, color);
+}
+
+// Draws how many votes each direction has so far (with `--vote-input`) at the
+// left end of the prompt row, as long as it fits beside the prompt
+pub fn draw_votes(canvas: &mut impl Canvas, prompt: &str, counts: [usize; 4]) {
+    let votes: Vec<String> = HEADING_CHARS
+        .iter()
+        .zip(counts)
+        .map(|(heading, count)| format!("{heading}{count}"))
+        .collect();
+    let text = votes.join(" ");
+    let prompt_start = (canvas.width() as usize / 2).saturating_sub(prompt.chars().count() / 2);
+    if text.chars().count() + 2 <= prompt_start {
+        canvas.print_fbg(1, 0, &text, Color::Reset, BORDER_COLOR);
+    }
+}
+
+// Draws the speed curve and how many moves a second the snek's making (with
+// `--debug`) at the left end of the row under the map, as long as it fits
+// beside the score
+pub fn draw_speed(canvas: &mut impl Canvas, world: &World) {
+    let text = format!(
+        "{} {}{SPEED_SUFFIX}",
+        world.options.ramp_curve.name().to_uppercase(),
+        world.fps()
+    );
+    let score = score_text(canvas, world);
+    let score_start = (canvas.width() as usize / 2).saturating_sub(score.chars().count() / 2);
+    if text.chars().count() + 2 <= score_start {
+        let bottom = canvas.height() as i32 - 1;
+        canvas.print_fbg(1, bottom, &text, Color::Reset, BORDER_COLOR);
+    }
+}
+
+// Draws the first of some texts that fits at the right end of a row of the
+// border without running into the prompt centered on it, if any of them do
+fn draw_beside(canvas: &mut impl Canvas, prompt: &str, y: i32, texts: &[String], color: Color) {
+    let prompt_len = prompt.chars().count();
+    let prompt_end = (canvas.width() as usize / 2).saturating_sub(prompt_len / 2) + prompt_len;
+    let Some(text) = texts
+        .iter()
+        .find(|text| prompt_end + text.chars().count() + 2 <= canvas.width() as usize)
+    else {
+        return;
+    };
+    let x = canvas.width() as usize - text.chars().count() - 1;
+    canvas.print_fbg(x as i32, y, text, color, BORDER_COLOR);
+}
+
+// Draws a prompt centered on a row of the border
+pub fn draw_prompt(canvas: &mut impl Canvas, prompt: &str, y: i32) {
+    let mid = (canvas.width() / 2).saturating_sub(prompt.chars().count() as u32 / 2);
+    canvas.print_fbg(mid as i32, y, prompt, Color::Reset, BORDER_COLOR);
+}
+
+// Draws lines of text centered on the map, cutting off what doesn't fit
+pub fn draw_lines(canvas: &mut impl Canvas, options: &Options, lines: &[String]) {
+    let max_len = (options.width * options.cell_width) as usize;
+    let rows = options.height as usize;
+    let top = rows.saturating_sub(lines.len()) / 2;
+    for (row, line) in lines.iter().take(rows).enumerate() {
+        let line: String = line.chars().take(max_len).collect();
+        let mid = canvas.width() as usize / 2 - line.chars().count() / 2;
+        canvas.print_fbg(
+            mid as i32,
+            (top + row) as i32 + options.margin_y as i32,
+            &line,
+            Color::Reset,
+            MAP_COLOR,
+        );
+    }
+}
+
+// Fills every column of a cell on the map with a pixel
+pub fn set_cell(canvas: &mut impl Canvas, point: Point, options: &Options, pixel: Pixel) {
+    let (x, y) = cell_to_screen(point, options);
+    for column in 0..options.cell_width as i32 {
+        canvas.set_pxl(x + column, y, pixel);
+    }
+}
+
+// Draws the walls of the level being played, if there is one
+pub fn draw_walls(canvas: &mut impl Canvas, options: &Options) {
+    for wall in &options.walls {
+        set_cell(canvas, *wall, options, pixel::pxl_bg(' ', WALL_COLOR));
+    }
+}
+
+// Draws the cells the tail just left that nothing's on now, fading from blue
+// into the map's color on terminals that can show it (and plain dark green
+// until they're gone elsewhere)
+pub fn draw_trail(canvas: &mut impl Canvas, trail: &Trail, world: &World) {
+    for (cell, fade) in trail.cells() {
+        if world.snek.body.contains(&cell)
+            || world.food.contains(&cell)
+            || world.decoy == Some(cell)
+        {
+            continue;
+        }
+        let color = match truecolor() {
+            true => {
+                let [r, g, b] = [0, 1, 2].map(|channel| {
+                    let (start, end) = (TRAIL_RGB[channel], TRAIL_FADED_RGB[channel]);
+                    (start as f32 + (end as f32 - start as f32) * fade).round() as u8
+                });
+                Color::Rgb { r, g, b }
+            }
+            false => TRAIL_COLOR,
+        };
+        set_cell(canvas, cell, &world.options, pixel::pxl_bg(' ', color));
+    }
+}
+
+// Draws the snek partway into its next move (with `--smooth-motion`), as far
+// as the time until the move has gone by: the cell ahead of the head filling
+// up from the side it's coming in on, and the tail's cell emptying toward the
+// rest of the body (unless the snek's about to grow, and the tail stays put).
+// Moves that would kill the snek, turn it straight back, or eat something
+// aren't drawn ahead of time, and nothing is on terminals that aren't set up
+// for Unicode
+pub fn draw_glide(canvas: &mut impl Canvas, world: &World, direction: Direction, progress: f32) {
+    if unicode() {
+        draw_glide_cells(canvas, world, direction, progress);
+    }
+}
+
+// Draws the snek partway into its next move, whatever the terminal's locale
+fn draw_glide_cells(canvas: &mut impl Canvas, world: &World, direction: Direction, progress: f32) {
+    let (snek, options) = (&world.snek, &world.options);
+    let ahead = *snek.body.last().unwrap() + direction.to_vector();
+    if snek.heading.angle_to(direction) == Turn::Around
+        || snek.move_fatal(
+            direction,
+            &world.food,
+            &options.walls,
+            world.width(),
+            world.height(),
+        )
+        || world.food.contains(&ahead)
+        || world.decoy == Some(ahead)
+    {
+        return;
+    }
+    let color = SNEK_COLORS[length_tier(snek.body.len()).min(SNEK_COLORS.len() - 1)];
+    if !snek.move_grows(direction, &world.food)
+        && options.mode != Mode::Twin
+        && let [tail, next, ..] = snek.body[..]
+        && let Some(toward) = Direction::all()
+            .into_iter()
+            .find(|toward| tail + toward.to_vector() == next)
+    {
+        draw_part_cell(canvas, tail, toward, 1.0 - progress, color, options);
+    }
+    let head_color = match options.fixed_head {
+        true => FIXED_HEAD_COLOR,
+        false => color,
+    };
+    draw_part_cell(
+        canvas,
+        ahead,
+        direction.opposite(),
+        progress,
+        head_color,
+        options,
+    );
+}
+
+// Fills part of a cell (from 0 to 1 of it, to the nearest eighth of a column
+// or row) with a color, starting from one of its sides
+fn draw_part_cell(
+    canvas: &mut impl Canvas,
+    cell: Point,
+    side: Direction,
+    amount: f32,
+    color: Color,
+    options: &Options,
+) {
+    let (x, y) = cell_to_screen(cell, options);
+    let columns = options.cell_width as usize;
+    match side {
+        Direction::Left | Direction::Right => {
+            let eighths = (amount * (columns * 8) as f32).round() as usize;
+            for column in 0..columns {
+                let filled = eighths.saturating_sub(column * 8).min(8);
+                let (offset, from_left) = match side {
+                    Direction::Left => (column, true),
+                    _ => (columns - 1 - column, false),
+                };
+                let pixel = eighths_pixel(filled, from_left, &LEFT_EIGHTH_CHARS, color);
+                canvas.set_pxl(x + offset as i32, y, pixel);
+            }
+        }
+        Direction::Up | Direction::Down => {
+            let eighths = ((amount * 8.0).round() as usize).min(8);
+            let from_bottom = side == Direction::Down;
+            let pixel = eighths_pixel(eighths, from_bottom, &LOWER_EIGHTH_CHARS, color);
+            for column in 0..columns {
+                canvas.set_pxl(x + column as i32, y, pixel);
+            }
+        }
+    }
+}
+
+// Returns the pixel for a column or row with some eighths of it filled with a
+// color, from the side a set of block characters grows from (the left or the
+// bottom) or from the other side, and the map's color in the rest
+fn eighths_pixel(eighths: usize, from_start: bool, blocks: &[char; 7], color: Color) -> Pixel {
+    match eighths {
+        0 => pixel::pxl_bg(' ', MAP_COLOR),
+        8.. => pixel::pxl_bg(' ', color),
+        _ if from_start => pixel::pxl_fbg(blocks[eighths - 1], color, MAP_COLOR),
+        _ => pixel::pxl_fbg(blocks[7 - eighths], MAP_COLOR, color),
+    }
+}
+
+// Draws the ghost of a past run's snek (its body on the current tick) in the
+// cells that nothing else is in
+pub fn draw_ghost(canvas: &mut impl Canvas, body: &[Point], world: &World) {
+    let (width, height) = (world.width(), world.height());
+    for cell in body {
+        if !world::in_bounds(*cell, width, height)
+            || world.snek.body.contains(cell)
+            || world.food.contains(cell)
+            || world.decoy == Some(*cell)
+            || world.options.walls.contains(cell)
+        {
+            continue;
+        }
+        set_cell(
+            canvas,
+            *cell,
+            &world.options,
+            pixel::pxl_bg(' ', GHOST_COLOR),
+        );
+    }
+}
+
+// Draws the food in a color
+pub fn draw_food(canvas: &mut impl Canvas, food: &[Point], options: &Options, color: Color) {
+    for food in food {
+        set_cell(canvas, *food, options, pixel::pxl_bg(' ', color));
+    }
+}
+
+// Draws what each food's worth right now on it, over the food's color, if
+// it fits in a cell
+pub fn draw_food_values(canvas: &mut impl Canvas, world: &World, color: Color) {
+    let cell_width = world.options.cell_width as usize;
+    for (index, food) in world.food.iter().enumerate() {
+        let value = world.food_points(index).to_string();
+        if value.len() > cell_width {
+            continue;
+        }
+        let (x, y) = cell_to_screen(*food, &world.options);
+        let start = x + (cell_width - value.len()) as i32;
+        for (column, chr) in value.chars().enumerate() {
+            canvas.set_pxl(
+                start + column as i32,
+                y,
+                pixel::pxl_fbg(chr, FOOD_VALUE_COLOR, color),
+            );
+        }
+    }
+}
+
+// Returns the color of the food some time into the game, which pulses if
+// that's turned on and the terminal can show it (and is plain otherwise)
+pub fn food_color(options: &Options, elapsed: Duration) -> Color {
+    let Some(period) = options.food_pulse.filter(|_| truecolor()) else {
+        return FOOD_COLOR;
+    };
+    let brightness = pulse_brightness(elapsed.as_millis(), period);
+    let [r, g, b] = [0, 1, 2].map(|channel| {
+        let (dim, bright) = (FOOD_DIM_RGB[channel], FOOD_BRIGHT_RGB[channel]);
+        (dim as f32 + (bright as f32 - dim as f32) * brightness).round() as u8
+    });
+    Color::Rgb { r, g, b }
+}
+
+// Returns how bright something pulsing once every `period` milliseconds is
+// some milliseconds in, from 0 (dimmest) to 1 (brightest)
+pub fn pulse_brightness(elapsed: u128, period: u32) -> f32 {
+    let phase = (elapsed % u128::from(period.max(1))) as f32 / period.max(1) as f32;
+    (1.0 - (phase * TAU).cos()) / 2.0
+}
+
+// Returns whether the terminal says it can show any RGB color
+fn truecolor() -> bool {
+    static TRUECOLOR: OnceLock<bool> = OnceLock::new();
+    *TRUECOLOR.get_or_init(|| {
+        env::var("COLORTERM").is_ok_and(|value| value == "truecolor" || value == "24bit")
+    })
+}
+
+// Returns whether the terminal's locale says it can show Unicode
+fn unicode() -> bool {
+    static UNICODE: OnceLock<bool> = OnceLock::new();
+    *UNICODE.get_or_init(|| {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+            .unwrap_or_default()
+            .to_lowercase();
+        locale.contains("utf-8") || locale.contains("utf8")
+    })
+}
+
+// Draws a snek in the color of the length milestone it's reached in a palette
+// (staying on the last color once it's past them all)
+pub fn draw_snek(canvas: &mut impl Canvas, snek: &Snek, options: &Options, palette: &[Color]) {
+    let color = palette[length_tier(snek.body.len()).min(palette.len() - 1)];
+    for part in &snek.body {
+        set_cell(canvas, *part, options, pixel::pxl_bg(' ', color));
+    }
+    let last = *snek.body.last().unwrap();
+    let eye = match snek.alive {
+        true => EYE_CHAR,
+        false => DEAD_EYE_CHAR,
+    };
+    // A head the same color as the body can get lost in it, so it can be
+    // kept in one color that stands out from every color the body goes through
+    let head_color = match options.fixed_head {
+        true => FIXED_HEAD_COLOR,
+        false => color,
+    };
+    set_cell(
+        canvas,
+        last,
+        options,
+        pixel::pxl_fbg(eye, HEAD_COLOR, head_color),
+    );
+    // In twin mode, the tail's a head too (just not the one leading)
+    if options.mode == Mode::Twin {
+        set_cell(
+            canvas,
+            snek.body[0],
+            options,
+            pixel::pxl_fbg(eye, HEAD_COLOR, head_color),
+        );
+    }
+}
+
+// Draws where the snek starts (in a level being edited), with an arrow on its
+// head for the way it's heading
+pub fn draw_start(canvas: &mut impl Canvas, start: Option<(Point, Direction)>, options: &Options) {
+    let body = world::starting_body(start);
+    for part in &body {
+        set_cell(canvas, *part, options, pixel::pxl_bg(' ', SNEK_COLOR));
+    }
+    let heading = start.map_or(Direction::Right, |(_, heading)| heading);
+    let pixel = pixel::pxl_fbg(HEADING_CHARS[heading as usize], HEAD_COLOR, SNEK_COLOR);
+    set_cell(canvas, *body.last().unwrap(), options, pixel);
+}
+
+// Colors the snek's head to warn that it's about to die (with hints shown)
+pub fn draw_danger(canvas: &mut impl Canvas, world: &World) {
+    let head = *world.snek.body.last().unwrap();
+    let pixel = pixel::pxl_fbg(EYE_CHAR, HEAD_COLOR, DANGER_COLOR);
+    set_cell(canvas, head, &world.options, pixel);
+}
+
+// Recolors the border in a buffer (everything on it that's drawn on the
+// border's color)
+pub fn tint_border(buffer: &mut Buffer, color: Color) {
+    for pixel in &mut buffer.pixels {
+        if pixel.bg == BORDER_COLOR {
+            pixel.bg = color;
+        }
+    }
+}
+
+// Marks the cell the snek is suggested to move into next
+pub fn draw_hint(canvas: &mut impl Canvas, cell: Point, world: &World) {
+    let background = match world.food.contains(&cell) {
+        true => FOOD_COLOR,
+        false => MAP_COLOR,
+    };
+    let pixel = pixel::pxl_fbg(HINT_CHAR, HINT_COLOR, background);
+    set_cell(canvas, cell, &world.options, pixel);
+}
+
+// Returns how many length milestones a snek of a length has reached, one for
+// every `LENGTH_MILESTONE` segments it's grown past its starting body
+pub fn length_tier(length: usize) -> usize {
+    length.saturating_sub(STARTING_BODY.len()) / LENGTH_MILESTONE
+}
+
+// Returns how many cells of a progress bar are filled for a score toward a target
+pub fn bar_fill(score: usize, target: usize, len: usize) -> usize {
+    score.min(target) * len / target.max(1)
+}
+
+// Draws a progress bar toward a target score out of block characters
+pub fn progress_bar(score: usize, target: usize, len: usize) -> String {
+    let filled = bar_fill(score, target, len);
+    let mut bar = String::new();
+    bar.extend(std::iter::repeat_n(BAR_FULL_CHAR, filled));
+    bar.extend(std::iter::repeat_n(BAR_EMPTY_CHAR, len - filled));
+    bar
+}
+
+// Converts a point on the map to the screen position of its leftmost column
+pub fn cell_to_screen(point: Point, options: &Options) -> (i32, i32) {
+    (
+        point.x * options.cell_width as i32 + options.margin_x as i32,
+        point.y + options.margin_y as i32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::SnekEvent;
+
+    #[test]
+    fn three_column_cells_map_to_the_screen() {
+        let options = Options {
+            cell_width: 3,
+            margin_x: 2,
+            margin_y: 1,
+            ..Options::default()
+        };
+        assert_eq!(cell_to_screen(Point::new(0, 0), &options), (2, 1));
+        assert_eq!(cell_to_screen(Point::new(4, 2), &options), (14, 3));
+        assert_eq!(options.screen_size(), (17 * 3 + 4, 15 + 2));
+        let mut buffer = Buffer::new(options.screen_size().0, options.screen_size().1);
+        set_cell(
+            &mut buffer,
+            Point::new(4, 2),
+            &options,
+            pixel::pxl_bg(' ', FOOD_COLOR),
+        );
+        let filled: Vec<u32> = (0..buffer.width())
+            .filter(|x| buffer.get(*x, 3).bg == FOOD_COLOR)
+            .collect();
+        assert_eq!(filled, [14, 15, 16]);
+    }
+
+    // Returns text without its color escape sequences
+    fn plain(text: &str) -> String {
+        let mut plain = String::new();
+        let mut escaped = false;
+        for chr in text.chars() {
+            match (escaped, chr) {
+                (false, '\x1b') => escaped = true,
+                (false, _) => plain.push(chr),
+                (true, 'm') => escaped = false,
+                (true, _) => (),
+            }
+        }
+        plain
+    }
+
+    #[test]
+    fn text_only_changes_colors_when_it_has_to() {
+        let mut buffer = Buffer::new(3, 2);
+        buffer.print_fbg(0, 0, "ab", Color::Red, Color::Black);
+        assert_eq!(
+            buffer.to_text(),
+            "\x1b[38;5;9;48;5;0mab\x1b[39;49m \x1b[0m\n\x1b[39;49m   \x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn screenshots_show_a_small_game() {
+        let options = Options {
+            width: 5,
+            height: 5,
+            cell_width: 1,
+            margin_x: 1,
+            margin_y: 1,
+            border: Border::Sharp,
+            seed: 1,
+            ..Options::default()
+        };
+        let mut world = World::new(options, &STARTING_BODY);
+        world.food = vec![Point::new(2, 3)];
+        let mut buffer = Buffer::new(7, 7);
+        draw_world(&mut buffer, &world, "GO");
+        let text = buffer.to_text();
+        let lines: Vec<&str> = text.lines().collect();
+        let plain: Vec<String> = lines.iter().map(|line| plain(line)).collect();
+        assert_eq!(
+            plain,
+            [
+                "┌─GO──┐",
+                &format!("│   {EYE_CHAR} │"),
+                "│     │",
+                "│     │",
+                "│     │",
+                "│     │",
+                "SCORE: "
+            ]
+        );
+        let map = format!("\x1b[39;{}m", sgr(MAP_COLOR, true));
+        let food = format!("\x1b[39;{}m", sgr(FOOD_COLOR, true));
+        assert!(lines[4].contains(&format!("{map}  {food} {map}  ")));
+        assert!(lines[1].contains(&format!("{}m   ", sgr(SNEK_COLORS[0], true))));
+        assert!(lines[1].ends_with(&format!("{map} \x1b[38;5;10;48;5;0m│\x1b[0m")));
+    }
+
+    #[test]
+    fn fixed_heads_stand_out_from_the_body() {
+        // Returns the colors of the head and the part behind it, drawn and
+        // as they come out in text, for a snek as long as given
+        let colors = |length: usize, fixed_head: bool| {
+            let options = Options {
+                fixed_head,
+                seed: 1,
+                ..Options::default()
+            };
+            // Back and forth across the board, a row at a time
+            let body: Vec<Point> = (0..length as i32)
+                .map(|index| match (index / 17) % 2 {
+                    0 => Point::new(index % 17, index / 17),
+                    _ => Point::new(16 - index % 17, index / 17),
+                })
+                .collect();
+            let snek = Snek {
+                body,
+                ..Snek::new(&STARTING_BODY, false)
+            };
+            let (width, height) = options.screen_size();
+            let mut buffer = Buffer::new(width, height);
+            draw_snek(&mut buffer, &snek, &options, &SNEK_COLORS);
+            let text = buffer.to_text();
+            let lines: Vec<&str> = text.lines().collect();
+            let [.., behind, head] = snek.body[..] else {
+                unreachable!()
+            };
+            [head, behind].map(|part| {
+                let (x, y) = cell_to_screen(part, &options);
+                let color = buffer.get(x as u32, y as u32).bg;
+                assert!(lines[y as usize].contains(&sgr(color, true)));
+                color
+            })
+        };
+        for (tier, color) in SNEK_COLORS.into_iter().enumerate() {
+            let length = STARTING_BODY.len() + tier * LENGTH_MILESTONE;
+            assert_eq!(colors(length, true), [FIXED_HEAD_COLOR, color], "{length}");
+            assert_eq!(colors(length, false), [color; 2], "{length}");
+        }
+    }
+
+    #[test]
+    fn the_fatal_move_is_drawn_with_a_dead_eye_where_it_hit() {
+        let options = Options {
+            width: 5,
+            height: 5,
+            cell_width: 1,
+            walls: vec![Point::new(4, 0)],
+            seed: 1,
+            ..Options::default()
+        };
+        let mut world = World::new(options, &STARTING_BODY);
+        world.food = vec![Point::new(2, 3)];
+        // Returns the characters on the top row of the map, as drawn now
+        let top_row = |world: &World| {
+            let (width, height) = world.options.screen_size();
+            let mut buffer = Buffer::new(width, height);
+            draw_world(&mut buffer, world, "");
+            (0..5)
+                .map(|x| {
+                    buffer
+                        .get(x + world.options.margin_x, world.options.margin_y)
+                        .chr
+                })
+                .collect::<String>()
+        };
+        assert_eq!(top_row(&world), format!("   {EYE_CHAR} "));
+        world.advance();
+        assert_eq!(world.check_death(), Some(SnekEvent::Died(Point::new(4, 0))));
+        assert_eq!(top_row(&world), format!("    {DEAD_EYE_CHAR}"));
+        // Nothing moves once it's dead, so that's the frame it ends on
+        assert_eq!(world.check_death(), None);
+        assert_eq!(top_row(&world), format!("    {DEAD_EYE_CHAR}"));
+    }
+
+    #[test]
+    fn victory_laps_flash_and_cycle_colors() {
+        let world = World::new(
+            Options {
+                width: 5,
+                height: 5,
+                seed: 1,
+                ..Options::default()
+            },
+            &STARTING_BODY,
+        );
+        let (width, height) = world.options.screen_size();
+        let (x, y) = cell_to_screen(STARTING_BODY[0], &world.options);
+        for step in 0..VICTORY_COLORS.len() * 2 {
+            let mut buffer = Buffer::new(width, height);
+            let shown = VICTORY_STEP * step as u32 + VICTORY_STEP / 2;
+            draw_victory_lap(&mut buffer, &world, shown);
+            let prompt: String = (0..width).map(|x| buffer.get(x, 0).chr).collect();
+            assert_eq!(prompt.contains(WIN_PROMPT), step % 2 == 0, "{step}");
+            let color = VICTORY_COLORS[step % VICTORY_COLORS.len()];
+            assert_eq!(buffer.get(x as u32, y as u32).bg, color, "{step}");
+        }
+    }
+
+    #[test]
+    fn borders_go_around_the_map_without_touching_it() {
+        // Returns the characters drawn for a 3x2 map in a border, and which
+        // cells are the map's color
+        let draw = |border: Border| {
+            let options = Options {
+                width: 3,
+                height: 2,
+                cell_width: 1,
+                margin_x: 1,
+                margin_y: 1,
+                border,
+                ..Options::default()
+            };
+            let (width, height) = options.screen_size();
+            let mut buffer = Buffer::new(width, height);
+            draw_map(&mut buffer, &options);
+            let rows: Vec<String> = (0..height)
+                .map(|y| (0..width).map(|x| buffer.get(x, y).chr).collect())
+                .collect();
+            let map: Vec<(u32, u32)> = (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .filter(|(x, y)| buffer.get(*x, *y).bg == MAP_COLOR)
+                .collect();
+            (rows, map)
+        };
+        let corners = match unicode() {
+            true => ROUNDED_BORDER_CHARS,
+            false => SHARP_BORDER_CHARS,
+        };
+        let [top_left, top_right, bottom_left, bottom_right, ..] = corners;
+        let (rounded, map) = draw(Border::Rounded);
+        assert_eq!(
+            rounded,
+            [
+                format!("{top_left}───{top_right}"),
+                "│   │".to_owned(),
+                "│   │".to_owned(),
+                format!("{bottom_left}───{bottom_right}"),
+            ]
+        );
+        let (sharp, sharp_map) = draw(Border::Sharp);
+        assert_eq!(sharp, ["┌───┐", "│   │", "│   │", "└───┘"]);
+        let (solid, solid_map) = draw(Border::Solid);
+        assert_eq!(solid, ["     "; 4]);
+        let inside: Vec<(u32, u32)> = (1..3).flat_map(|y| (1..4).map(move |x| (x, y))).collect();
+        assert_eq!(map, inside);
+        assert_eq!(sharp_map, inside);
+        assert_eq!(solid_map, inside);
+    }
+
+    #[test]
+    fn cells_are_as_wide_as_the_cell_width() {
+        // Returns the text of a small game drawn with a cell width, and a map
+        // of what's in each column of it (the snek, food, map, or border)
+        let draw = |cell_width: u32| {
+            let options = Options {
+                width: 4,
+                height: 3,
+                cell_width,
+                margin_x: 1,
+                margin_y: 1,
+                border: Border::Sharp,
+                seed: 1,
+                ..Options::default()
+            };
+            let mut world = World::new(options, &STARTING_BODY);
+            world.food = vec![Point::new(2, 1)];
+            let (width, height) = world.options.screen_size();
+            let mut buffer = Buffer::new(width, height);
+            draw_world(&mut buffer, &world, "GO");
+            let text: Vec<String> = buffer.to_text().lines().map(plain).collect();
+            let colors: Vec<String> = (0..height)
+                .map(|y| {
+                    (0..width)
+                        .map(|x| match buffer.get(x, y).bg {
+                            color if color == SNEK_COLORS[0] => 's',
+                            color if color == FOOD_COLOR => 'f',
+                            color if color == MAP_COLOR => 'm',
+                            _ => 'b',
+                        })
+                        .collect()
+                })
+                .collect();
+            (text, colors)
+        };
+        let (text, colors) = draw(1);
+        assert_eq!(
+            text,
+            [
+                "┌─GO─┐".to_owned(),
+                format!("│   {EYE_CHAR}│"),
+                "│    │".to_owned(),
+                "│    │".to_owned(),
+                "SCORE:".to_owned(),
+            ]
+        );
+        assert_eq!(colors, ["bbbbbb", "bssssb", "bmmfmb", "bmmmmb", "bbbbbb"]);
+        let (text, colors) = draw(3);
+        assert_eq!(
+            text,
+            [
+                "┌─────GO─────┐".to_owned(),
+                format!("│         {}│", EYE_CHAR.to_string().repeat(3)),
+                "│            │".to_owned(),
+                "│            │".to_owned(),
+                "└──SCORE: 0──┘".to_owned(),
+            ]
+        );
+        assert_eq!(
+            colors,
+            [
+                "bbbbbbbbbbbbbb",
+                "bssssssssssssb",
+                "bmmmmmmfffmmmb",
+                "bmmmmmmmmmmmmb",
+                "bbbbbbbbbbbbbb",
+            ]
+        );
+    }
+
+    #[test]
+    fn part_cells_fill_by_eighths_from_their_side() {
+        let options = Options {
+            margin_x: 0,
+            margin_y: 0,
+            ..Options::default()
+        };
+        // Returns the two columns of the first cell, after filling some of it
+        let part = |side: Direction, amount: f32| {
+            let mut buffer = Buffer::new(2, 1);
+            buffer.fill(pixel::pxl_bg(' ', MAP_COLOR));
+            draw_part_cell(
+                &mut buffer,
+                Point::new(0, 0),
+                side,
+                amount,
+                GOLD_COLOR,
+                &options,
+            );
+            [0, 1].map(|x| {
+                let pixel = buffer.get(x, 0);
+                (pixel.chr, pixel.fg == GOLD_COLOR, pixel.bg == GOLD_COLOR)
+            })
+        };
+        let (map, full) = ((' ', false, false), (' ', false, true));
+        assert_eq!(part(Direction::Left, 0.0), [map, map]);
+        assert_eq!(part(Direction::Left, 1.0), [full, full]);
+        assert_eq!(part(Direction::Left, 0.5), [full, map]);
+        assert_eq!(part(Direction::Left, 0.25), [('▌', true, false), map]);
+        assert_eq!(part(Direction::Left, 0.06), [('▏', true, false), map]);
+        assert_eq!(part(Direction::Left, 0.6), [full, ('▎', true, false)]);
+        // From the right, it's the map that's drawn with the blocks
+        assert_eq!(part(Direction::Right, 0.25), [map, ('▌', false, true)]);
+        assert_eq!(part(Direction::Right, 0.75), [('▌', false, true), full]);
+        // And up and down, every column's the same
+        assert_eq!(part(Direction::Down, 0.5), [('▄', true, false); 2]);
+        assert_eq!(part(Direction::Down, 0.875), [('▇', true, false); 2]);
+        assert_eq!(part(Direction::Up, 0.25), [('▆', false, true); 2]);
+        assert_eq!(part(Direction::Up, 1.0), [full; 2]);
+    }
+
+    #[test]
+    fn glides_are_only_drawn_into_safe_empty_cells() {
+        let mut world = World::new(
+            Options {
+                seed: 1,
+                ..Options::default()
+            },
+            &STARTING_BODY,
+        );
+        world.food = vec![Point::new(8, 8)];
+        // Returns the cells a glide changed from how the world's drawn
+        let glide = |world: &World, direction: Direction| {
+            let (width, height) = world.options.screen_size();
+            let mut still = Buffer::new(width, height);
+            draw_world(&mut still, world, "");
+            let mut gliding = Buffer::new(width, height);
+            draw_world(&mut gliding, world, "");
+            draw_glide_cells(&mut gliding, world, direction, 0.5);
+            (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .filter(|(x, y)| gliding.get(*x, *y) != still.get(*x, *y))
+                .collect::<Vec<(u32, u32)>>()
+        };
+        // Half into the cell ahead, and half out of the tail's
+        let ahead = cell_to_screen(Point::new(4, 0), &world.options);
+        let tail = cell_to_screen(Point::new(0, 0), &world.options);
+        assert_eq!(
+            glide(&world, Direction::Right),
+            [
+                (tail.0 as u32, tail.1 as u32),
+                (ahead.0 as u32, ahead.1 as u32)
+            ]
+        );
+        // Not off the board, straight back, or onto food
+        assert_eq!(glide(&world, Direction::Up), []);
+        assert_eq!(glide(&world, Direction::Left), []);
+        world.food = vec![Point::new(4, 0)];
+        assert_eq!(glide(&world, Direction::Right), []);
+    }
+
+    #[test]
+    fn grid_labels_are_the_last_hex_digits() {
+        let labels: Vec<String> = (0..6).map(|index| grid_label(index * 7, 2)).collect();
+        assert_eq!(labels, ["00", "07", "0e", "15", "1c", "23"]);
+        assert_eq!(grid_label(300, 2), "2c");
+        assert_eq!(grid_label(300, 1), "c");
+        assert_eq!(grid_label(3, 4), "0003");
+    }
+
+    #[test]
+    fn grid_labels_keep_clear_of_the_prompt_and_line() {
+        // Returns the text on some rows of a small board with grid labels
+        let rows = |margin_y: u32, border: Border, rows: &[u32]| {
+            let options = Options {
+                width: 5,
+                height: 5,
+                margin_y,
+                border,
+                grid_labels: true,
+                ..Options::default()
+            };
+            let (width, height) = options.screen_size();
+            let mut buffer = Buffer::new(width, height);
+            draw_map(&mut buffer, &options);
+            draw_prompt(&mut buffer, "GO", 0);
+            rows.iter()
+                .map(|y| {
+                    (0..width)
+                        .map(|x| buffer.get(x, *y).chr)
+                        .collect::<String>()
+                })
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            rows(1, Border::Solid, &[0, 1]),
+            ["      GO      ", "00            "]
+        );
+        assert_eq!(
+            rows(2, Border::Solid, &[0, 1, 2]),
+            ["      GO      ", "  0001020304  ", "00            "]
+        );
+        assert_eq!(
+            rows(2, Border::Sharp, &[1, 2]),
+            [" ┌──────────┐ ", "0│          │ "]
+        );
+        assert_eq!(
+            rows(3, Border::Sharp, &[1, 2, 3]),
+            ["  0001020304  ", " ┌──────────┐ ", "0│          │ "]
+        );
+    }
+
+    #[test]
+    fn length_tier_goes_up_every_milestone_grown() {
+        let start = STARTING_BODY.len();
+        assert_eq!(length_tier(0), 0);
+        assert_eq!(length_tier(start), 0);
+        assert_eq!(length_tier(start + LENGTH_MILESTONE - 1), 0);
+        assert_eq!(length_tier(start + LENGTH_MILESTONE), 1);
+        assert_eq!(length_tier(start + LENGTH_MILESTONE * 3 + 5), 3);
+    }
+
+    #[test]
+    fn pulse_stays_in_range_over_a_cycle() {
+        let period = 700;
+        for elapsed in 0..=u128::from(period) * 2 {
+            let brightness = pulse_brightness(elapsed, period);
+            assert!(
+                (0.0..=1.0).contains(&brightness),
+                "{brightness} at {elapsed}ms"
+            );
+        }
+        assert_eq!(pulse_brightness(0, period), 0.0);
+        assert!((pulse_brightness(350, period) - 1.0).abs() < 1e-6);
+        assert_eq!(pulse_brightness(1400, period), 0.0);
+        assert_eq!(pulse_brightness(5, 0), 0.0);
+    }
+
+    #[test]
+    fn progress_bar_fills_up_to_the_target() {
+        assert_eq!(bar_fill(0, 10, 8), 0);
+        assert_eq!(bar_fill(5, 10, 8), 4);
+        assert_eq!(bar_fill(10, 10, 8), 8);
+        assert_eq!(bar_fill(25, 10, 8), 8);
+        assert_eq!(bar_fill(3, 0, 8), 0);
+        assert_eq!(progress_bar(0, 4, 4), BAR_EMPTY_CHAR.to_string().repeat(4));
+        assert_eq!(progress_bar(4, 4, 4), BAR_FULL_CHAR.to_string().repeat(4));
+        assert_eq!(progress_bar(2, 4, 0), "");
+    }
+
+    #[test]
+    fn cells_map_past_a_custom_margin() {
+        let options = Options {
+            margin_x: 5,
+            margin_y: 3,
+            ..Options::default()
+        };
+        assert_eq!(cell_to_screen(Point::new(0, 0), &options), (5, 3));
+        assert_eq!(cell_to_screen(Point::new(16, 14), &options), (37, 17));
+        assert_eq!(options.screen_size(), (17 * 2 + 10, 15 + 6));
+        let (width, height) = options.screen_size();
+        let mut buffer = Buffer::new(width, height);
+        draw_map(&mut buffer, &options);
+        assert_eq!(buffer.get(5, 3).bg, MAP_COLOR);
+        assert_eq!(buffer.get(38, 17).bg, MAP_COLOR);
+        assert_eq!(buffer.get(4, 3).bg, BORDER_COLOR);
+        assert_eq!(buffer.get(39, 18).bg, BORDER_COLOR);
+    }
+}
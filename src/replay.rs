@@ -0,0 +1,216 @@
+// A recorded game that can be played back without a terminal.
+//
+// Games are deterministic given their options (which include the seed) and the
+// direction the snek moved in on every tick, so only the ticks where the
+// direction changed are recorded.
+//
+// File format (version 2): a JSON object holding the `version`, the game's
+// share `code` (for people reading the file; it's ignored when playing it
+// back), its `options` (as in a config), its `turns` as `[tick, direction]`
+// for every change of direction, the snek's final `length`, and the `ticks`
+// it ended on and its `score`. Replays without a length still play back, but
+// version 1 replays (made of `key value` lines) can't be read.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::options::Options;
+use crate::share::ShareCode;
+use crate::storage;
+use crate::world::{Direction, World};
+
+// Version of the replay file format
+const REPLAY_VERSION: u32 = 2;
+
+// What's in a replay file
+#[derive(Serialize)]
+struct ReplayFile<'a> {
+    version: u32,
+    code: String,
+    #[serde(flatten)]
+    replay: &'a Replay,
+}
+
+// A finished game's options and inputs
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    #[serde(deserialize_with = "Options::deserialize_checked")]
+    pub options: Options,
+    pub turns: Vec<(u64, Direction)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length: Option<usize>,
+    pub ticks: u64,
+    pub score: usize,
+}
+
+impl Replay {
+    // Records the game played in a world
+    pub fn from_world(world: &World) -> Self {
+        Self {
+            options: world.options.clone(),
+            turns: world.turns.clone(),
+            ticks: world.ticks,
+            score: world.score(),
+            length: Some(world.snek.body.len()),
+        }
+    }
+
+    // Loads a replay from a file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    // Saves the replay to a file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        storage::write_atomic(path, &self.serialize())
+    }
+
+    // Writes the replay in the replay file format
+    pub fn serialize(&self) -> String {
+        let file = ReplayFile {
+            version: REPLAY_VERSION,
+            code: ShareCode::from_options(&self.options).encode(),
+            replay: self,
+        };
+        serde_json::to_string_pretty(&file).unwrap_or_default() + "\n"
+    }
+
+    // Reads a replay from the contents of a replay file
+    pub fn parse(contents: &str) -> io::Result<Self> {
+        if storage::json_version(contents) != Some(REPLAY_VERSION) {
+            return Err(invalid("unknown replay file version"));
+        }
+        serde_json::from_str(contents)
+            .map_err(|err| invalid(&format!("malformed replay file: {err}")))
+    }
+
+    // Plays the replay back, calling a function with the world before every
+    // move and once more at the end, and returning the finished world
+    pub fn simulate(&self, mut frame: impl FnMut(&World)) -> World {
+        let mut world = World::new(self.options.clone(), &self.options.starting_body());
+        let mut turns = self.turns.iter().peekable();
+        loop {
+            world.check_death();
+            frame(&world);
+            if !world.running() || world.ticks >= self.ticks {
+                return world;
+            }
+            while let Some((_, direction)) = turns.next_if(|(tick, _)| *tick <= world.ticks) {
+                world.snek.direction = *direction;
+            }
+            if world.advance().is_none() {
+                return world;
+            }
+        }
+    }
+
+    // Plays the replay back and compares how it ends with what was recorded,
+    // returning what didn't match (nothing, if the replay is genuine)
+    pub fn verify(&self) -> Vec<String> {
+        let world = self.simulate(|_| ());
+        let mut mismatches = Vec::new();
+        if world.ticks != self.ticks {
+            mismatches.push(format!(
+                "the game ended on tick {} but the replay says {}",
+                world.ticks, self.ticks
+            ));
+        }
+        if world.score() != self.score {
+            mismatches.push(format!(
+                "the game scored {} but the replay says {}",
+                world.score(),
+                self.score
+            ));
+        }
+        if let Some(length) = self.length
+            && world.snek.body.len() != length
+        {
+            mismatches.push(format!(
+                "the snek ended {} long but the replay says {length}",
+                world.snek.body.len()
+            ));
+        }
+        mismatches
+    }
+}
+
+// Returns an error for a malformed replay file
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A short recorded game that eats three pellets and then runs into the
+    // right wall, and the same game claiming two more pellets than it ate
+    const VALID: &str = include_str!("../tests/fixtures/valid.snekreplay");
+    const TAMPERED: &str = include_str!("../tests/fixtures/tampered.snekreplay");
+
+    #[test]
+    fn genuine_replay_verifies() {
+        let replay = Replay::parse(VALID).unwrap();
+        assert_eq!(
+            (replay.ticks, replay.score, replay.length),
+            (18, 3, Some(7))
+        );
+        assert_eq!(replay.verify(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn tampered_replay_fails_verification() {
+        let mismatches = Replay::parse(TAMPERED).unwrap().verify();
+        assert_eq!(
+            mismatches,
+            [
+                "the game scored 3 but the replay says 5",
+                "the snek ended 7 long but the replay says 9",
+            ]
+        );
+    }
+
+    // Returns a replay file as JSON, to pick apart
+    fn json(contents: &str) -> serde_json::Value {
+        serde_json::from_str(contents).unwrap()
+    }
+
+    #[test]
+    fn replays_without_a_length_still_verify() {
+        let mut old = json(VALID);
+        old.as_object_mut().unwrap().remove("length");
+        let replay = Replay::parse(&old.to_string()).unwrap();
+        assert_eq!(replay.length, None);
+        assert!(replay.verify().is_empty());
+        assert_eq!(json(&replay.serialize()).get("length"), None);
+        let mut late = replay.clone();
+        late.turns[0].0 += 1;
+        assert!(!late.verify().is_empty());
+    }
+
+    #[test]
+    fn replays_round_trip() {
+        let replay = Replay::parse(VALID).unwrap();
+        assert_eq!(replay.serialize(), VALID);
+    }
+
+    #[test]
+    fn malformed_replays_are_refused() {
+        assert!(Replay::parse("snek-replay 1\nwidth 17\n").is_err());
+        let mut newer = json(VALID);
+        newer["version"] = 3.into();
+        assert!(Replay::parse(&newer.to_string()).is_err());
+        let mut unscored = json(VALID);
+        unscored.as_object_mut().unwrap().remove("score");
+        assert!(Replay::parse(&unscored.to_string()).is_err());
+        let mut sideways = json(VALID);
+        sideways["turns"][0][1] = "sideways".into();
+        assert!(Replay::parse(&sideways.to_string()).is_err());
+        let mut empty = json(VALID);
+        empty["options"]["width"] = 0.into();
+        assert!(Replay::parse(&empty.to_string()).is_err());
+    }
+}
@@ -0,0 +1,255 @@
+// A game in progress, saved from the pause screen so it can be resumed later.
+//
+// File format (version 2): a JSON object holding the `version` and the fields
+// of `SaveState`, starting with the game's `options` (as in a config). Points
+// are `[x, y]` and the body is a list of them from tail to head. The `decoy`
+// (only in choice mode) is a point, or null if there isn't one. `points` is the
+// score kept with `--scoring points`, and 0 otherwise. `food_spawned` is the
+// tick each food appeared on, in the same order as `food`. `wall_ticks` is how
+// many moves in a row the head has been next to a wall, and `food_placed` how
+// many foods have been put on the map. In marathon mode, `marathon` has how
+// many lives are over and the score and ticks they added up to (all 0
+// otherwise). `heading` is the way the snek last moved, which `direction` can
+// differ from when a turn's been made since. `start_heading` and `turns` are
+// the way the snek started off and every change of direction since as
+// `[tick, direction]`, so the game's replay still covers it all once it's
+// resumed. `queued_turns` are the turn keys waiting to be used, as the
+// directions of their arrows. Version 1 saves (`save.txt`, made of `key value`
+// lines) can't be resumed.
+// The save is deleted when it's resumed so the same game can't be replayed.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use fastrand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::Point;
+use crate::marathon::Marathon;
+use crate::options::Options;
+use crate::storage;
+use crate::world::{Direction, World};
+
+// Name of the save file inside the data directory
+pub const SAVE_FILE: &str = "save.json";
+
+// Version of the save file format
+const SAVE_VERSION: u32 = 2;
+
+// What's in a save file
+#[derive(Serialize)]
+struct SaveFile<'a> {
+    version: u32,
+    #[serde(flatten)]
+    save: &'a SaveState,
+}
+
+// Everything needed to carry on with a game exactly where it was left
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SaveState {
+    #[serde(deserialize_with = "Options::deserialize_checked")]
+    pub options: Options,
+    pub body: Vec<Point>,
+    pub start_len: usize,
+    pub direction: Direction,
+    pub heading: Direction,
+    pub start_heading: Direction,
+    pub turns: Vec<(u64, Direction)>,
+    pub queued_turns: Vec<Direction>,
+    pub food: Vec<Point>,
+    pub food_spawned: Vec<u64>,
+    pub food_placed: usize,
+    pub decoy: Option<Point>,
+    pub rng_state: u64,
+    pub ticks: u64,
+    pub eaten: usize,
+    pub points: usize,
+    pub elapsed: u64,
+    pub first_move_pending: bool,
+    pub touched_body: bool,
+    pub wall_ticks: u32,
+    pub pending_growth: usize,
+    pub growth_ticks: u32,
+    pub marathon: Marathon,
+}
+
+impl SaveState {
+    // Returns the save of a game in a world, which has gone on for `elapsed`
+    // seconds with some turns waiting to be made
+    pub fn new(
+        world: &World,
+        elapsed: u64,
+        marathon: Marathon,
+        queued_turns: Vec<Direction>,
+    ) -> Self {
+        Self {
+            options: world.options.clone(),
+            body: world.snek.body.clone(),
+            start_len: world.snek.start_len,
+            direction: world.snek.direction,
+            heading: world.snek.heading,
+            start_heading: world.start_heading,
+            turns: world.turns.clone(),
+            queued_turns,
+            food: world.food.clone(),
+            food_spawned: world.food_spawned.clone(),
+            food_placed: world.food_placed,
+            decoy: world.decoy,
+            rng_state: world.rng.get_seed(),
+            ticks: world.ticks,
+            eaten: world.eaten,
+            points: world.points,
+            elapsed,
+            first_move_pending: world.snek.first_move_pending,
+            touched_body: world.touched_body,
+            wall_ticks: world.wall_ticks,
+            pending_growth: world.snek.pending_growth,
+            growth_ticks: world.snek.growth_ticks,
+            marathon,
+        }
+    }
+
+    // Returns the world the save was made in, carrying on where it left off
+    pub fn world(&self) -> World {
+        let mut world = World::new(self.options.clone(), &self.body);
+        world.snek.start_len = self.start_len;
+        world.snek.direction = self.direction;
+        world.snek.heading = self.heading;
+        world.snek.first_move_pending = self.first_move_pending;
+        world.snek.pending_growth = self.pending_growth;
+        world.snek.growth_ticks = self.growth_ticks;
+        world.start_heading = self.start_heading;
+        world.turns = self.turns.clone();
+        world.food = self.food.clone();
+        world.food_spawned = self.food_spawned.clone();
+        world.food_placed = self.food_placed;
+        world.decoy = self.decoy;
+        world.rng = Rng::with_seed(self.rng_state);
+        world.ticks = self.ticks;
+        world.eaten = self.eaten;
+        world.points = self.points;
+        world.touched_body = self.touched_body;
+        world.wall_ticks = self.wall_ticks;
+        world
+    }
+
+    // Writes the save to a file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        storage::write_atomic(path, &self.serialize())
+    }
+
+    // Reads the save from a file and deletes it, returning nothing if there isn't one
+    pub fn take(path: &Path) -> io::Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        fs::remove_file(path)?;
+        Self::parse(&contents).map(Some)
+    }
+
+    // Writes the save in the save file format
+    pub fn serialize(&self) -> String {
+        let file = SaveFile {
+            version: SAVE_VERSION,
+            save: self,
+        };
+        serde_json::to_string_pretty(&file).unwrap_or_default() + "\n"
+    }
+
+    // Reads the save from the contents of a save file
+    pub fn parse(contents: &str) -> io::Result<Self> {
+        if storage::json_version(contents) != Some(SAVE_VERSION) {
+            return Err(invalid("unknown save file version"));
+        }
+        let save: Self = serde_json::from_str(contents)
+            .map_err(|err| invalid(&format!("malformed save file: {err}")))?;
+        if save.body.is_empty() {
+            return Err(invalid("empty snek in save file"));
+        }
+        if save.food_spawned.len() != save.food.len() {
+            return Err(invalid(
+                "food and the ticks it appeared on don't match in save file",
+            ));
+        }
+        Ok(save)
+    }
+}
+
+// Returns an error for a malformed save file
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::STARTING_BODY;
+    use crate::replay::Replay;
+
+    // Moves the snek some ticks in a direction
+    fn play(world: &mut World, direction: Direction, ticks: usize) {
+        world.snek.change_direction(direction);
+        for _ in 0..ticks {
+            world.advance();
+        }
+    }
+
+    #[test]
+    fn resumed_game_still_verifies() {
+        let options = Options {
+            seed: 7,
+            ..Options::default()
+        };
+        let mut world = World::new(options.clone(), &STARTING_BODY);
+        play(&mut world, Direction::Right, 2);
+        play(&mut world, Direction::Down, 3);
+        let queued_turns = vec![Direction::Right, Direction::Up];
+        let save = SaveState::new(&world, 12, Marathon::default(), queued_turns.clone());
+        let save = SaveState::parse(&save.serialize()).unwrap();
+        assert_eq!(save.turns, world.turns);
+        assert_eq!(save.queued_turns, queued_turns);
+        let mut resumed = save.world();
+        for direction in save.queued_turns {
+            play(&mut resumed, direction, 1);
+        }
+        for direction in queued_turns {
+            play(&mut world, direction, 1);
+        }
+        assert_eq!(resumed.snek.body, world.snek.body);
+        assert_eq!(resumed.food, world.food);
+        assert_eq!(resumed.turns, world.turns);
+        assert!(Replay::from_world(&resumed).verify().is_empty());
+    }
+
+    #[test]
+    fn saves_are_checked() {
+        let world = World::new(Options::default(), &STARTING_BODY);
+        let save = SaveState::new(&world, 0, Marathon::default(), Vec::new());
+        let mut json = serde_json::to_value(SaveFile {
+            version: SAVE_VERSION,
+            save: &save,
+        })
+        .unwrap();
+        assert_eq!(json["body"][0], serde_json::json!([0, 0]));
+        assert!(SaveState::parse(&json.to_string()).is_ok());
+        json["options"]["width"] = 0.into();
+        assert!(SaveState::parse(&json.to_string()).is_err());
+        json["options"]["width"] = 17.into();
+        json["food_spawned"] = serde_json::json!([]);
+        assert!(SaveState::parse(&json.to_string()).is_err());
+        json["version"] = 1.into();
+        assert!(SaveState::parse(&json.to_string()).is_err());
+        // Saves from before they were JSON can't be read
+        assert!(
+            SaveState::parse(
+                "snek-save 1
+width 17
+"
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,318 @@
+// The ranked score table kept in the data directory.
+//
+// File format (version 1): a `snek-scores 1` header line followed by one entry
+// per line with these tab separated fields:
+//
+//     name  score  mode  width  height  seed  date  duration
+//
+// `date` is in seconds since the unix epoch and `duration` is in seconds. Tabs
+// and newlines are stripped from names before saving.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::options::Mode;
+use crate::storage;
+
+// Name of the score file inside the data directory
+pub const SCORES_FILE: &str = "scores.tsv";
+
+// Header line (including version) of the score file
+const HEADER: &str = "snek-scores 1";
+
+// How many entries are kept for each mode
+pub const MAX_ENTRIES: usize = 10;
+
+// One finished game in the score table
+#[derive(Clone, PartialEq, Debug)]
+pub struct ScoreEntry {
+    pub name: String,
+    pub score: usize,
+    pub mode: Mode,
+    pub width: u32,
+    pub height: u32,
+    pub seed: u64,
+    pub date: u64,
+    pub duration: u64,
+}
+
+impl ScoreEntry {
+    // Returns whether this entry ranks above another one (earlier dates win ties)
+    fn beats(&self, other: &ScoreEntry) -> bool {
+        self.score > other.score || (self.score == other.score && self.date < other.date)
+    }
+
+    // Converts the entry to a line of the score file
+    fn to_line(&self) -> String {
+        let name: String = self
+            .name
+            .chars()
+            .filter(|c| *c != '\t' && *c != '\n' && *c != '\r')
+            .collect();
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            name,
+            self.score,
+            self.mode.name(),
+            self.width,
+            self.height,
+            self.seed,
+            self.date,
+            self.duration
+        )
+    }
+
+    // Reads an entry from a line of the score file
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let entry = Self {
+            name: fields.next()?.to_owned(),
+            score: fields.next()?.parse().ok()?,
+            mode: Mode::from_name(fields.next()?)?,
+            width: fields.next()?.parse().ok()?,
+            height: fields.next()?.parse().ok()?,
+            seed: fields.next()?.parse().ok()?,
+            date: fields.next()?.parse().ok()?,
+            duration: fields.next()?.parse().ok()?,
+        };
+        match fields.next() {
+            Some(_) => None,
+            None => Some(entry),
+        }
+    }
+}
+
+// The best scores for every mode, ordered from best to worst
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct ScoreTable {
+    entries: Vec<ScoreEntry>,
+}
+
+impl ScoreTable {
+    // Loads the table from a file, which is empty if the file doesn't exist
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Loads the table from the data directory, ignoring any errors
+    pub fn load_default() -> Self {
+        storage::data_file(SCORES_FILE)
+            .and_then(|path| Self::load(&path).ok())
+            .unwrap_or_default()
+    }
+
+    // Saves the table to a file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        storage::write_atomic(path, &self.serialize())
+    }
+
+    // Reads the table from the contents of a score file
+    pub fn parse(contents: &str) -> io::Result<Self> {
+        let mut lines = contents.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown score file version",
+            ));
+        }
+        let mut table = Self::default();
+        for line in lines.filter(|line| !line.is_empty()) {
+            let entry = ScoreEntry::from_line(line).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "malformed score entry")
+            })?;
+            table.insert(entry);
+        }
+        Ok(table)
+    }
+
+    // Writes the table in the score file format
+    pub fn serialize(&self) -> String {
+        let mut contents = HEADER.to_owned() + "\n";
+        for entry in &self.entries {
+            contents += &(entry.to_line() + "\n");
+        }
+        contents
+    }
+
+    // Inserts an entry, returning its rank in its mode if it made the table
+    pub fn insert(&mut self, entry: ScoreEntry) -> Option<usize> {
+        let index = self
+            .entries
+            .iter()
+            .position(|other| entry.beats(other))
+            .unwrap_or(self.entries.len());
+        let mode = entry.mode;
+        let rank = self.entries[..index]
+            .iter()
+            .filter(|other| other.mode == mode)
+            .count();
+        self.entries.insert(index, entry);
+        self.prune(mode);
+        (rank < MAX_ENTRIES).then_some(rank + 1)
+    }
+
+    // Removes the entries of a mode that fall outside the table
+    fn prune(&mut self, mode: Mode) {
+        let mut kept = 0;
+        self.entries.retain(|entry| {
+            if entry.mode != mode {
+                return true;
+            }
+            kept += 1;
+            kept <= MAX_ENTRIES
+        });
+    }
+
+    // Returns the entries of a mode, from best to worst
+    pub fn mode_entries(&self, mode: Mode) -> impl Iterator<Item = &ScoreEntry> {
+        self.entries.iter().filter(move |entry| entry.mode == mode)
+    }
+
+    // Formats the whole table for printing
+    pub fn format(&self) -> String {
+        if self.entries.is_empty() {
+            return "No scores yet".to_owned();
+        }
+        let mut text = String::new();
+        let mut modes: Vec<Mode> = self.entries.iter().map(|entry| entry.mode).collect();
+        modes.sort_by_key(|mode| mode.name());
+        modes.dedup();
+        for mode in modes {
+            text += &format!("{}\n", mode.name().to_uppercase());
+            text += "  #  NAME             SCORE  BOARD  SEED                  DATE        TIME\n";
+            for (rank, entry) in self.mode_entries(mode).enumerate() {
+                text += &format!(
+                    "{:>3}  {:<16} {:>5}  {:>5}  {:<20}  {}  {}:{:02}\n",
+                    rank + 1,
+                    entry.name,
+                    entry.score,
+                    format!("{}x{}", entry.width, entry.height),
+                    entry.seed,
+                    storage::format_date(entry.date),
+                    entry.duration / 60,
+                    entry.duration % 60
+                );
+            }
+        }
+        text.trim_end().to_owned()
+    }
+}
+
+// Returns whether a game that made the table at a rank (if it did) is a new
+// high score for its mode, which takes scoring anything at all
+pub fn new_high_score(rank: Option<usize>, score: usize) -> bool {
+    rank == Some(1) && score > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns an entry with everything but the score, mode, and date made up
+    fn entry(score: usize, mode: Mode, date: u64) -> ScoreEntry {
+        ScoreEntry {
+            name: "snek".to_owned(),
+            score,
+            mode,
+            width: 17,
+            height: 15,
+            seed: 42,
+            date,
+            duration: 90,
+        }
+    }
+
+    #[test]
+    fn insert_ranks_by_score() {
+        let mut table = ScoreTable::default();
+        assert_eq!(table.insert(entry(5, Mode::Classic, 1)), Some(1));
+        assert_eq!(table.insert(entry(9, Mode::Classic, 2)), Some(1));
+        assert_eq!(table.insert(entry(7, Mode::Classic, 3)), Some(2));
+        let scores: Vec<usize> = table.mode_entries(Mode::Classic).map(|e| e.score).collect();
+        assert_eq!(scores, [9, 7, 5]);
+    }
+
+    #[test]
+    fn insert_breaks_ties_by_earlier_date() {
+        let mut table = ScoreTable::default();
+        table.insert(entry(5, Mode::Classic, 20));
+        assert_eq!(table.insert(entry(5, Mode::Classic, 10)), Some(1));
+        assert_eq!(table.insert(entry(5, Mode::Classic, 30)), Some(3));
+        let dates: Vec<u64> = table.mode_entries(Mode::Classic).map(|e| e.date).collect();
+        assert_eq!(dates, [10, 20, 30]);
+    }
+
+    #[test]
+    fn insert_ranks_within_the_mode() {
+        let mut table = ScoreTable::default();
+        table.insert(entry(50, Mode::Rush, 1));
+        table.insert(entry(40, Mode::Rush, 2));
+        assert_eq!(table.insert(entry(10, Mode::Classic, 3)), Some(1));
+    }
+
+    #[test]
+    fn prune_keeps_the_best_of_each_mode() {
+        let mut table = ScoreTable::default();
+        for score in 0..MAX_ENTRIES + 5 {
+            table.insert(entry(score, Mode::Classic, score as u64));
+        }
+        table.insert(entry(1, Mode::Rush, 0));
+        let classic: Vec<usize> = table.mode_entries(Mode::Classic).map(|e| e.score).collect();
+        assert_eq!(classic.len(), MAX_ENTRIES);
+        assert_eq!(classic.first(), Some(&(MAX_ENTRIES + 4)));
+        assert_eq!(classic.last(), Some(&5));
+        assert_eq!(table.mode_entries(Mode::Rush).count(), 1);
+        assert_eq!(table.insert(entry(0, Mode::Classic, 99)), None);
+        assert_eq!(table.mode_entries(Mode::Classic).count(), MAX_ENTRIES);
+    }
+
+    #[test]
+    fn serialize_round_trips() {
+        let mut table = ScoreTable::default();
+        table.insert(entry(12, Mode::Classic, 1_700_000_000));
+        table.insert(entry(3, Mode::Ouroboros, 1_700_000_100));
+        let mut named = entry(8, Mode::Classic, 1_700_000_200);
+        named.name = "tab\tand\nnewline".to_owned();
+        table.insert(named);
+        let parsed = ScoreTable::parse(&table.serialize()).unwrap();
+        let names: Vec<&str> = parsed
+            .mode_entries(Mode::Classic)
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(names, ["snek", "tabandnewline"]);
+        assert_eq!(parsed.serialize(), table.serialize());
+    }
+
+    #[test]
+    fn parse_rejects_bad_files() {
+        assert!(ScoreTable::parse("snek-scores 2\n").is_err());
+        assert!(ScoreTable::parse("").is_err());
+        assert!(ScoreTable::parse("snek-scores 1\nsnek\t5\tclassic\n").is_err());
+        assert!(ScoreTable::parse("snek-scores 1\nsnek\t5\tnope\t17\t15\t1\t1\t1\n").is_err());
+        assert_eq!(
+            ScoreTable::parse("snek-scores 1\n\n").unwrap(),
+            ScoreTable::default()
+        );
+    }
+
+    #[test]
+    fn new_high_scores_have_to_top_their_mode() {
+        let mut table = ScoreTable::default();
+        // A first game counts, as long as it scored
+        let rank = table.insert(entry(0, Mode::Classic, 1));
+        assert!(!new_high_score(rank, 0));
+        let rank = table.insert(entry(4, Mode::Classic, 2));
+        assert!(new_high_score(rank, 4));
+        // Tying the best isn't enough, since the earlier game keeps its place
+        let rank = table.insert(entry(4, Mode::Classic, 3));
+        assert!(!new_high_score(rank, 4));
+        let rank = table.insert(entry(1, Mode::Rush, 4));
+        assert!(new_high_score(rank, 1));
+        assert!(!new_high_score(None, 9));
+    }
+}
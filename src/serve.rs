@@ -0,0 +1,405 @@
+// A tiny leaderboard server for playing on a local network (`snek serve`).
+//
+// It accepts the submissions `online.rs` sends to `/scores`, checks them by
+// hashing and playing back their replays, and keeps the best ones in
+// `leaderboard.tsv` in the data directory (in the score file format, so each
+// mode keeps its top `MAX_ENTRIES`). `/leaderboard` serves them as tab
+// separated `name  score  mode` lines and `/leaderboard.json` as a JSON array.
+//
+// Every connection is answered on a thread of its own, so a slow client or a
+// long replay doesn't hold up anyone else, and the table's only locked while
+// a checked submission goes in.
+
+use std::io::{self, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::MIN_BOARD;
+use crate::online::{self, Submission};
+use crate::options::Mode;
+use crate::replay::Replay;
+use crate::scores::{ScoreEntry, ScoreTable};
+use crate::spectate;
+use crate::storage;
+
+// Name of the server's score file inside the data directory
+pub const LEADERBOARD_FILE: &str = "leaderboard.tsv";
+
+// Biggest request body that's accepted, in bytes
+const MAX_BODY: usize = 1 << 20;
+
+// How long a client gets to send its whole request, however slowly it trickles in
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// Most headers a request can have
+const MAX_HEADERS: usize = 64;
+
+// Most connections answered at once, past which new ones are turned away
+const MAX_CONNECTIONS: usize = 32;
+
+// Most ticks a submitted game can have lasted, so playing it back can't take forever
+const MAX_TICKS: u64 = 1_000_000;
+
+// A kept score, as `/leaderboard.json` serves it
+#[derive(Serialize)]
+struct LeaderboardEntry {
+    name: String,
+    score: usize,
+    mode: Mode,
+    // A string, since it may not fit in a double
+    seed: String,
+    date: u64,
+}
+
+// A leaderboard server and the scores it keeps, shared by the threads
+// answering its connections
+#[derive(Clone)]
+pub struct Server {
+    token: Option<String>,
+    path: PathBuf,
+    table: Arc<Mutex<ScoreTable>>,
+    connections: Arc<AtomicUsize>,
+}
+
+impl Server {
+    // Creates a server that keeps its scores in the data directory
+    pub fn new(token: Option<String>) -> io::Result<Self> {
+        let path = storage::data_file(LEADERBOARD_FILE)
+            .ok_or_else(|| io::Error::other("no data directory"))?;
+        Self::with_path(token, path)
+    }
+
+    // Creates a server that keeps its scores in a file
+    pub fn with_path(token: Option<String>, path: PathBuf) -> io::Result<Self> {
+        Ok(Self {
+            token,
+            table: Arc::new(Mutex::new(ScoreTable::load(&path)?)),
+            path,
+            connections: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    // Answers requests on a port until the program is stopped
+    pub fn run(&self, port: u16) -> io::Result<()> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        eprintln!("Serving the leaderboard on port {port}");
+        self.serve(listener);
+        Ok(())
+    }
+
+    // Answers the requests made to a listener, each on its own thread
+    pub fn serve(&self, listener: TcpListener) {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            if self.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                self.connections.fetch_sub(1, Ordering::SeqCst);
+                continue;
+            }
+            let server = self.clone();
+            thread::spawn(move || {
+                if let Err(err) = server.handle(stream) {
+                    eprintln!("Couldn't answer a request: {err}");
+                }
+                server.connections.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    }
+
+    // Reads one request and writes its response
+    fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(Deadline {
+            stream: &stream,
+            deadline: Instant::now() + TIMEOUT,
+        });
+        let request_line = spectate::read_line(&mut reader)?.unwrap_or_default();
+        let mut length = 0;
+        let mut authorization = None;
+        for headers in 0.. {
+            let header = spectate::read_line(&mut reader)?.unwrap_or_default();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if headers == MAX_HEADERS {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "too many headers",
+                ));
+            }
+            let Some((name, value)) = header.split_once(':') else {
+                continue;
+            };
+            match name.to_ascii_lowercase().as_str() {
+                "content-length" => length = value.trim().parse().unwrap_or(0),
+                "authorization" => authorization = Some(value.trim().to_owned()),
+                _ => (),
+            }
+        }
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let (status, body) = if length > MAX_BODY {
+            ("413 Payload Too Large", "submission too big".to_owned())
+        } else {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body)?;
+            let body = String::from_utf8_lossy(&body);
+            let mut words = request_line.split(' ');
+            match (words.next(), words.next()) {
+                (Some("POST"), Some("/scores")) => self.submit(&body, authorization.as_deref()),
+                (Some("GET"), Some("/leaderboard")) => ("200 OK", self.leaderboard()),
+                (Some("GET"), Some("/leaderboard.json")) => ("200 OK", self.leaderboard_json()),
+                _ => ("404 Not Found", "not found".to_owned()),
+            }
+        };
+        write!(
+            &stream,
+            "HTTP/1.0 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    // Checks and stores a submitted game, returning the response
+    fn submit(&self, body: &str, authorization: Option<&str>) -> (&'static str, String) {
+        if let Some(token) = &self.token
+            && authorization != Some(&format!("Bearer {token}"))
+        {
+            return ("401 Unauthorized", "wrong token".to_owned());
+        }
+        let entry = match check_submission(body) {
+            Ok(entry) => entry,
+            Err(reason) => return ("400 Bad Request", reason.to_owned()),
+        };
+        let mut table = self.table.lock().unwrap_or_else(|err| err.into_inner());
+        let rank = table.insert(entry);
+        if let Err(err) = table.save(&self.path) {
+            eprintln!("Couldn't save the leaderboard: {err}");
+            return ("500 Internal Server Error", "couldn't save".to_owned());
+        }
+        match rank {
+            Some(rank) => ("200 OK", format!("rank {rank}")),
+            None => ("200 OK", "not ranked".to_owned()),
+        }
+    }
+
+    // Returns the best scores as `name  score  mode` lines, grouped by mode
+    fn leaderboard(&self) -> String {
+        self.entries()
+            .iter()
+            .map(|entry| format!("{}\t{}\t{}\n", entry.name, entry.score, entry.mode.name()))
+            .collect()
+    }
+
+    // Returns the best scores as a JSON array, grouped by mode
+    fn leaderboard_json(&self) -> String {
+        let entries: Vec<LeaderboardEntry> = self
+            .entries()
+            .into_iter()
+            .map(|entry| LeaderboardEntry {
+                seed: entry.seed.to_string(),
+                name: entry.name,
+                score: entry.score,
+                mode: entry.mode,
+                date: entry.date,
+            })
+            .collect();
+        serde_json::to_string(&entries).unwrap_or_default()
+    }
+
+    // Returns every kept entry, best first within each mode
+    fn entries(&self) -> Vec<ScoreEntry> {
+        let table = self.table.lock().unwrap_or_else(|err| err.into_inner());
+        Mode::all()
+            .iter()
+            .flat_map(|mode| table.mode_entries(*mode))
+            .cloned()
+            .collect()
+    }
+}
+
+// Reads from a stream, giving up once a deadline's passed however slowly the
+// request trickles in
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "the request took too long",
+            ));
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf)
+    }
+}
+
+// Checks that a submission's replay is intact and really scores what it claims,
+// returning the score entry for it
+fn check_submission(body: &str) -> Result<ScoreEntry, &'static str> {
+    let submission: Submission = serde_json::from_str(body).map_err(|_| "malformed submission")?;
+    if format!("{:016x}", online::hash(&submission.replay)) != submission.replay_hash {
+        return Err("replay doesn't match its hash");
+    }
+    let replay = Replay::parse(&submission.replay).map_err(|_| "malformed replay")?;
+    check_bounds(&replay)?;
+    let summary = submission.summary;
+    // Playing back a replay no real game could have made might panic, which
+    // shouldn't take the thread's answer with it
+    let mismatches = panic::catch_unwind(AssertUnwindSafe(|| replay.verify()))
+        .map_err(|_| "replay couldn't be played back")?;
+    if !mismatches.is_empty() || replay.score != summary.score {
+        return Err("replay doesn't reach the submitted score");
+    }
+    Ok(ScoreEntry {
+        name: summary
+            .name
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(16)
+            .collect(),
+        score: summary.score,
+        mode: replay.options.mode,
+        width: replay.options.width,
+        height: replay.options.height,
+        seed: replay.options.seed,
+        date: storage::now(),
+        duration: summary.duration,
+    })
+}
+
+// Checks that a replay's game is one that can be played back in a sensible
+// amount of time, before it is (reading it already made sure its options are
+// in range)
+fn check_bounds(replay: &Replay) -> Result<(), &'static str> {
+    let options = &replay.options;
+    if options.width < MIN_BOARD || options.height < MIN_BOARD {
+        return Err("board size out of range");
+    }
+    if replay.ticks > MAX_TICKS {
+        return Err("game too long");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+    use crate::online::OnlineConfig;
+    use crate::options::Options;
+    use crate::summary::GameSummary;
+    use crate::world::World;
+    use crate::{Point, STARTING_BODY};
+
+    // Starts a server with an empty leaderboard on a free port, returning the
+    // config to reach it with
+    fn start(name: &str) -> OnlineConfig {
+        let path = env::temp_dir().join(format!("snek-{name}-{}.tsv", process::id()));
+        let _ = fs::remove_file(&path);
+        let server = Server::with_path(None, path).unwrap();
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || server.serve(listener));
+        OnlineConfig { url, token: None }
+    }
+
+    // Plays a short game that eats one food and runs into the right wall,
+    // returning its results and replay
+    fn game() -> (GameSummary, Replay) {
+        let options = Options {
+            name: "tester".to_owned(),
+            seed: 5,
+            food_sequence: vec![Point::new(8, 0), Point::new(5, 5)],
+            ..Options::default()
+        };
+        let mut world = World::new(options, &STARTING_BODY);
+        while world.running() {
+            world.advance();
+            world.check_death();
+        }
+        (GameSummary::new(&world, 2), Replay::from_world(&world))
+    }
+
+    // Returns a submission's body, the way `OnlineConfig::submit` sends it
+    fn submission(summary: &GameSummary, replay: &str, hash: u64) -> String {
+        let submission = Submission {
+            replay_hash: format!("{hash:016x}"),
+            ..Submission::new(summary, replay)
+        };
+        serde_json::to_string(&submission).unwrap()
+    }
+
+    #[test]
+    fn submitted_games_make_the_leaderboard() {
+        let config = start("submit");
+        // Someone holding a connection open doesn't hold up anyone else
+        let _idle = TcpStream::connect(config.url.trim_start_matches("http://")).unwrap();
+        let (summary, replay) = game();
+        config.submit(&summary, &replay).unwrap();
+        let leaderboard = config.leaderboard().unwrap();
+        let row: Vec<&str> = leaderboard
+            .lines()
+            .nth(1)
+            .unwrap()
+            .split_whitespace()
+            .collect();
+        assert_eq!(row, ["1", "tester", "1", "classic"]);
+    }
+
+    #[test]
+    fn tampered_games_are_rejected() {
+        let config = start("tampered");
+        let (mut summary, mut replay) = game();
+        summary.score = 9;
+        replay.score = 9;
+        assert!(config.submit(&summary, &replay).is_err());
+        assert_eq!(config.leaderboard().unwrap().lines().count(), 1);
+        let (summary, replay) = game();
+        let replay = replay.serialize();
+        let hash = online::hash(&replay);
+        assert!(check_submission(&submission(&summary, &replay, hash)).is_ok());
+        assert_eq!(
+            check_submission(&submission(&summary, &replay, hash ^ 1)),
+            Err("replay doesn't match its hash")
+        );
+        let tampered = replay.replace("\"score\": 1\n", "\"score\": 2\n");
+        let hash = online::hash(&tampered);
+        assert_eq!(
+            check_submission(&submission(&summary, &tampered, hash)),
+            Err("replay doesn't reach the submitted score")
+        );
+    }
+
+    #[test]
+    fn games_out_of_bounds_are_rejected() {
+        let (summary, replay) = game();
+        let cases = [
+            ("\"width\": 17,", "\"width\": 100000,", "malformed replay"),
+            (
+                "\"height\": 15,",
+                "\"height\": 2,",
+                "board size out of range",
+            ),
+            ("\"fps\": 8,", "\"fps\": 0,", "malformed replay"),
+            ("\"ticks\": 14,", "\"ticks\": 99999999,", "game too long"),
+        ];
+        for (from, to, reason) in cases {
+            let replay = replay.serialize().replace(from, to);
+            let body = submission(&summary, &replay, online::hash(&replay));
+            assert_eq!(check_submission(&body), Err(reason));
+        }
+    }
+}
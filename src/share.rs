@@ -0,0 +1,223 @@
+// Share codes, which pack everything that decides where food appears (the
+// seed, board size, and mode) into something short enough to send a friend,
+// so they can play the same game with `snek --code CODE`.
+//
+// A code is 15 bytes written in Crockford's base32, in groups of six
+// characters joined by dashes:
+//
+//     version and mode  width  height  seed  checksum
+//
+// The first byte holds the format version (1) in its top four bits and the
+// mode's place in `Mode::all` in the bottom four. The width and height take two
+// bytes and the seed eight, all big endian, and the checksum is a Fletcher-16
+// of everything before it. Reading a code ignores case, dashes, and spaces, and
+// takes `O` for `0` and `I` and `L` for `1`, as Crockford's base32 does.
+
+use crate::options::{Mode, Options};
+
+// Version of the share code format
+const VERSION: u8 = 1;
+
+// Characters of Crockford's base32, in the order of their values
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+// How many bytes a code holds, and how many characters they take up
+const BYTES: usize = 15;
+const CHARS: usize = BYTES * 8 / 5;
+
+// How many characters are in each dash separated group of a code
+const GROUP: usize = 6;
+
+// The options a share code carries
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ShareCode {
+    pub seed: u64,
+    pub width: u16,
+    pub height: u16,
+    pub mode: Mode,
+}
+
+impl ShareCode {
+    // Returns the share code of a game's options
+    pub fn from_options(options: &Options) -> Self {
+        Self {
+            seed: options.seed,
+            width: options.width as u16,
+            height: options.height as u16,
+            mode: options.mode,
+        }
+    }
+
+    // Sets the options a code carries, leaving the rest as they were
+    pub fn apply(&self, options: &mut Options) {
+        options.seed = self.seed;
+        options.width = self.width.into();
+        options.height = self.height.into();
+        options.mode = self.mode;
+    }
+
+    // Writes the code out
+    pub fn encode(&self) -> String {
+        let mode = Mode::all()
+            .iter()
+            .position(|mode| *mode == self.mode)
+            .unwrap() as u8;
+        let mut bytes = vec![VERSION << 4 | mode];
+        bytes.extend(self.width.to_be_bytes());
+        bytes.extend(self.height.to_be_bytes());
+        bytes.extend(self.seed.to_be_bytes());
+        bytes.extend(checksum(&bytes).to_be_bytes());
+        let mut chars = Vec::new();
+        let (mut bits, mut count) = (0u32, 0);
+        for byte in bytes {
+            bits = bits << 8 | u32::from(byte);
+            count += 8;
+            while count >= 5 {
+                count -= 5;
+                chars.push(ALPHABET[(bits >> count & 31) as usize]);
+            }
+        }
+        let groups: Vec<&str> = chars
+            .chunks(GROUP)
+            .map(|group| std::str::from_utf8(group).unwrap())
+            .collect();
+        groups.join("-")
+    }
+
+    // Reads a code, explaining what's wrong with it if it can't be
+    pub fn decode(code: &str) -> Result<Self, String> {
+        let values = code
+            .chars()
+            .filter(|chr| *chr != '-' && !chr.is_whitespace())
+            .map(|chr| {
+                value(chr)
+                    .ok_or_else(|| format!("'{chr}' can't be in a share code (is it a typo?)"))
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+        if values.len() != CHARS {
+            return Err(format!(
+                "a share code has {CHARS} letters and numbers, but this one has {}",
+                values.len()
+            ));
+        }
+        let mut bytes = Vec::new();
+        let (mut bits, mut count) = (0u32, 0);
+        for value in values {
+            bits = bits << 5 | u32::from(value);
+            count += 5;
+            if count >= 8 {
+                count -= 8;
+                bytes.push((bits >> count) as u8);
+            }
+        }
+        let (data, sum) = bytes.split_at(BYTES - 2);
+        if checksum(data).to_be_bytes() != sum {
+            return Err("that share code doesn't add up (is there a typo in it?)".to_owned());
+        }
+        if data[0] >> 4 != VERSION {
+            return Err("that share code is from a different version of snek".to_owned());
+        }
+        let mode = *Mode::all()
+            .get(usize::from(data[0] & 15))
+            .ok_or("that share code is for a mode this snek doesn't have")?;
+        let width = u16::from_be_bytes([data[1], data[2]]);
+        let height = u16::from_be_bytes([data[3], data[4]]);
+        if width == 0 || height == 0 {
+            return Err("that share code is for an empty board".to_owned());
+        }
+        Ok(Self {
+            seed: u64::from_be_bytes(data[5..13].try_into().unwrap()),
+            width,
+            height,
+            mode,
+        })
+    }
+}
+
+// Returns the value of a character in a code, forgiving the usual mix-ups
+fn value(chr: char) -> Option<u8> {
+    let chr = match chr.to_ascii_uppercase() {
+        'O' => '0',
+        'I' | 'L' => '1',
+        chr => chr,
+    };
+    ALPHABET
+        .iter()
+        .position(|letter| char::from(*letter) == chr)
+        .map(|value| value as u8)
+}
+
+// Returns the Fletcher-16 checksum of some bytes
+fn checksum(bytes: &[u8]) -> u16 {
+    let (mut low, mut high) = (0u16, 0u16);
+    for byte in bytes {
+        low = (low + u16::from(*byte)) % 255;
+        high = (high + low) % 255;
+    }
+    high << 8 | low
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastrand::Rng;
+
+    // Returns some share codes with everything in them picked at random
+    fn random_codes() -> Vec<ShareCode> {
+        let mut rng = Rng::with_seed(5);
+        (0..50)
+            .map(|_| ShareCode {
+                seed: rng.u64(..),
+                width: rng.u16(1..),
+                height: rng.u16(1..),
+                mode: Mode::all()[rng.usize(..Mode::all().len())],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn codes_round_trip() {
+        for code in random_codes() {
+            let encoded = code.encode();
+            assert_eq!(ShareCode::decode(&encoded), Ok(code));
+            let sloppy = encoded.to_lowercase().replace('-', " ").replace('0', "o");
+            assert_eq!(ShareCode::decode(&sloppy), Ok(code));
+        }
+    }
+
+    #[test]
+    fn any_one_wrong_character_is_caught() {
+        for code in random_codes() {
+            let encoded: Vec<char> = code.encode().chars().collect();
+            for index in (0..encoded.len()).filter(|index| encoded[*index] != '-') {
+                for letter in ALPHABET.iter().map(|letter| char::from(*letter)) {
+                    if letter == encoded[index] {
+                        continue;
+                    }
+                    let mut corrupted = encoded.clone();
+                    corrupted[index] = letter;
+                    let corrupted: String = corrupted.into_iter().collect();
+                    assert!(
+                        ShareCode::decode(&corrupted).is_err(),
+                        "{corrupted} was read"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bad_codes_are_explained() {
+        let encoded = random_codes()[0].encode();
+        assert!(
+            ShareCode::decode(&encoded[1..])
+                .unwrap_err()
+                .contains("has 23")
+        );
+        assert!(
+            ShareCode::decode(&encoded.replacen('-', "U", 1))
+                .unwrap_err()
+                .contains("'U'")
+        );
+    }
+}
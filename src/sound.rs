@@ -0,0 +1,140 @@
+// Sound effects for what happens in a game (`--sound`), played through a
+// `SoundSink` so the bell and the audio effects (with the `audio` feature)
+// are made the same way.
+//
+// The bell is the BEL character written to the terminal. The engine draws a
+// frame with escape sequences, so a bell written in the middle of one could
+// split a sequence in two; instead, sinks are told what happened as it does
+// and only make their sounds once the frame's been drawn, with `flush`.
+
+use std::io::{self, Write};
+
+use crate::events::SnekEvent;
+use crate::options::Sound;
+
+// The terminal bell
+const BEL: &[u8] = b"\x07";
+
+// Something in a game there's a sound for
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Cue {
+    Ate,
+    Died,
+    Countdown,
+    HighScore,
+}
+
+impl Cue {
+    // Returns the cue for something that happened to the snek, if there's one
+    pub fn for_event(event: SnekEvent) -> Option<Self> {
+        match event {
+            SnekEvent::Ate(_) => Some(Cue::Ate),
+            SnekEvent::Died(_) => Some(Cue::Died),
+            SnekEvent::Moved(_) => None,
+        }
+    }
+}
+
+// Something that can make sounds for what happens in a game
+pub trait SoundSink {
+    // Lines up the sound for something that happened
+    fn play(&mut self, cue: Cue);
+
+    // Makes the sounds lined up since the last frame, once it's been drawn
+    fn flush(&mut self);
+}
+
+// Makes the sink for a sound setting at a volume (in percent), which makes no
+// sound at all at 0
+pub fn sink(sound: Sound, volume: u8) -> Box<dyn SoundSink> {
+    if volume == 0 {
+        return Box::new(NoSound);
+    }
+    match sound {
+        Sound::Bell => Box::new(Bell::default()),
+        #[cfg(feature = "audio")]
+        Sound::Effects => Box::new(crate::audio::Effects::start(volume)),
+        // Games from a snek with audio (like saves and replays) still ring
+        // the bell on one without it
+        #[cfg(not(feature = "audio"))]
+        Sound::Effects => Box::new(Bell::default()),
+        Sound::Off => Box::new(NoSound),
+    }
+}
+
+// Makes no sound at all
+pub struct NoSound;
+
+impl SoundSink for NoSound {
+    fn play(&mut self, _cue: Cue) {}
+
+    fn flush(&mut self) {}
+}
+
+// Rings the terminal bell once when the snek eats and twice when it dies
+#[derive(Default)]
+pub struct Bell {
+    rings: usize,
+}
+
+impl SoundSink for Bell {
+    fn play(&mut self, cue: Cue) {
+        self.rings += match cue {
+            Cue::Ate => 1,
+            Cue::Died => 2,
+            Cue::Countdown | Cue::HighScore => 0,
+        };
+    }
+
+    fn flush(&mut self) {
+        if self.rings == 0 {
+            return;
+        }
+        let mut stdout = io::stdout().lock();
+        // A terminal that can't ring isn't worth stopping the game over
+        let _ = stdout
+            .write_all(&BEL.repeat(self.rings))
+            .and_then(|()| stdout.flush());
+        self.rings = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point;
+
+    // Every cue there's a sound for
+    const CUES: [Cue; 4] = [Cue::Ate, Cue::Died, Cue::Countdown, Cue::HighScore];
+
+    #[test]
+    fn every_event_has_its_cue() {
+        let point = Point::new(1, 2);
+        for event in [
+            SnekEvent::Moved(point),
+            SnekEvent::Ate(point),
+            SnekEvent::Died(point),
+        ] {
+            let cue = match event {
+                SnekEvent::Moved(_) => None,
+                SnekEvent::Ate(_) => Some(Cue::Ate),
+                SnekEvent::Died(_) => Some(Cue::Died),
+            };
+            assert_eq!(Cue::for_event(event), cue);
+        }
+    }
+
+    #[test]
+    fn bell_rings_for_eating_and_dying() {
+        let rings = CUES.map(|cue| {
+            let mut bell = Bell::default();
+            bell.play(cue);
+            bell.rings
+        });
+        assert_eq!(rings, [1, 2, 0, 0]);
+        let mut bell = Bell::default();
+        bell.play(Cue::Ate);
+        bell.play(Cue::Died);
+        assert_eq!(bell.rings, 3);
+    }
+}
@@ -0,0 +1,410 @@
+// Watching a game live from another terminal (`--broadcast` and `snek watch`).
+//
+// The player's snek listens for spectators on a TCP address. Every line it
+// sends is a JSON object with a `type` field. Each spectator that joins is
+// sent
+//
+//     {"type":"start","version":2,"options":OPTIONS}
+//
+// where OPTIONS are the game's options (like in a save file), and a snapshot
+// of the game as it is:
+//
+//     {"type":"snapshot","score":SCORE,"food":[[X,Y],...],"body":[[X,Y],...]}
+//
+// with the snek's body from tail to head. After that, every move is one line:
+//
+//     {"type":"move","head":[X,Y],"length":LENGTH,"food":[[X,Y],...],"score":SCORE}
+//
+// where the watcher adds the new head and drops parts from the tail until the
+// snek is LENGTH long. `{"type":"end"}` says the game is over. Spectators
+// can't affect the game, and one that hangs up or falls behind is just
+// dropped.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use console_engine::ConsoleEngine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::input;
+use crate::options::{Options, Scoring};
+use crate::render;
+use crate::world::World;
+use crate::{DISCONNECTED_PROMPT, GAME_OVER_PROMPT, INPUT_POLLS, Point, QUIT_KEY, WATCH_PROMPT};
+
+// Version of the stream, sent when it starts
+const VERSION: u32 = 2;
+
+// Longest line that's read from the stream, in bytes
+const MAX_LINE: u64 = 1 << 20;
+
+// How often the broadcast checks for new spectators while the game is quiet
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+
+// How long a spectator gets to take a line before it's dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// A line of the stream
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Line {
+    Start {
+        version: u32,
+        #[serde(deserialize_with = "Options::deserialize_checked")]
+        options: Box<Options>,
+    },
+    Snapshot(Snapshot),
+    Move {
+        head: Point,
+        length: usize,
+        food: Vec<Point>,
+        score: usize,
+    },
+    End,
+}
+
+// The game as it is, for spectators that have just joined
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    score: usize,
+    food: Vec<Point>,
+    body: Vec<Point>,
+}
+
+impl Snapshot {
+    // Returns the snapshot of a world
+    fn new(world: &World) -> Self {
+        Self {
+            score: world.score(),
+            food: world.food.clone(),
+            body: world.snek.body.clone(),
+        }
+    }
+
+    // Returns the snapshot if it fits on a map with some options
+    fn check(self, options: &Options) -> Option<Self> {
+        let fits = !self.body.is_empty()
+            && self.body.len() <= cells(options) + 1
+            && self.food.len() <= cells(options);
+        fits.then_some(self)
+    }
+}
+
+// What the game tells the broadcast thread
+enum Message {
+    Moved { line: String, snapshot: String },
+    Ended,
+}
+
+// A running broadcast of a game to its spectators
+pub struct Broadcaster {
+    sender: Sender<Message>,
+    thread: JoinHandle<()>,
+    ticks: u64,
+}
+
+impl Broadcaster {
+    // Starts listening for spectators on an address, before the game has started
+    pub fn listen(address: &str) -> io::Result<TcpListener> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(listener)
+    }
+
+    // Starts broadcasting a game to the spectators that join a listener
+    pub fn start(listener: TcpListener, world: &World) -> Self {
+        let greeting = encode(&Line::Start {
+            version: VERSION,
+            options: Box::new(world.options.clone()),
+        });
+        let snapshot = encode(&Line::Snapshot(Snapshot::new(world)));
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::spawn(move || run(listener, greeting, snapshot, receiver));
+        Self {
+            sender,
+            thread,
+            ticks: world.ticks,
+        }
+    }
+
+    // Sends the snek's latest move to the spectators, if it's moved since the last one
+    pub fn update(&mut self, world: &World) {
+        if world.ticks == self.ticks {
+            return;
+        }
+        self.ticks = world.ticks;
+        let line = encode(&Line::Move {
+            head: *world.snek.body.last().unwrap(),
+            length: world.snek.body.len(),
+            food: world.food.clone(),
+            score: world.score(),
+        });
+        let snapshot = encode(&Line::Snapshot(Snapshot::new(world)));
+        // The thread only stops once it's told to, so this can't fail
+        let _ = self.sender.send(Message::Moved { line, snapshot });
+    }
+
+    // Tells the spectators the game is over and waits for them to be told
+    pub fn finish(self) {
+        let _ = self.sender.send(Message::Ended);
+        let _ = self.thread.join();
+    }
+}
+
+// Sends the game to spectators as they join and as it's played, until it ends
+fn run(listener: TcpListener, greeting: String, mut snapshot: String, messages: Receiver<Message>) {
+    let mut spectators: Vec<TcpStream> = Vec::new();
+    loop {
+        let message = messages.recv_timeout(ACCEPT_INTERVAL);
+        while let Ok((stream, _)) = listener.accept() {
+            let joined = stream.set_nonblocking(false).is_ok()
+                && stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_ok()
+                && send(&stream, &(greeting.clone() + &snapshot));
+            if joined {
+                spectators.push(stream);
+            }
+        }
+        let line = match message {
+            Ok(Message::Moved {
+                line,
+                snapshot: latest,
+            }) => {
+                snapshot = latest;
+                line
+            }
+            Ok(Message::Ended) | Err(RecvTimeoutError::Disconnected) => {
+                spectators.retain(|stream| send(stream, &encode(&Line::End)));
+                return;
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+        };
+        spectators.retain(|stream| send(stream, &line));
+    }
+}
+
+// Writes text to a spectator, returning whether it's still there
+fn send(mut stream: &TcpStream, text: &str) -> bool {
+    stream.write_all(text.as_bytes()).is_ok()
+}
+
+// Writes a line of the stream as JSON, with its newline
+fn encode(line: &Line) -> String {
+    serde_json::to_string(line).unwrap_or_default() + "\n"
+}
+
+// Watches a game broadcast from an address until it's quit
+pub fn watch(address: &str) -> io::Result<()> {
+    let mut reader = BufReader::new(TcpStream::connect(address)?);
+    let (options, snapshot) = read_start(&mut reader)?;
+    options.check_fits().map_err(io::Error::other)?;
+    let (width, height) = options.screen_size();
+    let mut engine = ConsoleEngine::init(width, height, options.fps * INPUT_POLLS)?;
+    let mut world = World::new(options, &snapshot.body);
+    // The score comes from the stream, however the game counts it
+    world.options.scoring = Scoring::Length;
+    set_score(&mut world, snapshot.score);
+    world.food = snapshot.food;
+
+    let stream = read_stream(reader);
+    let mut prompt = WATCH_PROMPT;
+    engine.set_title("SNEK");
+    loop {
+        for line in stream.try_iter() {
+            let Some(line) = line else {
+                if prompt == WATCH_PROMPT {
+                    prompt = DISCONNECTED_PROMPT;
+                }
+                continue;
+            };
+            match serde_json::from_str(&line) {
+                Ok(Line::End) => {
+                    world.snek.alive = false;
+                    prompt = GAME_OVER_PROMPT;
+                }
+                Ok(line) => apply(&mut world, line),
+                Err(_) => (),
+            }
+        }
+        render::draw_world(&mut engine, &world, prompt);
+
+        engine.draw();
+        engine.clear_screen();
+        engine.wait_frame();
+
+        if input::pressed(&engine, QUIT_KEY) {
+            return Ok(());
+        }
+    }
+}
+
+// Reads the start of a broadcast (up to and including the snapshot),
+// returning the game's options and how it was when the spectator joined
+fn read_start(reader: &mut impl BufRead) -> io::Result<(Options, Snapshot)> {
+    let start: Value = read_line(reader)?
+        .and_then(|line| serde_json::from_str(&line).ok())
+        .filter(|start: &Value| start["type"] == "start" && start["version"] == VERSION)
+        .ok_or_else(|| invalid("that isn't a snek broadcast"))?;
+    // The other end might not be a snek that checked its options, so they're
+    // checked as they're read
+    let Line::Start { options, .. } =
+        Line::deserialize(start).map_err(|err| invalid(&err.to_string()))?
+    else {
+        return Err(invalid("that isn't a snek broadcast"));
+    };
+    let mut options = *options;
+    options.fit_min_board().map_err(|err| invalid(&err))?;
+    let snapshot = match read_line(reader)?.map(|line| serde_json::from_str(&line)) {
+        Some(Ok(Line::Snapshot(snapshot))) => snapshot.check(&options),
+        _ => None,
+    };
+    let snapshot = snapshot.ok_or_else(|| invalid("malformed snapshot"))?;
+    Ok((options, snapshot))
+}
+
+// Reads the stream's lines on another thread, so the game keeps being drawn
+// while it's waiting; `None` is sent once the stream ends or breaks
+pub fn read_stream(mut reader: BufReader<TcpStream>) -> Receiver<Option<String>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        while let Ok(Some(line)) = read_line(&mut reader) {
+            if sender.send(Some(line)).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send(None);
+    });
+    receiver
+}
+
+// Applies a line of the stream to the watched world, ignoring it if it's malformed
+fn apply(world: &mut World, line: Line) {
+    match line {
+        Line::Move {
+            head,
+            length,
+            food,
+            score,
+        } => {
+            let body = &mut world.snek.body;
+            if length < 1 || length > body.len() + 1 || food.len() > cells(&world.options) {
+                return;
+            }
+            body.push(head);
+            let extra = body.len() - length;
+            body.drain(..extra);
+            world.food = food;
+            set_score(world, score);
+        }
+        Line::Snapshot(snapshot) => {
+            if let Some(snapshot) = snapshot.check(&world.options) {
+                world.snek.body = snapshot.body;
+                world.food = snapshot.food;
+                set_score(world, snapshot.score);
+            }
+        }
+        Line::Start { .. } | Line::End => (),
+    }
+}
+
+// Returns how many cells there are on the map
+fn cells(options: &Options) -> usize {
+    options.width as usize * options.height as usize
+}
+
+// Makes the watched snek show a score, by moving where its length starts counting from
+fn set_score(world: &mut World, score: usize) {
+    world.snek.start_len = world.snek.body.len().saturating_sub(score);
+}
+
+// Reads a line (without its newline) that's no longer than `MAX_LINE`,
+// returning `None` at the end of the stream
+pub fn read_line(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if reader.by_ref().take(MAX_LINE).read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    match line.strip_suffix('\n') {
+        Some(line) => Ok(Some(line.to_owned())),
+        None if line.len() as u64 >= MAX_LINE => Err(invalid("line too long")),
+        None => Ok(Some(line)),
+    }
+}
+
+// Returns an error for a malformed broadcast
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{MIN_BOARD, STARTING_BODY};
+
+    // Returns the start of a broadcast of a new game with some options
+    fn start(options: &Options) -> String {
+        let world = World::new(options.clone(), &STARTING_BODY);
+        let start = Line::Start {
+            version: VERSION,
+            options: Box::new(options.clone()),
+        };
+        encode(&start) + &encode(&Line::Snapshot(Snapshot::new(&world)))
+    }
+
+    // Reads the start of a broadcast, returning what was wrong with it if anything
+    fn read(start: &str) -> Result<Options, String> {
+        read_start(&mut Cursor::new(start))
+            .map(|(options, ..)| options)
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn broadcast_start_is_read_back() {
+        let played = Options {
+            seed: 1,
+            ..Options::default()
+        };
+        let (options, snapshot) = read_start(&mut Cursor::new(start(&played))).unwrap();
+        assert_eq!(options, played);
+        assert_eq!((snapshot.score, snapshot.food.len()), (0, 1));
+        assert_eq!(snapshot.body, STARTING_BODY);
+    }
+
+    #[test]
+    fn broadcasts_out_of_range_are_refused() {
+        let good = start(&Options::default());
+        let cases = [
+            ("\"fps\":8", "\"fps\":0"),
+            ("\"width\":17", "\"width\":100000"),
+            ("\"height\":15", "\"height\":2"),
+            ("\"cell_width\":2", "\"cell_width\":9"),
+            ("\"margin_x\":2", "\"margin_x\":4000000000"),
+            ("\"version\":2", "\"version\":1"),
+            ("\"body\":[[0,0]", "\"body\":[[0,0.5]"),
+        ];
+        for (from, to) in cases {
+            assert!(good.contains(from));
+            assert!(
+                read(&good.replace(from, to)).is_err(),
+                "{to} was let through"
+            );
+        }
+        assert!(read("snek-spectate 1\n").is_err());
+        assert!(read(&good.replace("\"score\":0", "\"score\":\"x\"")).is_err());
+        assert_eq!(
+            read(
+                &good
+                    .replace("\"min_board\":\"error\"", "\"min_board\":\"clamp\"")
+                    .replace("\"height\":15", "\"height\":2")
+            )
+            .unwrap()
+            .height,
+            MIN_BOARD
+        );
+    }
+}
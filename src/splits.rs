@@ -0,0 +1,370 @@
+// Speedrun splits: the tick and time (since the game started) the snek first
+// reaches each length milestone, every `SPLIT_LENGTH` parts. While playing,
+// the latest split is shown against the best one for its milestone, and the
+// best splits are kept for each mode and board size. A split that beats the
+// best one there was before the game is a gold.
+//
+// File format (version 1): a `snek-splits 1` header line followed by one best
+// split per line with tab separated `mode`, `board` (as `WIDTHxHEIGHT`),
+// `length`, `ticks`, and `millis` fields.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::options::{Mode, Options};
+use crate::storage;
+
+// Name of the splits file inside the data directory
+pub const SPLITS_FILE: &str = "splits.tsv";
+
+// Header line (including version) of the splits file
+const HEADER: &str = "snek-splits 1";
+
+// How many parts apart the length milestones are
+pub const SPLIT_LENGTH: usize = 5;
+
+// When the snek first reached a length
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Split {
+    pub length: usize,
+    pub ticks: u64,
+    pub millis: u64,
+}
+
+// The best split for a milestone on a board in a mode
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct BestSplit {
+    mode: Mode,
+    width: u32,
+    height: u32,
+    split: Split,
+}
+
+// The best splits for every mode and board they've been played on
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct BestSplits {
+    splits: Vec<BestSplit>,
+}
+
+impl BestSplits {
+    // Loads the best splits from a file, which has none if it doesn't exist
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Loads the best splits from the data directory, ignoring any errors
+    pub fn load_default() -> Self {
+        storage::data_file(SPLITS_FILE)
+            .and_then(|path| Self::load(&path).ok())
+            .unwrap_or_default()
+    }
+
+    // Saves the best splits to a file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        storage::write_atomic(path, &self.serialize())
+    }
+
+    // Reads the best splits from the contents of a splits file, skipping
+    // malformed lines
+    pub fn parse(contents: &str) -> io::Result<Self> {
+        let mut lines = contents.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown splits file version",
+            ));
+        }
+        let splits = lines
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let mode = Mode::from_name(fields.next()?)?;
+                let (width, height) = fields.next()?.split_once('x')?;
+                Some(BestSplit {
+                    mode,
+                    width: width.parse().ok()?,
+                    height: height.parse().ok()?,
+                    split: Split {
+                        length: fields.next()?.parse().ok()?,
+                        ticks: fields.next()?.parse().ok()?,
+                        millis: fields.next()?.parse().ok()?,
+                    },
+                })
+            })
+            .collect();
+        Ok(Self { splits })
+    }
+
+    // Writes the best splits in the splits file format
+    pub fn serialize(&self) -> String {
+        let mut contents = format!("{HEADER}\n");
+        for best in &self.splits {
+            contents += &format!(
+                "{}\t{}x{}\t{}\t{}\t{}\n",
+                best.mode.name(),
+                best.width,
+                best.height,
+                best.split.length,
+                best.split.ticks,
+                best.split.millis
+            );
+        }
+        contents
+    }
+
+    // Returns the best splits for the mode and board of some options, by length
+    pub fn for_board(&self, options: &Options) -> Vec<Split> {
+        let mut splits: Vec<Split> = self
+            .splits
+            .iter()
+            .filter(|best| on_board(best, options))
+            .map(|best| best.split)
+            .collect();
+        splits.sort_by_key(|split| split.length);
+        splits
+    }
+
+    // Keeps any splits from a game on a board that beat the best ones for
+    // their milestones, returning whether there were any
+    pub fn record(&mut self, options: &Options, splits: &[Split]) -> bool {
+        let mut improved = false;
+        for split in splits {
+            let best = self
+                .splits
+                .iter_mut()
+                .find(|best| on_board(best, options) && best.split.length == split.length);
+            match best {
+                Some(best) if best.split.millis <= split.millis => continue,
+                Some(best) => best.split = *split,
+                None => self.splits.push(BestSplit {
+                    mode: options.mode,
+                    width: options.width,
+                    height: options.height,
+                    split: *split,
+                }),
+            }
+            improved = true;
+        }
+        improved
+    }
+}
+
+// Returns whether a best split is for the mode and board of some options
+fn on_board(best: &BestSplit, options: &Options) -> bool {
+    best.mode == options.mode && best.width == options.width && best.height == options.height
+}
+
+// The splits of a game being played, against the best ones from before it
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct SplitTracker {
+    splits: Vec<Split>,
+    best: Vec<Split>,
+    next: usize,
+}
+
+impl SplitTracker {
+    // Starts tracking the splits of a game with a snek of some length, from
+    // the first milestone it hasn't reached yet
+    pub fn new(best: &BestSplits, options: &Options, length: usize) -> Self {
+        Self {
+            splits: Vec::new(),
+            best: best.for_board(options),
+            next: (length / SPLIT_LENGTH + 1) * SPLIT_LENGTH,
+        }
+    }
+
+    // Returns the game's splits so far, in the order they were reached
+    pub fn splits(&self) -> &[Split] {
+        &self.splits
+    }
+
+    // Adds a split if the snek's reached the next milestone, returning whether it has
+    pub fn update(&mut self, length: usize, ticks: u64, elapsed: Duration) -> bool {
+        if length < self.next {
+            return false;
+        }
+        self.splits.push(Split {
+            length: self.next,
+            ticks,
+            millis: elapsed.as_millis() as u64,
+        });
+        self.next += SPLIT_LENGTH;
+        true
+    }
+
+    // Returns how far ahead (negative) or behind (positive) of its best a
+    // split is, in milliseconds, if there's a best to go by
+    pub fn delta(&self, split: &Split) -> Option<i64> {
+        let best = self.best.iter().find(|best| best.length == split.length)?;
+        Some(split.millis as i64 - best.millis as i64)
+    }
+
+    // Returns whether a split beat the best one for its milestone
+    pub fn gold(&self, split: &Split) -> bool {
+        self.delta(split).is_some_and(|delta| delta < 0)
+    }
+
+    // Returns the latest split and how it compares to its best, if there's
+    // been one
+    pub fn latest(&self) -> Option<(Option<i64>, bool)> {
+        let split = self.splits.last()?;
+        Some((self.delta(split), self.gold(split)))
+    }
+
+    // Formats every split for printing, with its delta and any gold flagged
+    pub fn report(&self) -> String {
+        let mut text = "SPLITS".to_owned();
+        for split in &self.splits {
+            let delta = self.delta(split).map_or(String::new(), |delta| {
+                format!("  ({})", format_delta(delta, true))
+            });
+            let gold = match self.gold(split) {
+                true => "  GOLD",
+                false => "",
+            };
+            text += &format!(
+                "\n{:>4}  {:>8}  {:>5} ticks{delta}{gold}",
+                split.length,
+                format_time(split.millis),
+                split.ticks
+            );
+        }
+        text
+    }
+}
+
+// Formats a time as minutes, seconds, and tenths of a second
+pub fn format_time(millis: u64) -> String {
+    let tenths = millis / 100;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+// Formats a delta in milliseconds as signed seconds, to a tenth of a second
+// if it's `precise`, or else to the second
+pub fn format_delta(delta: i64, precise: bool) -> String {
+    let sign = match delta < 0 {
+        true => '-',
+        false => '+',
+    };
+    let tenths = delta.unsigned_abs() / 100;
+    match precise {
+        true => format!("{sign}{}.{}", tenths / 10, tenths % 10),
+        false => format!("{sign}{}", tenths / 10),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns a split reached some seconds in
+    fn split(length: usize, ticks: u64, seconds: u64) -> Split {
+        Split {
+            length,
+            ticks,
+            millis: seconds * 1000,
+        }
+    }
+
+    // Returns the options of a game on a board in a mode
+    fn board(mode: Mode, width: u32) -> Options {
+        Options {
+            mode,
+            width,
+            seed: 1,
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn milestones_are_split_the_first_time_theyre_reached() {
+        let options = board(Mode::Classic, 17);
+        // A snek that starts at 3 parts long first splits at 5
+        let mut tracker = SplitTracker::new(&BestSplits::default(), &options, 3);
+        assert!(!tracker.update(4, 2, Duration::from_secs(1)));
+        assert!(tracker.update(5, 4, Duration::from_secs(2)));
+        // Staying that long, or shrinking back and growing again, isn't a new split
+        assert!(!tracker.update(5, 5, Duration::from_secs(3)));
+        assert!(!tracker.update(9, 12, Duration::from_secs(6)));
+        assert!(tracker.update(10, 15, Duration::from_millis(7_250)));
+        assert!(tracker.update(15, 30, Duration::from_secs(14)));
+        assert_eq!(
+            tracker.splits(),
+            [
+                split(5, 4, 2),
+                Split {
+                    length: 10,
+                    ticks: 15,
+                    millis: 7_250
+                },
+                split(15, 30, 14),
+            ]
+        );
+        // A snek that starts past a milestone skips it
+        let tracker = SplitTracker::new(&BestSplits::default(), &options, 5);
+        assert_eq!(tracker.next, 10);
+    }
+
+    #[test]
+    fn golds_beat_the_best_from_before_the_game() {
+        let options = board(Mode::Classic, 17);
+        let mut best = BestSplits::default();
+        assert!(best.record(&options, &[split(5, 4, 3), split(10, 15, 9)]));
+        // Other boards and modes keep their own
+        best.record(&board(Mode::Classic, 20), &[split(5, 4, 1)]);
+        best.record(&board(Mode::Rush, 17), &[split(5, 4, 1)]);
+        let mut tracker = SplitTracker::new(&best, &options, 3);
+        assert_eq!(tracker.latest(), None);
+        tracker.update(5, 4, Duration::from_secs(2));
+        assert_eq!(tracker.latest(), Some((Some(-1000), true)));
+        tracker.update(10, 16, Duration::from_secs(9));
+        assert_eq!(tracker.latest(), Some((Some(0), false)));
+        tracker.update(15, 30, Duration::from_secs(14));
+        assert_eq!(tracker.latest(), Some((None, false)));
+        assert_eq!(
+            tracker.report(),
+            "SPLITS\n   5    0:02.0      4 ticks  (-1.0)  GOLD\n  10    0:09.0     16 ticks  (+0.0)\n  \
+            15    0:14.0     30 ticks"
+        );
+        // Only the gold and the new milestone are kept as the best
+        assert!(best.record(&options, tracker.splits()));
+        assert_eq!(
+            best.for_board(&options),
+            [split(5, 4, 2), split(10, 15, 9), split(15, 30, 14)]
+        );
+        assert!(!best.record(&options, &[split(5, 9, 8)]));
+    }
+
+    #[test]
+    fn best_splits_round_trip() {
+        let mut best = BestSplits::default();
+        best.record(
+            &board(Mode::Classic, 17),
+            &[split(5, 4, 3), split(10, 15, 9)],
+        );
+        best.record(&board(Mode::Twin, 30), &[split(5, 6, 4)]);
+        let serialized = best.serialize();
+        assert_eq!(
+            serialized,
+            "snek-splits 1\nclassic\t17x15\t5\t4\t3000\nclassic\t17x15\t10\t15\t9000\n\
+            twin\t30x15\t5\t6\t4000\n"
+        );
+        assert_eq!(BestSplits::parse(&serialized).unwrap(), best);
+        // Lines that can't be read are skipped, but not a file of another version
+        let damaged = serialized.replace("twin", "nope") + "classic\t17x15\n";
+        assert_eq!(BestSplits::parse(&damaged).unwrap().splits.len(), 2);
+        assert!(BestSplits::parse("snek-splits 2\n").is_err());
+    }
+
+    #[test]
+    fn deltas_are_signed_seconds() {
+        assert_eq!(format_delta(-1_250, true), "-1.2");
+        assert_eq!(format_delta(61_000, false), "+61");
+        assert_eq!(format_time(83_450), "1:23.4");
+    }
+}
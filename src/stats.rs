@@ -0,0 +1,309 @@
+// Cumulative statistics over every game played, kept in the data directory.
+//
+// File format (version 1): a `snek-stats 1` header line followed by one
+// `key value` pair per line. Unknown keys are ignored so older versions of the
+// game can still read newer files. Every unlocked achievement has its own
+// `achievement id` line.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::achievements::{self, Achievement};
+use crate::history::HistoryEntry;
+use crate::storage;
+use crate::summary::GameSummary;
+use crate::world::DeathCause;
+
+// Name of the stats file inside the data directory
+pub const STATS_FILE: &str = "stats.txt";
+
+// Header line (including version) of the stats file
+const HEADER: &str = "snek-stats 1";
+
+// Statistics accumulated over every game
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct Stats {
+    pub games_played: u64,
+    pub pellets_eaten: u64,
+    pub ticks: u64,
+    pub wall_deaths: u64,
+    pub body_deaths: u64,
+    pub quits: u64,
+    pub wins: u64,
+    pub longest_snek: usize,
+    pub longest_game: u64,
+    pub current_streak: u64,
+    pub best_streak: u64,
+    pub last_day: Option<u64>,
+    pub achievements: Vec<String>,
+}
+
+impl Stats {
+    // Loads the stats from a file, which are empty if the file doesn't exist
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Loads the stats from the data directory, ignoring any errors
+    pub fn load_default() -> Self {
+        storage::data_file(STATS_FILE)
+            .and_then(|path| Self::load(&path).ok())
+            .unwrap_or_default()
+    }
+
+    // Saves the stats to a file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        storage::write_atomic(path, &self.serialize())
+    }
+
+    // Reads the stats from the contents of a stats file
+    pub fn parse(contents: &str) -> io::Result<Self> {
+        let mut lines = contents.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown stats file version",
+            ));
+        }
+        let mut stats = Self::default();
+        for line in lines {
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+            if key == "achievement" {
+                stats.achievements.push(value.to_owned());
+                continue;
+            }
+            let Ok(value) = value.parse::<u64>() else {
+                continue;
+            };
+            match key {
+                "games_played" => stats.games_played = value,
+                "pellets_eaten" => stats.pellets_eaten = value,
+                "ticks" => stats.ticks = value,
+                "wall_deaths" => stats.wall_deaths = value,
+                "body_deaths" => stats.body_deaths = value,
+                "quits" => stats.quits = value,
+                "wins" => stats.wins = value,
+                "longest_snek" => stats.longest_snek = value as usize,
+                "longest_game" => stats.longest_game = value,
+                "current_streak" => stats.current_streak = value,
+                "best_streak" => stats.best_streak = value,
+                "last_day" => stats.last_day = Some(value),
+                _ => (),
+            }
+        }
+        Ok(stats)
+    }
+
+    // Writes the stats in the stats file format
+    pub fn serialize(&self) -> String {
+        let mut contents = format!(
+            "{HEADER}\ngames_played {}\npellets_eaten {}\nticks {}\nwall_deaths {}\n\
+            body_deaths {}\nquits {}\nwins {}\nlongest_snek {}\nlongest_game {}\ncurrent_streak {}\n\
+            best_streak {}\n",
+            self.games_played,
+            self.pellets_eaten,
+            self.ticks,
+            self.wall_deaths,
+            self.body_deaths,
+            self.quits,
+            self.wins,
+            self.longest_snek,
+            self.longest_game,
+            self.current_streak,
+            self.best_streak
+        );
+        if let Some(day) = self.last_day {
+            contents += &format!("last_day {day}\n");
+        }
+        for id in &self.achievements {
+            contents += &format!("achievement {id}\n");
+        }
+        contents
+    }
+
+    // Adds a finished game (played on the given day since the unix epoch),
+    // returning the achievements it unlocked
+    pub fn record(&mut self, summary: &GameSummary, day: u64) -> Vec<&'static Achievement> {
+        self.games_played += 1;
+        self.pellets_eaten += summary.eaten as u64;
+        self.ticks += summary.ticks;
+        match (summary.won, summary.cause) {
+            (true, _) => self.wins += 1,
+            (false, Some(DeathCause::Wall)) => self.wall_deaths += 1,
+            (false, Some(DeathCause::Body)) => self.body_deaths += 1,
+            (false, None) => self.quits += 1,
+        }
+        self.longest_snek = self.longest_snek.max(summary.length);
+        self.longest_game = self.longest_game.max(summary.duration);
+        self.current_streak = match self.last_day {
+            Some(last) if last == day => self.current_streak,
+            Some(last) if last + 1 == day => self.current_streak + 1,
+            _ => 1,
+        };
+        self.best_streak = self.best_streak.max(self.current_streak);
+        self.last_day = Some(day);
+        let unlocked: Vec<&Achievement> =
+            achievements::newly_earned(summary, &self.achievements).collect();
+        for achievement in &unlocked {
+            self.achievements.push(achievement.id.to_owned());
+        }
+        unlocked
+    }
+
+    // Works the stats out again from scratch from the games in the history
+    // (each on the day it was played), which comes out the same as recording
+    // them one at a time as long as none have dropped out of it
+    pub fn rebuild(entries: &[HistoryEntry]) -> Self {
+        let mut stats = Self::default();
+        for entry in entries {
+            stats.record(&entry.summary, storage::day(entry.summary.date));
+        }
+        stats
+    }
+
+    // Returns the current daily streak, which is broken if a day was missed
+    pub fn streak(&self, today: u64) -> u64 {
+        match self.last_day {
+            Some(last) if last + 1 >= today => self.current_streak,
+            _ => 0,
+        }
+    }
+
+    // Returns a few short lines worth showing on the start screen
+    pub fn highlights(&self, today: u64) -> Vec<String> {
+        if self.games_played == 0 {
+            return Vec::new();
+        }
+        vec![
+            format!("GAMES: {}", self.games_played),
+            format!("LONGEST: {}", self.longest_snek),
+            format!("STREAK: {}", self.streak(today)),
+        ]
+    }
+
+    // Formats all of the stats for printing
+    pub fn format(&self, today: u64) -> String {
+        format!(
+            "Games played: {}\nPellets eaten: {}\nTicks: {}\nDeaths by wall: {}\n\
+            Deaths by body: {}\nQuits: {}\nWins: {}\nLongest snek: {}\nLongest game: {}:{:02}\n\
+            Daily streak: {} (best {})\nAchievements: {}/{}",
+            self.games_played,
+            self.pellets_eaten,
+            self.ticks,
+            self.wall_deaths,
+            self.body_deaths,
+            self.quits,
+            self.wins,
+            self.longest_snek,
+            self.longest_game / 60,
+            self.longest_game % 60,
+            self.streak(today),
+            self.best_streak,
+            self.achievements.len(),
+            achievements::ACHIEVEMENTS.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_adds_up_games() {
+        let mut stats = Stats::default();
+        let won = GameSummary {
+            won: true,
+            cause: None,
+            length: 12,
+            ..GameSummary::example()
+        };
+        let quit = GameSummary {
+            cause: None,
+            duration: 300,
+            ..GameSummary::example()
+        };
+        for summary in [GameSummary::example(), won, quit] {
+            stats.record(&summary, 100);
+        }
+        assert_eq!(stats.games_played, 3);
+        assert_eq!(stats.pellets_eaten, 9);
+        assert_eq!(stats.ticks, 120);
+        assert_eq!((stats.wall_deaths, stats.body_deaths), (1, 0));
+        assert_eq!((stats.wins, stats.quits), (1, 1));
+        assert_eq!(stats.longest_snek, 12);
+        assert_eq!(stats.longest_game, 300);
+    }
+
+    #[test]
+    fn streak_counts_days_in_a_row() {
+        let mut stats = Stats::default();
+        let summary = GameSummary::example();
+        stats.record(&summary, 10);
+        stats.record(&summary, 10);
+        stats.record(&summary, 11);
+        stats.record(&summary, 12);
+        assert_eq!(stats.streak(12), 3);
+        assert_eq!(stats.streak(13), 3);
+        assert_eq!(stats.streak(14), 0);
+        stats.record(&summary, 20);
+        assert_eq!((stats.current_streak, stats.best_streak), (1, 3));
+    }
+
+    #[test]
+    fn serialize_round_trips() {
+        let mut stats = Stats::default();
+        stats.record(&GameSummary::example(), 19_000);
+        assert!(!stats.achievements.is_empty());
+        assert_eq!(Stats::parse(&stats.serialize()).unwrap(), stats);
+        assert!(Stats::parse("snek-stats 2\n").is_err());
+    }
+
+    #[test]
+    fn parse_skips_unknown_keys() {
+        let stats = Stats::parse("snek-stats 1\ngames_played 4\nfrom_the_future 1\nwins x\n");
+        assert_eq!(stats.unwrap().games_played, 4);
+    }
+
+    #[test]
+    fn rebuilding_matches_recording_as_they_went() {
+        let day = 86_400;
+        let summaries = [
+            GameSummary::example(),
+            GameSummary {
+                date: GameSummary::example().date + 60,
+                cause: Some(DeathCause::Body),
+                length: 15,
+                ..GameSummary::example()
+            },
+            GameSummary {
+                date: GameSummary::example().date + day,
+                won: true,
+                cause: None,
+                duration: 400,
+                ..GameSummary::example()
+            },
+            GameSummary {
+                date: GameSummary::example().date + 5 * day,
+                cause: None,
+                ..GameSummary::example()
+            },
+        ];
+        let mut stats = Stats::default();
+        let mut entries = Vec::new();
+        for summary in summaries {
+            stats.record(&summary, storage::day(summary.date));
+            entries.push(HistoryEntry::new(&summary, &Default::default()));
+        }
+        let rebuilt = Stats::rebuild(&entries);
+        assert_eq!(rebuilt, stats);
+        assert_eq!((rebuilt.games_played, rebuilt.best_streak), (4, 2));
+    }
+}
@@ -0,0 +1,197 @@
+// Where snek keeps its files. Config goes in the platform's config directory,
+// scores and everything else worth keeping in its data directory, and crash
+// reports and other things that can be thrown away in its cache directory
+// (`$XDG_CONFIG_HOME/snek`, `$XDG_DATA_HOME/snek`, and `$XDG_CACHE_HOME/snek`
+// on Linux, and their equivalents on macOS and Windows). With `--portable`,
+// all of them are the `snek-data` directory next to the executable instead.
+// Directories are made when something's first written to them, and on Unix
+// only the user can look inside.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use directories::ProjectDirs;
+use serde::Deserialize;
+
+// Name of the directory next to the executable that portable snek keeps its files in
+const PORTABLE_DIR: &str = "snek-data";
+
+// The directory every file goes in, once snek's been made portable
+static PORTABLE: OnceLock<PathBuf> = OnceLock::new();
+
+// Keeps every file in the directory next to the executable from now on
+pub fn use_portable() -> io::Result<()> {
+    let exe = env::current_exe()?;
+    let dir = exe
+        .parent()
+        .ok_or_else(|| io::Error::other("the executable isn't in a directory"))?;
+    let _ = PORTABLE.set(dir.join(PORTABLE_DIR));
+    Ok(())
+}
+
+// Returns one of the platform's directories for snek (or the portable
+// directory), if it can be found
+fn dir(pick: fn(&ProjectDirs) -> &Path) -> Option<PathBuf> {
+    match PORTABLE.get() {
+        Some(dir) => Some(dir.clone()),
+        None => ProjectDirs::from("", "", "snek").map(|dirs| pick(&dirs).to_owned()),
+    }
+}
+
+// Returns the directory config files are kept in, if one can be found
+pub fn config_dir() -> Option<PathBuf> {
+    dir(ProjectDirs::config_dir)
+}
+
+// Returns the directory persistent files are kept in, if one can be found
+pub fn data_dir() -> Option<PathBuf> {
+    dir(ProjectDirs::data_dir)
+}
+
+// Returns the directory files that can be thrown away are kept in, if one can be found
+pub fn cache_dir() -> Option<PathBuf> {
+    dir(ProjectDirs::cache_dir)
+}
+
+// Returns the path of a file in the config directory, or in the data
+// directory if it's only there (where config files were kept before)
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    let path = config_dir()?.join(name);
+    match data_file(name) {
+        Some(old) if !path.exists() && old.exists() => Some(old),
+        _ => Some(path),
+    }
+}
+
+// Returns the path of a file in the data directory
+pub fn data_file(name: &str) -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(name))
+}
+
+// Makes a directory and any missing parents, which only the user can look
+// inside on Unix
+pub fn create_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+// Writes a file by writing a temporary one next to it and renaming it over
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir(parent)?;
+    }
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    fs::write(&temp, contents)?;
+    fs::rename(&temp, path)
+}
+
+// Returns the version of a file written as a JSON object, kept in its
+// `version` field, if it has one
+pub fn json_version(contents: &str) -> Option<u32> {
+    #[derive(Deserialize)]
+    struct Versioned {
+        version: u32,
+    }
+    serde_json::from_str::<Versioned>(contents)
+        .ok()
+        .map(|versioned| versioned.version)
+}
+
+// Returns the current time in seconds since the unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+// Returns the current UTC day in days since the unix epoch
+pub fn today() -> u64 {
+    day(now())
+}
+
+// Returns the UTC day (in days since the unix epoch) some seconds since the
+// unix epoch are in
+pub fn day(secs: u64) -> u64 {
+    secs / 86400
+}
+
+// Formats seconds since the unix epoch as a UTC date (YYYY-MM-DD)
+pub fn format_date(secs: u64) -> String {
+    let (year, month, day) = civil_date(day(secs));
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+// Formats seconds since the unix epoch as a UTC date and time (YYYY-MM-DD HH:MM:SS)
+pub fn format_time(secs: u64) -> String {
+    let time = secs % 86400;
+    format!(
+        "{} {:02}:{:02}:{:02}",
+        format_date(secs),
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+// Converts days since the unix epoch to a (year, month, day) date
+pub fn civil_date(days: u64) -> (i64, u32, u32) {
+    let days = days as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    #[test]
+    fn write_atomic_makes_the_directory_and_replaces_the_file() {
+        let dir = env::temp_dir().join(format!("snek-storage-{}", process::id()));
+        let path = dir.join("nested").join("file.txt");
+        write_atomic(&path, "first").unwrap();
+        write_atomic(&path, "second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert!(!dir.join("nested").join("file.txt.tmp").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(path.parent().unwrap())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dates_are_utc() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(19_782), (2024, 2, 29));
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_time(1_710_045_000), "2024-03-10 04:30:00");
+        assert_eq!(day(86_399), 0);
+        assert_eq!(day(86_400), 1);
+    }
+}
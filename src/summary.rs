@@ -0,0 +1,311 @@
+use serde::{Deserialize, Serialize};
+
+use crate::options::{Assist, Difficulty, Mode};
+use crate::scores::ScoreEntry;
+use crate::share::ShareCode;
+use crate::storage;
+use crate::world::{DeathCause, World};
+
+// Version of the JSON results schema, bumped whenever a field changes meaning or goes away
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+// Everything worth knowing about a finished game (written in JSON as its
+// results, which leave out the goal)
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(into = "Results", try_from = "Results")]
+pub struct GameSummary {
+    pub name: String,
+    pub score: usize,
+    pub length: usize,
+    pub eaten: usize,
+    pub ticks: u64,
+    pub duration: u64,
+    pub cause: Option<DeathCause>,
+    pub won: bool,
+    pub goal: Option<usize>,
+    pub touched_body: bool,
+    pub difficulty: Difficulty,
+    pub mode: Mode,
+    pub assist: Option<Assist>,
+    pub width: u32,
+    pub height: u32,
+    pub seed: u64,
+    pub date: u64,
+}
+
+impl GameSummary {
+    // Summarizes the game played in a world so far, which has taken some seconds
+    pub fn new(world: &World, duration: u64) -> Self {
+        Self {
+            name: world.options.name.clone(),
+            score: world.score(),
+            length: world.snek.body.len(),
+            eaten: world.eaten,
+            ticks: world.ticks,
+            duration,
+            cause: world.cause,
+            won: world.won,
+            goal: world.options.goal,
+            touched_body: world.touched_body,
+            difficulty: world.options.difficulty,
+            mode: world.options.mode,
+            assist: world.options.assist,
+            width: world.width(),
+            height: world.height(),
+            seed: world.options.seed,
+            date: storage::now(),
+        }
+    }
+
+    // Returns the score table entry for the game
+    pub fn score_entry(&self) -> ScoreEntry {
+        ScoreEntry {
+            name: self.name.clone(),
+            score: self.score,
+            mode: self.mode,
+            width: self.width,
+            height: self.height,
+            seed: self.seed,
+            date: self.date,
+            duration: self.duration,
+        }
+    }
+
+    // Returns the share code for playing the same game
+    pub fn share_code(&self) -> String {
+        ShareCode {
+            seed: self.seed,
+            width: self.width as u16,
+            height: self.height as u16,
+            mode: self.mode,
+        }
+        .encode()
+    }
+
+    // Returns how the game ended ("died", "quit", or "won")
+    pub fn outcome(&self) -> &'static str {
+        match (self.won, self.cause) {
+            (true, _) => "won",
+            (false, Some(_)) => "died",
+            (false, None) => "quit",
+        }
+    }
+
+    // Returns the results of the game as a single line JSON object
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    // Returns the post-game stats shown after the end message
+    pub fn format(&self) -> String {
+        let cause = match (self.won, self.cause, self.goal) {
+            (true, _, _) if self.mode == Mode::Rush => "surviving the rush".to_owned(),
+            (true, _, Some(goal)) => format!("eating {goal} pellets"),
+            (true, _, None) => "winning".to_owned(),
+            (false, Some(cause), _) => cause.description().to_owned(),
+            (false, None, _) => "quitting".to_owned(),
+        };
+        format!(
+            "Difficulty: {}\nLength: {}\nPellets eaten: {}\nTicks: {}\nTime: {}:{:02}\nEnded by: {}\n\
+            Share code: {}",
+            self.difficulty.name(),
+            self.length,
+            self.eaten,
+            self.ticks,
+            self.duration / 60,
+            self.duration % 60,
+            cause,
+            self.share_code()
+        ) + &self.assist.map_or(String::new(), |assist| {
+            format!("\nAssist: {} (kept out of the high scores)", assist.name())
+        })
+    }
+}
+
+// The results of a game, in the JSON results schema
+#[derive(Serialize, Deserialize)]
+struct Results {
+    schema_version: u32,
+    name: String,
+    score: usize,
+    length: usize,
+    outcome: String,
+    cause: Option<DeathCause>,
+    duration: u64,
+    ticks: u64,
+    // A string, since it may not fit in a double
+    seed: String,
+    code: String,
+    mode: Mode,
+    // Results from before assists don't have one
+    #[serde(default)]
+    assist: Option<Assist>,
+    difficulty: Difficulty,
+    board: Board,
+    stats: Stats,
+    date: u64,
+}
+
+// The size of a game's board, in its results
+#[derive(Serialize, Deserialize)]
+struct Board {
+    width: u32,
+    height: u32,
+}
+
+// How a game went, in its results
+#[derive(Serialize, Deserialize)]
+struct Stats {
+    eaten: usize,
+    touched_body: bool,
+}
+
+impl From<GameSummary> for Results {
+    fn from(summary: GameSummary) -> Self {
+        Self {
+            schema_version: JSON_SCHEMA_VERSION,
+            outcome: summary.outcome().to_owned(),
+            cause: summary.cause,
+            duration: summary.duration,
+            ticks: summary.ticks,
+            seed: summary.seed.to_string(),
+            code: summary.share_code(),
+            mode: summary.mode,
+            assist: summary.assist,
+            difficulty: summary.difficulty,
+            board: Board {
+                width: summary.width,
+                height: summary.height,
+            },
+            stats: Stats {
+                eaten: summary.eaten,
+                touched_body: summary.touched_body,
+            },
+            date: summary.date,
+            score: summary.score,
+            length: summary.length,
+            name: summary.name,
+        }
+    }
+}
+
+impl TryFrom<Results> for GameSummary {
+    type Error = String;
+
+    fn try_from(results: Results) -> Result<Self, String> {
+        Ok(Self {
+            seed: results
+                .seed
+                .parse()
+                .map_err(|_| format!("invalid seed '{}'", results.seed))?,
+            name: results.name,
+            score: results.score,
+            length: results.length,
+            eaten: results.stats.eaten,
+            ticks: results.ticks,
+            duration: results.duration,
+            cause: results.cause,
+            won: results.outcome == "won",
+            goal: None,
+            touched_body: results.stats.touched_body,
+            difficulty: results.difficulty,
+            mode: results.mode,
+            assist: results.assist,
+            width: results.board.width,
+            height: results.board.height,
+            date: results.date,
+        })
+    }
+}
+
+#[cfg(test)]
+impl GameSummary {
+    // Returns the summary of a short classic game that died on a wall, for
+    // tests to change what they need in
+    pub fn example() -> Self {
+        Self {
+            name: "snek".to_owned(),
+            score: 3,
+            length: 7,
+            eaten: 3,
+            ticks: 40,
+            duration: 65,
+            cause: Some(DeathCause::Wall),
+            won: false,
+            goal: None,
+            touched_body: false,
+            difficulty: Difficulty::Normal,
+            mode: Mode::Classic,
+            assist: None,
+            width: 17,
+            height: 15,
+            seed: 42,
+            date: 1_700_000_000,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::options::Options;
+    use crate::{Point, STARTING_BODY};
+
+    #[test]
+    fn json_has_every_field() {
+        // The snek eats the food in its way and runs into the right wall
+        let options = Options {
+            name: "tester".to_owned(),
+            seed: 99,
+            food_sequence: vec![Point::new(8, 0), Point::new(5, 5)],
+            ..Options::default()
+        };
+        let mut world = World::new(options, &STARTING_BODY);
+        while world.running() {
+            world.advance();
+            world.check_death();
+        }
+        let summary = GameSummary {
+            date: 1_700_000_000,
+            ..GameSummary::new(&world, 3)
+        };
+        let json: Value = serde_json::from_str(&summary.to_json()).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "schema_version": JSON_SCHEMA_VERSION,
+                "name": "tester",
+                "score": 1,
+                "length": 5,
+                "outcome": "died",
+                "cause": "wall",
+                "duration": 3,
+                "ticks": 14,
+                "seed": "99",
+                "code": summary.share_code(),
+                "mode": "classic",
+                "assist": null,
+                "difficulty": "normal",
+                "board": {"width": 17, "height": 15},
+                "stats": {"eaten": 1, "touched_body": false},
+                "date": 1_700_000_000,
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<GameSummary>(json).unwrap(),
+            summary
+        );
+    }
+
+    #[test]
+    fn results_from_before_assists_still_load() {
+        let mut json = serde_json::to_value(GameSummary::example()).unwrap();
+        json.as_object_mut().unwrap().remove("assist");
+        let summary: GameSummary = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(summary, GameSummary::example());
+        json["seed"] = "lots".into();
+        assert!(serde_json::from_value::<GameSummary>(json).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::Point;
+
+// How many frames a cell the tail left takes to fade back into the map
+const TRAIL_FRAMES: u32 = 8;
+
+// The cells the snek's tail just left, fading away (with `--trail-fade`)
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Trail {
+    cells: Vec<(Point, u32)>,
+}
+
+impl Trail {
+    // Starts fading the cells a snek's body left when it moved, going by
+    // where the body was before and after
+    pub fn vacate(&mut self, before: &[Point], after: &[Point]) {
+        for cell in before.iter().filter(|cell| !after.contains(cell)) {
+            self.cells.retain(|(other, _)| other != cell);
+            self.cells.push((*cell, 0));
+        }
+    }
+
+    // Ages every cell by a frame, forgetting the ones that have faded away
+    pub fn frame(&mut self) {
+        for (_, age) in &mut self.cells {
+            *age += 1;
+        }
+        self.cells.retain(|(_, age)| *age < TRAIL_FRAMES);
+    }
+
+    // Returns the cells that are still fading, with how far along they are
+    // from 0 (just left) to 1 (faded away)
+    pub fn cells(&self) -> impl Iterator<Item = (Point, f32)> + '_ {
+        self.cells
+            .iter()
+            .map(|(cell, age)| (*cell, *age as f32 / TRAIL_FRAMES as f32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns the trail's cells with how far they've faded
+    fn faded(trail: &Trail) -> Vec<(Point, f32)> {
+        trail.cells().collect()
+    }
+
+    #[test]
+    fn vacated_cells_fade_then_go_away() {
+        let mut trail = Trail::default();
+        let before = [Point::new(0, 0), Point::new(1, 0), Point::new(2, 0)];
+        let after = [Point::new(1, 0), Point::new(2, 0), Point::new(3, 0)];
+        trail.vacate(&before, &after);
+        assert_eq!(faded(&trail), [(Point::new(0, 0), 0.0)]);
+        let mut last = 0.0;
+        for _ in 1..TRAIL_FRAMES {
+            trail.frame();
+            let fade = faded(&trail)[0].1;
+            assert!(fade > last && fade < 1.0);
+            last = fade;
+        }
+        trail.frame();
+        assert_eq!(faded(&trail), []);
+    }
+
+    #[test]
+    fn leaving_a_cell_again_starts_it_over() {
+        let mut trail = Trail::default();
+        let cell = Point::new(4, 4);
+        trail.vacate(&[cell], &[]);
+        trail.frame();
+        trail.frame();
+        trail.vacate(&[cell, Point::new(5, 4)], &[Point::new(5, 4)]);
+        assert_eq!(faded(&trail), [(cell, 0.0)]);
+    }
+}
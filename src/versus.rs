@@ -0,0 +1,417 @@
+// Head-to-head games over the network (`snek host` and `snek join`).
+//
+// Both players run the same arena from the same options (the host's, including
+// its seed), so only directions cross the network. Play is in lockstep: every
+// tick, each side sends the direction its snek will move in and waits for the
+// other's before stepping, so a slow connection slows the game down rather
+// than letting the two boards drift apart.
+//
+// Every message is a JSON object on its own line, with a `type` field. The
+// guest starts by sending `{"type":"hello","version":2}`, and the host answers
+// with the same hello and `{"type":"game","options":OPTIONS}`, where OPTIONS
+// are its options (like in a save file). After that, each side sends
+//
+//     {"type":"turn","tick":TICK,"direction":"up"}
+//
+// for every tick, and `{"type":"quit"}` if its player gives up. If the
+// connection drops, the game pauses while the guest tries to reconnect for
+// `RECONNECT_WINDOW`. Once it has, both sides send the hello again and
+// `{"type":"resume","tick":TICK}` with the first tick they're missing the
+// other's turn for, and the other sends its turns from there on again.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use console_engine::{Color, ConsoleEngine, KeyCode};
+use serde::{Deserialize, Serialize};
+
+use crate::arena::{Arena, ArenaOutcome};
+use crate::input;
+use crate::options::{Mode, Options};
+use crate::render;
+use crate::world::Direction;
+use crate::{
+    DOWN_KEY, DRAW_PROMPT, FOOD_COLOR, GAME_PROMPT, INPUT_POLLS, LEFT_KEY, LOSE_PROMPT, QUIT_KEY,
+    RECONNECTING_PROMPT, RIGHT_KEY, RIVAL_COLOR, SNEK_COLORS, UP_KEY, WAITING_PROMPT, WIN_PROMPT,
+};
+
+// Version of the protocol, sent in the hello
+const VERSION: u32 = 2;
+
+// How long the other player gets to send the game before the guest gives up
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How long a dropped game waits for the players to reconnect
+const RECONNECT_WINDOW: Duration = Duration::from_secs(30);
+
+// How long the guest waits between tries to reconnect
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Which side of a game this snek is on (hosting on a port, or joining an address)
+pub enum Role {
+    Host(u16),
+    Guest(String),
+}
+
+// How a head-to-head game ended for this player
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VersusEnd {
+    Won,
+    Lost,
+    Draw,
+    OpponentQuit,
+    Quit,
+    Disconnected,
+}
+
+impl VersusEnd {
+    // Returns what's printed once the game has closed
+    pub fn message(&self) -> &'static str {
+        match self {
+            VersusEnd::Won => "You won!",
+            VersusEnd::Lost => "You lost",
+            VersusEnd::Draw => "It's a draw",
+            VersusEnd::OpponentQuit => "The other player quit, so you win",
+            VersusEnd::Quit => "You quit",
+            VersusEnd::Disconnected => "Lost the connection to the other player",
+        }
+    }
+}
+
+// A message between the players
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Hello {
+        version: u32,
+    },
+    Game {
+        #[serde(deserialize_with = "Options::deserialize_checked")]
+        options: Box<Options>,
+    },
+    Turn {
+        tick: usize,
+        direction: Direction,
+    },
+    Resume {
+        tick: usize,
+    },
+    Quit,
+}
+
+// The hello each side starts with
+const HELLO: Message = Message::Hello { version: VERSION };
+
+// A connection to the other player, with its lines read on another thread
+struct Link {
+    stream: TcpStream,
+    lines: Receiver<Option<String>>,
+    greeted: bool,
+}
+
+impl Link {
+    // Starts reading lines from a connection; `None` is sent once it ends or breaks
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        let reader = BufReader::new(stream.try_clone()?);
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(Some(line)).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(None);
+        });
+        Ok(Self {
+            stream,
+            lines,
+            greeted: false,
+        })
+    }
+
+    // Sends a message, returning whether it could be
+    fn send(&mut self, message: &Message) -> bool {
+        let line = serde_json::to_string(message).unwrap_or_default();
+        writeln!(self.stream, "{line}").is_ok()
+    }
+
+    // Waits a while for the next message, erroring if there isn't one or it's malformed
+    fn receive(&self) -> io::Result<Message> {
+        match self.lines.recv_timeout(HANDSHAKE_TIMEOUT) {
+            Ok(Some(line)) => serde_json::from_str(&line).map_err(|err| invalid(&err.to_string())),
+            _ => Err(invalid("the other player didn't answer")),
+        }
+    }
+}
+
+// A head-to-head game in progress
+struct Match {
+    arena: Arena,
+    me: usize,
+    role: Role,
+    engine: ConsoleEngine,
+    listener: Option<TcpListener>,
+    link: Option<Link>,
+    turns: [Vec<Direction>; 2],
+    steering: Direction,
+    lost_at: Instant,
+    last_retry: Option<Instant>,
+}
+
+// Plays a head-to-head game, returning how it ended for this player
+pub fn play(role: Role, options: Options) -> io::Result<VersusEnd> {
+    let (link, listener, mut options) = match &role {
+        Role::Host(port) => {
+            let listener = TcpListener::bind(("0.0.0.0", *port))?;
+            eprintln!("Waiting for the other player on port {port}");
+            let mut link = Link::new(listener.accept()?.0)?;
+            if !matches!(link.receive(), Ok(Message::Hello { version: VERSION })) {
+                return Err(invalid("that isn't a snek"));
+            }
+            let game = Message::Game {
+                options: Box::new(options.clone()),
+            };
+            link.send(&HELLO);
+            link.send(&game);
+            (link, Some(listener), options)
+        }
+        Role::Guest(address) => {
+            let mut link = Link::new(TcpStream::connect(address)?)?;
+            link.send(&HELLO);
+            if !matches!(link.receive(), Ok(Message::Hello { version: VERSION })) {
+                return Err(invalid("that isn't a snek hosting a game"));
+            }
+            // The other end might not be a snek that checked its options, so
+            // they're checked as they're read
+            let Message::Game { options } = link.receive()? else {
+                return Err(invalid("that isn't a snek hosting a game"));
+            };
+            let options = *options;
+            if let Err(err) = options.check_fits() {
+                link.send(&Message::Quit);
+                return Err(io::Error::other(err));
+            }
+            (link, None, options)
+        }
+    };
+    let mut link = link;
+    link.greeted = true;
+    options.mode = Mode::Classic;
+    let (width, height) = options.screen_size();
+    let engine = ConsoleEngine::init(width, height, options.fps * INPUT_POLLS)?;
+    let me = match role {
+        Role::Host(_) => 0,
+        Role::Guest(_) => 1,
+    };
+    let arena = Arena::new(options);
+    let mut game = Match {
+        steering: arena.sneks[me].direction,
+        arena,
+        me,
+        role,
+        engine,
+        listener,
+        link: Some(link),
+        turns: [Vec::new(), Vec::new()],
+        lost_at: Instant::now(),
+        last_retry: None,
+    };
+    game.engine.set_title("SNEK");
+    Ok(game.run())
+}
+
+impl Match {
+    // Plays the game out in lockstep with the other player
+    fn run(&mut self) -> VersusEnd {
+        let other = 1 - self.me;
+        loop {
+            for _ in 0..INPUT_POLLS {
+                if let Some(end) = self.frame(GAME_PROMPT) {
+                    return end;
+                }
+            }
+            let tick = self.arena.ticks;
+            self.turns[self.me].push(self.steering);
+            self.send_turns(tick as usize);
+            let mut waited = 0;
+            while self.turns[other].len() as u64 <= tick {
+                let prompt = match (&self.link, waited >= INPUT_POLLS) {
+                    (None, _) => RECONNECTING_PROMPT,
+                    (Some(_), true) => WAITING_PROMPT,
+                    (Some(_), false) => GAME_PROMPT,
+                };
+                if let Some(end) = self.frame(prompt) {
+                    return end;
+                }
+                waited += 1;
+            }
+            let tick = tick as usize;
+            self.arena
+                .advance(&[self.turns[0][tick], self.turns[1][tick]]);
+            if let Some(outcome) = self.arena.outcome() {
+                return self.finish(outcome);
+            }
+        }
+    }
+
+    // Draws a frame and deals with keys and the other player's messages,
+    // returning how the game ended if it just did
+    fn frame(&mut self, prompt: &str) -> Option<VersusEnd> {
+        self.draw(prompt);
+        if input::pressed(&self.engine, QUIT_KEY) {
+            if let Some(link) = &mut self.link {
+                link.send(&Message::Quit);
+            }
+            return Some(VersusEnd::Quit);
+        }
+        let keys = [
+            (UP_KEY, Direction::Up),
+            (DOWN_KEY, Direction::Down),
+            (LEFT_KEY, Direction::Left),
+            (RIGHT_KEY, Direction::Right),
+        ];
+        if let Some((_, direction)) = keys
+            .into_iter()
+            .find(|(key, _)| input::pressed(&self.engine, *key))
+        {
+            self.steering = direction;
+        }
+        match self.link {
+            Some(_) => self.receive(),
+            None => self.reconnect(),
+        }
+    }
+
+    // Shows how the game ended until the player quits
+    fn finish(&mut self, outcome: ArenaOutcome) -> VersusEnd {
+        let (end, prompt) = match outcome {
+            ArenaOutcome::Winner(winner) if winner == self.me => (VersusEnd::Won, WIN_PROMPT),
+            ArenaOutcome::Winner(_) => (VersusEnd::Lost, LOSE_PROMPT),
+            ArenaOutcome::Draw => (VersusEnd::Draw, DRAW_PROMPT),
+        };
+        while !input::pressed(&self.engine, QUIT_KEY)
+            && !input::pressed(&self.engine, KeyCode::Char(' '))
+        {
+            self.draw(prompt);
+        }
+        end
+    }
+
+    // Draws the arena, with a prompt on top and both scores underneath
+    fn draw(&mut self, prompt: &str) {
+        let options = &self.arena.options;
+        let engine = &mut self.engine;
+        render::draw_map(engine, options);
+        render::draw_prompt(engine, prompt, 0);
+        let scores = format!(
+            "YOU: {}  THEM: {}",
+            self.arena.sneks[self.me].score(),
+            self.arena.sneks[1 - self.me].score()
+        );
+        let bottom = engine.get_height() as i32 - 1;
+        render::draw_prompt(engine, &scores, bottom);
+        render::draw_food(engine, &self.arena.food, options, FOOD_COLOR);
+        for (index, snek) in self.arena.sneks.iter().enumerate() {
+            let palette: &[Color] = match index == self.me {
+                true => &SNEK_COLORS,
+                false => &[RIVAL_COLOR],
+            };
+            render::draw_snek(engine, snek, options, palette);
+        }
+        engine.draw();
+        engine.clear_screen();
+        engine.wait_frame();
+    }
+
+    // Sends this player's turns from a tick on
+    fn send_turns(&mut self, from: usize) {
+        let Some(link) = &mut self.link else {
+            return;
+        };
+        for (tick, direction) in self.turns[self.me].iter().enumerate().skip(from) {
+            let direction = *direction;
+            if !link.send(&Message::Turn { tick, direction }) {
+                return;
+            }
+        }
+    }
+
+    // Deals with the lines the other player has sent, ignoring malformed ones
+    fn receive(&mut self) -> Option<VersusEnd> {
+        let link = self.link.as_mut()?;
+        let lines: Vec<Option<String>> = link.lines.try_iter().collect();
+        let other = 1 - self.me;
+        for line in lines {
+            let Some(line) = line else {
+                self.link = None;
+                self.lost_at = Instant::now();
+                return None;
+            };
+            let message = serde_json::from_str(&line).ok();
+            let link = self.link.as_mut()?;
+            if !link.greeted {
+                link.greeted = matches!(message, Some(Message::Hello { version: VERSION }));
+                if !link.greeted {
+                    self.link = None;
+                    return None;
+                }
+                continue;
+            }
+            match message {
+                Some(Message::Quit) => return Some(VersusEnd::OpponentQuit),
+                Some(Message::Turn { tick, direction }) if tick == self.turns[other].len() => {
+                    self.turns[other].push(direction);
+                }
+                Some(Message::Resume { tick }) => self.send_turns(tick),
+                _ => (),
+            }
+        }
+        None
+    }
+
+    // Tries to get the connection back after it dropped, giving up once the
+    // window for it has passed
+    fn reconnect(&mut self) -> Option<VersusEnd> {
+        if self.lost_at.elapsed() > RECONNECT_WINDOW {
+            return Some(VersusEnd::Disconnected);
+        }
+        let stream = match &self.role {
+            Role::Host(_) => {
+                let listener = self.listener.as_ref()?;
+                listener.set_nonblocking(true).ok()?;
+                let (stream, _) = listener.accept().ok()?;
+                stream.set_nonblocking(false).ok()?;
+                stream
+            }
+            Role::Guest(address) => {
+                if self
+                    .last_retry
+                    .is_some_and(|retry| retry.elapsed() < RECONNECT_INTERVAL)
+                {
+                    return None;
+                }
+                self.last_retry = Some(Instant::now());
+                let address = address.to_socket_addrs().ok()?.next()?;
+                TcpStream::connect_timeout(&address, RECONNECT_INTERVAL).ok()?
+            }
+        };
+        let mut link = Link::new(stream).ok()?;
+        let missing = self.turns[1 - self.me].len();
+        if link.send(&HELLO) && link.send(&Message::Resume { tick: missing }) {
+            self.link = Some(link);
+        }
+        None
+    }
+}
+
+// Returns an error for a malformed message from the other player
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
@@ -0,0 +1,178 @@
+// Letting an audience steer the snek (`--vote-input ADDRESS`), for "chat plays
+// snek" streams.
+//
+// Snek listens for UDP datagrams on the address, each one a vote for a
+// direction: `up`, `down`, `left`, or `right` (surrounding whitespace is
+// ignored, so a trailing newline is fine). Anything else is dropped. Votes are
+// counted between moves, and when the snek moves it turns the way most of them
+// went; a tie (or no votes at all) keeps it going the way it was. The count
+// starts over with every move.
+
+use std::io::{self, ErrorKind};
+use std::net::UdpSocket;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::world::Direction;
+
+// Longest vote that's read, in bytes (longer datagrams are cut short, and so
+// can't be votes)
+const MAX_VOTE: usize = 16;
+
+// How often the listening thread checks whether the game is over
+const STOP_INTERVAL: Duration = Duration::from_millis(100);
+
+// The votes counted since the last move, by direction
+#[derive(Default)]
+struct Tally {
+    counts: [usize; 4],
+    stopped: bool,
+}
+
+// The votes coming in for a game
+pub struct Votes {
+    tally: Arc<Mutex<Tally>>,
+    thread: JoinHandle<()>,
+}
+
+impl Votes {
+    // Starts listening for votes on an address, before the game has started
+    pub fn listen(address: &str) -> io::Result<UdpSocket> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_read_timeout(Some(STOP_INTERVAL))?;
+        Ok(socket)
+    }
+
+    // Starts counting the votes that come in on a socket
+    pub fn start(socket: UdpSocket) -> Self {
+        let tally = Arc::new(Mutex::new(Tally::default()));
+        let counted = Arc::clone(&tally);
+        let thread = thread::spawn(move || count(&socket, &counted));
+        Self { tally, thread }
+    }
+
+    // Returns the votes for each direction since the last move, in the order
+    // of `Direction::all`
+    pub fn counts(&self) -> [usize; 4] {
+        self.tally.lock().unwrap().counts
+    }
+
+    // Starts the count over for the next move, returning the direction the
+    // last one chose (if one got the most votes)
+    pub fn take(&self) -> Option<Direction> {
+        let counts = std::mem::take(&mut self.tally.lock().unwrap().counts);
+        winner(counts)
+    }
+
+    // Stops listening for votes
+    pub fn finish(self) {
+        self.tally.lock().unwrap().stopped = true;
+        let _ = self.thread.join();
+    }
+}
+
+// Returns the direction with more votes than any other, if there is one
+fn winner(counts: [usize; 4]) -> Option<Direction> {
+    let most = *counts.iter().max()?;
+    let mut leaders = Direction::all()
+        .into_iter()
+        .filter(|direction| counts[*direction as usize] == most);
+    match (most, leaders.next(), leaders.next()) {
+        (1.., Some(direction), None) => Some(direction),
+        _ => None,
+    }
+}
+
+// Reads a vote from a datagram, if it's one
+fn parse_vote(datagram: &[u8]) -> Option<Direction> {
+    let text = std::str::from_utf8(datagram).ok()?;
+    Direction::from_name(&text.trim().to_ascii_lowercase())
+}
+
+// Counts the votes that come in on a socket until the game is over
+fn count(socket: &UdpSocket, tally: &Mutex<Tally>) {
+    let mut buffer = [0; MAX_VOTE + 1];
+    loop {
+        let received = socket.recv(&mut buffer);
+        let mut tally = tally.lock().unwrap();
+        if tally.stopped {
+            return;
+        }
+        match received {
+            Ok(len) if len <= MAX_VOTE => {
+                if let Some(direction) = parse_vote(&buffer[..len]) {
+                    tally.counts[direction as usize] += 1;
+                }
+            }
+            Ok(_) => {}
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(err) => {
+                log::warn!("vote input stopped: {err}");
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn most_votes_win_and_ties_keep_going() {
+        assert_eq!(winner([0, 0, 0, 0]), None);
+        assert_eq!(winner([0, 3, 1, 0]), Some(Direction::all()[1]));
+        assert_eq!(winner([2, 0, 2, 1]), None);
+        assert_eq!(winner([0, 0, 0, 1]), Some(Direction::all()[3]));
+    }
+
+    #[test]
+    fn only_directions_are_votes() {
+        assert_eq!(parse_vote(b"up"), Some(Direction::Up));
+        assert_eq!(parse_vote(b" Left\r\n"), Some(Direction::Left));
+        assert_eq!(parse_vote(b""), None);
+        assert_eq!(parse_vote(b"upp"), None);
+        assert_eq!(parse_vote(b"\xff\xfe"), None);
+    }
+
+    #[test]
+    fn votes_are_counted_over_localhost() {
+        let socket = Votes::listen("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap();
+        let votes = Votes::start(socket);
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let vote = |text: &[u8]| {
+            sender.send_to(text, address).unwrap();
+        };
+        // Waits for the count to reach some number of votes
+        let counted = |total: usize| {
+            let start = Instant::now();
+            while votes.counts().iter().sum::<usize>() < total {
+                assert!(
+                    start.elapsed() < Duration::from_secs(5),
+                    "votes went missing"
+                );
+                thread::sleep(Duration::from_millis(5));
+            }
+        };
+        for text in [&b"down"[..], b"nope", b"down\n", &[b'd'; 64], b"left"] {
+            vote(text);
+        }
+        counted(3);
+        let mut expected = [0; 4];
+        expected[Direction::Down as usize] = 2;
+        expected[Direction::Left as usize] = 1;
+        assert_eq!(votes.counts(), expected);
+        assert_eq!(votes.take(), Some(Direction::Down));
+        // The count starts over with every move
+        assert_eq!(votes.counts(), [0; 4]);
+        vote(b"up");
+        vote(b"right");
+        counted(2);
+        assert_eq!(votes.take(), None);
+        votes.finish();
+    }
+}
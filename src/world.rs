@@ -0,0 +1,1722 @@
+use std::collections::HashSet;
+
+use fastrand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::events::SnekEvent;
+use crate::options::{FoodPlacement, Mode, Options, Scoring};
+use crate::{LENGTH_MILESTONE, Point, STARTING_BODY, Vector};
+
+// Most food there can be on the map at once in a rush
+const RUSH_MAX_FOOD: usize = 6;
+
+// How much food has to be eaten to survive a rush (each speeding the snek up
+// by a move a second)
+pub const RUSH_GOAL: usize = 20;
+
+// How many pellets a clump of food can have in cluster mode (fewer when
+// there's no room for that many side by side)
+const CLUSTER_SIZES: std::ops::RangeInclusive<usize> = 2..=4;
+
+// How many cells are picked at random for new food to go in with
+// `--food-placement far` or `weighted`, before it's put in one of them
+const PLACEMENT_CANDIDATES: usize = 8;
+
+// How many cells are picked for new food before giving up on finding one the
+// snek can reach at random
+const FAIR_FOOD_TRIES: usize = 8;
+
+// How many times as fast the snek moves while a key's held with `--hold-boost`
+const HOLD_BOOST: u32 = 2;
+
+// How many segments eating the decoy in choice mode costs the snek
+pub const DECOY_PENALTY: usize = 2;
+
+// How many points a food is worth with `--scoring points`, for every length
+// milestone the snek has reached (plus one)
+const FOOD_POINTS: usize = 10;
+
+// How many ticks food takes to lose all the points it can with `--food-decay`,
+// and what it's worth then (as a fraction of its full value)
+const FOOD_DECAY_TICKS: u64 = 40;
+const FOOD_DECAY_FLOOR: usize = 5;
+
+// How many ticks in a row the head can spend next to a wall before
+// `--wall-hug-penalty` starts taking a point off every tick
+const WALL_HUG_TICKS: u32 = 10;
+
+// Ways to change where food goes and what the score is from outside the
+// rules, for things like a day when food's worth double (which `--double-food`
+// sets up). They can hold on to whatever they need to decide, and leave
+// everything as it is unless they're set
+pub struct Hooks {
+    // Returns where a food put on the map after it's started goes, given where
+    // it would otherwise (it's up to the hook to keep it on a free cell)
+    pub spawn: SpawnHook,
+    // Returns the score, given what it would be otherwise in whatever the game
+    // is scored by (so it can't ask the world for its score)
+    pub score: ScoreHook,
+}
+
+pub type SpawnHook = Box<dyn Fn(&World, Point) -> Point>;
+pub type ScoreHook = Box<dyn Fn(&World, usize) -> usize>;
+
+impl Hooks {
+    // Returns the hooks a game's options ask for
+    pub fn for_options(options: &Options) -> Self {
+        match options.double_food {
+            true => Self {
+                score: Box::new(|_, score| score * 2),
+                ..Self::default()
+            },
+            false => Self::default(),
+        }
+    }
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            spawn: Box::new(|_, food| food),
+            score: Box::new(|_, score| score),
+        }
+    }
+}
+
+// Everything about a game that doesn't need a terminal (the snek, food, and rules)
+pub struct World {
+    pub snek: Snek,
+    pub food: Vec<Point>,
+    // The tick each food in `food` appeared on, in the same order
+    pub food_spawned: Vec<u64>,
+    // How many foods have been put on the map, for picking the next one from
+    // a level's `food_sequence`
+    pub food_placed: usize,
+    pub decoy: Option<Point>,
+    pub rng: Rng,
+    pub options: Options,
+    pub ticks: u64,
+    pub eaten: usize,
+    pub points: usize,
+    pub cause: Option<DeathCause>,
+    pub won: bool,
+    pub touched_body: bool,
+    // How many ticks in a row the head has been next to a wall
+    pub wall_ticks: u32,
+    pub turns: Vec<(u64, Direction)>,
+    pub start_heading: Direction,
+    pub hooks: Hooks,
+}
+
+impl World {
+    // Creates a new world with the snek in its starting position
+    pub fn new(options: Options, starting_body: &[Point]) -> Self {
+        let mut rng = Rng::with_seed(options.seed);
+        let taken = [starting_body, &options.walls].concat();
+        let food = match options.mode {
+            Mode::Cluster => rand_cluster(&mut rng, &options, &taken, starting_body),
+            _ => vec![
+                sequenced_food(&options, 0, &taken)
+                    .unwrap_or_else(|| place_food(&mut rng, &options, &taken, starting_body)),
+            ],
+        };
+        let hooks = Hooks::for_options(&options);
+        let mut world = Self {
+            // Turning straight back is how the ends are swapped in twin mode
+            snek: Snek::new(starting_body, options.safe_start)
+                .with_growth_delay(options.growth_delay)
+                .with_reverse_lockout(options.reverse_lockout && options.mode != Mode::Twin),
+            food_spawned: vec![0; food.len()],
+            food_placed: food.len(),
+            food,
+            decoy: None,
+            rng,
+            options,
+            ticks: 0,
+            eaten: 0,
+            points: 0,
+            cause: None,
+            won: false,
+            touched_body: false,
+            wall_ticks: 0,
+            turns: Vec::new(),
+            start_heading: Direction::Right,
+            hooks,
+        };
+        let mut heading = world
+            .options
+            .start
+            .map_or(Direction::Right, |(_, heading)| heading);
+        if world.options.random_heading {
+            heading = world.random_safe_heading().unwrap_or(heading);
+        }
+        world.snek.direction = heading;
+        world.snek.heading = heading;
+        world.start_heading = heading;
+        world.spawn_decoy();
+        world
+    }
+
+    // Picks a random direction (from the seeded RNG) that the snek can start
+    // off in without doubling back into itself or running into a wall, if
+    // there is one
+    fn random_safe_heading(&mut self) -> Option<Direction> {
+        let (width, height) = (self.width(), self.height());
+        let safe: Vec<Direction> = Direction::all()
+            .into_iter()
+            .filter(|direction| {
+                !self
+                    .snek
+                    .move_fatal(*direction, &self.food, &self.options.walls, width, height)
+            })
+            .collect();
+        (!safe.is_empty()).then(|| safe[self.rng.usize(..safe.len())])
+    }
+
+    // Returns the width of the map
+    pub fn width(&self) -> u32 {
+        self.options.width
+    }
+
+    // Returns the height of the map
+    pub fn height(&self) -> u32 {
+        self.options.height
+    }
+
+    // Returns the score of the game, counted the way the options say (and then
+    // put through the score hook)
+    pub fn score(&self) -> usize {
+        let score = match self.options.scoring {
+            Scoring::Length => self.snek.score(),
+            Scoring::Food => self.eaten,
+            Scoring::Points => self.points,
+        };
+        (self.hooks.score)(self, score)
+    }
+
+    // Returns how many points eating a food (by its index in `food`) is worth
+    // right now, which with `--food-decay` goes down from its full value with
+    // every tick after the first it's been on the map, to a floor
+    pub fn food_points(&self, food: usize) -> usize {
+        let points = FOOD_POINTS * (1 + self.snek.body.len() / LENGTH_MILESTONE);
+        if !self.options.food_decay {
+            return points;
+        }
+        // Food set from outside the game (like when spectating) counts as new
+        let spawned = self.food_spawned.get(food).copied().unwrap_or(self.ticks);
+        let age = self.ticks.saturating_sub(spawned).saturating_sub(1);
+        let lost = (points - points / FOOD_DECAY_FLOOR) as u64 * age.min(FOOD_DECAY_TICKS);
+        points - (lost / FOOD_DECAY_TICKS) as usize
+    }
+
+    // Returns how many times a second the snek moves, which goes up with the
+    // score along the speed curve and with every food eaten in a rush, kept
+    // from going over `max_fps` or under `min_fps` (which wins if they cross)
+    // once everything that changes it is counted
+    pub fn fps(&self) -> u32 {
+        self.clamp_fps(self.ramped_fps())
+    }
+
+    // Returns how many times a second the snek moves while it might be sped
+    // up by holding a key down with `--hold-boost` or slowed down to half
+    // speed by `--assist slowmo`, kept in the same bounds once both are counted
+    pub fn move_fps(&self, boosted: bool, slowed: bool) -> u32 {
+        let mut fps = self.ramped_fps();
+        if boosted {
+            fps *= HOLD_BOOST;
+        }
+        if slowed {
+            fps = (fps / 2).max(1);
+        }
+        self.clamp_fps(fps)
+    }
+
+    // Returns how many times a second the snek moves before it's kept in bounds
+    fn ramped_fps(&self) -> u32 {
+        let fps = self
+            .options
+            .ramp_curve
+            .fps_for(self.score(), self.options.fps);
+        match self.options.mode {
+            Mode::Rush => fps + self.eaten as u32,
+            _ => fps,
+        }
+    }
+
+    // Keeps a speed from going over `max_fps` or under `min_fps`
+    fn clamp_fps(&self, fps: u32) -> u32 {
+        let fps = self.options.max_fps.map_or(fps, |max| fps.min(max));
+        self.options.min_fps.map_or(fps, |min| fps.max(min))
+    }
+
+    // Returns whether the game is still going (the snek is alive and hasn't won
+    // by filling the board, eating the goal's worth of food, or surviving a rush)
+    pub fn running(&self) -> bool {
+        self.snek.alive && !self.won
+    }
+
+    // Checks if the snek has died, returning the event if it just did
+    pub fn check_death(&mut self) -> Option<SnekEvent> {
+        if !self.snek.alive {
+            return None;
+        }
+        let mut cause = self.death();
+        if cause == Some(DeathCause::Body) && self.options.mode == Mode::Ouroboros {
+            self.snek.bite_tail();
+            cause = None;
+        }
+        self.cause = cause;
+        self.snek.alive = cause.is_none();
+        cause.map(|_| SnekEvent::Died(*self.snek.body.last().unwrap()))
+    }
+
+    // Returns what killed the snek, if it's dead (a wall around or on the map,
+    // or inside itself). Walls are checked first, as in `Snek::death`
+    pub fn death(&self) -> Option<DeathCause> {
+        let head = self.snek.body.last().unwrap();
+        match self.options.walls.contains(head) {
+            true => Some(DeathCause::Wall),
+            false => self.snek.death(self.width(), self.height()),
+        }
+    }
+
+    // Moves the snek one step, unless its first move is being held back
+    pub fn advance(&mut self) -> Option<SnekEvent> {
+        if !self.running() || self.holding_first_move() {
+            return None;
+        }
+        let last_direction = self.turns.last().map_or(self.start_heading, |turn| turn.1);
+        if self.snek.direction != last_direction {
+            self.turns.push((self.ticks, self.snek.direction));
+        }
+        // In twin mode, turning straight back swaps which end of the snek
+        // leads instead of running into the neck
+        if self.options.mode == Mode::Twin
+            && self.snek.heading.angle_to(self.snek.direction) == Turn::Around
+        {
+            self.snek.swap_ends();
+        }
+        let (width, height) = (self.width(), self.height());
+        let event = self.snek.slither(&self.food);
+        self.ticks += 1;
+        match event {
+            SnekEvent::Ate(head) => {
+                self.eaten += 1;
+                let eaten = self.food.iter().position(|food| *food == head).unwrap();
+                self.points += self.food_points(eaten);
+                self.food.remove(eaten);
+                self.food_spawned.remove(eaten);
+                self.spawn_food();
+                self.spawn_decoy();
+            }
+            // Eating the decoy shrinks the snek, and the round starts over
+            // with both foods somewhere new
+            SnekEvent::Moved(head) if self.decoy == Some(head) => {
+                self.snek.shrink(DECOY_PENALTY);
+                self.points = self.points.saturating_sub(DECOY_PENALTY * FOOD_POINTS);
+                self.food.clear();
+                self.food_spawned.clear();
+                self.decoy = None;
+                self.spawn_food();
+                self.spawn_decoy();
+            }
+            _ => {}
+        }
+        self.touched_body |= self.snek.touching_body();
+        self.drain_wall_hug();
+        let reached_goal = match self.options.mode {
+            Mode::Rush => self.eaten >= RUSH_GOAL,
+            _ => self.options.goal.is_some_and(|goal| self.eaten >= goal),
+        };
+        // A move that runs into the tail while growing fills one more cell
+        // than there is, so it can't count as filling the board
+        let alive = self.death().is_none();
+        let filled = self.snek.body.len() + self.options.walls.len() >= (width * height) as usize;
+        self.won = alive && (reached_goal || filled);
+        Some(event)
+    }
+
+    // Counts the ticks the head has spent next to a wall (the edge of the map
+    // or one on it) in a row, and with `--wall-hug-penalty` takes a point off
+    // for every one past `WALL_HUG_TICKS`
+    fn drain_wall_hug(&mut self) {
+        let head = *self.snek.body.last().unwrap();
+        let (width, height) = (self.width(), self.height());
+        let by_wall = Direction::all().into_iter().any(|direction| {
+            let next = head + direction.to_vector();
+            !in_bounds(next, width, height) || self.options.walls.contains(&next)
+        });
+        self.wall_ticks = match by_wall {
+            true => self.wall_ticks + 1,
+            false => 0,
+        };
+        if self.options.wall_hug_penalty && self.wall_ticks > WALL_HUG_TICKS {
+            self.points = self.points.saturating_sub(1);
+        }
+    }
+
+    // Puts new food on the map after some was eaten (two in a rush, while
+    // there's room for them, or a whole clump in cluster mode once the last
+    // one's gone)
+    fn spawn_food(&mut self) {
+        if self.options.mode == Mode::Cluster {
+            self.spawn_cluster();
+            return;
+        }
+        let count = match self.options.mode {
+            Mode::Rush => RUSH_MAX_FOOD.saturating_sub(self.food.len()).min(2),
+            _ => 1,
+        };
+        let (width, height) = (self.width(), self.height());
+        for _ in 0..count {
+            let taken = [
+                &self.snek.body[..],
+                &self.food[..],
+                self.decoy.as_slice(),
+                &self.options.walls,
+            ]
+            .concat();
+            if taken.len() >= (width * height) as usize {
+                return;
+            }
+            let food =
+                sequenced_food(&self.options, self.food_placed, &taken).unwrap_or_else(|| {
+                    place_food(&mut self.rng, &self.options, &taken, &self.snek.body)
+                });
+            let food = (self.hooks.spawn)(self, food);
+            self.food_placed += 1;
+            self.food.push(food);
+            self.food_spawned.push(self.ticks);
+        }
+    }
+
+    // Puts a clump of food on the map in cluster mode, once every pellet of
+    // the last one has been eaten
+    fn spawn_cluster(&mut self) {
+        let (width, height) = (self.width(), self.height());
+        let taken = [&self.snek.body[..], &self.food[..], &self.options.walls].concat();
+        if !self.food.is_empty() || taken.len() >= (width * height) as usize {
+            return;
+        }
+        let cluster: Vec<Point> =
+            rand_cluster(&mut self.rng, &self.options, &taken, &self.snek.body)
+                .into_iter()
+                .map(|food| (self.hooks.spawn)(self, food))
+                .collect();
+        self.food_placed += cluster.len();
+        self.food_spawned.extend(cluster.iter().map(|_| self.ticks));
+        self.food.extend(cluster);
+    }
+
+    // Moves the decoy somewhere new in choice mode, or takes it away if
+    // there's no room for it
+    fn spawn_decoy(&mut self) {
+        if self.options.mode != Mode::Choice {
+            return;
+        }
+        let (width, height) = (self.width(), self.height());
+        let taken = [&self.snek.body[..], &self.food[..], &self.options.walls].concat();
+        self.decoy = (taken.len() < (width * height) as usize)
+            .then(|| rand_point(&mut self.rng, width, height, &taken, None));
+    }
+
+    // Returns whether the first move is held back because it would be fatal
+    fn holding_first_move(&mut self) -> bool {
+        if !self.snek.first_move_pending {
+            return false;
+        }
+        let fatal =
+            self.snek
+                .next_move_fatal(&self.food, &self.options.walls, self.width(), self.height());
+        self.snek.first_move_pending = fatal;
+        fatal
+    }
+}
+
+// Contains information about the snek
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snek {
+    pub body: Vec<Point>,
+    pub start_len: usize,
+    pub direction: Direction,
+    pub heading: Direction,
+    pub pending_growth: usize,
+    pub growth_ticks: u32,
+    pub growth_delay: u32,
+    pub reverse_lockout: bool,
+    pub alive: bool,
+    pub first_move_pending: bool,
+}
+
+impl Snek {
+    // Creates a new snek, which can wait to make its first move until it's safe
+    pub fn new(starting_body: &[Point], safe_start: bool) -> Self {
+        Self {
+            body: Vec::from(starting_body),
+            start_len: starting_body.len(),
+            direction: Direction::Right,
+            heading: Direction::Right,
+            pending_growth: 0,
+            growth_ticks: 0,
+            growth_delay: 0,
+            reverse_lockout: true,
+            alive: true,
+            first_move_pending: safe_start,
+        }
+    }
+
+    // Makes the snek grow a segment every `delay` ticks after eating instead
+    // of straight away
+    pub fn with_growth_delay(mut self, delay: u32) -> Self {
+        self.growth_delay = delay;
+        self
+    }
+
+    // Makes the snek turn straight back on itself when told to (into its own
+    // neck) if `lockout` is off, instead of ignoring it
+    pub fn with_reverse_lockout(mut self, lockout: bool) -> Self {
+        self.reverse_lockout = lockout;
+        self
+    }
+
+    // Moves the snek in the current direction, returning what happened. The
+    // tail is only dropped after the head has moved, so moving into the cell
+    // the tail is leaving is safe, unless the snek is growing this move and the
+    // tail stays (which `death` then catches, since the head is on it)
+    pub fn slither(&mut self, food: &[Point]) -> SnekEvent {
+        let head = *self.body.last().unwrap() + self.direction.to_vector();
+        self.heading = self.direction;
+        self.body.push(head);
+        let ate = food.contains(&head);
+        if self.grows(ate) {
+            // Every segment follows the one in front of it along the body, so
+            // growing anywhere along it (like in the middle, for twins) comes
+            // out as the half behind the new segment holding still, down to
+            // the tail. The tick it grows on counts toward the next segment
+            self.pending_growth = (self.pending_growth + ate as usize) - 1;
+            self.growth_ticks = (self.pending_growth > 0) as u32;
+        } else {
+            self.pending_growth += ate as usize;
+            self.growth_ticks += (self.pending_growth > 0) as u32;
+            self.body.remove(0);
+        }
+        match ate {
+            true => SnekEvent::Ate(head),
+            false => SnekEvent::Moved(head),
+        }
+    }
+
+    // Returns whether the snek's next move lengthens it (leaving its tail
+    // where it is), given whether it eats on the way
+    fn grows(&self, eats: bool) -> bool {
+        (self.pending_growth > 0 || eats) && self.growth_ticks >= self.growth_delay
+    }
+
+    // Returns what killed the snek, if it's dead (the edge of the map, or
+    // inside itself). The edge is checked first, so a head that's off the map
+    // always counts as hitting the wall, whatever else it might be in
+    pub fn death(&self, width: u32, height: u32) -> Option<DeathCause> {
+        let last = self.body.last().unwrap();
+        if !in_bounds(*last, width, height) {
+            Some(DeathCause::Wall)
+        } else if self.body[0..self.body.len() - 1].contains(last) {
+            Some(DeathCause::Body)
+        } else {
+            None
+        }
+    }
+
+    // Removes the body up to where the head ran into it, so the snek survives
+    fn bite_tail(&mut self) {
+        let head = *self.body.last().unwrap();
+        if let Some(bitten) = self.body.iter().position(|part| *part == head)
+            && bitten < self.body.len() - 1
+        {
+            self.body.drain(..=bitten);
+        }
+    }
+
+    // Returns whether moving in the current direction would kill the snek
+    fn next_move_fatal(&self, food: &[Point], walls: &[Point], width: u32, height: u32) -> bool {
+        self.move_fatal(self.direction, food, walls, width, height)
+    }
+
+    // Returns whether moving in a direction lengthens the snek (leaving its
+    // tail where it is)
+    pub fn move_grows(&self, direction: Direction, food: &[Point]) -> bool {
+        let head = *self.body.last().unwrap() + direction.to_vector();
+        self.grows(food.contains(&head))
+    }
+
+    // Returns whether moving in a direction would kill the snek (running into
+    // the edge of the map, its body, or a wall on it)
+    pub fn move_fatal(
+        &self,
+        direction: Direction,
+        food: &[Point],
+        walls: &[Point],
+        width: u32,
+        height: u32,
+    ) -> bool {
+        let head = *self.body.last().unwrap() + direction.to_vector();
+        // The tail only moves out of the way if the snek isn't about to grow
+        let body = match self.move_grows(direction, food) {
+            true => &self.body[..],
+            false => &self.body[1..],
+        };
+        !in_bounds(head, width, height) || body.contains(&head) || walls.contains(&head)
+    }
+
+    // Turns the snek around end to end, so its tail leads, heading on the way
+    // the tail was going (or back the way it came, if it's only a head)
+    pub fn swap_ends(&mut self) {
+        self.body.reverse();
+        self.heading = match self.body[..] {
+            [.., neck, head] => Direction::all()
+                .into_iter()
+                .find(|direction| neck + direction.to_vector() == head)
+                .unwrap_or(self.direction),
+            _ => self.direction,
+        };
+        self.direction = self.heading;
+    }
+
+    // Drops segments off the tail, always leaving at least the head
+    fn shrink(&mut self, segments: usize) {
+        let segments = segments.min(self.body.len() - 1);
+        self.body.drain(..segments);
+    }
+
+    // Returns whether the head is next to a part of the body other than the neck
+    fn touching_body(&self) -> bool {
+        let head = *self.body.last().unwrap();
+        self.body[..self.body.len().saturating_sub(2)]
+            .iter()
+            .any(|part| (*part - head).abs().to_array().iter().sum::<i32>() == 1)
+    }
+
+    // Turns the snek from the direction it last moved in
+    pub fn rotate(&mut self, turn: Turn) {
+        self.direction = self.heading.turn(turn);
+    }
+
+    // Does some checking (unless the reverse lockout is off) and then changes
+    // the direction of the snek. The check is against the way it last moved,
+    // since more than one turn can come in before the next move
+    pub fn change_direction(&mut self, direction: Direction) {
+        if !self.reverse_lockout || self.heading.angle_to(direction) != Turn::Around {
+            self.direction = direction;
+        }
+    }
+
+    // Returns the score (current len - starting len, or 0 if it's shorter)
+    pub fn score(&self) -> usize {
+        self.body.len().saturating_sub(self.start_len)
+    }
+}
+
+// What the snek died from
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeathCause {
+    Wall,
+    Body,
+}
+
+impl DeathCause {
+    // Returns the name used for the cause in files
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeathCause::Wall => "wall",
+            DeathCause::Body => "body",
+        }
+    }
+
+    // Returns a short description of the cause
+    pub fn description(&self) -> &'static str {
+        match self {
+            DeathCause::Wall => "hitting a wall",
+            DeathCause::Body => "eating itself",
+        }
+    }
+}
+
+// Represents one of the four directions
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    // Returns every direction
+    pub fn all() -> [Self; 4] {
+        [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ]
+    }
+
+    // Returns the name used for the direction in files
+    pub fn name(&self) -> &'static str {
+        match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        }
+    }
+
+    // Finds the direction with the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "up" => Some(Direction::Up),
+            "down" => Some(Direction::Down),
+            "left" => Some(Direction::Left),
+            "right" => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    // Returns the opposite direction
+    pub fn opposite(&self) -> Self {
+        match self {
+            Direction::Up => Self::Down,
+            Direction::Down => Self::Up,
+            Direction::Left => Self::Right,
+            Direction::Right => Self::Left,
+        }
+    }
+
+    // Returns the direction a quarter turn counterclockwise
+    pub fn rotate_left(&self) -> Self {
+        match self {
+            Direction::Up => Self::Left,
+            Direction::Left => Self::Down,
+            Direction::Down => Self::Right,
+            Direction::Right => Self::Up,
+        }
+    }
+
+    // Returns the direction a quarter turn clockwise
+    pub fn rotate_right(&self) -> Self {
+        self.rotate_left().opposite()
+    }
+
+    // Returns the direction after making a turn
+    pub fn turn(&self, turn: Turn) -> Self {
+        match turn {
+            Turn::Straight => *self,
+            Turn::Left => self.rotate_left(),
+            Turn::Right => self.rotate_right(),
+            Turn::Around => self.opposite(),
+        }
+    }
+
+    // Returns the turn that goes from this direction to another
+    pub fn angle_to(&self, other: Self) -> Turn {
+        Turn::all()
+            .into_iter()
+            .find(|turn| self.turn(*turn) == other)
+            .unwrap()
+    }
+
+    // Converts the direction to a vector
+    pub fn to_vector(self) -> Vector {
+        match self {
+            Direction::Up => Vector::new(0, -1),
+            Direction::Down => Vector::new(0, 1),
+            Direction::Left => Vector::new(-1, 0),
+            Direction::Right => Vector::new(1, 0),
+        }
+    }
+}
+
+// A turn from one direction to another, relative to the first
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Turn {
+    Straight,
+    Left,
+    Right,
+    Around,
+}
+
+impl Turn {
+    // Returns every turn
+    pub fn all() -> [Self; 4] {
+        [Turn::Straight, Turn::Left, Turn::Right, Turn::Around]
+    }
+}
+
+// Returns the snek's body at the start, from tail to head: behind a head and
+// heading if it's given one, or in the top left corner heading right
+pub fn starting_body(start: Option<(Point, Direction)>) -> Vec<Point> {
+    match start {
+        Some((head, heading)) => (0..STARTING_BODY.len() as i32)
+            .rev()
+            .map(|back| head - heading.to_vector() * back)
+            .collect(),
+        None => STARTING_BODY.to_vec(),
+    }
+}
+
+// Returns whether a point is on the map
+pub fn in_bounds(point: Point, width: u32, height: u32) -> bool {
+    point.x >= 0 && point.y >= 0 && point.x < width as i32 && point.y < height as i32
+}
+
+// Randomizes a point, excluding a list points and, if it's given a head and a
+// radius, the cells that many or fewer steps (up, down, left, or right) away
+// from the head, with the radius shrunk until there's a free cell outside of it
+// (so on a full enough board, any free cell will do)
+pub fn rand_point(
+    rng: &mut Rng,
+    width: u32,
+    height: u32,
+    exclude: &[Point],
+    clear_of: Option<(Point, u32)>,
+) -> Point {
+    let (head, mut radius) = clear_of.unwrap_or((Point::new(0, 0), 0));
+    let near = |point: Point, radius: u32| {
+        let distance = (point.x - head.x).unsigned_abs() + (point.y - head.y).unsigned_abs();
+        radius > 0 && distance <= radius
+    };
+    if radius > 0 {
+        let taken: HashSet<Point> = exclude.iter().copied().collect();
+        let cells: Vec<Point> = (0..height as i32)
+            .flat_map(|y| (0..width as i32).map(move |x| Point::new(x, y)))
+            .filter(|cell| !taken.contains(cell))
+            .collect();
+        while radius > 0 && cells.iter().all(|cell| near(*cell, radius)) {
+            radius -= 1;
+        }
+    }
+    let mut point = Point::new(rng.i32(0..width as i32), rng.i32(0..height as i32));
+    while exclude.contains(&point) || near(point, radius) {
+        point = Point::new(rng.i32(0..width as i32), rng.i32(0..height as i32));
+    }
+    point
+}
+
+// Returns the cell in the options' food sequence that comes after the foods
+// already placed (starting over after the last one), unless there's no
+// sequence or something's in the cell, where `place_food` picks one instead
+fn sequenced_food(options: &Options, placed: usize, exclude: &[Point]) -> Option<Point> {
+    let sequence = &options.food_sequence;
+    (!sequence.is_empty())
+        .then(|| sequence[placed % sequence.len()])
+        .filter(|food| !exclude.contains(food))
+}
+
+// Picks a cell for new food, away from the snek's head (a body from tail to
+// head) the way the options say, and where the head can get to unless
+// `fair_food` is off. If none of a few cells picked can be reached, it's the
+// reachable free cell closest to the last of them (or that cell, if the head
+// can't reach any)
+fn place_food(rng: &mut Rng, options: &Options, exclude: &[Point], body: &[Point]) -> Point {
+    let head = *body.last().unwrap();
+    if !options.fair_food {
+        return pick_food_cell(rng, options, exclude, head);
+    }
+    let reachable = reachable_cells(body, &options.walls, options.width, options.height);
+    let reached: HashSet<Point> = reachable.iter().copied().collect();
+    let mut food = head;
+    for _ in 0..FAIR_FOOD_TRIES {
+        food = pick_food_cell(rng, options, exclude, head);
+        if reached.contains(&food) {
+            return food;
+        }
+    }
+    let taken: HashSet<Point> = exclude.iter().copied().collect();
+    reachable
+        .into_iter()
+        .filter(|cell| !taken.contains(cell))
+        .min_by_key(|cell| (cell.x - food.x).unsigned_abs() + (cell.y - food.y).unsigned_abs())
+        .unwrap_or(food)
+}
+
+// Picks a cell for new food the way `--food-placement` says: from
+// `rand_point`, or out of a few cells from it, the farthest from the head or
+// one picked with the odds of each going up with how far it is (in steps up,
+// down, left, and right)
+fn pick_food_cell(rng: &mut Rng, options: &Options, exclude: &[Point], head: Point) -> Point {
+    let (width, height) = (options.width, options.height);
+    let clear_of = Some((head, options.food_clearance));
+    if options.food_placement == FoodPlacement::Uniform {
+        return rand_point(rng, width, height, exclude, clear_of);
+    }
+    let candidates: Vec<(Point, u32)> = (0..PLACEMENT_CANDIDATES)
+        .map(|_| {
+            let cell = rand_point(rng, width, height, exclude, clear_of);
+            let distance = (cell.x - head.x).unsigned_abs() + (cell.y - head.y).unsigned_abs();
+            (cell, distance)
+        })
+        .collect();
+    if options.food_placement == FoodPlacement::Far {
+        return candidates
+            .iter()
+            .max_by_key(|(_, distance)| *distance)
+            .unwrap()
+            .0;
+    }
+    let total: u32 = candidates.iter().map(|(_, distance)| distance).sum();
+    let mut pick = rng.u32(0..total.max(1));
+    for (cell, distance) in &candidates {
+        if pick < *distance {
+            return *cell;
+        }
+        pick -= distance;
+    }
+    candidates[0].0
+}
+
+// Returns the cells on the map the snek's head (the end of a body from tail
+// to head) can get to without going through the body or a wall, nearest
+// first, by flooding out from it
+fn reachable_cells(body: &[Point], walls: &[Point], width: u32, height: u32) -> Vec<Point> {
+    let mut seen: HashSet<Point> = body.iter().chain(walls).copied().collect();
+    let mut reachable = vec![*body.last().unwrap()];
+    let mut next = 0;
+    while let Some(cell) = reachable.get(next).copied() {
+        next += 1;
+        for direction in Direction::all() {
+            let neighbor = cell + direction.to_vector();
+            if in_bounds(neighbor, width, height) && seen.insert(neighbor) {
+                reachable.push(neighbor);
+            }
+        }
+    }
+    reachable.remove(0);
+    reachable
+}
+
+// Randomizes a clump of two to four cells side by side (up, down, left, or
+// right of each other), starting from a point picked by `place_food` and
+// growing into free cells next to the ones it already has, so it stays in one
+// piece. It's smaller when it runs out of free cells to grow into
+fn rand_cluster(rng: &mut Rng, options: &Options, exclude: &[Point], body: &[Point]) -> Vec<Point> {
+    let (width, height) = (options.width, options.height);
+    let size = rng.usize(CLUSTER_SIZES);
+    let mut cluster = vec![place_food(rng, options, exclude, body)];
+    while cluster.len() < size {
+        let free: Vec<Point> = cluster
+            .iter()
+            .flat_map(|cell| Direction::all().map(|direction| *cell + direction.to_vector()))
+            .filter(|cell| {
+                in_bounds(*cell, width, height)
+                    && !exclude.contains(cell)
+                    && !cluster.contains(cell)
+            })
+            .collect();
+        if free.is_empty() {
+            break;
+        }
+        cluster.push(free[rng.usize(..free.len())]);
+    }
+    cluster
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::summary::GameSummary;
+
+    // Returns a world with the snek in its usual starting position
+    fn world(options: Options) -> World {
+        World::new(options, &STARTING_BODY)
+    }
+
+    #[test]
+    fn safe_start_holds_a_fatal_first_move() {
+        let mut world = world(Options {
+            safe_start: true,
+            ..Options::default()
+        });
+        world.snek.change_direction(Direction::Up);
+        assert_eq!(world.advance(), None);
+        assert_eq!(world.snek.body, STARTING_BODY);
+        assert!(world.running());
+        world.snek.change_direction(Direction::Right);
+        assert_eq!(world.advance(), Some(SnekEvent::Moved(Point::new(4, 0))));
+        assert!(!world.snek.first_move_pending);
+        // Only the first move's held back
+        world.snek.change_direction(Direction::Up);
+        world.advance();
+        assert_eq!(world.death(), Some(DeathCause::Wall));
+    }
+
+    #[test]
+    fn ouroboros_bites_off_the_tail() {
+        let mut world = world(Options {
+            mode: Mode::Ouroboros,
+            food_sequence: vec![Point::new(10, 10)],
+            ..Options::default()
+        });
+        world.snek.body = [(2, 2), (3, 2), (4, 2), (5, 2), (5, 3), (4, 3)]
+            .map(Point::from)
+            .to_vec();
+        world.snek.heading = Direction::Left;
+        world.snek.change_direction(Direction::Up);
+        world.advance();
+        assert_eq!(world.check_death(), None);
+        assert!(world.running());
+        let body: Vec<Point> = [(5, 2), (5, 3), (4, 3), (4, 2)].map(Point::from).to_vec();
+        assert_eq!(world.snek.body, body);
+    }
+
+    #[test]
+    fn eating_in_a_rush_speeds_up_and_doubles_the_food() {
+        let eat = |mode| {
+            let mut world = world(Options {
+                mode,
+                food_sequence: [(4, 0), (8, 8), (9, 9)].map(Point::from).to_vec(),
+                ..Options::default()
+            });
+            let fps = world.fps();
+            assert_eq!(world.advance(), Some(SnekEvent::Ate(Point::new(4, 0))));
+            (world.fps() - fps, world.food.len())
+        };
+        let (classic_bump, classic_food) = eat(Mode::Classic);
+        let (rush_bump, rush_food) = eat(Mode::Rush);
+        assert_eq!(rush_bump, classic_bump + 1);
+        assert_eq!((classic_food, rush_food), (1, 2));
+    }
+
+    #[test]
+    fn growth_delay_adds_a_segment_every_few_ticks() {
+        let mut snek = Snek::new(&STARTING_BODY, false).with_growth_delay(2);
+        let food = [Point::new(4, 0), Point::new(5, 0)];
+        let grown: Vec<usize> = (0..8)
+            .map(|_| {
+                snek.slither(&food);
+                snek.score()
+            })
+            .collect();
+        assert_eq!(grown, [0, 0, 1, 1, 2, 2, 2, 2]);
+        let mut snek = Snek::new(&STARTING_BODY, false);
+        snek.slither(&food);
+        assert_eq!(snek.score(), 1);
+    }
+
+    // Returns a snek curled up in a square, with its head just below its tail
+    // and about to move onto it
+    fn curled_snek() -> Snek {
+        let body = [
+            Point::new(2, 2),
+            Point::new(3, 2),
+            Point::new(3, 3),
+            Point::new(2, 3),
+        ];
+        let mut snek = Snek::new(&body, false);
+        snek.heading = Direction::Left;
+        snek.direction = Direction::Up;
+        snek
+    }
+
+    #[test]
+    fn moving_onto_the_tail_is_safe_if_it_moves_away() {
+        let mut snek = curled_snek();
+        assert!(!snek.move_fatal(Direction::Up, &[], &[], 8, 8));
+        snek.slither(&[]);
+        assert_eq!(snek.death(8, 8), None);
+        assert_eq!(snek.body.len(), 4);
+    }
+
+    #[test]
+    fn moving_onto_the_tail_is_fatal_while_growing() {
+        let mut snek = curled_snek();
+        snek.pending_growth = 1;
+        assert!(snek.move_fatal(Direction::Up, &[], &[], 8, 8));
+        snek.slither(&[]);
+        assert_eq!(snek.death(8, 8), Some(DeathCause::Body));
+        let mut snek = curled_snek();
+        let food = [Point::new(2, 2)];
+        assert!(snek.move_fatal(Direction::Up, &food, &[], 8, 8));
+        snek.slither(&food);
+        assert_eq!(snek.death(8, 8), Some(DeathCause::Body));
+    }
+
+    #[test]
+    fn no_food_is_placed_once_the_decoy_fills_the_board() {
+        let mut world = world(Options {
+            width: 6,
+            height: 1,
+            mode: Mode::Choice,
+            seed: 1,
+            ..Options::default()
+        });
+        world.food = vec![Point::new(4, 0)];
+        world.decoy = Some(Point::new(5, 0));
+        assert_eq!(world.advance(), Some(SnekEvent::Ate(Point::new(4, 0))));
+        assert!(world.food.is_empty());
+        assert_eq!(world.decoy, Some(Point::new(5, 0)));
+    }
+
+    #[test]
+    fn random_headings_are_never_backwards_or_fatal() {
+        let mut seen = Vec::new();
+        for seed in 0..40 {
+            let world = world(Options {
+                random_heading: true,
+                seed,
+                ..Options::default()
+            });
+            let heading = world.snek.direction;
+            // In the top left corner, up is a wall and left is the snek's body
+            assert!(heading != Direction::Right.opposite() && heading != Direction::Up);
+            assert!(!world.snek.move_fatal(heading, &world.food, &[], 17, 15));
+            assert_eq!(world.start_heading, heading);
+            if !seen.contains(&heading) {
+                seen.push(heading);
+            }
+        }
+        assert_eq!(seen.len(), 2);
+        // With a wall ahead, it has to turn
+        for seed in 0..10 {
+            let start = (Point::new(8, 7), Direction::Right);
+            let options = Options {
+                random_heading: true,
+                start: Some(start),
+                walls: vec![Point::new(9, 7)],
+                seed,
+                ..Options::default()
+            };
+            let world = World::new(options, &starting_body(Some(start)));
+            assert!(matches!(
+                world.snek.direction,
+                Direction::Up | Direction::Down
+            ));
+        }
+    }
+
+    #[test]
+    fn score_follows_the_scoring_mode() {
+        let scores = [Scoring::Length, Scoring::Food, Scoring::Points].map(|scoring| {
+            let mut world = world(Options {
+                scoring,
+                seed: 1,
+                ..Options::default()
+            });
+            for food in [4, 5] {
+                world.food = vec![Point::new(food, 0)];
+                world.food_spawned = vec![world.ticks];
+                assert_eq!(world.advance(), Some(SnekEvent::Ate(Point::new(food, 0))));
+            }
+            world.food = vec![Point::new(0, 10)];
+            world.advance();
+            // Losing a segment takes away from the length alone
+            world.snek.shrink(1);
+            world.score()
+        });
+        assert_eq!(scores, [1, 2, 2 * FOOD_POINTS]);
+        assert_eq!(Options::default().scoring, Scoring::Length);
+    }
+
+    #[test]
+    fn reversing_is_ignored_unless_the_lockout_is_off() {
+        let mut locked = world(Options::default());
+        locked.snek.change_direction(Direction::Left);
+        assert_eq!(locked.snek.direction, Direction::Right);
+        locked.advance();
+        assert!(locked.running());
+
+        let mut unlocked = world(Options {
+            reverse_lockout: false,
+            ..Options::default()
+        });
+        unlocked.snek.change_direction(Direction::Left);
+        assert_eq!(unlocked.snek.direction, Direction::Left);
+        unlocked.advance();
+        assert_eq!(unlocked.death(), Some(DeathCause::Body));
+    }
+
+    #[test]
+    fn two_turns_between_moves_cant_reverse() {
+        let mut world = world(Options::default());
+        // Up and then left before the snek's moved up is still back into its neck
+        world.snek.change_direction(Direction::Up);
+        world.snek.change_direction(Direction::Left);
+        assert_eq!(world.snek.direction, Direction::Up);
+        world.snek.change_direction(Direction::Down);
+        assert_eq!(world.snek.direction, Direction::Down);
+        world.advance();
+        assert_eq!(world.check_death(), None);
+        world.snek.change_direction(Direction::Up);
+        assert_eq!(world.snek.direction, Direction::Down);
+    }
+
+    #[test]
+    fn food_is_worth_less_the_longer_it_waits() {
+        let eaten_after = |ticks: u64| {
+            let mut world = world(Options {
+                food_decay: true,
+                scoring: Scoring::Points,
+                seed: 1,
+                ..Options::default()
+            });
+            world.food = vec![Point::new(4, 0)];
+            world.food_spawned = vec![0];
+            world.ticks = ticks;
+            assert_eq!(world.advance(), Some(SnekEvent::Ate(Point::new(4, 0))));
+            world.score()
+        };
+        // Food's worth all of its points on the tick after it appears
+        assert_eq!(eaten_after(0), FOOD_POINTS);
+        assert_eq!(eaten_after(FOOD_DECAY_TICKS / 2), FOOD_POINTS * 3 / 5);
+        // And goes down to the floor, but no further
+        assert_eq!(
+            eaten_after(FOOD_DECAY_TICKS),
+            FOOD_POINTS / FOOD_DECAY_FLOOR
+        );
+        assert_eq!(eaten_after(1000), FOOD_POINTS / FOOD_DECAY_FLOOR);
+    }
+
+    #[test]
+    fn speed_stays_in_bounds_however_its_changed() {
+        let mut world = world(Options {
+            fps: 30,
+            min_fps: Some(20),
+            max_fps: Some(40),
+            ..Options::default()
+        });
+        assert_eq!(world.move_fps(false, false), 30);
+        // Slowing a fast snek down stops at the floor
+        assert_eq!(world.move_fps(false, true), 20);
+        // And speeding it up stops at the ceiling
+        assert_eq!(world.move_fps(true, false), 40);
+        assert_eq!(world.move_fps(true, true), 30);
+        world.options.fps = 100;
+        assert_eq!(world.move_fps(false, true), 40);
+        assert_eq!(world.fps(), 40);
+        // The floor wins if they cross
+        world.options.min_fps = Some(50);
+        assert_eq!(world.move_fps(false, true), 50);
+        world.options.fps = 1;
+        world.options.min_fps = None;
+        assert_eq!(world.move_fps(false, true), 1);
+    }
+
+    #[test]
+    fn directions_turn_both_ways() {
+        use Direction::{Down, Left, Right, Up};
+        // Each direction, and the ones a quarter turn left and right of it
+        let table = [
+            (Up, Left, Right),
+            (Down, Right, Left),
+            (Left, Down, Up),
+            (Right, Up, Down),
+        ];
+        for (direction, left, right) in table {
+            assert_eq!(direction.rotate_left(), left);
+            assert_eq!(direction.rotate_right(), right);
+            assert_eq!(direction.turn(Turn::Straight), direction);
+            assert_eq!(direction.turn(Turn::Left), left);
+            assert_eq!(direction.turn(Turn::Right), right);
+            assert_eq!(direction.turn(Turn::Around), direction.opposite());
+            assert_eq!(direction.angle_to(direction), Turn::Straight);
+            assert_eq!(direction.angle_to(left), Turn::Left);
+            assert_eq!(direction.angle_to(right), Turn::Right);
+            assert_eq!(direction.angle_to(direction.opposite()), Turn::Around);
+            assert_eq!(direction.rotate_left().rotate_right(), direction);
+            assert_eq!(
+                direction.to_vector() + direction.opposite().to_vector(),
+                Vector::new(0, 0)
+            );
+        }
+    }
+
+    #[test]
+    fn food_keeps_clear_of_the_head() {
+        let head = Point::new(8, 7);
+        let steps =
+            |cell: Point| (cell.x - head.x).unsigned_abs() + (cell.y - head.y).unsigned_abs();
+        for seed in 0..200 {
+            let mut rng = Rng::with_seed(seed);
+            let food = rand_point(&mut rng, 17, 15, &STARTING_BODY, Some((head, 3)));
+            assert!(steps(food) > 3, "{seed}: {food:?}");
+            assert!(!STARTING_BODY.contains(&food));
+        }
+        // With every cell far enough away taken, it gets as far away as it can
+        let taken: Vec<Point> = (0..15)
+            .flat_map(|y| (0..17).map(move |x| Point::new(x, y)))
+            .filter(|cell| steps(*cell) > 1 && *cell != Point::new(8, 10))
+            .collect();
+        for seed in 0..20 {
+            let mut rng = Rng::with_seed(seed);
+            let food = rand_point(&mut rng, 17, 15, &taken, Some((head, 5)));
+            assert_eq!(food, Point::new(8, 10), "{seed}");
+        }
+        // And when all that's left is right by the head, it goes there
+        let taken: Vec<Point> = (0..15)
+            .flat_map(|y| (0..17).map(move |x| Point::new(x, y)))
+            .filter(|cell| *cell != Point::new(8, 8))
+            .collect();
+        let food = rand_point(&mut Rng::with_seed(1), 17, 15, &taken, Some((head, 5)));
+        assert_eq!(food, Point::new(8, 8));
+    }
+
+    #[test]
+    fn new_food_is_two_steps_from_the_head_by_default() {
+        let head = *STARTING_BODY.last().unwrap();
+        for seed in 0..100 {
+            let world = world(Options {
+                seed,
+                ..Options::default()
+            });
+            let food = world.food[0];
+            let steps = (food.x - head.x).unsigned_abs() + (food.y - head.y).unsigned_abs();
+            assert!(steps > 2, "{seed}: {food:?}");
+        }
+        // Unless the only cell left for it is right in front of the snek
+        let walls: Vec<Point> = (0..15)
+            .flat_map(|y| (0..17).map(move |x| Point::new(x, y)))
+            .filter(|cell| !STARTING_BODY.contains(cell) && *cell != Point::new(4, 0))
+            .collect();
+        let world = world(Options {
+            walls,
+            seed: 1,
+            ..Options::default()
+        });
+        assert_eq!(world.food, [Point::new(4, 0)]);
+    }
+
+    #[test]
+    fn twins_eat_at_either_end_and_grow() {
+        let body = [5, 6, 7, 8].map(|x| Point::new(x, 5));
+        let twin = |food: Point| {
+            let mut world = World::new(
+                Options {
+                    mode: Mode::Twin,
+                    seed: 1,
+                    ..Options::default()
+                },
+                &body,
+            );
+            world.food = vec![food];
+            world.food_spawned = vec![0];
+            world
+        };
+        // Eating ahead, growing out of the middle
+        let mut world = twin(Point::new(9, 5));
+        assert_eq!(world.advance(), Some(SnekEvent::Ate(Point::new(9, 5))));
+        assert_eq!(world.snek.body.len(), 5);
+        assert_eq!(world.snek.body[0], Point::new(5, 5));
+        // Turning back leads with the tail, which eats, and it grows with the
+        // half that was the head holding still
+        let mut world = twin(Point::new(4, 5));
+        world.snek.change_direction(Direction::Left);
+        assert_eq!(world.advance(), Some(SnekEvent::Ate(Point::new(4, 5))));
+        assert_eq!(world.snek.heading, Direction::Left);
+        assert_eq!(world.snek.body.len(), 5);
+        assert_eq!(world.snek.body[0], Point::new(8, 5));
+        world.advance();
+        assert_eq!(*world.snek.body.last().unwrap(), Point::new(3, 5));
+        assert!(world.running());
+    }
+
+    #[test]
+    fn twins_grow_in_the_middle() {
+        // Bent around a corner, so each half of the body is easy to tell apart
+        let body = [(5, 7), (5, 6), (5, 5), (6, 5), (7, 5), (8, 5)].map(|(x, y)| Point::new(x, y));
+        let mut world = World::new(
+            Options {
+                mode: Mode::Twin,
+                seed: 1,
+                ..Options::default()
+            },
+            &body,
+        );
+        world.food = vec![Point::new(9, 5)];
+        world.food_spawned = vec![0];
+        assert_eq!(world.advance(), Some(SnekEvent::Ate(Point::new(9, 5))));
+        // The new segment sits where the middle was, with the half ahead of it
+        // moved on and the half behind it still where it was
+        let (behind, ahead) = body.split_at(body.len() / 2);
+        let middle = behind.len();
+        assert_eq!(world.snek.body.len(), body.len() + 1);
+        assert_eq!(world.snek.body[..middle], *behind);
+        assert_eq!(world.snek.body[middle], ahead[0]);
+        assert_eq!(
+            world.snek.body[middle + 1..],
+            [&ahead[1..], &[Point::new(9, 5)]].concat()[..]
+        );
+    }
+
+    #[test]
+    fn filling_a_tiny_board_wins() {
+        // Back and forth across a 5x5 board, leaving only its last cell
+        let body: Vec<Point> = (0..24)
+            .map(|index| match index / 5 % 2 {
+                0 => Point::new(index % 5, index / 5),
+                _ => Point::new(4 - index % 5, index / 5),
+            })
+            .collect();
+        let mut world = World::new(
+            Options {
+                width: 5,
+                height: 5,
+                seed: 1,
+                ..Options::default()
+            },
+            &body,
+        );
+        assert_eq!(world.food, [Point::new(4, 4)]);
+        assert!(world.running());
+        assert_eq!(world.advance(), Some(SnekEvent::Ate(Point::new(4, 4))));
+        assert_eq!(world.snek.body.len(), 25);
+        assert_eq!(world.check_death(), None);
+        assert!(world.won);
+        assert!(!world.running());
+        assert!(world.food.is_empty());
+    }
+
+    #[test]
+    fn eating_the_goal_wins() {
+        let foods = [5, 8, 11, 14].map(|x| Point::new(x, 0));
+        let mut world = world(Options {
+            goal: Some(3),
+            seed: 1,
+            food_sequence: foods.to_vec(),
+            ..Options::default()
+        });
+        // Straight along the top row, eating the food three moves apart
+        for food in &foods[..2] {
+            while world.advance() != Some(SnekEvent::Ate(*food)) {
+                assert_eq!(world.check_death(), None);
+            }
+        }
+        assert_eq!(world.eaten, 2);
+        assert!(world.running() && !world.won);
+        while world.advance() != Some(SnekEvent::Ate(foods[2])) {}
+        assert_eq!(world.check_death(), None);
+        assert!(world.won && !world.running());
+        assert_eq!(world.advance(), None);
+        let summary = GameSummary::new(&world, 4);
+        assert_eq!((summary.eaten, summary.goal), (3, Some(3)));
+        assert_eq!(summary.outcome(), "won");
+    }
+
+    #[test]
+    fn walls_come_before_the_body() {
+        // Round in a loop back onto the tail, where there's a wall as well
+        let body = [(5, 5), (6, 5), (6, 6), (5, 6), (5, 5)].map(|(x, y)| Point::new(x, y));
+        for mode in [Mode::Classic, Mode::Ouroboros] {
+            let mut world = world(Options {
+                mode,
+                walls: vec![Point::new(5, 5)],
+                seed: 1,
+                ..Options::default()
+            });
+            world.snek.body = body.to_vec();
+            assert_eq!(world.death(), Some(DeathCause::Wall), "{mode:?}");
+            assert_eq!(world.check_death(), Some(SnekEvent::Died(Point::new(5, 5))));
+            assert_eq!(world.cause, Some(DeathCause::Wall));
+            // Without the wall, it's the body it ran into (or bit, in ouroboros)
+            world.options.walls.clear();
+            assert_eq!(world.death(), Some(DeathCause::Body), "{mode:?}");
+        }
+        // And off the edge of the board onto a part that's off it too
+        let snek = Snek {
+            body: vec![Point::new(-1, 0), Point::new(0, 0), Point::new(-1, 0)],
+            ..Snek::new(&STARTING_BODY, false)
+        };
+        assert_eq!(snek.death(17, 15), Some(DeathCause::Wall));
+    }
+
+    #[test]
+    fn clusters_are_in_one_piece() {
+        let options = Options {
+            mode: Mode::Cluster,
+            seed: 1,
+            ..Options::default()
+        };
+        let cells: Vec<Point> = (0..15)
+            .flat_map(|y| (0..17).map(move |x| Point::new(x, y)))
+            .collect();
+        // Returns whether the cells of a cluster all join up
+        let joined = |cluster: &[Point]| {
+            let mut reached = vec![cluster[0]];
+            let mut next = 0;
+            while let Some(cell) = reached.get(next).copied() {
+                next += 1;
+                for direction in Direction::all() {
+                    let neighbor = cell + direction.to_vector();
+                    if cluster.contains(&neighbor) && !reached.contains(&neighbor) {
+                        reached.push(neighbor);
+                    }
+                }
+            }
+            reached.len() == cluster.len()
+        };
+        for seed in 0..300 {
+            let mut rng = Rng::with_seed(seed);
+            // Anything from an empty board to a mostly full one
+            let fullness = rng.u32(0..90);
+            let exclude: Vec<Point> = cells
+                .iter()
+                .copied()
+                .filter(|cell| STARTING_BODY.contains(cell) || rng.u32(0..100) < fullness)
+                .collect();
+            let cluster = rand_cluster(&mut rng, &options, &exclude, &STARTING_BODY);
+            assert!(
+                (1..=*CLUSTER_SIZES.end()).contains(&cluster.len()),
+                "{seed}"
+            );
+            if fullness == 0 {
+                assert!(CLUSTER_SIZES.contains(&cluster.len()), "{seed}");
+            }
+            for (index, cell) in cluster.iter().enumerate() {
+                assert!(in_bounds(*cell, 17, 15), "{seed}: {cell:?}");
+                assert!(!exclude.contains(cell), "{seed}: {cell:?}");
+                assert!(!cluster[..index].contains(cell), "{seed}: {cell:?}");
+            }
+            assert!(joined(&cluster), "{seed}: {cluster:?}");
+        }
+        // With no two free cells side by side, they're only ever one cell
+        let exclude: Vec<Point> = cells
+            .iter()
+            .copied()
+            .filter(|cell| STARTING_BODY.contains(cell) || (cell.x + cell.y) % 2 == 0)
+            .collect();
+        for seed in 0..20 {
+            let cluster = rand_cluster(
+                &mut Rng::with_seed(seed),
+                &options,
+                &exclude,
+                &STARTING_BODY,
+            );
+            assert_eq!(cluster.len(), 1, "{seed}");
+        }
+    }
+
+    #[test]
+    fn the_next_cluster_waits_for_the_last_one_to_be_eaten() {
+        let mut world = world(Options {
+            mode: Mode::Cluster,
+            seed: 1,
+            ..Options::default()
+        });
+        world.food = vec![Point::new(4, 0), Point::new(5, 0)];
+        world.food_spawned = vec![0, 0];
+        assert_eq!(world.advance(), Some(SnekEvent::Ate(Point::new(4, 0))));
+        assert_eq!(world.food, [Point::new(5, 0)]);
+        assert_eq!(world.advance(), Some(SnekEvent::Ate(Point::new(5, 0))));
+        assert!(CLUSTER_SIZES.contains(&world.food.len()));
+        assert_eq!((world.eaten, world.snek.body.len()), (2, 6));
+    }
+
+    #[test]
+    fn hugging_the_wall_drains_points_until_it_moves_away() {
+        let hugger = |wall_hug_penalty: bool| {
+            let mut world = world(Options {
+                wall_hug_penalty,
+                seed: 1,
+                ..Options::default()
+            });
+            world.food = vec![Point::new(8, 10)];
+            world.points = 100;
+            world
+        };
+        let mut world = hugger(true);
+        // Along the top edge, free for a while and then a point a move
+        let mut points = Vec::new();
+        for _ in 0..12 {
+            world.advance();
+            points.push(world.points);
+        }
+        assert_eq!(
+            points[..WALL_HUG_TICKS as usize],
+            [100; WALL_HUG_TICKS as usize]
+        );
+        assert_eq!(points[WALL_HUG_TICKS as usize..], [99, 98]);
+        // Then away from it, where it stops
+        world.snek.change_direction(Direction::Down);
+        for _ in 0..3 {
+            world.advance();
+        }
+        assert_eq!((world.points, world.wall_ticks), (98, 0));
+        // Back to the wall starts the count over
+        world.snek.change_direction(Direction::Right);
+        world.advance();
+        assert_eq!((world.points, world.wall_ticks), (98, 1));
+        // And without the penalty, it's only counted
+        let mut world = hugger(false);
+        for _ in 0..12 {
+            world.advance();
+        }
+        assert_eq!((world.points, world.wall_ticks), (100, 12));
+    }
+
+    #[test]
+    fn far_and_weighted_food_lands_farther_from_the_head() {
+        let head = Point::new(3, 0);
+        // Returns the average distance of food from the head over many picks
+        let average = |food_placement: FoodPlacement| {
+            let options = Options {
+                food_placement,
+                food_clearance: 0,
+                ..Options::default()
+            };
+            let mut rng = Rng::with_seed(7);
+            let total: u32 = (0..2_000)
+                .map(|_| {
+                    let cell = pick_food_cell(&mut rng, &options, &STARTING_BODY, head);
+                    (cell.x - head.x).unsigned_abs() + (cell.y - head.y).unsigned_abs()
+                })
+                .sum();
+            total as f32 / 2_000.0
+        };
+        let uniform = average(FoodPlacement::Uniform);
+        let weighted = average(FoodPlacement::Weighted);
+        let far = average(FoodPlacement::Far);
+        // On this board, about 13, 15, and 21 steps
+        assert!(uniform + 1.0 < weighted, "{uniform} {weighted}");
+        assert!(weighted + 4.0 < far, "{weighted} {far}");
+        // And the same seed always puts it in the same place
+        for food_placement in [FoodPlacement::Weighted, FoodPlacement::Far] {
+            let options = Options {
+                food_placement,
+                ..Options::default()
+            };
+            let pick =
+                |seed| pick_food_cell(&mut Rng::with_seed(seed), &options, &STARTING_BODY, head);
+            assert!((0..20).all(|seed| pick(seed) == pick(seed)));
+        }
+    }
+
+    #[test]
+    fn fair_food_stays_out_of_walled_off_pockets() {
+        // A wall down the board, shutting off everything right of it
+        let walls: Vec<Point> = (0..15).map(|y| Point::new(5, y)).collect();
+        let reachable = reachable_cells(&STARTING_BODY, &walls, 17, 15);
+        assert_eq!(reachable.len(), 5 * 15 - STARTING_BODY.len());
+        assert!(reachable.iter().all(|cell| cell.x < 5));
+        // Nearest first
+        assert!([Point::new(4, 0), Point::new(3, 1)].contains(&reachable[0]));
+        assert_eq!(*reachable.last().unwrap(), Point::new(0, 14));
+        for seed in 0..100 {
+            let options = Options {
+                fair_food: true,
+                walls: walls.clone(),
+                seed,
+                ..Options::default()
+            };
+            let exclude = [&STARTING_BODY[..], &walls].concat();
+            let food = place_food(
+                &mut Rng::with_seed(seed),
+                &options,
+                &exclude,
+                &STARTING_BODY,
+            );
+            assert!(food.x < 5, "{seed}: {food:?}");
+            assert!(!exclude.contains(&food), "{seed}: {food:?}");
+        }
+        // The snek's own body shuts off the corner it's curled around
+        let body = [(0, 2), (1, 2), (2, 2), (2, 1), (2, 0), (3, 0)].map(|(x, y)| Point::new(x, y));
+        let reachable = reachable_cells(&body, &[], 17, 15);
+        assert_eq!(reachable.len(), 17 * 15 - body.len() - 4);
+        for cell in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            assert!(!reachable.contains(&Point::new(cell.0, cell.1)));
+        }
+    }
+
+    #[test]
+    fn sequenced_food_goes_round_and_skips_taken_cells() {
+        let sequence = vec![Point::new(4, 0), Point::new(6, 0), Point::new(2, 5)];
+        let options = Options {
+            food_sequence: sequence.clone(),
+            seed: 1,
+            ..Options::default()
+        };
+        let placed: Vec<Option<Point>> = (0..7)
+            .map(|placed| sequenced_food(&options, placed, &STARTING_BODY))
+            .collect();
+        let wrapped: Vec<Option<Point>> =
+            sequence.iter().cycle().take(7).copied().map(Some).collect();
+        assert_eq!(placed, wrapped);
+        // A cell that's taken is left for `place_food` to pick another
+        assert_eq!(sequenced_food(&options, 1, &[Point::new(6, 0)]), None);
+        assert_eq!(sequenced_food(&Options::default(), 0, &[]), None);
+        // Played out, it's eaten in order and then starts over
+        let mut world = world(options.clone());
+        let mut eaten = Vec::new();
+        let moves = [
+            (Direction::Right, 3),
+            (Direction::Down, 5),
+            (Direction::Left, 4),
+        ];
+        for (direction, count) in moves {
+            world.snek.change_direction(direction);
+            for _ in 0..count {
+                if let Some(SnekEvent::Ate(food)) = world.advance() {
+                    eaten.push(food);
+                }
+            }
+        }
+        assert_eq!(eaten, sequence);
+        assert_eq!(world.food, [Point::new(4, 0)]);
+        // Starting on the snek, the first is put somewhere else
+        let world = World::new(options, &[2, 3, 4, 5].map(|x| Point::new(x, 0)));
+        assert_eq!(world.food.len(), 1);
+        assert_ne!(world.food, [Point::new(4, 0)]);
+    }
+
+    #[test]
+    fn hooks_can_double_the_score_and_move_the_food() {
+        let play = |scoring: Scoring, hooks: Hooks| {
+            let mut world = world(Options {
+                scoring,
+                seed: 1,
+                ..Options::default()
+            });
+            world.hooks = hooks;
+            world.food = vec![Point::new(4, 0)];
+            world.food_spawned = vec![0];
+            assert_eq!(world.advance(), Some(SnekEvent::Ate(Point::new(4, 0))));
+            world
+        };
+        // Every way of scoring goes through the hook
+        for scoring in [Scoring::Length, Scoring::Food, Scoring::Points] {
+            let plain = play(scoring, Hooks::default());
+            let doubled = play(
+                scoring,
+                Hooks {
+                    spawn: Box::new(|_, _| Point::new(8, 8)),
+                    score: Box::new(|_, score| score * 2),
+                },
+            );
+            assert!(plain.score() > 0);
+            assert_eq!(doubled.score(), plain.score() * 2);
+            assert_eq!(doubled.food, [Point::new(8, 8)]);
+            assert_ne!(plain.food, [Point::new(8, 8)]);
+        }
+        // A hook can hang on to what it needs, like how much a day's worth
+        let bonus = 3;
+        let tripled = play(
+            Scoring::Food,
+            Hooks {
+                score: Box::new(move |_, score| score * bonus),
+                ..Hooks::default()
+            },
+        );
+        assert_eq!(tripled.score(), 3);
+    }
+
+    #[test]
+    fn double_food_doubles_the_score() {
+        let mut world = world(Options {
+            double_food: true,
+            seed: 1,
+            ..Options::default()
+        });
+        world.food = vec![Point::new(4, 0)];
+        world.food_spawned = vec![0];
+        world.advance();
+        assert_eq!((world.snek.score(), world.score()), (1, 2));
+    }
+}
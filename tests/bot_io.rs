@@ -0,0 +1,56 @@
+// Drives `snek --bot-io` as a bot would, over the binary's stdin and stdout.
+
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+#[test]
+fn bot_plays_a_game_into_the_wall() {
+    // Keeps the game's files out of the real data directory
+    let home = env::temp_dir().join(format!("snek-bot-io-{}", std::process::id()));
+    let mut snek = Command::new(env!("CARGO_BIN_EXE_snek"))
+        .args([
+            "--bot-io",
+            "--headless",
+            "--bot-timeout",
+            "5000",
+            "--seed",
+            "3",
+        ])
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = snek.stdin.take().unwrap();
+    let stdout = BufReader::new(snek.stdout.take().unwrap());
+    let mut states = 0;
+    let mut results = None;
+    for line in stdout.lines() {
+        let line = line.unwrap();
+        if !line.starts_with("{\"tick\":") {
+            results = Some(line);
+            break;
+        }
+        if states == 0 {
+            assert!(line.starts_with(
+                "{\"tick\":0,\"width\":17,\"height\":15,\"snek\":[[0,0],[1,0],[2,0],[3,0]],"
+            ));
+        }
+        states += 1;
+        writeln!(stdin, "right").unwrap();
+    }
+    assert!(snek.wait().unwrap().success());
+    let _ = std::fs::remove_dir_all(&home);
+    // The head starts 13 cells from the right wall, and runs into it on the
+    // 14th move
+    assert_eq!(states, 14);
+    let results = results.unwrap();
+    assert!(results.contains("\"outcome\":\"died\""));
+    assert!(results.contains("\"cause\":\"wall\""));
+    assert!(results.contains("\"ticks\":14"));
+}
@@ -0,0 +1,69 @@
+// Checks where `snek paths` says files go, with the platform's directories
+// (on Linux, where they come from the XDG variables) and with `--portable`.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Runs `snek paths` with a scratch home directory, returning each path by name
+fn paths(home: &Path, args: &[&str]) -> Vec<(String, PathBuf)> {
+    let output = Command::new(env!("CARGO_BIN_EXE_snek"))
+        .args(args)
+        .arg("paths")
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let (name, path) = line.split_at(15);
+            (name.trim_end().to_owned(), PathBuf::from(path))
+        })
+        .collect()
+}
+
+// Returns the path of a file by name
+fn path<'a>(paths: &'a [(String, PathBuf)], name: &str) -> &'a Path {
+    &paths.iter().find(|(other, _)| other == name).unwrap().1
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn files_go_in_the_xdg_directories() {
+    let home = env::temp_dir().join(format!("snek-paths-{}", std::process::id()));
+    let paths = paths(&home, &[]);
+    assert_eq!(path(&paths, "config"), home.join("config/snek/config.json"));
+    assert_eq!(path(&paths, "scores"), home.join("data/snek/scores.tsv"));
+    assert_eq!(path(&paths, "levels"), home.join("data/snek/levels"));
+    assert_eq!(path(&paths, "crash reports"), home.join("cache/snek"));
+    // A config from before it moved is still found where it was
+    fs::create_dir_all(home.join("data/snek")).unwrap();
+    fs::write(home.join("data/snek/config.json"), "{\"version\": 3}\n").unwrap();
+    let paths = self::paths(&home, &[]);
+    assert_eq!(path(&paths, "config"), home.join("data/snek/config.json"));
+    fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn portable_files_go_next_to_the_executable() {
+    let home = env::temp_dir().join(format!("snek-portable-{}", std::process::id()));
+    let portable = Path::new(env!("CARGO_BIN_EXE_snek"))
+        .parent()
+        .unwrap()
+        .join("snek-data");
+    let paths = paths(&home, &["--portable"]);
+    for (name, path) in &paths {
+        assert!(
+            path.starts_with(&portable),
+            "{name} is in {}",
+            path.display()
+        );
+    }
+    assert_eq!(self::path(&paths, "stats"), portable.join("stats.txt"));
+}
@@ -0,0 +1,41 @@
+// Runs `snek verify` on the replay fixtures.
+
+use std::env;
+use std::process::{Command, Output};
+
+// Runs `snek verify` on a fixture
+fn verify(fixture: &str) -> Output {
+    // Keeps the game's files out of the real data directory
+    let home = env::temp_dir().join(format!("snek-verify-{}", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_snek"))
+        .arg("verify")
+        .arg(format!(
+            "{}/tests/fixtures/{fixture}",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_dir_all(&home);
+    output
+}
+
+#[test]
+fn genuine_replay_passes() {
+    let output = verify("valid.snekreplay");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Verified: scored 3 on tick 18\n"
+    );
+}
+
+#[test]
+fn tampered_replay_fails() {
+    let output = verify("tampered.snekreplay");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("the game scored 3"));
+}